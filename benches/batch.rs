@@ -0,0 +1,66 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Validation of 100k tokens, one at a time and as a batch. Enable the `rayon` feature to verify
+//! the batch in parallel.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use tokenize::Tokenize;
+use tokenize::store::MemoryAccountStore;
+
+const TOKENS: usize = 100_000;
+const ACCOUNTS: usize = 1_000;
+
+fn batch_validation(c: &mut Criterion) {
+    let tokenize = Tokenize::new("uwu");
+    let store = MemoryAccountStore::new();
+    for id in 0..ACCOUNTS {
+        store.insert(id.to_string(), ());
+    }
+
+    let tokens = (0..TOKENS)
+        .map(|i| tokenize.generate((i % ACCOUNTS) as u64).expect("Couldn't generate new token"))
+        .collect::<Vec<String>>();
+
+    let mut group = c.benchmark_group("validate 100k tokens");
+    group.sample_size(10);
+
+    group.bench_function("one at a time", |b| b.iter_batched(
+        || tokens.clone(),
+        |tokens| tokens.into_iter()
+            .map(|token| tokenize.validate_with_store(token, &store))
+            .collect::<Vec<_>>(),
+        BatchSize::LargeInput
+    ));
+
+    group.bench_function("batch", |b| b.iter(|| tokenize.validate_batch(&tokens, &store)));
+
+    group.finish();
+}
+
+criterion_group!(benches, batch_validation);
+criterion_main!(benches);
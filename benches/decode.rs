@@ -0,0 +1,53 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Decoding of the segments of tokens with short and long account ids. Run with and without the
+//! `simd` feature to compare the decoders.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tokenize::{Encoding, Tokenize};
+use tokenize::store::MemoryAccount;
+
+fn decode(c: &mut Criterion) {
+    let account = MemoryAccount { data: (), last_token_reset: 0 };
+
+    for encoding in [Encoding::Standard, Encoding::UrlSafe] {
+        let tokenize = Tokenize::new("uwu").set_encoding(encoding);
+        let short = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let long = tokenize.generate([7; 64].to_vec()).expect("Couldn't generate new token");
+
+        let mut group = c.benchmark_group(format!("decode {:?}", encoding));
+        group.bench_function("parse, 18 bytes", |b| b.iter(|| tokenize.parse(black_box(&short))));
+        group.bench_function("parse, 64 bytes", |b| b.iter(|| tokenize.parse(black_box(&long))));
+        group.bench_function("parse_ref, 64 bytes", |b| b.iter(|| tokenize.parse_ref(black_box(&long)).and_then(|token| token.account_id())));
+        group.bench_function("validate_ref, 64 bytes", |b| b.iter(|| tokenize.validate_ref(black_box(&long), |_id| Some(account.clone())).map(|_| ())));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Generation of tokens.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tokenize::{Tokenize, TokenVersion};
+
+fn generate(c: &mut Criterion) {
+    let tokenize = Tokenize::new("uwu");
+    let prefixed = Tokenize::new("uwu").set_prefix("bot").set_version(TokenVersion::V2);
+
+    let mut group = c.benchmark_group("generate");
+    group.bench_function("plain", |b| b.iter(|| tokenize.generate(black_box("326359466171826176"))));
+    group.bench_function("prefixed v2", |b| b.iter(|| prefixed.generate(black_box("326359466171826176"))));
+    group.bench_function("scopes", |b| b.iter(|| tokenize.generate_with_scopes(black_box("326359466171826176"), ["read", "write"])));
+    group.finish();
+}
+
+criterion_group!(benches, generate);
+criterion_main!(benches);
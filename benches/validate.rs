@@ -0,0 +1,58 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Validation of valid and rejected tokens, with and without allocations.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tokenize::Tokenize;
+use tokenize::store::MemoryAccount;
+
+const TOKEN: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+const FORGED: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wd";
+const MALFORMED: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc";
+
+fn validate(c: &mut Criterion) {
+    let tokenize = Tokenize::new("uwu");
+    let account = MemoryAccount { data: (), last_token_reset: 0 };
+    let reset = MemoryAccount { data: (), last_token_reset: 4102444800000 };
+
+    let mut group = c.benchmark_group("validate success");
+    group.bench_function("validate", |b| b.iter(|| tokenize.validate(black_box(TOKEN), |_id| Some(account.clone()))));
+    group.bench_function("validate_ref", |b| b.iter(|| tokenize.validate_ref(black_box(TOKEN), |_id| Some(account.clone())).map(|_| ())));
+    group.finish();
+
+    let mut group = c.benchmark_group("validate failure");
+    group.bench_function("invalid signature", |b| b.iter(|| tokenize.validate(black_box(FORGED), |_id| Some(account.clone()))));
+    group.bench_function("malformed", |b| b.iter(|| tokenize.validate(black_box(MALFORMED), |_id| Some(account.clone()))));
+    group.bench_function("invalidated", |b| b.iter(|| tokenize.validate(black_box(TOKEN), |_id| Some(reset.clone()))));
+    group.bench_function("account not found", |b| b.iter(|| tokenize.validate(black_box(TOKEN), |_id| None::<MemoryAccount>)));
+    group.bench_function("invalid signature, validate_ref", |b| b.iter(|| tokenize.validate_ref(black_box(FORGED), |_id| Some(account.clone())).map(|_| ())));
+    group.finish();
+}
+
+criterion_group!(benches, validate);
+criterion_main!(benches);
@@ -0,0 +1,116 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Derive macros for the [`Account`] and [`MutableAccount`] traits of [tokenize], re-exported by
+//! tokenize with the `derive` feature.
+//! 
+//! The field holding the last token reset, in milliseconds, is marked with `#[token_reset]`.
+//! 
+//! ```ignore
+//! use tokenize::{Account, MutableAccount};
+//! 
+//! #[derive(Account, MutableAccount)]
+//! struct User {
+//!     id: String,
+//!     #[token_reset]
+//!     last_token_reset: u64
+//! }
+//! ```
+//! 
+//! [tokenize]: https://docs.rs/tokenize
+//! [`Account`]: https://docs.rs/tokenize/latest/tokenize/trait.Account.html
+//! [`MutableAccount`]: https://docs.rs/tokenize/latest/tokenize/trait.MutableAccount.html
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Index, Member};
+
+/// The attribute marking the field holding the last token reset.
+const TOKEN_RESET: &str = "token_reset";
+
+/// Implements `Account`, reading the last token reset from the `#[token_reset]` field.
+#[proc_macro_derive(Account, attributes(token_reset))]
+pub fn derive_account(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, |field| quote! {
+        fn last_token_reset(&self) -> u64 {
+            ::core::convert::From::from(self.#field)
+        }
+    }, quote!(::tokenize::Account)).into()
+}
+
+/// Implements `MutableAccount`, writing the last token reset to the `#[token_reset]` field. The type
+/// must implement `Account` as well.
+#[proc_macro_derive(MutableAccount, attributes(token_reset))]
+pub fn derive_mutable_account(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, |field| quote! {
+        fn set_last_token_reset(&mut self, last_token_reset: u64) {
+            self.#field = ::core::convert::From::from(last_token_reset);
+        }
+    }, quote!(::tokenize::MutableAccount)).into()
+}
+
+/// Implements `trait_path` for `input`, with the body built by `body` from the `#[token_reset]` field.
+fn expand(input: &DeriveInput, body: impl FnOnce(Member) -> TokenStream2, trait_path: TokenStream2) -> TokenStream2 {
+    let field = match token_reset_field(input) {
+        Ok(field) => field,
+        Err(err) => return err.to_compile_error()
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let body = body(field);
+
+    quote! {
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #body
+        }
+    }
+}
+
+/// Finds the field marked with `#[token_reset]`, which must be unique.
+fn token_reset_field(input: &DeriveInput) -> syn::Result<Member> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(Error::new_spanned(&input.ident, "Account can only be derived for structs"))
+    };
+
+    let mut marked = fields.iter().enumerate().filter(|(_, field)| field.attrs.iter().any(|attr| attr.path().is_ident(TOKEN_RESET)));
+    let (index, field) = marked.next()
+        .ok_or_else(|| Error::new_spanned(&input.ident, "Expected a field marked with #[token_reset]"))?;
+
+    if let Some((_, duplicate)) = marked.next() {
+        return Err(Error::new_spanned(duplicate, "Only one field can be marked with #[token_reset]"))
+    }
+
+    Ok(match (fields, &field.ident) {
+        (Fields::Named(_), Some(ident)) => Member::Named(ident.clone()),
+        _ => Member::Unnamed(Index::from(index))
+    })
+}
@@ -0,0 +1,85 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! A realtime gateway authenticating WebSocket connections, echoing messages back along with the id
+//! of the account that sent them.
+//! 
+//! Run it with `cargo run --example gateway --features websocket`. A token is printed on startup;
+//! clients connect to `ws://127.0.0.1:8080` offering the `tokenize` subprotocol followed by the token,
+//! or send the token as their first message.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use tokenize::{Account, Encoding, Tokenize};
+
+#[derive(Clone)]
+struct User {
+    id: String,
+    last_token_reset: u64
+}
+
+impl Account for User {
+    fn last_token_reset(&self) -> u64 {
+        self.last_token_reset
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> std::io::Result<()> {
+    // URL-safe tokens can be sent as a subprotocol
+    let tokenize = Arc::new(Tokenize::new("uwu").set_encoding(Encoding::UrlSafe));
+    let users = Arc::new(HashMap::from([
+        ("326359466171826176".to_string(), User { id: "326359466171826176".to_string(), last_token_reset: 0 })
+    ]));
+
+    println!("Token: {}", tokenize.generate("326359466171826176").expect("Couldn't generate token"));
+
+    let listener = TcpListener::bind("127.0.0.1:8080").await?;
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let tokenize = tokenize.clone();
+        let users = users.clone();
+
+        tokio::spawn(async move {
+            let (mut socket, user) = match tokenize::websocket::accept(&tokenize, stream, |id| async move { users.get(&id).cloned() }).await {
+                Ok(connection) => connection,
+                Err(err) => return eprintln!("{}: {}", peer, err)
+            };
+
+            while let Some(Ok(message)) = socket.next().await {
+                if let Message::Text(text) = message {
+                    if socket.send(Message::text(format!("{}: {}", user.id, text))).await.is_err() {
+                        break
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Applies arbitrary edits to valid tokens, which must be rejected unless the edits cancel out.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tokenize::store::MemoryAccount;
+use tokenize::{Keyring, TokenVersion, Tokenize};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, edits)) = data.split_first() else { return };
+    let account = MemoryAccount { data: (), last_token_reset: 0 };
+
+    let tokenize = match selector % 3 {
+        0 => Tokenize::new("uwu"),
+        1 => Tokenize::new("uwu").set_prefix("app").set_version(TokenVersion::V2),
+        _ => Tokenize::with_keyring(Keyring::new("k1", "uwu").add_key("k2", "owo"))
+    };
+    let token = match selector / 3 % 3 {
+        0 => tokenize.generate("326359466171826176"),
+        1 => tokenize.generate_with_scopes("326359466171826176", ["read", "write"]),
+        _ => tokenize.generate_single_use("326359466171826176")
+    }.expect("Couldn't generate token");
+
+    // Each edit is a position and a byte: even bytes overwrite the character at the position, odd
+    // ones remove it
+    let mut mutated = token.clone().into_bytes();
+    for edit in edits.chunks_exact(2) {
+        if mutated.is_empty() {
+            break
+        }
+
+        let index = edit[0] as usize % mutated.len();
+        if edit[1] % 2 == 0 {
+            mutated[index] = edit[1] / 2;
+        } else {
+            mutated.remove(index);
+        }
+    }
+
+    let mutated = String::from_utf8_lossy(&mutated);
+    if mutated != token {
+        assert!(tokenize.parse(&*mutated).is_err());
+        assert!(tokenize.parse_ref(&mutated).is_err());
+        assert!(tokenize.validate(&*mutated, |_id| Some(account.clone())).is_err());
+        assert!(tokenize.validate_single_use(&*mutated, |_id| Some(account.clone())).is_err());
+    }
+});
@@ -0,0 +1,59 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Feeds arbitrary input to every parsing and validation entry point, which must reject it without
+//! panicking.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tokenize::store::MemoryAccount;
+use tokenize::{Encoding, Keyring, Token, TokenVersion, Tokenize, ValidationOptions};
+
+fuzz_target!(|data: &[u8]| {
+    let token = String::from_utf8_lossy(data);
+    let account = MemoryAccount { data: (), last_token_reset: 0 };
+
+    let instances = [
+        Tokenize::new("uwu"),
+        Tokenize::new("uwu").set_prefixes(["app", "bot"]).set_version(TokenVersion::V2),
+        Tokenize::new("uwu").set_encoding(Encoding::UrlSafe).accept_any_encoding(),
+        Tokenize::with_secrets("uwu", ["owo"]).set_validation_options(ValidationOptions::new().strict().hardened()),
+        Tokenize::with_keyring(Keyring::new("k1", "uwu").add_key("k2", "owo")),
+        Tokenize::new("uwu").set_validation_options(ValidationOptions::new().max_length(usize::MAX).max_account_id_length(usize::MAX))
+    ];
+
+    for tokenize in &instances {
+        assert!(tokenize.parse(&*token).is_err());
+        assert!(tokenize.parse_ref(&token).is_err());
+        assert!(tokenize.validate(&*token, |_id| Some(account.clone())).is_err());
+        assert!(tokenize.validate_ref(&token, |_id| Some(account.clone())).is_err());
+        assert!(tokenize.validate_bytes(&*token, |_id| Some(account.clone())).is_err());
+    }
+
+    let _ = token.parse::<Token>();
+});
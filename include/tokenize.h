@@ -0,0 +1,131 @@
+#ifndef TOKENIZE_H
+#define TOKENIZE_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define TOKENIZE_VERSION 1
+
+#define TOKENIZE_EPOCH 1546300800000
+
+/**
+ * Outcome of a call, `TOKENIZE_STATUS_OK` on success.
+ */
+typedef enum TokenizeStatus {
+  TOKENIZE_STATUS_OK = 0,
+  /**
+   * A pointer was null, or a string wasn't valid UTF-8.
+   */
+  TOKENIZE_STATUS_INVALID_ARGUMENT,
+  TOKENIZE_STATUS_MALFORMED,
+  TOKENIZE_STATUS_PREFIX_MISMATCH,
+  TOKENIZE_STATUS_INVALID_SIGNATURE,
+  TOKENIZE_STATUS_UNKNOWN_KEY,
+  TOKENIZE_STATUS_DECODE,
+  TOKENIZE_STATUS_TOKEN_TOO_LONG,
+  TOKENIZE_STATUS_ACCOUNT_NOT_FOUND,
+  TOKENIZE_STATUS_INVALIDATED,
+  TOKENIZE_STATUS_EXPIRED,
+  TOKENIZE_STATUS_ISSUED_IN_FUTURE,
+  TOKENIZE_STATUS_REVOKED,
+  TOKENIZE_STATUS_MISSING_SCOPE,
+  TOKENIZE_STATUS_ALREADY_USED,
+  TOKENIZE_STATUS_SINGLE_USE,
+  TOKENIZE_STATUS_INVALID_CLAIMS,
+  TOKENIZE_STATUS_STORE,
+  TOKENIZE_STATUS_DEVICE_MISMATCH,
+  TOKENIZE_STATUS_AUDIENCE_MISMATCH,
+  TOKENIZE_STATUS_RATE_LIMITED,
+  TOKENIZE_STATUS_POLICY_VIOLATION,
+} TokenizeStatus;
+
+typedef struct Tokenize Tokenize;
+
+/**
+ * Looks up the account `account_id` belongs to for [`tokenize_validate`].
+ * 
+ * Returns `false` if there is no such account, otherwise writes the account's last token reset
+ * timestamp, in milliseconds, to `last_token_reset` and returns `true`.
+ */
+typedef bool (*TokenizeFetchFn)(const char *account_id, void *user_data, uint64_t *last_token_reset);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an instance signing tokens with the `secret_len` bytes at `secret`, and with `prefix` if it
+ * isn't null.
+ * 
+ * Returns null if the secret is empty or the prefix is invalid. The instance must be freed with
+ * [`tokenize_free`].
+ * 
+ * # Safety
+ * 
+ * `secret` must point to `secret_len` readable bytes, and `prefix` must be null or a nul-terminated
+ * string.
+ */
+struct Tokenize *tokenize_new(const uint8_t *secret,
+                              size_t secret_len,
+                              const char *prefix);
+
+/**
+ * Frees an instance created with [`tokenize_new`]. Does nothing if `tokenize` is null.
+ * 
+ * # Safety
+ * 
+ * `tokenize` must be null or returned by [`tokenize_new`], and not be used afterwards.
+ */
+void tokenize_free(struct Tokenize *tokenize);
+
+/**
+ * Generates a new token for `account_id`, writing it to `token_out`.
+ * 
+ * # Safety
+ * 
+ * `tokenize` must be a live instance, `account_id` a nul-terminated string and `token_out` a valid
+ * pointer.
+ */
+enum TokenizeStatus tokenize_generate(const struct Tokenize *tokenize,
+                                      const char *account_id,
+                                      char **token_out);
+
+/**
+ * Validates a token, looking its account up with `fetch`, which is passed `user_data` as-is.
+ * 
+ * On success, the id of the account the token belongs to is written to `account_id_out` unless it's
+ * null.
+ * 
+ * # Safety
+ * 
+ * `tokenize` must be a live instance, `token` a nul-terminated string and `account_id_out` null or a
+ * valid pointer.
+ */
+enum TokenizeStatus tokenize_validate(const struct Tokenize *tokenize,
+                                      const char *token,
+                                      TokenizeFetchFn fetch,
+                                      void *user_data,
+                                      char **account_id_out);
+
+/**
+ * Frees a string returned by this library. Does nothing if `string` is null.
+ * 
+ * # Safety
+ * 
+ * `string` must be null or returned by this library, and not be used afterwards.
+ */
+void tokenize_string_free(char *string);
+
+/**
+ * Returns a static, nul-terminated description of `status`.
+ */
+const char *tokenize_status_message(enum TokenizeStatus status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TOKENIZE_H */
@@ -0,0 +1,27 @@
+const assert = require("node:assert");
+const test = require("node:test");
+const { Tokenize } = require("..");
+
+const TOKEN = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+const secret = new TextEncoder().encode("uwu");
+
+test("validates tokens generated by the Rust implementation", () => {
+  const tokenize = new Tokenize(secret);
+  assert.strictEqual(tokenize.validate(TOKEN, () => 0), "326359466171826176");
+  assert.strictEqual(tokenize.parse(TOKEN), "326359466171826176");
+});
+
+test("round-trips generated tokens", () => {
+  const tokenize = new Tokenize(secret, "app", 60);
+  const token = tokenize.generate("326359466171826176");
+  assert.ok(token.startsWith("app."));
+  assert.strictEqual(tokenize.validate(token, () => 0), "326359466171826176");
+});
+
+test("reports the kind of error", () => {
+  const tokenize = new Tokenize(new TextEncoder().encode("owo"));
+  assert.throws(() => tokenize.parse(TOKEN), { kind: "invalid_signature" });
+  assert.throws(() => new Tokenize(secret).validate(TOKEN, () => null), { kind: "account_not_found" });
+  assert.throws(() => new Tokenize(secret, undefined, 60).validate(TOKEN, () => 0), { kind: "expired" });
+  assert.throws(() => new Tokenize(secret).validate(TOKEN, () => { throw new RangeError("down"); }), RangeError);
+});
@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::fmt;
+use std::str;
+
+/// Identifier of the account a token belongs to.
+/// 
+/// Account ids are usually strings, but any sequence of bytes is supported, so binary ids such as
+/// raw UUIDs round-trip through tokens without being stringified. Integers are converted to their
+/// decimal form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccountId(Vec<u8>);
+
+impl AccountId {
+    /// Truncated, hex-encoded SHA-256 hash of the id, identifying the account in traces without
+    /// revealing it.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace_hash(bytes: &[u8]) -> String {
+        crate::crypto::sha256(bytes)[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn new<B: Into<Vec<u8>>>(bytes: B) -> AccountId {
+        AccountId(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The id as a string, if it's valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        str::from_utf8(&self.0).ok()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// The id as an integer, if it's written in decimal like snowflakes.
+    pub fn to_u64(&self) -> Option<u64> {
+        self.as_str()?.parse().ok()
+    }
+
+    /// The id as a UUID, whether it's made of its 16 bytes or of its string form. Available with the
+    /// `uuid` feature.
+    #[cfg(feature = "uuid")]
+    pub fn to_uuid(&self) -> Option<uuid::Uuid> {
+        uuid::Uuid::from_slice(&self.0).ok().or_else(|| self.as_str()?.parse().ok())
+    }
+}
+
+/// Writes the integer in decimal, like snowflakes are usually stringified.
+impl From<u64> for AccountId {
+    fn from(id: u64) -> Self {
+        AccountId(id.to_string().into_bytes())
+    }
+}
+
+/// Uses the 16 bytes of the UUID. Available with the `uuid` feature.
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for AccountId {
+    fn from(id: uuid::Uuid) -> Self {
+        AccountId(id.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for AccountId {
+    fn from(id: String) -> Self {
+        AccountId(id.into_bytes())
+    }
+}
+
+impl From<&String> for AccountId {
+    fn from(id: &String) -> Self {
+        AccountId(id.as_bytes().to_vec())
+    }
+}
+
+impl From<&str> for AccountId {
+    fn from(id: &str) -> Self {
+        AccountId(id.as_bytes().to_vec())
+    }
+}
+
+impl From<Vec<u8>> for AccountId {
+    fn from(id: Vec<u8>) -> Self {
+        AccountId(id)
+    }
+}
+
+impl From<&[u8]> for AccountId {
+    fn from(id: &[u8]) -> Self {
+        AccountId(id.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for AccountId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for AccountId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for AccountId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+/// Displays the id as a string, replacing invalid UTF-8 sequences.
+impl fmt::Display for AccountId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+/// Serialized as a string when the id is valid UTF-8, as bytes otherwise.
+#[cfg(feature = "serde")]
+impl serde::Serialize for AccountId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_str() {
+            Some(id) => serializer.serialize_str(id),
+            None => serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AccountId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AccountIdVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for AccountIdVisitor {
+            type Value = AccountId;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string or a byte array")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, id: &str) -> Result<AccountId, E> {
+                Ok(AccountId::from(id))
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, id: &[u8]) -> Result<AccountId, E> {
+                Ok(AccountId::from(id))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<AccountId, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+
+                Ok(AccountId(bytes))
+            }
+        }
+
+        deserializer.deserialize_any(AccountIdVisitor)
+    }
+}
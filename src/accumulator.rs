@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::fmt;
+use crate::{Account, Result, Token, Tokenize, TokenizeError};
+
+/// Maximum length of the tokens accumulated by [`Tokenize::accumulator`] when no
+/// [`ValidationOptions::max_length`](crate::ValidationOptions::max_length) is set.
+pub const DEFAULT_MAX_LENGTH: usize = 4096;
+
+/// A push-parser accumulating a token fed in chunks, as it arrives over a framed protocol.
+/// 
+/// The length limit and the alphabet of the token are enforced as chunks are pushed, so oversized or
+/// garbage input is rejected without being buffered. Once a chunk is rejected, the accumulator keeps
+/// failing with the same error.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::Tokenize;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let mut accumulator = tokenize.accumulator();
+/// 
+/// for chunk in [&b"MzI2MzU5NDY2MTcxODI2MTc2.OTUz"[..], b"MzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc"] {
+///     accumulator.push(chunk).expect("Invalid chunk");
+/// }
+/// 
+/// accumulator.validate(&tokenize, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 }))
+///     .expect("Couldn't validate token");
+/// ```
+#[derive(Clone)]
+pub struct TokenAccumulator {
+    buffer: Vec<u8>,
+    max_length: usize,
+    error: Option<TokenizeError>
+}
+
+impl TokenAccumulator {
+    /// Creates an accumulator rejecting tokens longer than `max_length` bytes.
+    pub fn new(max_length: usize) -> TokenAccumulator {
+        TokenAccumulator { buffer: Vec::new(), max_length, error: None }
+    }
+
+    /// Appends a chunk of the token, failing with [`TokenizeError::TokenTooLong`] if the token gets
+    /// longer than the limit, or with [`TokenizeError::Malformed`] if the chunk holds characters no
+    /// token can contain.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        if let Some(err) = &self.error {
+            return Err(err.clone())
+        }
+
+        let result = if self.buffer.len().saturating_add(chunk.len()) > self.max_length {
+            Err(TokenizeError::TokenTooLong)
+        } else if !chunk.iter().all(u8::is_ascii_graphic) {
+            Err(TokenizeError::Malformed)
+        } else {
+            Ok(())
+        };
+
+        match result {
+            Ok(()) => {
+                self.buffer.extend_from_slice(chunk);
+                Ok(())
+            },
+            Err(err) => {
+                self.buffer = Vec::new();
+                self.error = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// The number of bytes accumulated so far.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// The accumulated token, or the error a chunk was rejected with.
+    pub fn finish(self) -> Result<String> {
+        if let Some(err) = self.error {
+            return Err(err)
+        }
+
+        // Only ASCII characters are accumulated
+        String::from_utf8(self.buffer).map_err(|_| TokenizeError::Malformed)
+    }
+
+    /// Validates the accumulated token, see [`Tokenize::validate`].
+    pub fn validate<F, A>(self, tokenize: &Tokenize, account_fetcher: F) -> Result<A> where
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        tokenize.validate(self.finish()?, account_fetcher)
+    }
+
+    /// Validates the accumulated token, returning it along with the account. See
+    /// [`Tokenize::validate_token`].
+    pub fn validate_token<F, A>(self, tokenize: &Tokenize, account_fetcher: F) -> Result<(Token, A)> where
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        tokenize.validate_token(self.finish()?, account_fetcher)
+    }
+}
+
+impl fmt::Debug for TokenAccumulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenAccumulator")
+            .field("len", &self.buffer.len())
+            .field("max_length", &self.max_length)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl Tokenize {
+    /// Creates a [`TokenAccumulator`] enforcing [`ValidationOptions::max_length`](crate::ValidationOptions::max_length),
+    /// or [`DEFAULT_MAX_LENGTH`] if it isn't set.
+    pub fn accumulator(&self) -> TokenAccumulator {
+        TokenAccumulator::new(self.options.max_length.unwrap_or(DEFAULT_MAX_LENGTH))
+    }
+}
@@ -0,0 +1,213 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! [actix-web] integration, available with the `actix` feature.
+//! 
+//! [actix-web]: https://docs.rs/actix-web
+
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use actix_web::body::MessageBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::ErrorUnauthorized;
+use actix_web::{Error, HttpMessage};
+use crate::extract::TokenExtractor;
+use crate::{Account, Tokenize};
+
+pub use crate::extract::TokenSource;
+
+/// Middleware validating the token of incoming requests.
+/// 
+/// On success, the account is stored in the request extensions, where handlers can retrieve it through
+/// [`ReqData`](actix_web::web::ReqData). Requests without a valid token are rejected with a
+/// `401 Unauthorized` response.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use actix_web::{web, App, HttpResponse};
+/// use tokenize::{Account, Tokenize};
+/// use tokenize::actix::{TokenizeMiddleware, TokenSource};
+/// 
+/// #[derive(Clone)]
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// let middleware = TokenizeMiddleware::new(Tokenize::new("uwu"), |_id| async {
+///     Some(User { last_token_reset: 0 }) // query your database here
+/// }).source(TokenSource::Cookie("token".to_string()));
+/// 
+/// let app = App::new()
+///     .wrap(middleware)
+///     .route("/me", web::get().to(|user: web::ReqData<User>| async move {
+///         HttpResponse::Ok().body(user.last_token_reset.to_string())
+///     }));
+/// ```
+pub struct TokenizeMiddleware<F> {
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Rc<F>,
+    extractor: TokenExtractor
+}
+
+impl<F> TokenizeMiddleware<F> {
+    /// Creates a middleware validating tokens from the `Authorization` header.
+    pub fn new<T: Into<Arc<Tokenize>>>(tokenize: T, account_fetcher: F) -> TokenizeMiddleware<F> {
+        TokenizeMiddleware {
+            tokenize: tokenize.into(),
+            account_fetcher: Rc::new(account_fetcher),
+            extractor: TokenExtractor::new()
+        }
+    }
+
+    /// Replaces the places the token is looked for.
+    pub fn source(mut self, source: TokenSource) -> Self {
+        self.extractor = self.extractor.source(source);
+        self
+    }
+
+    /// Adds a place the token is looked for. Sources are tried in the order they were added.
+    pub fn fallback_source(mut self, source: TokenSource) -> Self {
+        self.extractor = self.extractor.fallback_source(source);
+        self
+    }
+}
+
+impl<S, B, F, Fut, A> Transform<S, ServiceRequest> for TokenizeMiddleware<F> where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+    F: Fn(String) -> Fut + 'static,
+    Fut: Future<Output = Option<A>> + 'static,
+    A: Account + 'static {
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = TokenizeMiddlewareService<S, F>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TokenizeMiddlewareService {
+            service: Rc::new(service),
+            tokenize: self.tokenize.clone(),
+            account_fetcher: self.account_fetcher.clone(),
+            extractor: self.extractor.clone()
+        }))
+    }
+}
+
+/// Service created by [`TokenizeMiddleware`].
+pub struct TokenizeMiddlewareService<S, F> {
+    service: Rc<S>,
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Rc<F>,
+    extractor: TokenExtractor
+}
+
+impl<S, B, F, Fut, A> Service<ServiceRequest> for TokenizeMiddlewareService<S, F> where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+    F: Fn(String) -> Fut + 'static,
+    Fut: Future<Output = Option<A>> + 'static,
+    A: Account + 'static {
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let tokenize = self.tokenize.clone();
+        let account_fetcher = self.account_fetcher.clone();
+        let token = self.extractor.extract(|name| req.headers().get(name).and_then(|value| value.to_str().ok()), Some(req.query_string()));
+
+        Box::pin(async move {
+            let token = token.ok_or_else(|| ErrorUnauthorized("Missing token"))?;
+            let account = tokenize.validate_async(token, |id| account_fetcher(id))
+                .await
+                .map_err(ErrorUnauthorized)?;
+
+            req.extensions_mut().insert(account);
+            service.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+    use actix_web::cookie::Cookie;
+    use actix_web::http::StatusCode;
+    use super::{TokenizeMiddleware, TokenSource};
+    use crate::{Account, Tokenize};
+
+    #[derive(Clone)]
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    const TOKEN: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+
+    #[test]
+    fn validate_requests() {
+        actix_web::rt::System::new().block_on(validate_requests_async());
+    }
+
+    async fn validate_requests_async() {
+        let middleware = TokenizeMiddleware::new(Tokenize::new("uwu"), |_id| async { Some(TestAccount { last_token_reset: 0 }) })
+            .fallback_source(TokenSource::Cookie("token".to_string()))
+            .fallback_source(TokenSource::Query("token".to_string()));
+        let app = test::init_service(App::new().wrap(middleware).route("/", web::get().to(|account: web::ReqData<TestAccount>| async move {
+            HttpResponse::Ok().body(account.last_token_reset.to_string())
+        }))).await;
+
+        let request = test::TestRequest::get().uri("/").insert_header(("Authorization", format!("Bearer {}", TOKEN))).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+
+        let request = test::TestRequest::get().uri("/").cookie(Cookie::new("token", TOKEN)).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+
+        let request = test::TestRequest::get().uri(&format!("/?token={}", TOKEN.replace('+', "%2B"))).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+
+        let request = test::TestRequest::get().uri("/").insert_header(("Authorization", "thisisinvalid")).to_request();
+        assert_eq!(test::try_call_service(&app, request).await.err().map(|err| err.as_response_error().status_code()), Some(StatusCode::UNAUTHORIZED));
+    }
+}
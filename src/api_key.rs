@@ -0,0 +1,251 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Long-lived API keys, authenticating machines rather than user sessions.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::{token, AccountId, ConfigError, Result, RevocationStore, StoreError, Tokenize, TokenizeError};
+
+/// Prefix of API keys, unless the instance has a prefix of its own.
+pub const DEFAULT_API_KEY_PREFIX: &str = "key";
+
+/// Issues API keys and keeps track of the keys of each account.
+/// 
+/// API keys aren't invalidated by the last token reset of their account, so logging a user out
+/// everywhere doesn't break their integrations. Each key is revoked on its own instead, which is why
+/// every validation checks the [`RevocationStore`](crate::RevocationStore) of the instance. The
+/// [`ApiKeyStore`] records which keys were issued, so they can be listed and rotated, and keys
+/// removed from it are rejected as well.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Tokenize, TokenizeError};
+/// use tokenize::api_key::{ApiKeyManager, MemoryApiKeyStore};
+/// use tokenize::revocation::MemoryRevocationStore;
+/// 
+/// let tokenize = Tokenize::new("uwu").set_revocation_store(MemoryRevocationStore::new());
+/// let keys = ApiKeyManager::new(tokenize, MemoryApiKeyStore::new()).expect("Invalid configuration");
+/// let account = |_id| Some("ci-bot");
+/// 
+/// let (key, token) = keys.issue("326359466171826176", Some("ci")).expect("Couldn't issue key");
+/// assert!(token.starts_with("key."));
+/// assert_eq!(keys.validate(&*token, account), Ok("ci-bot"));
+/// 
+/// let (_, rotated) = keys.rotate(&key.id).expect("Couldn't rotate key");
+/// assert_eq!(keys.validate(token, account).err(), Some(TokenizeError::Revoked));
+/// assert!(keys.validate(rotated, account).is_ok());
+/// ```
+pub struct ApiKeyManager {
+    tokenize: Tokenize,
+    keys: Box<dyn ApiKeyStore>
+}
+
+impl ApiKeyManager {
+    /// Creates a manager signing API keys with `tokenize`, recording them in `store`. Keys are
+    /// prefixed with [`DEFAULT_API_KEY_PREFIX`] unless `tokenize` has a prefix.
+    /// 
+    /// # Errors
+    /// 
+    /// Fails if `tokenize` has no revocation store.
+    pub fn new<S: ApiKeyStore + 'static>(tokenize: Tokenize, store: S) -> Result<ApiKeyManager, ConfigError> {
+        if tokenize.revocation_store.is_none() {
+            return Err(ConfigError::MissingRevocationStore)
+        }
+
+        let tokenize = if tokenize.prefixes.is_empty() { tokenize.set_prefix(DEFAULT_API_KEY_PREFIX) } else { tokenize };
+
+        Ok(ApiKeyManager { tokenize, keys: Box::new(store) })
+    }
+
+    /// The instance API keys are issued with.
+    pub fn tokens(&self) -> &Tokenize {
+        &self.tokenize
+    }
+
+    /// Issues a new API key for `account_id`, optionally named after what it's used for, returning it
+    /// along with its token.
+    pub fn issue<S: Into<AccountId>>(&self, account_id: S, name: Option<&str>) -> Result<(ApiKey, String)> {
+        let account_id = account_id.into();
+        let key = ApiKey {
+            id: token::nonce(),
+            account_id: account_id.as_str().ok_or(TokenizeError::Decode)?.to_string(),
+            name: name.map(str::to_string),
+            created_at: self.tokenize.clock.now_millis()
+        };
+
+        self.keys.create(&key)?;
+        let mut token = self.tokenize.unsigned_token(account_id);
+        token.nonce = Some(key.id.clone());
+
+        Ok((key, self.tokenize.sign(token).to_string()))
+    }
+
+    /// Validates an API key, returning the account it belongs to.
+    /// 
+    /// Unlike [`Tokenize::validate`], the last token reset of the account isn't checked: API keys are
+    /// only rejected once revoked or removed from the [`ApiKeyStore`], with [`TokenizeError::Revoked`].
+    pub fn validate<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A> {
+        let token = token.into();
+        self.tokenize.measured(&token, || {
+            let token = self.tokenize.parse_str(&token)?;
+            self.tokenize.check_token(&token)?;
+            let id = token.nonce().ok_or(TokenizeError::Malformed)?;
+            self.check_revoked(id)?;
+            match self.keys.get(id)? {
+                Some(key) if key.account_id == token.account_id_str()? => {},
+                _ => return Err(TokenizeError::Revoked)
+            }
+
+            account_fetcher(token.account_id_str()?.to_string()).ok_or(TokenizeError::AccountNotFound)
+        })
+    }
+
+    /// The API keys of `account_id`.
+    pub fn keys(&self, account_id: &str) -> Result<Vec<ApiKey>> {
+        Ok(self.keys.list(account_id)?)
+    }
+
+    /// Revokes an API key, returning whether it existed.
+    pub fn revoke(&self, id: &str) -> Result<bool> {
+        let existed = self.keys.remove(id)?.is_some();
+        self.revocation_store()?.revoke(&revocation_key(id), None)?;
+
+        Ok(existed)
+    }
+
+    /// Replaces an API key with a new one for the same account and with the same name, revoking it.
+    /// Keys which don't exist anymore are rejected with [`TokenizeError::Revoked`].
+    pub fn rotate(&self, id: &str) -> Result<(ApiKey, String)> {
+        let key = self.keys.get(id)?.ok_or(TokenizeError::Revoked)?;
+        let rotated = self.issue(key.account_id.as_str(), key.name.as_deref())?;
+        self.revoke(id)?;
+
+        Ok(rotated)
+    }
+
+    fn check_revoked(&self, id: &str) -> Result<()> {
+        if self.revocation_store()?.is_revoked(&revocation_key(id))? {
+            return Err(TokenizeError::Revoked)
+        }
+
+        Ok(())
+    }
+
+    fn revocation_store(&self) -> Result<&dyn RevocationStore> {
+        Ok(self.tokenize.revocation_store.as_deref().ok_or_else(|| StoreError::new("No revocation store is configured"))?)
+    }
+}
+
+/// The key an API key is recorded under in the revocation store, distinct from the ones of tokens.
+fn revocation_key(id: &str) -> String {
+    format!("api-key/{}", id)
+}
+
+/// An API key issued through [`ApiKeyManager::issue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApiKey {
+    pub id: String,
+    pub account_id: String,
+    /// What the key is used for, as given to [`ApiKeyManager::issue`].
+    pub name: Option<String>,
+    /// Unix timestamp in milliseconds at which the key was issued.
+    pub created_at: i64
+}
+
+/// A store recording the API keys issued through an [`ApiKeyManager`].
+pub trait ApiKeyStore: Send + Sync {
+    fn create(&self, key: &ApiKey) -> Result<(), StoreError>;
+
+    fn get(&self, id: &str) -> Result<Option<ApiKey>, StoreError>;
+
+    /// The keys of an account, in any order.
+    fn list(&self, account_id: &str) -> Result<Vec<ApiKey>, StoreError>;
+
+    /// Removes a key, returning it if it existed.
+    fn remove(&self, id: &str) -> Result<Option<ApiKey>, StoreError>;
+}
+
+impl<S: ApiKeyStore + ?Sized> ApiKeyStore for Arc<S> {
+    fn create(&self, key: &ApiKey) -> Result<(), StoreError> {
+        (**self).create(key)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<ApiKey>, StoreError> {
+        (**self).get(id)
+    }
+
+    fn list(&self, account_id: &str) -> Result<Vec<ApiKey>, StoreError> {
+        (**self).list(account_id)
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<ApiKey>, StoreError> {
+        (**self).remove(id)
+    }
+}
+
+/// An [`ApiKeyStore`] keeping keys in memory, listing them from the oldest to the newest.
+#[derive(Default)]
+pub struct MemoryApiKeyStore {
+    keys: Mutex<HashMap<String, ApiKey>>
+}
+
+impl MemoryApiKeyStore {
+    pub fn new() -> MemoryApiKeyStore {
+        MemoryApiKeyStore::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, ApiKey>> {
+        self.keys.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl ApiKeyStore for MemoryApiKeyStore {
+    fn create(&self, key: &ApiKey) -> Result<(), StoreError> {
+        self.lock().insert(key.id.clone(), key.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<ApiKey>, StoreError> {
+        Ok(self.lock().get(id).cloned())
+    }
+
+    fn list(&self, account_id: &str) -> Result<Vec<ApiKey>, StoreError> {
+        let mut keys = self.lock().values().filter(|key| key.account_id == account_id).cloned().collect::<Vec<ApiKey>>();
+        keys.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+        Ok(keys)
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<ApiKey>, StoreError> {
+        Ok(self.lock().remove(id))
+    }
+}
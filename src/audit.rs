@@ -0,0 +1,279 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Audit trail of the tokens issued, validated and revoked.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(feature = "audit")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "audit")]
+use std::io::{self, BufRead, BufReader, Write};
+#[cfg(feature = "audit")]
+use std::path::Path;
+use crate::{token, AccountId, Tokenize, TokenizeError, ValidationContext};
+
+/// An event of the audit trail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditEvent {
+    /// A token was issued for `account_id`.
+    TokenIssued { account_id: AccountId },
+    /// A token was validated successfully.
+    TokenValidated,
+    /// A token was rejected during validation.
+    TokenRejected { reason: TokenizeError },
+    /// A token was revoked through [`Tokenize::revoke`].
+    TokenRevoked
+}
+
+impl AuditEvent {
+    /// A short, stable `snake_case` name for the event.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditEvent::TokenIssued { .. } => "token_issued",
+            AuditEvent::TokenValidated => "token_validated",
+            AuditEvent::TokenRejected { .. } => "token_rejected",
+            AuditEvent::TokenRevoked => "token_revoked"
+        }
+    }
+}
+
+/// An event along with when it happened and the token it concerns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub event: AuditEvent,
+    /// Unix timestamp in milliseconds at which the event happened, according to the instance's
+    /// [`Clock`](crate::Clock).
+    pub timestamp: i64,
+    /// Hex-encoded SHA-256 hash of the token signature as sent, the same as its
+    /// [`Token::revocation_key`](crate::Token::revocation_key) for tokens using the standard alphabet
+    /// without a key id. It identifies the token across events without revealing it.
+    pub fingerprint: String,
+    /// The context of the request, for validations performed through
+    /// [`Tokenize::validate_with_context`]. Empty otherwise.
+    pub context: ValidationContext
+}
+
+/// A sink receiving the audit trail of a [`Tokenize`] instance, set through
+/// [`Tokenize::set_audit_sink`]. Sinks are called on the validation path, so they shouldn't block
+/// for long.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, record: &AuditRecord);
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Arc<S> {
+    fn record(&self, record: &AuditRecord) {
+        (**self).record(record)
+    }
+}
+
+/// An [`AuditSink`] keeping records in memory, for tests.
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    records: Mutex<Vec<AuditRecord>>
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> MemoryAuditSink {
+        MemoryAuditSink::default()
+    }
+
+    /// The records received so far, oldest first.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<AuditRecord>> {
+        self.records.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        self.lock().push(record.clone());
+    }
+}
+
+/// An [`AuditSink`] appending records to a file as JSON lines, available with the `audit` feature.
+/// 
+/// Each line carries the SHA-256 hash of the previous one in its `prev` field, so removing or
+/// altering a line breaks the chain, which [`JsonLinesSink::verify`] detects:
+/// 
+/// ```text
+/// {"event":"token_issued","timestamp":1641635607000,"fingerprint":"…","account_id":"326359466171826176","prev":"…"}
+/// {"event":"token_rejected","timestamp":1641635608000,"fingerprint":"…","reason":"expired","prev":"…"}
+/// ```
+/// 
+/// Write errors are ignored, so a full disk doesn't prevent validating tokens.
+#[cfg(feature = "audit")]
+pub struct JsonLinesSink {
+    file: Mutex<(File, String)>
+}
+
+#[cfg(feature = "audit")]
+impl JsonLinesSink {
+    /// Opens `path` for appending, creating it if needed. The chain continues from the last line of
+    /// an existing file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<JsonLinesSink> {
+        let file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+        let mut prev = GENESIS.to_string();
+        for line in BufReader::new(&file).lines() {
+            prev = hash(&line?);
+        }
+
+        Ok(JsonLinesSink { file: Mutex::new((file, prev)) })
+    }
+
+    /// Checks the chain of the file at `path`, returning the number of the first line (starting at 1)
+    /// which doesn't carry the hash of the previous one, if any.
+    pub fn verify<P: AsRef<Path>>(path: P) -> io::Result<Option<usize>> {
+        let mut prev = GENESIS.to_string();
+        for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let chained = serde_json::from_str::<serde_json::Value>(&line).ok()
+                .is_some_and(|record| record["prev"].as_str() == Some(&prev));
+            if !chained {
+                return Ok(Some(number + 1))
+            }
+
+            prev = hash(&line);
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "audit")]
+impl AuditSink for JsonLinesSink {
+    fn record(&self, record: &AuditRecord) {
+        let mut line = serde_json::Map::new();
+        line.insert("event".to_string(), record.event.name().into());
+        line.insert("timestamp".to_string(), record.timestamp.into());
+        line.insert("fingerprint".to_string(), record.fingerprint.clone().into());
+        match &record.event {
+            AuditEvent::TokenIssued { account_id } => { line.insert("account_id".to_string(), account_id.to_string().into()); },
+            AuditEvent::TokenRejected { reason } => { line.insert("reason".to_string(), reason.kind().into()); },
+            AuditEvent::TokenValidated | AuditEvent::TokenRevoked => {}
+        }
+
+        if !record.context.is_empty() {
+            let context = record.context.iter().map(|(key, value)| (key.clone(), value.clone().into())).collect::<serde_json::Map<_, _>>();
+            line.insert("context".to_string(), context.into());
+        }
+
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        line.insert("prev".to_string(), file.1.clone().into());
+        let line = serde_json::Value::Object(line).to_string();
+
+        if writeln!(file.0, "{}", line).is_ok() {
+            file.1 = hash(&line);
+        }
+    }
+}
+
+/// The `prev` hash of the first line.
+#[cfg(feature = "audit")]
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[cfg(feature = "audit")]
+fn hash(line: &str) -> String {
+    crate::crypto::sha256(line.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl Tokenize {
+    /// Sets the sink the audit trail is sent to.
+    pub fn set_audit_sink<S: AuditSink + 'static>(mut self, sink: S) -> Self {
+        self.audit_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Sends `event` about the token whose signature is `signature` to the audit sink.
+    pub(crate) fn audit(&self, event: AuditEvent, signature: &str) {
+        self.audit_in(event, signature, None)
+    }
+
+    /// Sends `event` to the audit sink along with the context of the request, if there's one.
+    fn audit_in(&self, event: AuditEvent, signature: &str, context: Option<&ValidationContext>) {
+        if let Some(sink) = &self.audit_sink {
+            sink.record(&AuditRecord {
+                event,
+                timestamp: self.clock.now_millis(),
+                fingerprint: token::signature_hash(signature),
+                context: context.cloned().unwrap_or_default()
+            });
+        }
+    }
+
+    /// Sends the outcome of the validation of `token` to the audit sink, along with the context of
+    /// the request if there's one.
+    pub(crate) fn audit_validation<T>(&self, token: &str, outcome: &Result<T, TokenizeError>, context: Option<&ValidationContext>) {
+        if self.audit_sink.is_some() {
+            let signature = token::split_key_id(token.rsplit('.').next().unwrap_or_default()).1;
+            let event = match outcome {
+                Ok(_) => AuditEvent::TokenValidated,
+                Err(reason) => AuditEvent::TokenRejected { reason: reason.clone() }
+            };
+
+            self.audit_in(event, signature, context);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "audit"))]
+mod tests {
+    use std::fs;
+    use crate::{AccountId, TokenizeError, ValidationContext};
+    use super::{AuditEvent, AuditRecord, AuditSink, JsonLinesSink};
+
+    #[test]
+    fn chain_json_lines() {
+        let path = std::env::temp_dir().join(format!("tokenize-audit-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let record = |event| AuditRecord { event, timestamp: 1641635607000, fingerprint: "00ff".to_string(), context: ValidationContext::new() };
+
+        let sink = JsonLinesSink::open(&path).expect("Couldn't open audit log");
+        sink.record(&record(AuditEvent::TokenIssued { account_id: AccountId::from("326359466171826176") }));
+        sink.record(&record(AuditEvent::TokenRejected { reason: TokenizeError::Expired }));
+        drop(sink);
+
+        // The chain continues across reopenings
+        let sink = JsonLinesSink::open(&path).expect("Couldn't open audit log");
+        sink.record(&AuditRecord { context: ValidationContext::new().with("peer", "203.0.113.7"), ..record(AuditEvent::TokenRevoked) });
+        drop(sink);
+        assert_eq!(JsonLinesSink::verify(&path).unwrap(), None);
+
+        let log = fs::read_to_string(&path).unwrap();
+        let lines = log.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains(r#""account_id":"326359466171826176""#));
+        assert!(lines[1].contains(r#""reason":"expired""#));
+        assert!(lines[2].contains(r#""context":{"peer":"203.0.113.7"}"#));
+
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert_eq!(JsonLinesSink::verify(&path).unwrap(), Some(2));
+        fs::remove_file(&path).unwrap();
+    }
+}
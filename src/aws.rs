@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Loading of secrets from [AWS Secrets Manager](https://aws.amazon.com/secrets-manager/).
+
+use chrono::Utc;
+use crate::keyloading::KeySource;
+use crate::{crypto, Encoding, SecretKey, StoreError};
+
+/// Credentials used to sign requests to AWS.
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>
+}
+
+impl AwsCredentials {
+    /// Reads the credentials from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN` environment variables. Returns `None` if the key id or the secret key is
+    /// missing.
+    pub fn from_env() -> Option<AwsCredentials> {
+        Some(AwsCredentials {
+            access_key_id: std::env::var("AWS_ACCESS_KEY_ID").ok()?,
+            secret_access_key: std::env::var("AWS_SECRET_ACCESS_KEY").ok()?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok()
+        })
+    }
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`KeySource`] reading a secret from AWS Secrets Manager.
+/// 
+/// Text secrets are decoded with [`SecretKey::decode`], binary secrets are used as-is. Secrets
+/// holding a JSON object can be read from one of its fields with [`AwsKeySource::field`].
+/// 
+/// # Examples
+/// 
+/// ```no_run
+/// use tokenize::Tokenize;
+/// use tokenize::aws::{AwsCredentials, AwsKeySource};
+/// use tokenize::keyloading::KeySource;
+/// 
+/// let credentials = AwsCredentials::from_env().expect("Missing AWS credentials");
+/// let source = AwsKeySource::new("eu-west-1", "prod/tokenize", credentials);
+/// let tokenize = Tokenize::new(source.load().expect("Couldn't load secret"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AwsKeySource {
+    region: String,
+    secret_id: String,
+    field: Option<String>,
+    credentials: AwsCredentials
+}
+
+impl AwsKeySource {
+    pub fn new<R, S>(region: R, secret_id: S, credentials: AwsCredentials) -> AwsKeySource where
+        R: Into<String>,
+        S: Into<String> {
+        AwsKeySource {
+            region: region.into(),
+            secret_id: secret_id.into(),
+            field: None,
+            credentials
+        }
+    }
+
+    /// Reads the secret from a field of the JSON object stored in the secret.
+    pub fn field<S: Into<String>>(mut self, field: S) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+}
+
+impl KeySource for AwsKeySource {
+    fn load(&self) -> Result<SecretKey, StoreError> {
+        let host = format!("secretsmanager.{}.amazonaws.com", self.region);
+        let payload = serde_json::json!({ "SecretId": self.secret_id }).to_string();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1"),
+            ("host", host.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+            ("x-amz-target", "secretsmanager.GetSecretValue")
+        ];
+        if let Some(session_token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", session_token));
+        }
+
+        let authorization = authorization(&self.credentials, &self.region, "secretsmanager", "POST", "/", &headers, payload.as_bytes(), &amz_date);
+        let mut request = ::ureq::post(&format!("https://{}/", host));
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+
+        let response = request.set("authorization", &authorization)
+            .send_string(&payload)
+            .map_err(|err| StoreError::new(format_args!("Couldn't read secret from AWS Secrets Manager: {}", err)))?
+            .into_string()
+            .map_err(StoreError::new)?;
+
+        secret_from_response(&response, self.field.as_deref())
+    }
+}
+
+/// Extracts the secret from the body of a `GetSecretValue` response.
+fn secret_from_response(response: &str, field: Option<&str>) -> Result<SecretKey, StoreError> {
+    let response = serde_json::from_str::<serde_json::Value>(response).map_err(StoreError::new)?;
+    if let Some(binary) = response["SecretBinary"].as_str() {
+        return Encoding::Standard.decode(binary).map(SecretKey::new).map_err(StoreError::new)
+    }
+
+    let secret = response["SecretString"].as_str().ok_or_else(|| StoreError::new("Secret has no value"))?;
+    let value = match field {
+        Some(field) => serde_json::from_str::<serde_json::Value>(secret).map_err(StoreError::new)?[field].as_str()
+            .ok_or_else(|| StoreError::new(format_args!("Secret has no {} field", field)))?
+            .to_string(),
+        None => secret.to_string()
+    };
+
+    SecretKey::decode(&value).map_err(StoreError::new)
+}
+
+/// Computes the `Authorization` header of a request signed with AWS Signature Version 4.
+/// 
+/// `headers` must be sorted by their lowercase name, and include every signed header.
+#[allow(clippy::too_many_arguments)]
+fn authorization(credentials: &AwsCredentials, region: &str, service: &str, method: &str, path: &str, headers: &[(&str, &str)], payload: &[u8], amz_date: &str) -> String {
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join(";");
+    let canonical_headers = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect::<String>();
+
+    let canonical_request = format!("{}\n{}\n\n{}\n{}\n{}", method, path, canonical_headers, signed_headers, hex(&crypto::sha256(payload)));
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex(&crypto::sha256(canonical_request.as_bytes())));
+
+    let key = [date, region, service, "aws4_request"].iter().fold(
+        format!("AWS4{}", credentials.secret_access_key).into_bytes(),
+        |key, part| crypto::hmac_sha256(&key, part.as_bytes()).to_vec()
+    );
+    let signature = hex(&crypto::hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", credentials.access_key_id, scope, signed_headers, signature)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_request() {
+        // The get-vanilla case of the AWS Signature Version 4 test suite
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None
+        };
+        let headers = [("host", "example.amazonaws.com"), ("x-amz-date", "20150830T123600Z")];
+
+        assert_eq!(
+            authorization(&credentials, "us-east-1", "service", "GET", "/", &headers, b"", "20150830T123600Z"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn secret_from_response() {
+        assert_eq!(super::secret_from_response(r#"{"SecretString": "base64:dXd1"}"#, None), Ok(SecretKey::from("uwu")));
+        assert_eq!(super::secret_from_response(r#"{"SecretString": "{\"key\": \"uwu\"}"}"#, Some("key")), Ok(SecretKey::from("uwu")));
+        assert_eq!(super::secret_from_response(r#"{"SecretBinary": "dXd1"}"#, None), Ok(SecretKey::from("uwu")));
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! [axum] integration, available with the `axum` feature.
+//! 
+//! [axum]: https://docs.rs/axum
+
+use std::future::Future;
+use ::axum::extract::FromRequestParts;
+use ::axum::http::{header, request::Parts, StatusCode};
+use ::axum::response::{IntoResponse, Response};
+use crate::{extract, Account, Tokenize, TokenizeError};
+
+/// Application state giving [`TokenizeAuth`] access to the validator and the accounts.
+pub trait AuthState<A>: Send + Sync {
+    /// The instance tokens are validated with.
+    fn tokenize(&self) -> &Tokenize;
+
+    /// Fetches the account tied to `id`, if there's one.
+    fn fetch_account(&self, id: String) -> impl Future<Output = Option<A>> + Send;
+}
+
+/// Extractor validating the token from the `Authorization` header and yielding the account it belongs to.
+/// 
+/// Both raw tokens and `Bearer` tokens are accepted. Requests without a valid token are rejected with
+/// a `401 Unauthorized` response.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use axum::{routing::get, Router};
+/// use tokenize::{Account, Tokenize};
+/// use tokenize::axum::{AuthState, TokenizeAuth};
+/// 
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// struct State { tokenize: Tokenize }
+/// 
+/// impl AuthState<User> for State {
+///     fn tokenize(&self) -> &Tokenize {
+///         &self.tokenize
+///     }
+/// 
+///     async fn fetch_account(&self, _id: String) -> Option<User> {
+///         Some(User { last_token_reset: 0 }) // query your database here
+///     }
+/// }
+/// 
+/// async fn me(TokenizeAuth(user): TokenizeAuth<User>) -> String {
+///     user.last_token_reset.to_string()
+/// }
+/// 
+/// let app: Router<std::sync::Arc<State>> = Router::new().route("/me", get(me));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TokenizeAuth<A>(pub A);
+
+impl<S, A> FromRequestParts<S> for TokenizeAuth<A> where
+    S: AuthState<A>,
+    A: Account + Send {
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let token = parts.headers.get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(extract::bearer)
+            .ok_or(AuthRejection::MissingToken)?;
+
+        let account = state.tokenize()
+            .validate_async(token, |id| state.fetch_account(id))
+            .await
+            .map_err(AuthRejection::InvalidToken)?;
+
+        Ok(TokenizeAuth(account))
+    }
+}
+
+impl<A, T> AuthState<A> for std::sync::Arc<T> where
+    T: AuthState<A>,
+    A: Send {
+    fn tokenize(&self) -> &Tokenize {
+        (**self).tokenize()
+    }
+
+    fn fetch_account(&self, id: String) -> impl Future<Output = Option<A>> + Send {
+        (**self).fetch_account(id)
+    }
+}
+
+/// Rejection returned by [`TokenizeAuth`], turned into a `401 Unauthorized` response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthRejection {
+    /// The request has no `Authorization` header.
+    MissingToken,
+    /// The token couldn't be validated.
+    InvalidToken(TokenizeError)
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let message = match self {
+            AuthRejection::MissingToken => "Missing token".to_string(),
+            AuthRejection::InvalidToken(err) => err.to_string()
+        };
+
+        (StatusCode::UNAUTHORIZED, message).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use ::axum::{body::Body, http::{Request, StatusCode}, routing::get, Router};
+    use tower::ServiceExt;
+    use super::{AuthState, TokenizeAuth};
+    use crate::{Account, Tokenize};
+
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    struct TestState {
+        tokenize: Tokenize
+    }
+
+    impl AuthState<TestAccount> for TestState {
+        fn tokenize(&self) -> &Tokenize {
+            &self.tokenize
+        }
+
+        async fn fetch_account(&self, id: String) -> Option<TestAccount> {
+            (id == "326359466171826176").then_some(TestAccount { last_token_reset: 0 })
+        }
+    }
+
+    async fn handler(TokenizeAuth(account): TokenizeAuth<TestAccount>) -> String {
+        account.last_token_reset.to_string()
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(handler))
+            .with_state(Arc::new(TestState { tokenize: Tokenize::new("uwu") }))
+    }
+
+    async fn status(authorization: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().uri("/");
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+
+        app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn extract_account() {
+        let token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+        assert_eq!(status(Some(token)).await, StatusCode::OK);
+        assert_eq!(status(Some(&format!("Bearer {}", token))).await, StatusCode::OK);
+        assert_eq!(status(Some("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Command line tool to generate, inspect and verify tokens.
+//! 
+//! Secrets are read from `--secret` or the `TOKENIZE_SECRET` environment variable, and can be given
+//! as raw text, `base64:...` or `hex:...`.
+
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+use std::time::Duration;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use tokenize::store::MemoryAccount;
+use tokenize::{Encoding, SecretKey, Token, TokenVersion, Tokenize, ValidationOptions, TOKENIZE_EPOCH};
+
+#[derive(Parser)]
+#[command(name = "tokenize", version, about = "Generate, inspect and verify Tokenize tokens")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generates a new token for an account
+    Generate {
+        /// The id of the account the token is issued for
+        account_id: String,
+        #[command(flatten)]
+        config: Config,
+        /// Generates a version 2 token
+        #[arg(long)]
+        v2: bool,
+        /// Scopes carried by the token, separated by commas
+        #[arg(long, value_delimiter = ',', conflicts_with = "single_use")]
+        scopes: Vec<String>,
+        /// Generates a token that can only be validated once
+        #[arg(long)]
+        single_use: bool
+    },
+    /// Decodes the segments of a token, without verifying its signature
+    Inspect {
+        /// The token to inspect, read from stdin if omitted
+        token: Option<String>,
+        /// The epoch the token was issued with, in milliseconds
+        #[arg(long, default_value_t = TOKENIZE_EPOCH)]
+        epoch: i64
+    },
+    /// Verifies the signature and age of a token
+    Verify {
+        /// The token to verify, read from stdin if omitted
+        token: Option<String>,
+        #[command(flatten)]
+        config: Config,
+        /// Maximum age of the token, in seconds
+        #[arg(long)]
+        max_age: Option<u64>
+    }
+}
+
+#[derive(Args)]
+struct Config {
+    /// The secret tokens are signed with
+    #[arg(long, env = "TOKENIZE_SECRET", hide_env_values = true)]
+    secret: String,
+    /// The prefix of the tokens
+    #[arg(long)]
+    prefix: Option<String>,
+    /// The epoch tokens are issued with, in milliseconds
+    #[arg(long, default_value_t = TOKENIZE_EPOCH)]
+    epoch: i64,
+    /// The base64 alphabet tokens are encoded with
+    #[arg(long, value_enum, default_value_t = EncodingArg::Standard)]
+    encoding: EncodingArg
+}
+
+impl Config {
+    fn tokenize(&self) -> Result<Tokenize, String> {
+        let secret = SecretKey::decode(&self.secret).map_err(|err| err.to_string())?;
+        let mut tokenize = Tokenize::new(secret)
+            .set_epoch(self.epoch)
+            .set_encoding(self.encoding.into());
+
+        if let Some(prefix) = &self.prefix {
+            tokenize = tokenize.try_set_prefix(prefix).map_err(|err| err.to_string())?;
+        }
+
+        Ok(tokenize)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EncodingArg {
+    Standard,
+    UrlSafe
+}
+
+impl From<EncodingArg> for Encoding {
+    fn from(encoding: EncodingArg) -> Self {
+        match encoding {
+            EncodingArg::Standard => Encoding::Standard,
+            EncodingArg::UrlSafe => Encoding::UrlSafe
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        },
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<String, String> {
+    match command {
+        Command::Generate { account_id, config, v2, scopes, single_use } => {
+            let mut tokenize = config.tokenize()?;
+            if v2 {
+                tokenize = tokenize.set_version(TokenVersion::V2);
+            }
+
+            let token = if single_use {
+                tokenize.generate_single_use(account_id)
+            } else if !scopes.is_empty() {
+                tokenize.generate_with_scopes(account_id, scopes)
+            } else {
+                tokenize.generate(account_id)
+            };
+
+            token.map_err(|err| err.to_string())
+        },
+        Command::Inspect { token, epoch } => {
+            let token = read_token(token)?.parse::<Token>().map_err(|err| err.to_string())?;
+            Ok(describe(&token.set_epoch(epoch)))
+        },
+        Command::Verify { token, config, max_age } => {
+            let mut options = ValidationOptions::new();
+            if let Some(max_age) = max_age {
+                options = options.max_age(Duration::from_secs(max_age));
+            }
+
+            let tokenize = config.tokenize()?
+                .accept_any_encoding()
+                .set_validation_options(options);
+            let (token, _) = tokenize.validate_token(read_token(token)?, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 }))
+                .map_err(|err| err.to_string())?;
+
+            Ok(format!("{}\nvalid:       true", describe(&token)))
+        }
+    }
+}
+
+/// Returns `token`, or the first line of stdin if it's missing.
+fn read_token(token: Option<String>) -> Result<String, String> {
+    match token {
+        Some(token) => Ok(token),
+        None => match io::stdin().lock().lines().next() {
+            Some(line) => line.map(|line| line.trim().to_string()).map_err(|err| err.to_string()),
+            None => Err("No token given".to_string())
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    let mut lines = vec![
+        format!("version:     {}", token.version().number()),
+        format!("prefix:      {}", token.prefix().unwrap_or("-")),
+        format!("account id:  {}", token.account_id()),
+        format!("issued at:   {} ({})", token.issued_at().to_rfc3339(), token.issued_at_millis()),
+        format!("key id:      {}", token.key_id().unwrap_or("-")),
+        format!("encoding:    {:?}", token.encoding())
+    ];
+
+    if !token.scopes().is_empty() {
+        lines.push(format!("scopes:      {}", token.scopes().join(",")));
+    }
+
+    if let Some(claims) = token.raw_claims() {
+        lines.push(format!("claims:      {}", claims));
+    }
+
+    lines.push(format!("single use:  {}", token.is_single_use()));
+    lines.push(format!("signature:   {}", token.signature()));
+    lines.join("\n")
+}
@@ -0,0 +1,267 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use crate::{AuditSink, Clock, ConfigError, Encoding, FailurePolicy, Keyring, MetricsSink, RevocationStore, SecretKey, SignatureFormat, UsageStore, Signer, TimestampPrecision, Tokenize, TokenVersion, ValidationOptions};
+
+/// Builder for [`Tokenize`] instances, validating the configuration before building.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Encoding, Tokenize};
+/// 
+/// let tokenize = Tokenize::builder()
+///     .secret("uwu")
+///     .prefix("bot")
+///     .encoding(Encoding::UrlSafe)
+///     .build()
+///     .expect("Invalid configuration");
+/// ```
+#[derive(Default)]
+pub struct TokenizeBuilder {
+    secret: Option<SecretKey>,
+    old_secrets: Vec<SecretKey>,
+    keyring: Option<Keyring>,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<SecretKey>,
+    prefixes: Vec<String>,
+    audience: Option<String>,
+    version: TokenVersion,
+    precision: TimestampPrecision,
+    encoding: Encoding,
+    accept_any_encoding: bool,
+    signature_format: SignatureFormat,
+    accept_any_signature_format: bool,
+    unique_tokens: bool,
+    signer: Option<Box<dyn Signer>>,
+    clock: Option<Box<dyn Clock>>,
+    revocation_store: Option<Box<dyn RevocationStore>>,
+    usage_store: Option<Box<dyn UsageStore>>,
+    metrics: Option<Box<dyn MetricsSink>>,
+    failure_policy: Option<Box<dyn FailurePolicy>>,
+    audit_sink: Option<Box<dyn AuditSink>>,
+    epoch: Option<i64>,
+    options: ValidationOptions
+}
+
+impl TokenizeBuilder {
+    pub fn new() -> TokenizeBuilder {
+        TokenizeBuilder::default()
+    }
+
+    /// Sets the secret used to sign and verify tokens.
+    pub fn secret<K: Into<SecretKey>>(mut self, secret: K) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Adds a secret only used to verify tokens. See [`Tokenize::with_secrets`].
+    pub fn old_secret<K: Into<SecretKey>>(mut self, secret: K) -> Self {
+        self.old_secrets.push(secret.into());
+        self
+    }
+
+    /// Uses a keyring instead of a single secret. See [`Tokenize::with_keyring`].
+    pub fn keyring(mut self, keyring: Keyring) -> Self {
+        self.keyring = Some(keyring);
+        self
+    }
+
+    /// Sets the key account ids are encrypted with. See [`Tokenize::set_encryption_key`].
+    #[cfg(feature = "encryption")]
+    pub fn encryption_key<K: Into<SecretKey>>(mut self, key: K) -> Self {
+        self.encryption_key = Some(key.into());
+        self
+    }
+
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefixes = vec![prefix.into()];
+        self
+    }
+
+    /// Accepts several prefixes. See [`Tokenize::set_prefixes`].
+    pub fn prefixes<I>(mut self, prefixes: I) -> Self where
+        I: IntoIterator,
+        I::Item: Into<String> {
+        self.prefixes = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the audience tokens are issued for. See [`Tokenize::set_audience`].
+    pub fn audience<S: Into<String>>(mut self, audience: S) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    pub fn version(mut self, version: TokenVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn accept_any_encoding(mut self) -> Self {
+        self.accept_any_encoding = true;
+        self
+    }
+
+    pub fn signature_format(mut self, format: SignatureFormat) -> Self {
+        self.signature_format = format;
+        self
+    }
+
+    pub fn accept_any_signature_format(mut self) -> Self {
+        self.accept_any_signature_format = true;
+        self
+    }
+
+    pub fn unique_tokens(mut self) -> Self {
+        self.unique_tokens = true;
+        self
+    }
+
+    pub fn signer<S: Signer + 'static>(mut self, signer: S) -> Self {
+        self.signer = Some(Box::new(signer));
+        self
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    pub fn revocation_store<R: RevocationStore + 'static>(mut self, store: R) -> Self {
+        self.revocation_store = Some(Box::new(store));
+        self
+    }
+
+    pub fn usage_store<U: UsageStore + 'static>(mut self, store: U) -> Self {
+        self.usage_store = Some(Box::new(store));
+        self
+    }
+
+    pub fn metrics_sink<M: MetricsSink + 'static>(mut self, sink: M) -> Self {
+        self.metrics = Some(Box::new(sink));
+        self
+    }
+
+    /// Sets the policy throttling peers presenting forged tokens. See [`Tokenize::set_failure_policy`].
+    pub fn failure_policy<P: FailurePolicy + 'static>(mut self, policy: P) -> Self {
+        self.failure_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Sets the sink the audit trail is sent to. See [`Tokenize::set_audit_sink`].
+    pub fn audit_sink<S: AuditSink + 'static>(mut self, sink: S) -> Self {
+        self.audit_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Sets the epoch token timestamps are relative to. See [`Tokenize::set_epoch`].
+    pub fn epoch(mut self, epoch: i64) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Validates the configuration and builds the [`Tokenize`] instance.
+    pub fn build(self) -> Result<Tokenize, ConfigError> {
+        let mut tokenize = match (self.secret, self.keyring) {
+            (Some(secret), None) => {
+                if secret.is_empty() || self.old_secrets.iter().any(SecretKey::is_empty) {
+                    return Err(ConfigError::EmptySecret)
+                }
+
+                Tokenize::with_secrets(secret, self.old_secrets)
+            },
+            (None, Some(keyring)) => {
+                if keyring.has_empty_key() || !self.old_secrets.is_empty() {
+                    return Err(ConfigError::EmptySecret)
+                }
+
+                Tokenize::with_keyring(keyring)
+            },
+            (Some(_), Some(_)) => return Err(ConfigError::ConflictingSecrets),
+            (None, None) => return Err(ConfigError::MissingSecret)
+        };
+
+        #[cfg(feature = "encryption")]
+        if let Some(key) = self.encryption_key {
+            if key.is_empty() {
+                return Err(ConfigError::EmptySecret)
+            }
+
+            tokenize = tokenize.set_encryption_key(key);
+        }
+
+        if !self.prefixes.is_empty() {
+            tokenize = tokenize.try_set_prefixes(self.prefixes)?;
+        }
+
+        if let Some(signer) = self.signer {
+            tokenize.signer = signer;
+        }
+
+        if let Some(clock) = self.clock {
+            tokenize.clock = clock;
+        }
+
+        if let Some(epoch) = self.epoch {
+            tokenize.epoch = epoch;
+        }
+
+        tokenize.audience = self.audience;
+        tokenize.revocation_store = self.revocation_store;
+        tokenize.usage_store = self.usage_store;
+        tokenize.metrics = self.metrics;
+        tokenize.failure_policy = self.failure_policy;
+        tokenize.audit_sink = self.audit_sink;
+        tokenize.version = self.version;
+        tokenize.precision = self.precision;
+        tokenize.encoding = self.encoding;
+        tokenize.accept_any_encoding = self.accept_any_encoding;
+        tokenize.signature_format = self.signature_format;
+        tokenize.accept_any_signature_format = self.accept_any_signature_format;
+        tokenize.unique_tokens = self.unique_tokens;
+        tokenize.options = self.options;
+
+        Ok(tokenize)
+    }
+}
@@ -0,0 +1,261 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Caching of validation results.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use crate::{crypto, Account, Token};
+
+/// An LRU cache of validated tokens, letting hot tokens skip signature verification and the account
+/// fetch.
+/// 
+/// Caches are used through [`Tokenize::validate_cached`](crate::Tokenize::validate_cached), and must
+/// only be used with a single [`Tokenize`](crate::Tokenize) instance. Tokens are keyed by their
+/// SHA-256 hash, the tokens themselves are never stored.
+/// 
+/// Each entry remembers the [`Account::last_token_reset`] it was validated against. Once the cache
+/// sees a fresh copy of an account with a different reset, every token cached for it is evicted,
+/// otherwise cached accounts can be stale for up to the cache's TTL. Call
+/// [`ValidationCache::invalidate_account`] when resetting the tokens of an account so they're
+/// rejected right away. The age and revocation of tokens are checked on every hit.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::time::Duration;
+/// use tokenize::Tokenize;
+/// use tokenize::cache::ValidationCache;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let cache = ValidationCache::new(10_000, Duration::from_secs(60));
+/// let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+/// 
+/// for _ in 0..3 {
+///     tokenize.validate_cached(&*token, &cache, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 }))
+///         .expect("Couldn't validate token");
+/// }
+/// 
+/// assert_eq!((cache.hits(), cache.misses()), (2, 1));
+/// ```
+#[derive(Debug)]
+pub struct ValidationCache<A> {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<CacheState<A>>,
+    hits: AtomicU64,
+    misses: AtomicU64
+}
+
+#[derive(Debug)]
+struct CacheState<A> {
+    entries: HashMap<[u8; 32], Entry<A>>,
+    /// Keys of the entries, from the least to the most recently used.
+    recency: BTreeMap<u64, [u8; 32]>,
+    /// The cached tokens of each account, along with the last token reset they were validated against.
+    accounts: HashMap<String, AccountEntries>,
+    tick: u64
+}
+
+#[derive(Debug)]
+struct AccountEntries {
+    last_token_reset: u64,
+    keys: HashSet<[u8; 32]>
+}
+
+#[derive(Debug)]
+struct Entry<A> {
+    account: A,
+    account_id: String,
+    issued_at_millis: i64,
+    expires_at_millis: Option<i64>,
+    revocation_key: String,
+    cached_at: i64,
+    tick: u64
+}
+
+/// A cached validation result.
+pub(crate) struct Hit<A> {
+    pub(crate) account: A,
+    pub(crate) issued_at_millis: i64,
+    pub(crate) expires_at_millis: Option<i64>,
+    pub(crate) revocation_key: String
+}
+
+impl<A: Account + Clone> ValidationCache<A> {
+    /// Looks up a token, counting the hit or miss. Entries older than the TTL are evicted.
+    pub(crate) fn get(&self, key: &[u8; 32], now: i64) -> Option<Hit<A>> {
+        let mut state = self.lock();
+        let tick = state.next_tick();
+        let hit = match state.entries.get_mut(key) {
+            Some(entry) if now.saturating_sub(entry.cached_at) as u128 <= self.ttl.as_millis() => {
+                let previous = std::mem::replace(&mut entry.tick, tick);
+                let hit = Hit {
+                    account: entry.account.clone(),
+                    issued_at_millis: entry.issued_at_millis,
+                    expires_at_millis: entry.expires_at_millis,
+                    revocation_key: entry.revocation_key.clone()
+                };
+                state.recency.remove(&previous);
+                state.recency.insert(tick, *key);
+
+                Some(hit)
+            },
+            Some(_) => {
+                state.remove(key);
+                None
+            },
+            None => None
+        };
+
+        let counter = if hit.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        hit
+    }
+
+    /// Caches a validated token, evicting the least recently used one if the cache is full.
+    /// 
+    /// The tokens of the account cached with another [`Account::last_token_reset`] are evicted too.
+    pub(crate) fn insert(&self, key: [u8; 32], token: &Token, account: A, now: i64) {
+        if self.capacity == 0 {
+            return
+        }
+
+        let account_id = token.account_id().to_string();
+        let last_token_reset = account.last_token_reset();
+        let mut state = self.lock();
+        state.remove(&key);
+        if state.accounts.get(&account_id).is_some_and(|entries| entries.last_token_reset != last_token_reset) {
+            state.remove_account(&account_id);
+        }
+
+        if state.entries.len() >= self.capacity {
+            if let Some(&oldest) = state.recency.values().next() {
+                state.remove(&oldest);
+            }
+        }
+
+        let tick = state.next_tick();
+        state.recency.insert(tick, key);
+        state.accounts.entry(account_id.clone())
+            .or_insert_with(|| AccountEntries { last_token_reset, keys: HashSet::new() })
+            .keys.insert(key);
+        state.entries.insert(key, Entry {
+            account,
+            account_id,
+            issued_at_millis: token.issued_at_millis(),
+            expires_at_millis: token.expires_at_millis(),
+            revocation_key: token.revocation_key(),
+            cached_at: now,
+            tick
+        });
+    }
+}
+
+impl<A> ValidationCache<A> {
+    /// Creates a cache holding up to `capacity` tokens, each for up to `ttl`.
+    pub fn new(capacity: usize, ttl: Duration) -> ValidationCache<A> {
+        ValidationCache {
+            capacity,
+            ttl,
+            state: Mutex::new(CacheState { entries: HashMap::new(), recency: BTreeMap::new(), accounts: HashMap::new(), tick: 0 }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0)
+        }
+    }
+
+    /// Evicts every token of an account, for instance after resetting its tokens.
+    pub fn invalidate_account(&self, id: &str) {
+        self.lock().remove_account(id);
+    }
+
+    /// Evicts every token.
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.recency.clear();
+        state.accounts.clear();
+    }
+
+    /// The number of lookups which found a cached token.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of lookups which didn't find a cached token.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState<A>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<A> CacheState<A> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn remove(&mut self, key: &[u8; 32]) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.tick);
+            if let Some(entries) = self.accounts.get_mut(&entry.account_id) {
+                entries.keys.remove(key);
+                if entries.keys.is_empty() {
+                    self.accounts.remove(&entry.account_id);
+                }
+            }
+        }
+    }
+
+    /// Evicts every token of an account.
+    fn remove_account(&mut self, id: &str) {
+        for key in self.accounts.remove(id).map(|entries| entries.keys).unwrap_or_default() {
+            if let Some(entry) = self.entries.remove(&key) {
+                self.recency.remove(&entry.tick);
+            }
+        }
+    }
+}
+
+/// The key a token is cached under.
+pub(crate) fn key(token: &str) -> [u8; 32] {
+    crypto::sha256(token.as_bytes())
+}
@@ -0,0 +1,222 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::time::Duration;
+use crate::{ConfigError, Encoding, SecretKey, TimestampPrecision, Tokenize, TokenizeBuilder, TokenVersion, ValidationOptions};
+
+/// A configuration which can be built at compile time and held in a `static`, from which a global
+/// [`Tokenize`] instance is lazily created.
+/// 
+/// [`Tokenize`] is `Send + Sync`, so a single instance can be shared by every thread and task of an
+/// async runtime without locking.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::sync::LazyLock;
+/// use std::time::Duration;
+/// use tokenize::{StaticConfig, Tokenize};
+/// 
+/// static CONFIG: StaticConfig = StaticConfig::from_env("TOKENIZE_SECRET")
+///     .prefix("api")
+///     .max_age(Duration::from_secs(3600));
+/// 
+/// static TOKENIZE: LazyLock<Tokenize> = LazyLock::new(|| CONFIG.build().expect("Invalid configuration"));
+/// 
+/// # std::env::set_var("TOKENIZE_SECRET", "dXd1");
+/// let token = TOKENIZE.generate("326359466171826176").expect("Couldn't generate new token");
+/// assert!(token.starts_with("api."));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticConfig {
+    secret: StaticSecret,
+    prefix: Option<&'static str>,
+    audience: Option<&'static str>,
+    version: TokenVersion,
+    precision: TimestampPrecision,
+    encoding: Encoding,
+    epoch: Option<i64>,
+    max_age: Option<Duration>,
+    max_clock_skew: Option<Duration>,
+    expected_audience: Option<&'static str>,
+    strict: bool,
+    hardened: bool
+}
+
+/// Where the secret of a [`StaticConfig`] comes from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StaticSecret {
+    Env(&'static str),
+    Bytes(&'static [u8])
+}
+
+impl std::fmt::Debug for StaticSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StaticSecret::Env(var) => f.debug_tuple("Env").field(var).finish(),
+            StaticSecret::Bytes(_) => f.write_str(crate::token::REDACTED)
+        }
+    }
+}
+
+impl StaticConfig {
+    /// A configuration reading the secret from an environment variable when built. See
+    /// [`SecretKey::from_env`].
+    pub const fn from_env(var: &'static str) -> StaticConfig {
+        StaticConfig::with_secret(StaticSecret::Env(var))
+    }
+
+    /// A configuration with a secret embedded in the binary, mostly useful in tests.
+    pub const fn from_secret(secret: &'static [u8]) -> StaticConfig {
+        StaticConfig::with_secret(StaticSecret::Bytes(secret))
+    }
+
+    const fn with_secret(secret: StaticSecret) -> StaticConfig {
+        StaticConfig {
+            secret,
+            prefix: None,
+            audience: None,
+            version: TokenVersion::V1,
+            precision: TimestampPrecision::Seconds,
+            encoding: Encoding::Standard,
+            epoch: None,
+            max_age: None,
+            max_clock_skew: None,
+            expected_audience: None,
+            strict: false,
+            hardened: false
+        }
+    }
+
+    /// See [`Tokenize::set_prefix`].
+    pub const fn prefix(mut self, prefix: &'static str) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// See [`Tokenize::set_audience`].
+    pub const fn audience(mut self, audience: &'static str) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+
+    /// See [`Tokenize::set_version`].
+    pub const fn version(mut self, version: TokenVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// See [`Tokenize::set_precision`].
+    pub const fn precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// See [`Tokenize::set_encoding`].
+    pub const fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// See [`Tokenize::set_epoch`].
+    pub const fn epoch(mut self, epoch: i64) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// See [`ValidationOptions::max_age`].
+    pub const fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// See [`ValidationOptions::max_clock_skew`].
+    pub const fn max_clock_skew(mut self, max_clock_skew: Duration) -> Self {
+        self.max_clock_skew = Some(max_clock_skew);
+        self
+    }
+
+    /// See [`ValidationOptions::expected_audience`].
+    pub const fn expected_audience(mut self, audience: &'static str) -> Self {
+        self.expected_audience = Some(audience);
+        self
+    }
+
+    /// See [`ValidationOptions::strict`].
+    pub const fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// See [`ValidationOptions::hardened`].
+    pub const fn hardened(mut self) -> Self {
+        self.hardened = true;
+        self
+    }
+
+    /// Resolves the secret and returns a builder holding this configuration, to which stores and sinks
+    /// can be added.
+    pub fn builder(&self) -> Result<TokenizeBuilder, ConfigError> {
+        let secret = match self.secret {
+            StaticSecret::Env(var) => SecretKey::from_env(var)?,
+            StaticSecret::Bytes(secret) => SecretKey::from(secret)
+        };
+
+        let mut options = ValidationOptions::new();
+        options.max_age = self.max_age;
+        options.max_clock_skew = self.max_clock_skew;
+        options.expected_audience = self.expected_audience.map(str::to_string);
+        options.strict = self.strict;
+        options.hardened = self.hardened;
+
+        let mut builder = Tokenize::builder()
+            .secret(secret)
+            .version(self.version)
+            .precision(self.precision)
+            .encoding(self.encoding)
+            .validation_options(options);
+
+        if let Some(prefix) = self.prefix {
+            builder = builder.prefix(prefix);
+        }
+
+        if let Some(audience) = self.audience {
+            builder = builder.audience(audience);
+        }
+
+        if let Some(epoch) = self.epoch {
+            builder = builder.epoch(epoch);
+        }
+
+        Ok(builder)
+    }
+
+    /// Resolves the secret and builds the [`Tokenize`] instance.
+    pub fn build(&self) -> Result<Tokenize, ConfigError> {
+        self.builder()?.build()
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::collections::hash_map::{self, HashMap};
+
+/// Metadata about the request a token was presented with, such as the address of the peer, its user
+/// agent or the route it requested, handed to [`Tokenize::validate_with_context`](crate::Tokenize::validate_with_context).
+/// 
+/// The context is passed on to the [`Policy`](crate::policy::Policy) authorizing the token, to the
+/// [`AuditSink`](crate::AuditSink) through [`AuditRecord::context`](crate::audit::AuditRecord::context),
+/// and to the [`FailurePolicy`](crate::FailurePolicy), which throttles the peer it carries.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::ValidationContext;
+/// 
+/// let context = ValidationContext::new()
+///     .with(ValidationContext::PEER, "203.0.113.7")
+///     .with(ValidationContext::ROUTE, "/users/@me")
+///     .with("tenant", "acme");
+/// 
+/// assert_eq!(context.peer(), Some("203.0.113.7"));
+/// assert_eq!(context.get("tenant"), Some("acme"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ValidationContext {
+    values: HashMap<String, String>
+}
+
+impl ValidationContext {
+    /// Key of the address of the peer presenting the token.
+    pub const PEER: &'static str = "peer";
+    /// Key of the user agent of the peer presenting the token.
+    pub const USER_AGENT: &'static str = "user_agent";
+    /// Key of the route the token was presented to.
+    pub const ROUTE: &'static str = "route";
+
+    pub fn new() -> ValidationContext {
+        ValidationContext::default()
+    }
+
+    /// Sets `key` to `value`, replacing the previous value.
+    pub fn with<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Sets `key` to `value`, returning the previous value if there was one.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Option<String> {
+        self.values.insert(key.into(), value.into())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.values.remove(key)
+    }
+
+    /// The address of the peer presenting the token, under [`ValidationContext::PEER`].
+    pub fn peer(&self) -> Option<&str> {
+        self.get(ValidationContext::PEER)
+    }
+
+    /// The user agent of the peer, under [`ValidationContext::USER_AGENT`].
+    pub fn user_agent(&self) -> Option<&str> {
+        self.get(ValidationContext::USER_AGENT)
+    }
+
+    /// The route the token was presented to, under [`ValidationContext::ROUTE`].
+    pub fn route(&self) -> Option<&str> {
+        self.get(ValidationContext::ROUTE)
+    }
+
+    /// The entries of the context, in any order.
+    pub fn iter(&self) -> hash_map::Iter<'_, String, String> {
+        self.values.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl From<HashMap<String, String>> for ValidationContext {
+    fn from(values: HashMap<String, String>) -> Self {
+        ValidationContext { values }
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for ValidationContext {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        ValidationContext { values: iter.into_iter().map(|(key, value)| (key.into(), value.into())).collect() }
+    }
+}
+
+impl<'a> IntoIterator for &'a ValidationContext {
+    type Item = (&'a String, &'a String);
+    type IntoIter = hash_map::Iter<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
@@ -0,0 +1,95 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Cryptographic primitives used throughout the crate, gathered here so their implementation can be
+//! swapped without touching the callers.
+//! 
+//! Constant-time comparisons use [subtle], or the unmaintained `rust-crypto` with the `rust-crypto`
+//! feature for deployments which vetted it. Hashes and MACs use `hmac-sha256`.
+//! 
+//! [subtle]: https://docs.rs/subtle
+
+/// Compares two byte slices in constant time.
+#[cfg(not(all(feature = "rust-crypto", not(target_arch = "wasm32"))))]
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+
+    a.ct_eq(b).into()
+}
+
+/// Compares two byte slices in constant time.
+/// 
+/// `rust-crypto` doesn't build for `wasm32` targets, which always use `subtle`.
+#[cfg(all(feature = "rust-crypto", not(target_arch = "wasm32")))]
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    ::crypto::util::fixed_time_eq(a, b)
+}
+
+/// SHA-256 digest of `data`.
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    hmac_sha256::Hash::hash(data)
+}
+
+/// HMAC-SHA256 of `message` keyed with `key`.
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    hmac_sha256::HMAC::mac(message, key)
+}
+
+/// Derives a 32 bytes key from `key` using HKDF-SHA256 ([RFC 5869](https://www.rfc-editor.org/rfc/rfc5869)),
+/// with `info` as the info parameter and no salt.
+pub(crate) fn hkdf_sha256(key: &[u8], info: &[u8]) -> [u8; 32] {
+    // Extract, without salt: the key is the hash length worth of zeros
+    let pseudorandom_key = hmac_sha256(&[0; 32], key);
+
+    // Expand, a single block covers the 32 bytes
+    let mut hmac = hmac_sha256::HMAC::new(pseudorandom_key);
+    hmac.update(info);
+    hmac.update([1]);
+
+    hmac.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_time_eq, hkdf_sha256};
+
+    #[test]
+    fn compare_in_constant_time() {
+        assert!(constant_time_eq(b"uwu", b"uwu"));
+        assert!(!constant_time_eq(b"uwu", b"owo"));
+        assert!(!constant_time_eq(b"uwu", b"uwuu"));
+    }
+
+    #[test]
+    fn hkdf_test_vector() {
+        // RFC 5869, test case 3 (no salt, no info), truncated to 32 bytes
+        let key = [0x0b; 22];
+        let expected = "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d";
+        let derived = hkdf_sha256(&key, b"").iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        assert_eq!(derived, expected);
+    }
+}
@@ -0,0 +1,381 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use base64::display::Base64Display;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::{alphabet, Engine};
+
+use crate::{crypto, TokenizeError};
+
+const CONFIG: GeneralPurposeConfig = GeneralPurposeConfig::new()
+    .with_encode_padding(false)
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+
+static STANDARD: Base64 = Base64 { name: "base64", engine: GeneralPurpose::new(&alphabet::STANDARD, CONFIG) };
+static URL_SAFE: Base64 = Base64 { name: "base64url", engine: GeneralPurpose::new(&alphabet::URL_SAFE, CONFIG) };
+
+/// Converts the segments of a token between bytes and text.
+/// 
+/// Tokens are base64-encoded by default. Deployments needing another alphabet, such as base32 or
+/// z-base-32, can implement this trait and use it with [`Encoding::Custom`]. Encoded segments must
+/// not contain `.` or `:`, which separate segments and extension tags.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Codec, Encoding, Tokenize, TokenizeError};
+/// 
+/// struct Hex;
+/// 
+/// impl Codec for Hex {
+///     fn name(&self) -> &'static str {
+///         "hex"
+///     }
+/// 
+///     fn encode(&self, input: &[u8]) -> String {
+///         input.iter().map(|b| format!("{:02x}", b)).collect()
+///     }
+/// 
+///     fn decode(&self, input: &str) -> Result<Vec<u8>, TokenizeError> {
+///         (0..input.len()).step_by(2)
+///             .map(|i| input.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+///             .collect::<Option<Vec<u8>>>()
+///             .ok_or(TokenizeError::Decode)
+///     }
+/// }
+/// 
+/// static HEX: Hex = Hex;
+/// 
+/// let tokenize = Tokenize::new("uwu").set_encoding(Encoding::Custom(&HEX));
+/// let token = tokenize.generate("123")?;
+/// assert!(token.starts_with("313233."));
+/// assert_eq!(tokenize.validate(&token, |_| Some(("uwu", 0)))?, ("uwu", 0));
+/// # Ok::<(), TokenizeError>(())
+/// ```
+pub trait Codec: Send + Sync {
+    /// Short name of the codec, used to compare and print encodings.
+    fn name(&self) -> &'static str;
+
+    /// Encodes `input`.
+    fn encode(&self, input: &[u8]) -> String;
+
+    /// Decodes `input`, failing with [`TokenizeError::Decode`] if it isn't valid.
+    fn decode(&self, input: &str) -> Result<Vec<u8>, TokenizeError>;
+}
+
+impl fmt::Debug for dyn Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The built-in base64 codecs, which accept padding when decoding.
+struct Base64 {
+    name: &'static str,
+    engine: GeneralPurpose
+}
+
+impl Codec for Base64 {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn encode(&self, input: &[u8]) -> String {
+        self.engine.encode(input)
+    }
+
+    fn decode(&self, input: &str) -> Result<Vec<u8>, TokenizeError> {
+        Ok(self.engine.decode(input)?)
+    }
+}
+
+/// Lowercase hexadecimal, used for signatures in [`SignatureFormat::Hex`].
+struct Hex;
+
+impl Codec for Hex {
+    fn name(&self) -> &'static str {
+        "hex"
+    }
+
+    fn encode(&self, input: &[u8]) -> String {
+        input.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn decode(&self, input: &str) -> Result<Vec<u8>, TokenizeError> {
+        let nibble = |b: u8| char::from(b).to_digit(16);
+        input.as_bytes().chunks(2)
+            .map(|pair| match pair {
+                [high, low] => Some((nibble(*high)? << 4 | nibble(*low)?) as u8),
+                _ => None
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(TokenizeError::Decode)
+    }
+}
+
+static HEX: Hex = Hex;
+
+/// The alphabet used to encode the segments of a token.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Encoding {
+    /// Standard base64 alphabet without padding, as mandated by the specification. Tokens may
+    /// contain `+` and `/`.
+    #[default]
+    Standard,
+    /// URL-safe base64 alphabet without padding, using `-` and `_` instead of `+` and `/`. Tokens
+    /// can be used in query strings and cookies as-is.
+    UrlSafe,
+    /// A custom [`Codec`]. Tokens using it aren't interoperable with other implementations, and
+    /// aren't checked against other encodings when parsed.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(&'static dyn Codec)
+}
+
+impl PartialEq for Encoding {
+    fn eq(&self, other: &Encoding) -> bool {
+        match (self, other) {
+            (Encoding::Standard, Encoding::Standard) | (Encoding::UrlSafe, Encoding::UrlSafe) => true,
+            (Encoding::Custom(codec), Encoding::Custom(other)) => codec.name() == other.name(),
+            _ => false
+        }
+    }
+}
+
+impl Eq for Encoding {}
+
+impl Hash for Encoding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.codec().name().hash(state)
+    }
+}
+
+impl Encoding {
+    /// The codec encoding the segments of a token.
+    pub fn codec(self) -> &'static dyn Codec {
+        match self {
+            Encoding::Standard => &STANDARD,
+            Encoding::UrlSafe => &URL_SAFE,
+            Encoding::Custom(codec) => codec
+        }
+    }
+
+    fn engine(self) -> Option<&'static GeneralPurpose> {
+        match self {
+            Encoding::Standard => Some(&STANDARD.engine),
+            Encoding::UrlSafe => Some(&URL_SAFE.engine),
+            Encoding::Custom(_) => None
+        }
+    }
+
+    pub(crate) fn encode<T: AsRef<[u8]>>(self, input: T) -> String {
+        self.codec().encode(input.as_ref())
+    }
+
+    /// Displays `input` encoded, without allocating for the built-in encodings.
+    pub(crate) fn display(self, input: &[u8]) -> Encoded<'_> {
+        Encoded { encoding: self, input }
+    }
+
+    /// Length of `len` bytes once encoded.
+    pub(crate) fn encoded_len(self, len: usize) -> usize {
+        match self.engine() {
+            Some(_) => (len * 4).div_ceil(3),
+            None => self.encode(vec![0; len]).len()
+        }
+    }
+
+    /// The SIMD decoder of `input`, if it's encoded with a built-in encoding. Padded inputs are left
+    /// to the base64 engines, which accept padding unlike the SIMD decoders.
+    #[cfg(feature = "simd")]
+    fn simd(self, input: &[u8]) -> Option<&'static base64_simd::Base64> {
+        match self {
+            _ if input.ends_with(b"=") => None,
+            Encoding::Standard => Some(&base64_simd::STANDARD_NO_PAD),
+            Encoding::UrlSafe => Some(&base64_simd::URL_SAFE_NO_PAD),
+            Encoding::Custom(_) => None
+        }
+    }
+
+    pub(crate) fn decode<T: AsRef<[u8]>>(self, input: T) -> Result<Vec<u8>, TokenizeError> {
+        #[cfg(feature = "simd")]
+        if let Some(simd) = self.simd(input.as_ref()) {
+            return simd.decode_to_vec(input).map_err(|_| TokenizeError::Decode)
+        }
+
+        match self.engine() {
+            Some(engine) => Ok(engine.decode(input)?),
+            None => self.codec().decode(std::str::from_utf8(input.as_ref())?)
+        }
+    }
+
+    /// Decodes `input` into `output`, returning the decoded bytes. Fails with
+    /// [`TokenizeError::Decode`] if `output` can't hold [`Encoding::max_decoded_len`] bytes, or
+    /// the decoded bytes of custom codecs.
+    pub(crate) fn decode_slice<T: AsRef<[u8]>>(self, input: T, output: &mut [u8]) -> Result<&[u8], TokenizeError> {
+        let input = input.as_ref();
+        if self.engine().is_some() && output.len() < Encoding::max_decoded_len(input.len()) {
+            return Err(TokenizeError::Decode)
+        }
+
+        #[cfg(feature = "simd")]
+        if let Some(simd) = self.simd(input) {
+            let len = simd.decode(input, base64_simd::Out::from_slice(output)).map_err(|_| TokenizeError::Decode)?.len();
+            return Ok(&output[..len])
+        }
+
+        let len = match self.engine() {
+            Some(engine) => engine.decode_slice(input, output)?,
+            None => {
+                let decoded = self.decode(input)?;
+                output.get_mut(..decoded.len()).ok_or(TokenizeError::Decode)?.copy_from_slice(&decoded);
+                decoded.len()
+            }
+        };
+
+        Ok(&output[..len])
+    }
+
+    /// Upper bound of the length of `encoded_len` base64 characters once decoded.
+    pub(crate) fn max_decoded_len(encoded_len: usize) -> usize {
+        encoded_len.div_ceil(4) * 3
+    }
+
+    /// Whether `encoded` is the encoding of `input`, compared in constant time. Inputs of up to 96
+    /// bytes are base64-encoded on the stack.
+    pub(crate) fn matches(self, input: &[u8], encoded: &str) -> bool {
+        let engine = match self.engine() {
+            Some(engine) if input.len() <= 96 => engine,
+            _ => return crypto::constant_time_eq(self.encode(input).as_bytes(), encoded.as_bytes())
+        };
+
+        let mut buffer = [0; 128];
+        match engine.encode_slice(input, &mut buffer) {
+            Ok(len) => crypto::constant_time_eq(&buffer[..len], encoded.as_bytes()),
+            Err(_) => false
+        }
+    }
+
+    /// Whether `segment` is exactly what encoding its decoded value would produce: no padding, no
+    /// characters outside of the alphabet and no non-zero trailing bits.
+    pub(crate) fn is_canonical(self, segment: &str) -> bool {
+        if let Encoding::Custom(codec) = self {
+            return codec.decode(segment).is_ok_and(|decoded| codec.encode(&decoded) == segment)
+        }
+
+        let sextet = |b: u8| match (b, self) {
+            (b'A'..=b'Z', _) => Some(b - b'A'),
+            (b'a'..=b'z', _) => Some(b - b'a' + 26),
+            (b'0'..=b'9', _) => Some(b - b'0' + 52),
+            (b'+', Encoding::Standard) | (b'-', Encoding::UrlSafe) => Some(62),
+            (b'/', Encoding::Standard) | (b'_', Encoding::UrlSafe) => Some(63),
+            _ => None
+        };
+
+        if !segment.bytes().all(|b| sextet(b).is_some()) {
+            return false
+        }
+
+        // The bits of the last character which don't make up a whole byte must be zero
+        let last = segment.bytes().last().and_then(sextet).unwrap_or(0);
+        match segment.len() % 4 {
+            1 => false,
+            2 => last & 0b1111 == 0,
+            3 => last & 0b11 == 0,
+            _ => true
+        }
+    }
+
+    /// Guesses the encoding of base64 segments from the characters they contain.
+    /// 
+    /// Returns `Ok(None)` if the segments only contain characters common to both alphabets, and
+    /// an error if they mix both.
+    pub(crate) fn detect<'a, I: IntoIterator<Item = &'a str>>(segments: I) -> Result<Option<Encoding>, TokenizeError> {
+        let (standard, url_safe) = segments.into_iter().fold((false, false), |(standard, url_safe), segment| (
+            standard || segment.contains(['+', '/']),
+            url_safe || segment.contains(['-', '_'])
+        ));
+
+        match (standard, url_safe) {
+            (true, true) => Err(TokenizeError::Malformed),
+            (true, false) => Ok(Some(Encoding::Standard)),
+            (false, true) => Ok(Some(Encoding::UrlSafe)),
+            (false, false) => Ok(None)
+        }
+    }
+}
+
+/// Bytes displayed with an encoding, see [`Encoding::display`].
+pub(crate) struct Encoded<'a> {
+    encoding: Encoding,
+    input: &'a [u8]
+}
+
+impl fmt::Display for Encoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.encoding.engine() {
+            Some(engine) => Base64Display::new(self.input, engine).fmt(f),
+            None => f.write_str(&self.encoding.encode(self.input))
+        }
+    }
+}
+
+/// How the signature segment of a token is encoded. The other segments always use the [`Encoding`]
+/// of the token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignatureFormat {
+    /// The same encoding as the other segments, as mandated by the specification.
+    #[default]
+    Base64,
+    /// Lowercase hexadecimal, for systems storing signatures hex-encoded. Signatures are about 50%
+    /// longer than in base64.
+    Hex
+}
+
+impl SignatureFormat {
+    /// The encoding of signatures in this format, in tokens whose other segments use `encoding`.
+    pub(crate) fn encoding(self, encoding: Encoding) -> Encoding {
+        match self {
+            SignatureFormat::Base64 => encoding,
+            SignatureFormat::Hex => Encoding::Custom(&HEX)
+        }
+    }
+
+    /// Guesses the format of a signature. Only lowercase hexadecimal digits are taken for hex, which
+    /// a base64 signature is astronomically unlikely to be made of.
+    pub(crate) fn detect(signature: &str) -> SignatureFormat {
+        match signature.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            true if !signature.is_empty() => SignatureFormat::Hex,
+            _ => SignatureFormat::Base64
+        }
+    }
+}
@@ -0,0 +1,297 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Encrypted tokens, available with the `encryption` feature.
+//! 
+//! The account id of regular tokens is only base64-encoded, so anyone holding a token can read it.
+//! Encrypted tokens hide it along with the generation time: both are encrypted with
+//! XChaCha20-Poly1305 under a key separate from the signing secret, set through
+//! [`Tokenize::set_encryption_key`]. The token is then signed as usual, so it's still verified against
+//! the secrets or the keyring before being decrypted.
+//! 
+//! Encrypted tokens look like `[prefix.]e.<nonce and ciphertext>.<signature>`. They don't carry any
+//! extension, and can only be validated through [`Tokenize::validate_encrypted`] and
+//! [`Tokenize::validate_encrypted_with_store`].
+//! 
+//! # Examples
+//! 
+//! ```
+//! use tokenize::Tokenize;
+//! use tokenize::store::MemoryAccount;
+//! 
+//! let tokenize = Tokenize::new("uwu").set_encryption_key("owo");
+//! let token = tokenize.generate_encrypted("326359466171826176").expect("Couldn't generate new token");
+//! assert!(!token.contains("MzI2MzU5NDY2MTcxODI2MTc2"));
+//! 
+//! let account = tokenize.validate_encrypted(token, |id| {
+//!     assert_eq!(id, "326359466171826176");
+//!     Some(MemoryAccount { data: (), last_token_reset: 0 })
+//! }).expect("Couldn't validate token");
+//! ```
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use crate::audit::AuditEvent;
+use crate::keyring::KEY_ID_SEPARATOR;
+use crate::{token, Account, AccountId, AccountStore, Result, SecretKey, SignatureFormat, Tokenize, TokenizeError, TokenVersion};
+
+/// Header segment identifying encrypted tokens.
+const ENCRYPTED_HEADER: &str = "e";
+
+/// Context the encryption key is derived from the configured key with. See [`SecretKey::derive`].
+const KEY_CONTEXT: &str = "tokenize token encryption";
+
+/// Length of the XChaCha20-Poly1305 nonce prepended to the ciphertext.
+const NONCE_LENGTH: usize = 24;
+
+impl Tokenize {
+    /// Sets the key account ids are encrypted with in tokens generated by
+    /// [`Tokenize::generate_encrypted`]. It should be distinct from the signing secret.
+    /// 
+    /// The 32 bytes XChaCha20-Poly1305 key is derived from `key` through [`SecretKey::derive`].
+    pub fn set_encryption_key<K: Into<SecretKey>>(mut self, key: K) -> Self {
+        self.encryption_key = Some(key.into().derive(KEY_CONTEXT));
+        self
+    }
+
+    /// Generates a new encrypted token for the given account id. See the [module](crate::encryption)
+    /// documentation.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if no encryption key was set through [`Tokenize::set_encryption_key`].
+    pub fn generate_encrypted<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        let mut plaintext = ((self.clock.now_millis() - self.epoch) / 1000).to_string().into_bytes();
+        plaintext.push(b'.');
+        let account_id = account_id.into();
+        plaintext.extend_from_slice(account_id.as_bytes());
+
+        let prefix = self.prefixes.first().map(String::as_str);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut payload = nonce.to_vec();
+        payload.extend(self.cipher().encrypt(&nonce, Payload { msg: &plaintext, aad: prefix.unwrap_or_default().as_bytes() })
+            .map_err(|_| TokenizeError::Malformed)?);
+
+        let mut token = String::new();
+        if let Some(prefix) = prefix {
+            token.push_str(prefix);
+            token.push('.');
+        }
+
+        token.push_str(ENCRYPTED_HEADER);
+        token.push('.');
+        token.push_str(&self.encoding.encode(payload));
+
+        let (key_id, secret) = match &self.keyring {
+            Some(keyring) => (Some(keyring.primary_id()), keyring.primary_secret()),
+            None => (None, self.secret.expose())
+        };
+
+        let signature = self.signature_format.encoding(self.encoding).encode(self.signer.sign_parts(secret, &[b"TTF.2.", token.as_bytes()]));
+        token.push('.');
+        if let Some(key_id) = key_id {
+            token.push_str(key_id);
+            token.push(KEY_ID_SEPARATOR);
+        }
+
+        token.push_str(&signature);
+        self.audit(AuditEvent::TokenIssued { account_id }, &signature);
+
+        Ok(token)
+    }
+
+    /// Validates an encrypted token, returning the account it belongs to. See [`Tokenize::validate`].
+    /// 
+    /// Tokens which can't be decrypted with the encryption key fail with
+    /// [`TokenizeError::InvalidSignature`].
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if no encryption key was set through [`Tokenize::set_encryption_key`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_encrypted<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: AsRef<str>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.measured(token.as_ref(), || {
+            let (account_id, issued_at_millis) = self.decrypt(token.as_ref())?;
+            let account = account_fetcher(account_id.as_str().ok_or(TokenizeError::Decode)?.to_string());
+
+            self.check_account(account, issued_at_millis)
+        })
+    }
+
+    /// Validates an encrypted token, fetching its account from `store`. See
+    /// [`Tokenize::validate_encrypted`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_encrypted_with_store<S, St, A>(&self, token: S, store: &St) -> Result<A> where
+        S: AsRef<str>,
+        St: AccountStore<A> + ?Sized,
+        A: Account {
+        self.measured(token.as_ref(), || {
+            let (account_id, issued_at_millis) = self.decrypt(token.as_ref())?;
+            let account = store.fetch(account_id.as_str().ok_or(TokenizeError::Decode)?)?;
+
+            self.check_account(account, issued_at_millis)
+        })
+    }
+
+    /// Verifies the signature of an encrypted token and decrypts it, performing the checks which don't
+    /// involve its account. Returns its account id and generation time.
+    fn decrypt(&self, token: &str) -> Result<(AccountId, i64)> {
+        if self.options.max_length.is_some_and(|max_length| token.len() > max_length) {
+            return Err(TokenizeError::TokenTooLong)
+        }
+
+        let (signed, signature_part) = token.rsplit_once('.').ok_or(TokenizeError::Malformed)?;
+        let (prefix, body) = if self.prefixes.is_empty() {
+            (None, signed)
+        } else {
+            let (prefix, body) = signed.split_once('.').ok_or(TokenizeError::Malformed)?;
+            (Some(prefix), body)
+        };
+
+        let payload_part = match body.split_once('.') {
+            Some((ENCRYPTED_HEADER, payload_part)) if !payload_part.contains('.') => payload_part,
+            _ => return Err(TokenizeError::Malformed)
+        };
+
+        if prefix.is_some_and(|prefix| !self.prefixes.iter().any(|p| p == prefix)) {
+            return Err(TokenizeError::PrefixMismatch)
+        }
+
+        let (key_id, signature) = token::split_key_id(signature_part);
+        let signature_format = self.detect_signature_format(signature)?;
+        let base64_signature = (signature_format == SignatureFormat::Base64).then_some(signature);
+        let encoding = self.segment_encoding([payload_part].into_iter().chain(base64_signature))?;
+        let signature_encoding = signature_format.encoding(encoding);
+
+        if self.options.strict && !(encoding.is_canonical(payload_part) && signature_encoding.is_canonical(signature)) {
+            return Err(TokenizeError::Malformed)
+        }
+
+        self.verify_signature(key_id, |secret| self.signature_matches(TokenVersion::V2, signed, secret, signature, signature_encoding))?;
+
+        // Encrypted tokens can't carry an audience
+        if self.options.expected_audience.is_some() {
+            return Err(TokenizeError::AudienceMismatch)
+        }
+
+        let payload = encoding.decode(payload_part)?;
+        if payload.len() < NONCE_LENGTH {
+            return Err(TokenizeError::Malformed)
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+        let plaintext = self.cipher().decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: prefix.unwrap_or_default().as_bytes() })
+            .map_err(|_| TokenizeError::InvalidSignature)?;
+
+        let separator = plaintext.iter().position(|b| *b == b'.').ok_or(TokenizeError::Malformed)?;
+        let generated_at = std::str::from_utf8(&plaintext[..separator])?.parse()?;
+        let account_id = AccountId::new(plaintext[separator + 1..].to_vec());
+        let issued_at_millis = token::issued_at_millis(generated_at, self.epoch);
+        self.check_validity(issued_at_millis, None, || token::revocation_key(key_id, signature, signature_encoding))?;
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("account", AccountId::trace_hash(account_id.as_bytes()));
+
+        Ok((account_id, issued_at_millis))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        let key = self.encryption_key.as_ref().expect("No encryption key was set");
+
+        XChaCha20Poly1305::new_from_slice(key.expose()).expect("Derived keys are 32 bytes long")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::clock::MockClock;
+    use crate::store::{MemoryAccount, MemoryAccountStore};
+    use crate::{Keyring, Tokenize, TokenizeError, ValidationOptions};
+
+    fn account(_id: String) -> Option<MemoryAccount> {
+        Some(MemoryAccount { data: (), last_token_reset: 0 })
+    }
+
+    #[test]
+    fn validate_encrypted() {
+        let clock = Arc::new(MockClock::new(1641731696000));
+        let tokenize = Tokenize::new("uwu")
+            .set_prefix("bot")
+            .set_encryption_key("owo")
+            .set_clock(clock.clone())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+
+        let token = tokenize.generate_encrypted("326359466171826176").expect("Couldn't generate new token");
+        assert!(token.starts_with("bot.e."));
+        assert_ne!(token, tokenize.generate_encrypted("326359466171826176").unwrap());
+
+        assert!(tokenize.validate_encrypted(&token, |id| {
+            assert_eq!(id, "326359466171826176");
+            account(id)
+        }).is_ok());
+        assert_eq!(tokenize.validate_encrypted(&token, |_id| Some(MemoryAccount { data: (), last_token_reset: 1641731697000 })).err(), Some(TokenizeError::Invalidated));
+        assert_eq!(tokenize.validate(token.clone(), account).err(), Some(TokenizeError::InvalidSignature));
+
+        let store = MemoryAccountStore::new();
+        assert_eq!(tokenize.validate_encrypted_with_store(&token, &store).err(), Some(TokenizeError::AccountNotFound));
+        store.insert("326359466171826176", ());
+        assert!(tokenize.validate_encrypted_with_store(&token, &store).is_ok());
+
+        clock.advance(61_000);
+        assert_eq!(tokenize.validate_encrypted(&token, account).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn validate_encrypted_with_wrong_keys() {
+        let tokenize = Tokenize::new("uwu").set_encryption_key("owo");
+        let token = tokenize.generate_encrypted("326359466171826176").expect("Couldn't generate new token");
+
+        assert_eq!(Tokenize::new("nya").set_encryption_key("owo").validate_encrypted(&token, account).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(Tokenize::new("uwu").set_encryption_key("nya").validate_encrypted(&token, account).err(), Some(TokenizeError::InvalidSignature));
+        assert!(Tokenize::with_secrets("nya", ["uwu"]).set_encryption_key("owo").validate_encrypted(&token, account).is_ok());
+
+        let (payload, signature) = token.rsplit_once('.').unwrap();
+        let tampered = format!("{}A.{}", payload, signature);
+        assert_eq!(tokenize.validate_encrypted(tampered, account).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn validate_encrypted_with_keyring() {
+        let tokenize = Tokenize::with_keyring(Keyring::new("k1", "uwu")).set_encryption_key("owo");
+        let token = tokenize.generate_encrypted("326359466171826176").expect("Couldn't generate new token");
+        assert!(token.rsplit_once('.').unwrap().1.starts_with("k1"));
+        assert!(tokenize.validate_encrypted(&token, account).is_ok());
+
+        let rotated = Tokenize::with_keyring(Keyring::new("k2", "uwu")).set_encryption_key("owo");
+        assert_eq!(rotated.validate_encrypted(&token, account).err(), Some(TokenizeError::UnknownKey));
+    }
+}
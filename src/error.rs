@@ -0,0 +1,240 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use std::fmt;
+
+/// Convenience alias for results returned by this crate.
+pub type Result<T, E = TokenizeError> = std::result::Result<T, E>;
+
+/// Errors that can occur while generating or validating a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenizeError {
+    /// The token doesn't have the expected structure.
+    Malformed,
+    /// The token prefix doesn't match the configured one.
+    PrefixMismatch,
+    /// The token signature doesn't match.
+    InvalidSignature,
+    /// The token was signed with a key that isn't part of the keyring.
+    UnknownKey,
+    /// A segment of the token couldn't be decoded.
+    Decode,
+    /// The token or its account id exceeds the configured length limits.
+    TokenTooLong,
+    /// The account fetcher didn't return any account for the token's id.
+    /// 
+    /// With [`ValidationOptions::hardened`](crate::ValidationOptions::hardened), this is only returned
+    /// after the same checks as for an existing account, so the time taken doesn't reveal whether the
+    /// account exists.
+    AccountNotFound,
+    /// The token was issued before the account's last token reset.
+    Invalidated,
+    /// The token is older than the configured maximum age.
+    Expired,
+    /// The token was issued further in the future than the configured maximum clock skew.
+    IssuedInFuture,
+    /// The token was revoked through the [`RevocationStore`](crate::RevocationStore).
+    Revoked,
+    /// The token wasn't issued with the required scope.
+    MissingScope(String),
+    /// The single-use token was already validated.
+    AlreadyUsed,
+    /// The token is single-use, and has to be validated through
+    /// [`Tokenize::validate_single_use`](crate::Tokenize::validate_single_use).
+    SingleUse,
+    /// The claims couldn't be serialized, or the token doesn't carry claims of the expected type.
+    InvalidClaims,
+    /// The token isn't bound to the device it was presented from.
+    DeviceMismatch,
+    /// The token wasn't issued for the expected audience. See
+    /// [`ValidationOptions::expected_audience`](crate::ValidationOptions::expected_audience).
+    AudienceMismatch,
+    /// The peer presenting the token is throttled by the [`FailurePolicy`](crate::FailurePolicy).
+    RateLimited,
+    /// The token was denied by a [`Policy`](crate::policy::Policy), for the given reason.
+    PolicyViolation(String),
+    /// The [`AccountStore`](crate::AccountStore) failed to fetch the account.
+    Store(StoreError),
+}
+
+impl TokenizeError {
+    /// A short, stable `snake_case` name for the kind of error, suited to metric labels and logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TokenizeError::Malformed => "malformed",
+            TokenizeError::PrefixMismatch => "prefix_mismatch",
+            TokenizeError::InvalidSignature => "invalid_signature",
+            TokenizeError::UnknownKey => "unknown_key",
+            TokenizeError::Decode => "decode",
+            TokenizeError::TokenTooLong => "token_too_long",
+            TokenizeError::AccountNotFound => "account_not_found",
+            TokenizeError::Invalidated => "invalidated",
+            TokenizeError::Expired => "expired",
+            TokenizeError::IssuedInFuture => "issued_in_future",
+            TokenizeError::Revoked => "revoked",
+            TokenizeError::MissingScope(_) => "missing_scope",
+            TokenizeError::AlreadyUsed => "already_used",
+            TokenizeError::SingleUse => "single_use",
+            TokenizeError::InvalidClaims => "invalid_claims",
+            TokenizeError::DeviceMismatch => "device_mismatch",
+            TokenizeError::AudienceMismatch => "audience_mismatch",
+            TokenizeError::RateLimited => "rate_limited",
+            TokenizeError::PolicyViolation(_) => "policy_violation",
+            TokenizeError::Store(_) => "store",
+        }
+    }
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::Malformed => write!(f, "Token is invalid"),
+            TokenizeError::PrefixMismatch => write!(f, "Token prefix doesn't match"),
+            TokenizeError::InvalidSignature => write!(f, "Token signature doesn't match"),
+            TokenizeError::UnknownKey => write!(f, "Token was signed with an unknown key"),
+            TokenizeError::Decode => write!(f, "Token segment couldn't be decoded"),
+            TokenizeError::TokenTooLong => write!(f, "Token is too long"),
+            TokenizeError::AccountNotFound => write!(f, "No account is tied to this id"),
+            TokenizeError::Invalidated => write!(f, "Token was invalidated"),
+            TokenizeError::Expired => write!(f, "Token has expired"),
+            TokenizeError::IssuedInFuture => write!(f, "Token was issued in the future"),
+            TokenizeError::Revoked => write!(f, "Token was revoked"),
+            TokenizeError::MissingScope(scope) => write!(f, "Token is missing the {} scope", scope),
+            TokenizeError::AlreadyUsed => write!(f, "Token was already used"),
+            TokenizeError::SingleUse => write!(f, "Token is single-use"),
+            TokenizeError::InvalidClaims => write!(f, "Token claims are invalid"),
+            TokenizeError::DeviceMismatch => write!(f, "Token is bound to another device"),
+            TokenizeError::AudienceMismatch => write!(f, "Token was issued for another audience"),
+            TokenizeError::RateLimited => write!(f, "Too many invalid tokens were presented"),
+            TokenizeError::PolicyViolation(reason) => write!(f, "Token was denied by a policy: {}", reason),
+            TokenizeError::Store(err) => write!(f, "Couldn't fetch account: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TokenizeError {}
+
+impl From<StoreError> for TokenizeError {
+    fn from(err: StoreError) -> Self {
+        TokenizeError::Store(err)
+    }
+}
+
+impl From<base64::DecodeError> for TokenizeError {
+    fn from(_: base64::DecodeError) -> Self {
+        TokenizeError::Decode
+    }
+}
+
+impl From<base64::DecodeSliceError> for TokenizeError {
+    fn from(_: base64::DecodeSliceError) -> Self {
+        TokenizeError::Decode
+    }
+}
+
+impl From<std::str::Utf8Error> for TokenizeError {
+    fn from(_: std::str::Utf8Error) -> Self {
+        TokenizeError::Decode
+    }
+}
+
+impl From<std::num::ParseIntError> for TokenizeError {
+    fn from(_: std::num::ParseIntError) -> Self {
+        TokenizeError::Decode
+    }
+}
+
+/// Errors caused by an invalid [`Tokenize`](crate::Tokenize) configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConfigError {
+    /// Neither a secret nor a keyring was provided.
+    MissingSecret,
+    /// One of the provided secrets is empty.
+    EmptySecret,
+    /// Both a secret and a keyring were provided, or verification-only secrets were combined with
+    /// a keyring.
+    ConflictingSecrets,
+    /// The access and refresh tokens of a [`SessionManager`](crate::session::SessionManager), or two
+    /// tenants of a [`TenantRegistry`](crate::tenant::TenantRegistry), share the same prefix.
+    SharedPrefix,
+    /// The refresh tokens of a [`SessionManager`](crate::session::SessionManager) have no
+    /// [`RevocationStore`](crate::RevocationStore) to record exchanged tokens in.
+    MissingRevocationStore,
+    /// The prefix is empty, contains a `.` or a `:`, or contains characters which aren't printable ASCII.
+    InvalidPrefix,
+    /// A tenant of a [`TenantRegistry`](crate::tenant::TenantRegistry) has no prefix.
+    MissingPrefix,
+    /// An encoded secret couldn't be decoded. See [`SecretKey::decode`](crate::SecretKey::decode).
+    InvalidSecret,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MissingSecret => write!(f, "No secret was provided"),
+            ConfigError::EmptySecret => write!(f, "Secret is empty"),
+            ConfigError::ConflictingSecrets => write!(f, "Both a secret and a keyring were provided"),
+            ConfigError::SharedPrefix => write!(f, "Prefix is shared by several token kinds"),
+            ConfigError::MissingRevocationStore => write!(f, "No revocation store was provided"),
+            ConfigError::InvalidPrefix => write!(f, "Prefix is invalid"),
+            ConfigError::MissingPrefix => write!(f, "No prefix was provided"),
+            ConfigError::InvalidSecret => write!(f, "Secret couldn't be decoded"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Error returned by an [`AccountStore`](crate::AccountStore) when the account couldn't be fetched,
+/// as opposed to not existing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StoreError {
+    message: String
+}
+
+impl StoreError {
+    /// Creates an error from whatever the underlying store reported.
+    pub fn new<E: fmt::Display>(err: E) -> StoreError {
+        StoreError { message: err.to_string() }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for StoreError {}
@@ -0,0 +1,238 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Extraction of tokens from HTTP requests.
+//! 
+//! The framework integrations rely on these helpers, which can also be used standalone with any
+//! HTTP library: [`bearer`] reads an `Authorization` header value, [`cookie`] a `Cookie` header value,
+//! [`query`] a query string and [`subprotocol`] a `Sec-WebSocket-Protocol` header value. [`TokenExtractor`] looks through several of these places in order.
+//! 
+//! # Examples
+//! 
+//! ```
+//! use tokenize::extract::{TokenExtractor, TokenSource};
+//! 
+//! let extractor = TokenExtractor::new()
+//!     .fallback_source(TokenSource::Cookie("token".to_string()))
+//!     .fallback_source(TokenSource::Query("token".to_string()));
+//! 
+//! let headers = |name: &str| (name == "cookie").then_some("theme=dark; token=MzI2.OTUz.ucU3");
+//! assert_eq!(extractor.extract(headers, None).as_deref(), Some("MzI2.OTUz.ucU3"));
+//! assert_eq!(extractor.extract(|_| None, Some("token=MzI2.OTUz.ucU3%2BWc")).as_deref(), Some("MzI2.OTUz.ucU3+Wc"));
+//! ```
+
+/// The WebSocket subprotocol announcing a token, see [`subprotocol`].
+pub const SUBPROTOCOL: &str = "tokenize";
+
+/// A place a token is looked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    /// A request header. `Bearer` tokens are accepted as well as raw tokens, see [`bearer`].
+    Header(String),
+    /// A cookie, read from the `Cookie` header.
+    Cookie(String),
+    /// A query parameter.
+    Query(String)
+}
+
+/// Looks for a token through a list of [`TokenSource`]s, in order. The first source holding a token
+/// wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenExtractor {
+    sources: Vec<TokenSource>
+}
+
+impl TokenExtractor {
+    /// Creates an extractor reading tokens from the `Authorization` header.
+    pub fn new() -> TokenExtractor {
+        TokenExtractor {
+            sources: vec![TokenSource::Header("authorization".to_string())]
+        }
+    }
+
+    /// Replaces the places the token is looked for.
+    pub fn source(mut self, source: TokenSource) -> Self {
+        self.sources = vec![source];
+        self
+    }
+
+    /// Adds a place the token is looked for. Sources are tried in the order they were added.
+    pub fn fallback_source(mut self, source: TokenSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// The places the token is looked for, in order.
+    pub fn sources(&self) -> &[TokenSource] {
+        &self.sources
+    }
+
+    /// Extracts a token from a request, given a function returning the value of a header from its
+    /// lowercase name and the query string of the request, if there's one.
+    pub fn extract<'a, H>(&self, headers: H, query_string: Option<&str>) -> Option<String> where
+        H: Fn(&str) -> Option<&'a str> {
+        self.sources.iter().find_map(|source| match source {
+            TokenSource::Header(name) => headers(&name.to_ascii_lowercase()).and_then(bearer).map(str::to_string),
+            TokenSource::Cookie(name) => headers("cookie").and_then(|cookies| cookie(cookies, name)).map(str::to_string),
+            TokenSource::Query(name) => query_string.and_then(|query_string| query(query_string, name))
+        })
+    }
+}
+
+impl Default for TokenExtractor {
+    fn default() -> Self {
+        TokenExtractor::new()
+    }
+}
+
+/// Reads the token from the value of an `Authorization` header. The `Bearer` scheme is optional, and
+/// matched case-insensitively. Returns [`None`] if there's no token.
+/// 
+/// ```
+/// use tokenize::extract::bearer;
+/// 
+/// assert_eq!(bearer("Bearer MzI2.OTUz.ucU3"), Some("MzI2.OTUz.ucU3"));
+/// assert_eq!(bearer("MzI2.OTUz.ucU3"), Some("MzI2.OTUz.ucU3"));
+/// assert_eq!(bearer("Bearer "), None);
+/// ```
+pub fn bearer(value: &str) -> Option<&str> {
+    let value = value.trim_start();
+    let token = match value.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token,
+        _ => value
+    };
+    let token = token.trim();
+
+    (!token.is_empty()).then_some(token)
+}
+
+/// Reads the cookie called `name` from the value of a `Cookie` header. Returns [`None`] if there's no
+/// such cookie, or if it's empty.
+pub fn cookie<'a>(cookies: &'a str, name: &str) -> Option<&'a str> {
+    cookies.split(';')
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value.trim_matches('"'))
+        .filter(|value| !value.is_empty())
+}
+
+/// Reads the parameter called `name` from a query string, with or without its leading `?`, and
+/// percent-decodes it. Returns [`None`] if there's no such parameter, or if it's empty or can't be
+/// decoded.
+/// 
+/// `+` is kept as is rather than decoded to a space, as it's part of the base64 alphabet.
+pub fn query(query_string: &str, name: &str) -> Option<String> {
+    query_string.strip_prefix('?').unwrap_or(query_string)
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(parameter_name, _)| *parameter_name == name)
+        .and_then(|(_, value)| percent_decode(value))
+        .filter(|value| !value.is_empty())
+}
+
+/// Reads the token from the value of a `Sec-WebSocket-Protocol` header, where browsers, which can't set
+/// headers on WebSocket connections, can carry it: clients offer the [`SUBPROTOCOL`] followed by the
+/// token, as in `tokenize, <token>`. Servers accepting the token must select the [`SUBPROTOCOL`].
+/// 
+/// Subprotocols can't contain `/` or `=`, so tokens sent this way must use
+/// [`Encoding::UrlSafe`](crate::Encoding::UrlSafe).
+/// 
+/// ```
+/// use tokenize::extract::subprotocol;
+/// 
+/// assert_eq!(subprotocol("tokenize, MzI2.OTUz.ucU3"), Some("MzI2.OTUz.ucU3"));
+/// assert_eq!(subprotocol("chat, MzI2.OTUz.ucU3"), None);
+/// ```
+pub fn subprotocol(protocols: &str) -> Option<&str> {
+    let mut protocols = protocols.split(',').map(str::trim);
+    protocols.find(|protocol| *protocol == SUBPROTOCOL)?;
+    protocols.next().filter(|token| !token.is_empty())
+}
+
+/// Decodes `%XX` escapes.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = value.bytes();
+    let mut decoded = Vec::with_capacity(value.len());
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bearer, cookie, query, subprotocol, TokenExtractor, TokenSource};
+
+    const TOKEN: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+
+    #[test]
+    fn extract_tokens() {
+        assert_eq!(bearer(&format!("bearer  {}", TOKEN)), Some(TOKEN));
+        assert_eq!(bearer("Basic dXNlcjpwYXNz"), Some("Basic dXNlcjpwYXNz"));
+        assert_eq!(bearer("  "), None);
+
+        assert_eq!(cookie(&format!("theme=dark; token=\"{}\"", TOKEN), "token"), Some(TOKEN));
+        assert_eq!(cookie("theme=dark; token=", "token"), None);
+        assert_eq!(cookie("mytoken=uwu", "token"), None);
+
+        assert_eq!(query(&format!("?page=2&token={}", TOKEN.replace('+', "%2B")), "token").as_deref(), Some(TOKEN));
+        assert_eq!(query(&format!("token={}", TOKEN), "token").as_deref(), Some(TOKEN));
+        assert_eq!(query("token=%zz", "token"), None);
+        assert_eq!(query("token", "token"), None);
+
+        assert_eq!(subprotocol(&format!("chat,tokenize,{}", TOKEN)), Some(TOKEN));
+        assert_eq!(subprotocol("tokenize"), None);
+    }
+
+    #[test]
+    fn respect_source_precedence() {
+        let cookies = format!("token={}", TOKEN);
+        let headers = |name: &str| match name {
+            "authorization" => Some("Bearer header"),
+            "cookie" => Some(cookies.as_str()),
+            _ => None
+        };
+
+        let extractor = TokenExtractor::new().fallback_source(TokenSource::Cookie("token".to_string()));
+        assert_eq!(extractor.extract(headers, None).as_deref(), Some("header"));
+        assert_eq!(extractor.extract(|name| headers(name).filter(|_| name != "authorization"), None).as_deref(), Some(TOKEN));
+
+        let extractor = TokenExtractor::new()
+            .source(TokenSource::Query("token".to_string()))
+            .fallback_source(TokenSource::Header("X-Token".to_string()));
+        assert_eq!(extractor.extract(headers, Some("token=query")).as_deref(), Some("query"));
+        assert_eq!(extractor.extract(|name| (name == "x-token").then_some("custom"), Some("page=2")).as_deref(), Some("custom"));
+        assert_eq!(extractor.extract(headers, None), None);
+    }
+}
@@ -0,0 +1,317 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! C bindings, available with the `ffi` feature.
+//! 
+//! The header is in `include/tokenize.h`, and is regenerated with
+//! `cbindgen --config cbindgen.toml --output include/tokenize.h`. Build a shared or static library with
+//! `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`).
+//! 
+//! Strings returned by these functions are owned by the caller, and must be freed with
+//! [`tokenize_string_free`].
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+use crate::{Account, Tokenize, TokenizeError};
+
+/// Outcome of a call, `TOKENIZE_STATUS_OK` on success.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizeStatus {
+    Ok = 0,
+    /// A pointer was null, or a string wasn't valid UTF-8.
+    InvalidArgument,
+    Malformed,
+    PrefixMismatch,
+    InvalidSignature,
+    UnknownKey,
+    Decode,
+    TokenTooLong,
+    AccountNotFound,
+    Invalidated,
+    Expired,
+    IssuedInFuture,
+    Revoked,
+    MissingScope,
+    AlreadyUsed,
+    SingleUse,
+    InvalidClaims,
+    Store,
+    DeviceMismatch,
+    AudienceMismatch,
+    RateLimited,
+    PolicyViolation
+}
+
+impl From<TokenizeError> for TokenizeStatus {
+    fn from(err: TokenizeError) -> Self {
+        match err {
+            TokenizeError::Malformed => TokenizeStatus::Malformed,
+            TokenizeError::PrefixMismatch => TokenizeStatus::PrefixMismatch,
+            TokenizeError::InvalidSignature => TokenizeStatus::InvalidSignature,
+            TokenizeError::UnknownKey => TokenizeStatus::UnknownKey,
+            TokenizeError::Decode => TokenizeStatus::Decode,
+            TokenizeError::TokenTooLong => TokenizeStatus::TokenTooLong,
+            TokenizeError::AccountNotFound => TokenizeStatus::AccountNotFound,
+            TokenizeError::Invalidated => TokenizeStatus::Invalidated,
+            TokenizeError::Expired => TokenizeStatus::Expired,
+            TokenizeError::IssuedInFuture => TokenizeStatus::IssuedInFuture,
+            TokenizeError::Revoked => TokenizeStatus::Revoked,
+            TokenizeError::MissingScope(_) => TokenizeStatus::MissingScope,
+            TokenizeError::AlreadyUsed => TokenizeStatus::AlreadyUsed,
+            TokenizeError::SingleUse => TokenizeStatus::SingleUse,
+            TokenizeError::InvalidClaims => TokenizeStatus::InvalidClaims,
+            TokenizeError::Store(_) => TokenizeStatus::Store,
+            TokenizeError::DeviceMismatch => TokenizeStatus::DeviceMismatch,
+            TokenizeError::AudienceMismatch => TokenizeStatus::AudienceMismatch,
+            TokenizeError::RateLimited => TokenizeStatus::RateLimited,
+            TokenizeError::PolicyViolation(_) => TokenizeStatus::PolicyViolation
+        }
+    }
+}
+
+/// Looks up the account `account_id` belongs to for [`tokenize_validate`].
+/// 
+/// Returns `false` if there is no such account, otherwise writes the account's last token reset
+/// timestamp, in milliseconds, to `last_token_reset` and returns `true`.
+pub type TokenizeFetchFn = extern "C" fn(account_id: *const c_char, user_data: *mut c_void, last_token_reset: *mut u64) -> bool;
+
+struct FfiAccount {
+    last_token_reset: u64
+}
+
+impl Account for FfiAccount {
+    fn last_token_reset(&self) -> u64 {
+        self.last_token_reset
+    }
+}
+
+/// Creates an instance signing tokens with the `secret_len` bytes at `secret`, and with `prefix` if it
+/// isn't null.
+/// 
+/// Returns null if the secret is empty or the prefix is invalid. The instance must be freed with
+/// [`tokenize_free`].
+/// 
+/// # Safety
+/// 
+/// `secret` must point to `secret_len` readable bytes, and `prefix` must be null or a nul-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn tokenize_new(secret: *const u8, secret_len: usize, prefix: *const c_char) -> *mut Tokenize {
+    if secret.is_null() || secret_len == 0 {
+        return ptr::null_mut()
+    }
+
+    let mut tokenize = Tokenize::new(std::slice::from_raw_parts(secret, secret_len));
+    if !prefix.is_null() {
+        tokenize = match CStr::from_ptr(prefix).to_str().ok().and_then(|prefix| tokenize.try_set_prefix(prefix).ok()) {
+            Some(tokenize) => tokenize,
+            None => return ptr::null_mut()
+        };
+    }
+
+    Box::into_raw(Box::new(tokenize))
+}
+
+/// Frees an instance created with [`tokenize_new`]. Does nothing if `tokenize` is null.
+/// 
+/// # Safety
+/// 
+/// `tokenize` must be null or returned by [`tokenize_new`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tokenize_free(tokenize: *mut Tokenize) {
+    if !tokenize.is_null() {
+        drop(Box::from_raw(tokenize));
+    }
+}
+
+/// Generates a new token for `account_id`, writing it to `token_out`.
+/// 
+/// # Safety
+/// 
+/// `tokenize` must be a live instance, `account_id` a nul-terminated string and `token_out` a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn tokenize_generate(tokenize: *const Tokenize, account_id: *const c_char, token_out: *mut *mut c_char) -> TokenizeStatus {
+    if tokenize.is_null() || token_out.is_null() {
+        return TokenizeStatus::InvalidArgument
+    }
+
+    let Some(account_id) = to_str(account_id) else {
+        return TokenizeStatus::InvalidArgument
+    };
+
+    match (*tokenize).generate(account_id) {
+        Ok(token) => {
+            *token_out = into_c_string(token);
+            TokenizeStatus::Ok
+        },
+        Err(err) => err.into()
+    }
+}
+
+/// Validates a token, looking its account up with `fetch`, which is passed `user_data` as-is.
+/// 
+/// On success, the id of the account the token belongs to is written to `account_id_out` unless it's
+/// null.
+/// 
+/// # Safety
+/// 
+/// `tokenize` must be a live instance, `token` a nul-terminated string and `account_id_out` null or a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn tokenize_validate(tokenize: *const Tokenize, token: *const c_char, fetch: TokenizeFetchFn, user_data: *mut c_void, account_id_out: *mut *mut c_char) -> TokenizeStatus {
+    if tokenize.is_null() {
+        return TokenizeStatus::InvalidArgument
+    }
+
+    let Some(token) = to_str(token) else {
+        return TokenizeStatus::InvalidArgument
+    };
+
+    let mut account_id = String::new();
+    let result = (*tokenize).validate(token, |id| {
+        let id_string = CString::new(id.as_str()).ok()?;
+        let mut last_token_reset = 0;
+        if !fetch(id_string.as_ptr(), user_data, &mut last_token_reset) {
+            return None
+        }
+
+        account_id = id;
+        Some(FfiAccount { last_token_reset })
+    });
+
+    match result {
+        Ok(_) => {
+            if !account_id_out.is_null() {
+                *account_id_out = into_c_string(account_id);
+            }
+
+            TokenizeStatus::Ok
+        },
+        Err(err) => err.into()
+    }
+}
+
+/// Frees a string returned by this library. Does nothing if `string` is null.
+/// 
+/// # Safety
+/// 
+/// `string` must be null or returned by this library, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tokenize_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Returns a static, nul-terminated description of `status`.
+#[no_mangle]
+pub extern "C" fn tokenize_status_message(status: TokenizeStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        TokenizeStatus::Ok => c"Success",
+        TokenizeStatus::InvalidArgument => c"Invalid argument",
+        TokenizeStatus::Malformed => c"Token is invalid",
+        TokenizeStatus::PrefixMismatch => c"Token prefix doesn't match",
+        TokenizeStatus::InvalidSignature => c"Token signature doesn't match",
+        TokenizeStatus::UnknownKey => c"Token was signed with an unknown key",
+        TokenizeStatus::Decode => c"Token segment couldn't be decoded",
+        TokenizeStatus::TokenTooLong => c"Token is too long",
+        TokenizeStatus::AccountNotFound => c"No account is tied to this id",
+        TokenizeStatus::Invalidated => c"Token was invalidated",
+        TokenizeStatus::Expired => c"Token has expired",
+        TokenizeStatus::IssuedInFuture => c"Token was issued in the future",
+        TokenizeStatus::Revoked => c"Token was revoked",
+        TokenizeStatus::MissingScope => c"Token is missing a scope",
+        TokenizeStatus::AlreadyUsed => c"Token was already used",
+        TokenizeStatus::SingleUse => c"Token is single-use",
+        TokenizeStatus::InvalidClaims => c"Token claims are invalid",
+        TokenizeStatus::Store => c"Couldn't fetch account",
+        TokenizeStatus::DeviceMismatch => c"Token is bound to another device",
+        TokenizeStatus::AudienceMismatch => c"Token was issued for another audience",
+        TokenizeStatus::RateLimited => c"Too many invalid tokens were presented",
+        TokenizeStatus::PolicyViolation => c"Token was denied by a policy"
+    };
+
+    message.as_ptr()
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None
+    }
+
+    CStr::from_ptr(string).to_str().ok()
+}
+
+fn into_c_string(string: String) -> *mut c_char {
+    // Tokens are base64, and account ids were already checked for nul bytes before fetching the account
+    CString::new(string).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn fetch(account_id: *const c_char, user_data: *mut c_void, last_token_reset: *mut u64) -> bool {
+        let account_id = unsafe { CStr::from_ptr(account_id) };
+        unsafe { *last_token_reset = *(user_data as *const u64) };
+        account_id.to_bytes() == b"326359466171826176"
+    }
+
+    #[test]
+    fn generate_and_validate() {
+        unsafe {
+            let tokenize = tokenize_new(b"uwu".as_ptr(), 3, c"app".as_ptr());
+            assert!(!tokenize.is_null());
+
+            let mut token = ptr::null_mut();
+            assert_eq!(tokenize_generate(tokenize, c"326359466171826176".as_ptr(), &mut token), TokenizeStatus::Ok);
+
+            let mut last_token_reset = 0u64;
+            let mut account_id = ptr::null_mut();
+            let status = tokenize_validate(tokenize, token, fetch, &mut last_token_reset as *mut u64 as *mut c_void, &mut account_id);
+            assert_eq!(status, TokenizeStatus::Ok);
+            assert_eq!(CStr::from_ptr(account_id).to_str(), Ok("326359466171826176"));
+            tokenize_string_free(account_id);
+
+            last_token_reset = i64::MAX as u64;
+            let status = tokenize_validate(tokenize, token, fetch, &mut last_token_reset as *mut u64 as *mut c_void, ptr::null_mut());
+            assert_eq!(status, TokenizeStatus::Invalidated);
+            assert_eq!(CStr::from_ptr(tokenize_status_message(status)).to_str(), Ok("Token was invalidated"));
+
+            let other = tokenize_new(b"owo".as_ptr(), 3, c"app".as_ptr());
+            assert_eq!(tokenize_validate(other, token, fetch, &mut last_token_reset as *mut u64 as *mut c_void, ptr::null_mut()), TokenizeStatus::InvalidSignature);
+            assert_eq!(tokenize_generate(other, ptr::null(), &mut token), TokenizeStatus::InvalidArgument);
+
+            assert!(tokenize_new(b"uwu".as_ptr(), 3, c"a.b".as_ptr()).is_null());
+            tokenize_string_free(token);
+            tokenize_free(tokenize);
+            tokenize_free(other);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Fixed-capacity tokens living on the stack, for latency-critical paths issuing and validating a
+//! token per message.
+
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+use arrayvec::ArrayString;
+use crate::{token, AccountId, Result, Tokenize, TokenizeError};
+
+/// Capacity of an [`InlineToken`] in bytes. Tokens of account ids of up to 64 bytes fit with a
+/// prefix, a key id, a few extensions and any built-in signer.
+pub const INLINE_TOKEN_CAPACITY: usize = 256;
+
+/// A token stored inline rather than in a heap-allocated string. See [`Tokenize::generate_inline`].
+/// 
+/// Inline tokens dereference to `str`, so they can be validated like any other token.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{InlineToken, Tokenize};
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let token = tokenize.generate_inline("326359466171826176")?;
+/// assert!(tokenize.parse_ref(&token).is_ok());
+/// 
+/// let received = token.as_str().parse::<InlineToken>()?;
+/// assert_eq!(received, token);
+/// # Ok::<(), tokenize::TokenizeError>(())
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InlineToken(ArrayString<INLINE_TOKEN_CAPACITY>);
+
+impl InlineToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for InlineToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InlineToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Fails with [`TokenizeError::TokenTooLong`] if the token doesn't fit in [`INLINE_TOKEN_CAPACITY`] bytes.
+impl FromStr for InlineToken {
+    type Err = TokenizeError;
+
+    fn from_str(token: &str) -> Result<InlineToken> {
+        ArrayString::from(token).map(InlineToken).map_err(|_| TokenizeError::TokenTooLong)
+    }
+}
+
+impl fmt::Display for InlineToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for InlineToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InlineToken").field(&format_args!("{}", token::REDACTED)).finish()
+    }
+}
+
+impl Tokenize {
+    /// Generates a new token stored inline, failing with [`TokenizeError::TokenTooLong`] if it
+    /// doesn't fit in [`INLINE_TOKEN_CAPACITY`] bytes.
+    /// 
+    /// Like [`Tokenize::generate_into`], the segments and signature are encoded straight into the
+    /// inline token.
+    pub fn generate_inline<S: Into<AccountId>>(&self, account_id: S) -> Result<InlineToken> {
+        let mut inline = ArrayString::new();
+        self.write_token(&self.unsigned_token(account_id.into()), None, &mut inline).map_err(|_| TokenizeError::TokenTooLong)?;
+
+        Ok(InlineToken(inline))
+    }
+}
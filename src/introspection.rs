@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Token introspection responses as described by [RFC 7662], available with the `introspection`
+//! feature.
+//! 
+//! Authentication services can expose an OAuth-compatible introspection endpoint by answering with
+//! [`Tokenize::introspect`], and resource servers can read the responses of such endpoints through
+//! [`IntrospectionResponse::from_json`].
+//! 
+//! [RFC 7662]: https://www.rfc-editor.org/rfc/rfc7662
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::{Account, Result, Token, Tokenize, TokenizeError};
+
+/// An introspection response, serialized as the JSON object defined by RFC 7662.
+/// 
+/// Only `active` is set for inactive tokens, so responses don't tell why a token was rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    /// Whether the token is valid.
+    pub active: bool,
+    /// The account id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    /// The generation time, in seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    /// The expiration time, in seconds since the unix epoch, when tokens have a maximum age.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    /// The scopes, separated by spaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// The audience the token was issued for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// The [fingerprint](Token::fingerprint) of the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>
+}
+
+impl IntrospectionResponse {
+    /// The response for a token which isn't valid.
+    pub fn inactive() -> IntrospectionResponse {
+        IntrospectionResponse::default()
+    }
+
+    /// The response for a valid token, expiring `max_age` after it was generated if set.
+    pub fn active(token: &Token, max_age: Option<Duration>) -> IntrospectionResponse {
+        let issued_at_millis = token.issued_at_millis();
+        let expires_at_millis = max_age.map(|max_age| issued_at_millis.saturating_add(i64::try_from(max_age.as_millis()).unwrap_or(i64::MAX)));
+
+        IntrospectionResponse {
+            active: true,
+            sub: Some(token.account_id().to_string()),
+            iat: Some(issued_at_millis.div_euclid(1000)),
+            exp: expires_at_millis.map(|millis| millis.div_euclid(1000)),
+            scope: (!token.scopes().is_empty()).then(|| token.scopes().join(" ")),
+            aud: token.audience().map(str::to_string),
+            jti: Some(token.fingerprint())
+        }
+    }
+
+    /// Parses a response returned by an introspection endpoint, failing with
+    /// [`TokenizeError::InvalidClaims`] if it isn't a valid introspection response.
+    pub fn from_json(json: &str) -> Result<IntrospectionResponse> {
+        serde_json::from_str(json).map_err(|_| TokenizeError::InvalidClaims)
+    }
+
+    /// Serializes the response to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Introspection responses are always serializable")
+    }
+
+    /// The scopes of the token.
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.as_deref().unwrap_or_default().split(' ').filter(|scope| !scope.is_empty())
+    }
+
+    /// Whether the token is active and carries `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.active && self.scopes().any(|s| s == scope)
+    }
+}
+
+impl Tokenize {
+    /// Validates a token like [`Tokenize::validate_token`], describing the outcome as an introspection
+    /// response. The expiration time is derived from [`ValidationOptions::max_age`](crate::ValidationOptions::max_age).
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.generate_with_scopes("326359466171826176", ["read"]).expect("Couldn't generate new token");
+    /// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+    /// 
+    /// let response = tokenize.introspect(token, account);
+    /// assert_eq!(response.sub.as_deref(), Some("326359466171826176"));
+    /// assert!(response.has_scope("read"));
+    /// 
+    /// assert_eq!(tokenize.introspect("thisisinvalid", account).to_json(), r#"{"active":false}"#);
+    /// ```
+    pub fn introspect<S, F, A>(&self, token: S, account_fetcher: F) -> IntrospectionResponse where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        match self.validate_token(token, account_fetcher) {
+            Ok((token, _)) => IntrospectionResponse::active(&token, self.options.max_age),
+            Err(_) => IntrospectionResponse::inactive()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use super::IntrospectionResponse;
+    use crate::clock::MockClock;
+    use crate::store::MemoryAccount;
+    use crate::{Tokenize, TokenizeError, ValidationOptions};
+
+    #[test]
+    fn introspect_tokens() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_audience("api")
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(3600)));
+        let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+
+        let token = tokenize.generate_token_with_scopes("326359466171826176", ["read", "write"]).expect("Couldn't generate new token");
+        let response = tokenize.introspect(token.to_string(), account);
+        assert_eq!(response, IntrospectionResponse {
+            active: true,
+            sub: Some("326359466171826176".to_string()),
+            iat: Some(1641635607),
+            exp: Some(1641639207),
+            scope: Some("read write".to_string()),
+            aud: Some("api".to_string()),
+            jti: Some(token.fingerprint())
+        });
+        assert_eq!(IntrospectionResponse::from_json(&response.to_json()), Ok(response.clone()));
+        assert_eq!(response.scopes().collect::<Vec<&str>>(), ["read", "write"]);
+
+        clock.advance(3601 * 1000);
+        assert_eq!(tokenize.introspect(token.to_string(), account), IntrospectionResponse::inactive());
+        assert!(!IntrospectionResponse::inactive().has_scope("read"));
+
+        // Unknown members are ignored, as required by the RFC
+        let response = IntrospectionResponse::from_json(r#"{"active":true,"sub":"1","client_id":"app"}"#).unwrap();
+        assert_eq!((response.active, response.sub.as_deref()), (true, Some("1")));
+        assert_eq!(IntrospectionResponse::from_json(r#"{"sub":"1"}"#), Err(TokenizeError::InvalidClaims));
+    }
+}
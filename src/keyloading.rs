@@ -0,0 +1,146 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Loading of secrets from the environment and secret managers.
+//! 
+//! Secrets can be loaded once through [`SecretKey::from_env`], or kept up to date with a
+//! [`KeyRefresher`] reloading them from a [`KeySource`] on a schedule. Sources for HashiCorp Vault and
+//! AWS Secrets Manager are available with the `vault` and `aws` features.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::{SecretKey, StoreError};
+
+/// A source secrets are loaded from.
+pub trait KeySource: Send + Sync {
+    /// Loads the current secret.
+    fn load(&self) -> Result<SecretKey, StoreError>;
+}
+
+impl<K: KeySource + ?Sized> KeySource for Arc<K> {
+    fn load(&self) -> Result<SecretKey, StoreError> {
+        (**self).load()
+    }
+}
+
+/// A [`KeySource`] reading an environment variable. See [`SecretKey::from_env`].
+#[derive(Debug, Clone)]
+pub struct EnvKeySource {
+    var: String
+}
+
+impl EnvKeySource {
+    pub fn new<S: Into<String>>(var: S) -> EnvKeySource {
+        EnvKeySource { var: var.into() }
+    }
+}
+
+impl KeySource for EnvKeySource {
+    fn load(&self) -> Result<SecretKey, StoreError> {
+        SecretKey::from_env(&self.var).map_err(|err| StoreError::new(format_args!("{}: {}", self.var, err)))
+    }
+}
+
+/// Reloads a secret from a [`KeySource`] on a schedule, in a background thread.
+/// 
+/// `on_update` is called with the first secret loaded, then whenever it changes, typically to swap a new
+/// [`Tokenize`](crate::Tokenize) instance into the one shared by the application, as
+/// [`SharedTokenize::refresh_from`](crate::shared::SharedTokenize::refresh_from) does. When loading fails,
+/// the current secret is kept and loading is retried at the next interval.
+/// 
+/// The thread stops after its current interval once the refresher is stopped or dropped.
+/// 
+/// # Examples
+/// 
+/// ```no_run
+/// use std::time::Duration;
+/// use tokenize::Tokenize;
+/// use tokenize::keyloading::{EnvKeySource, KeyRefresher};
+/// use tokenize::shared::SharedTokenize;
+/// 
+/// let shared = SharedTokenize::new(Tokenize::from_env("TOKENIZE_SECRET").expect("Invalid secret"));
+/// let tokenize = shared.clone();
+/// let refresher = KeyRefresher::spawn(EnvKeySource::new("TOKENIZE_SECRET"), Duration::from_secs(300), move |secret| {
+///     tokenize.store(Tokenize::new(secret));
+/// });
+/// ```
+#[derive(Debug)]
+pub struct KeyRefresher {
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl KeyRefresher {
+    /// Loads the secret from `source` every `interval`, calling `on_update` when it changed. The
+    /// first load happens after `interval`.
+    pub fn spawn<K, F>(source: K, interval: Duration, mut on_update: F) -> KeyRefresher where
+        K: KeySource + 'static,
+        F: FnMut(SecretKey) + Send + 'static {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stop = stopped.clone();
+
+        let handle = thread::spawn(move || {
+            let mut current = None;
+            loop {
+                thread::sleep(interval);
+                if stop.load(Ordering::Relaxed) {
+                    break
+                }
+
+                match source.load() {
+                    Ok(secret) if current.as_ref() != Some(&secret) => {
+                        current = Some(secret.clone());
+                        on_update(secret);
+                    },
+                    Ok(_) => {},
+                    #[cfg(feature = "tracing")]
+                    Err(err) => tracing::warn!(error = %err, "couldn't load secret"),
+                    #[cfg(not(feature = "tracing"))]
+                    Err(_) => {}
+                }
+            }
+        });
+
+        KeyRefresher { stopped, handle: Some(handle) }
+    }
+
+    /// Stops the refresher, waiting for its thread to finish its current interval.
+    pub fn stop(mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for KeyRefresher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
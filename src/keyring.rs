@@ -0,0 +1,111 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use std::collections::HashMap;
+use crate::SecretKey;
+
+/// Separator between the key id and the signature in the last segment of a token.
+pub(crate) const KEY_ID_SEPARATOR: char = '~';
+
+/// A set of secrets identified by a key id.
+/// 
+/// When a [`Tokenize`](crate::Tokenize) instance is configured with a keyring, the id of the key used
+/// to sign a token is embedded in its last segment (`<key id>~<signature>`), so validation can pick the
+/// right secret directly. Tokens are always signed with the primary key.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    primary: String,
+    keys: HashMap<String, SecretKey>
+}
+
+impl Keyring {
+    /// Creates a keyring whose primary key is `secret`, identified by `id`.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if `id` isn't a valid key id, see [`Keyring::is_valid_key_id`].
+    pub fn new<S: Into<String>, K: Into<SecretKey>>(id: S, secret: K) -> Keyring {
+        let id = id.into();
+        assert!(Self::is_valid_key_id(&id), "Invalid key id");
+
+        let mut keys = HashMap::new();
+        keys.insert(id.clone(), secret.into());
+
+        Keyring { primary: id, keys }
+    }
+
+    /// Adds a key only used to verify tokens.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if `id` isn't a valid key id, see [`Keyring::is_valid_key_id`].
+    pub fn add_key<S: Into<String>, K: Into<SecretKey>>(mut self, id: S, secret: K) -> Self {
+        let id = id.into();
+        assert!(Self::is_valid_key_id(&id), "Invalid key id");
+
+        self.keys.insert(id, secret.into());
+        self
+    }
+
+    /// Adds a key and makes it the one used to sign new tokens. The previous primary key is kept
+    /// for verification.
+    pub fn rotate<S: Into<String>, K: Into<SecretKey>>(mut self, id: S, secret: K) -> Self {
+        let id = id.into();
+        self = self.add_key(id.clone(), secret);
+        self.primary = id;
+        self
+    }
+
+    /// Removes a key, invalidating every token signed with it. The primary key can't be removed.
+    pub fn remove_key(&mut self, id: &str) -> Option<SecretKey> {
+        if id == self.primary { return None }
+        self.keys.remove(id)
+    }
+
+    /// The id of the key used to sign new tokens.
+    pub fn primary_id(&self) -> &str {
+        &self.primary
+    }
+
+    pub(crate) fn primary_secret(&self) -> &[u8] {
+        self.keys[&self.primary].expose()
+    }
+
+    pub(crate) fn has_empty_key(&self) -> bool {
+        self.keys.values().any(SecretKey::is_empty)
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<&[u8]> {
+        self.keys.get(id).map(SecretKey::expose)
+    }
+
+    /// Whether `id` can be used as a key id: non-empty and made of ASCII alphanumerics, `-` and `_`.
+    pub fn is_valid_key_id(id: &str) -> bool {
+        !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::fmt;
+use crate::{Account, AccountId, Result, StoreError, Token, Tokenize, TokenizeError};
+
+/// Kind of the account a token was issued for, telling apart the tokens of users and bots by their
+/// prefix. See [`Tokenize::set_token_kinds`].
+/// 
+/// Each kind has its own validation policy: bot tokens are long-lived credentials which survive the
+/// last token reset of their account. They aren't registered anywhere, revoking them one by one is
+/// the only way to invalidate them, so they're only accepted by instances with a
+/// [`RevocationStore`](crate::RevocationStore) to check them against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenKind {
+    /// Tokens of users, prefixed with `user.`. They go through every check of [`Tokenize::validate`].
+    User,
+    /// Tokens of bots, prefixed with `bot.`. The last token reset of their account is ignored, and
+    /// they're rejected unless the instance has a revocation store to check them against.
+    Bot
+}
+
+impl TokenKind {
+    /// The prefix of the tokens of this kind.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            TokenKind::User => "user",
+            TokenKind::Bot => "bot"
+        }
+    }
+
+    /// The kind of the tokens carrying `prefix`, if any.
+    pub fn from_prefix(prefix: &str) -> Option<TokenKind> {
+        [TokenKind::User, TokenKind::Bot].into_iter().find(|kind| kind.prefix() == prefix)
+    }
+
+    /// Whether tokens of this kind are invalidated by the last token reset of their account.
+    pub fn checks_reset(self) -> bool {
+        self == TokenKind::User
+    }
+
+    /// Whether tokens of this kind are only accepted by instances with a revocation store, checking
+    /// whether they were revoked.
+    pub fn requires_revocation_check(self) -> bool {
+        self == TokenKind::Bot
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
+
+impl Token {
+    /// The kind of the token according to its prefix, if it's one of the [`TokenKind`] prefixes.
+    pub fn kind(&self) -> Option<TokenKind> {
+        self.prefix().and_then(TokenKind::from_prefix)
+    }
+}
+
+impl Tokenize {
+    /// Accepts the prefixes of every [`TokenKind`], replacing the configured ones. Tokens are then
+    /// generated through [`Tokenize::generate_kind`] and validated through [`Tokenize::validate_kind`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenKind};
+    /// use tokenize::revocation::MemoryRevocationStore;
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu").set_token_kinds().set_revocation_store(MemoryRevocationStore::new());
+    /// let token = tokenize.generate_kind(TokenKind::Bot, "326359466171826176").expect("Couldn't generate new token");
+    /// assert!(token.starts_with("bot."));
+    /// 
+    /// // Resetting the tokens of the account leaves bot tokens valid
+    /// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: i64::MAX as u64 });
+    /// let (kind, _account) = tokenize.validate_kind(token, account).expect("Couldn't validate token");
+    /// assert_eq!(kind, TokenKind::Bot);
+    /// ```
+    pub fn set_token_kinds(self) -> Self {
+        self.set_prefixes([TokenKind::User.prefix(), TokenKind::Bot.prefix()])
+    }
+
+    /// Generates a new token of the given kind, failing with [`TokenizeError::PrefixMismatch`] unless
+    /// its prefix is accepted. See [`Tokenize::set_token_kinds`].
+    pub fn generate_kind<S: Into<AccountId>>(&self, kind: TokenKind, account_id: S) -> Result<String> {
+        self.generate_with_prefix(kind.prefix(), account_id)
+    }
+
+    /// Validates a token according to the policy of its [`TokenKind`], returning its kind along with
+    /// the account.
+    /// 
+    /// Tokens which aren't of any kind are rejected with [`TokenizeError::PrefixMismatch`]. Bot tokens
+    /// are rejected with [`TokenizeError::Store`] if no revocation store is configured, and go through
+    /// the same account checks as user tokens except for the last token reset.
+    pub fn validate_kind<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<(TokenKind, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            let kind = token.kind().ok_or(TokenizeError::PrefixMismatch)?;
+            if kind.requires_revocation_check() && self.revocation_store.is_none() {
+                return Err(StoreError::new("No revocation store is configured").into())
+            }
+
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            let account = self.check_account_with(account, token.issued_at_millis(), kind.checks_reset())?;
+
+            Ok((kind, account))
+        })
+    }
+}
@@ -0,0 +1,151 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::fmt;
+use std::time::Duration;
+use crate::{Account, Encoding, Result, Tokenize, TokenizeError};
+
+/// A check a token failed without being rejected by [`Tokenize::validate_lenient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ValidationWarning {
+    /// The token is older than the maximum age by the given duration, which is within
+    /// [`ValidationOptions::expiry_grace`](crate::ValidationOptions::expiry_grace).
+    Expired(Duration),
+    /// The token is encoded with another alphabet than the configured one, and was only accepted
+    /// through [`Tokenize::accept_any_encoding`].
+    DeprecatedEncoding(Encoding),
+    /// The token was issued before the last token reset of its account, within
+    /// [`ValidationOptions::reset_grace`](crate::ValidationOptions::reset_grace).
+    WithinResetGrace
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationWarning::Expired(overdue) => write!(f, "Token expired {}ms ago", overdue.as_millis()),
+            ValidationWarning::DeprecatedEncoding(encoding) => write!(f, "Token is encoded with a deprecated alphabet ({:?})", encoding),
+            ValidationWarning::WithinResetGrace => write!(f, "Token was issued before the last token reset")
+        }
+    }
+}
+
+/// An account returned by [`Tokenize::validate_lenient`], along with the checks its token failed
+/// without being rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedWith<A> {
+    pub account: A,
+    pub warnings: Vec<ValidationWarning>
+}
+
+impl<A> ValidatedWith<A> {
+    /// Whether the token passed every check.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+impl Tokenize {
+    /// Validates a token like [`Tokenize::validate`], downgrading the failures of non-critical checks
+    /// to warnings, so a stricter policy can be rolled out by logging the tokens it would reject
+    /// before enforcing it.
+    /// 
+    /// Tokens past their maximum age by less than [`ValidationOptions::expiry_grace`](crate::ValidationOptions::expiry_grace)
+    /// are accepted with [`ValidationWarning::Expired`]. Tokens using another encoding, accepted
+    /// through [`Tokenize::accept_any_encoding`], and tokens saved by
+    /// [`ValidationOptions::reset_grace`](crate::ValidationOptions::reset_grace) are flagged as well.
+    /// Every other check is enforced as usual.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use std::time::Duration;
+    /// use tokenize::{Tokenize, ValidationOptions, ValidationWarning};
+    /// use tokenize::clock::MockClock;
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let clock = std::sync::Arc::new(MockClock::new(1641635607000));
+    /// let tokenize = Tokenize::new("uwu")
+    ///     .set_clock(clock.clone())
+    ///     .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)).expiry_grace(Duration::from_secs(60)));
+    /// let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+    /// 
+    /// clock.advance(90 * 1000);
+    /// let validated = tokenize.validate_lenient(token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 }))
+    ///     .expect("Couldn't validate token");
+    /// assert_eq!(validated.warnings, vec![ValidationWarning::Expired(Duration::from_secs(30))]);
+    /// ```
+    pub fn validate_lenient<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<ValidatedWith<A>> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            if token.single_use {
+                return Err(TokenizeError::SingleUse)
+            }
+
+            let mut warnings = Vec::new();
+            if token.encoding != self.encoding {
+                warnings.push(ValidationWarning::DeprecatedEncoding(token.encoding));
+            }
+
+            match self.check_age(token.issued_at_millis(), token.expires_at_millis()) {
+                Err(TokenizeError::Expired) => warnings.push(ValidationWarning::Expired(self.overdue(token.issued_at_millis())?)),
+                result => result?
+            }
+
+            self.check_revocation(|| token.revocation_key())?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            let account = self.check_account(account, token.issued_at_millis())?;
+            if account.last_token_reset() as i64 > token.issued_at_millis() {
+                warnings.push(ValidationWarning::WithinResetGrace);
+            }
+
+            #[cfg(feature = "tracing")]
+            for warning in &warnings {
+                tracing::warn!(%warning, "token accepted leniently");
+            }
+
+            Ok(ValidatedWith { account, warnings })
+        })
+    }
+
+    /// How long an expired token is past its maximum age, failing with [`TokenizeError::Expired`] if
+    /// that's longer than the expiry grace.
+    fn overdue(&self, issued_at_millis: i64) -> Result<Duration> {
+        let age = self.clock.now_millis().saturating_sub(issued_at_millis).max(0) as u64;
+        let overdue = Duration::from_millis(age).saturating_sub(self.options.max_age.unwrap_or_default());
+        if self.options.expiry_grace.is_none_or(|grace| overdue > grace) {
+            return Err(TokenizeError::Expired)
+        }
+
+        Ok(overdue)
+    }
+}
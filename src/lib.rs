@@ -31,197 +31,4150 @@
 //! 
 //! [Tokenize]: https://github.com/cyyynthia/tokenize
 
-#[macro_use] extern crate anyhow;
 extern crate base64;
-extern crate crypto;
+// Lets the derive macros refer to `::tokenize` from within this crate
+#[cfg(feature = "derive")]
+extern crate self as tokenize;
 
-use chrono::Utc;
-use hmac_sha256::HMAC;
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "aws")]
+pub mod aws;
+mod account_id;
+mod accumulator;
+pub mod api_key;
+pub mod audit;
+mod builder;
+pub mod cache;
+pub mod clock;
+mod config;
+mod context;
+mod crypto;
+mod encoding;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+mod error;
+pub mod extract;
+#[cfg(feature = "arrayvec")]
+mod inline;
+#[cfg(feature = "hyper")]
+pub mod hyper;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod keyloading;
+#[cfg(feature = "introspection")]
+pub mod introspection;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+mod keyring;
+mod kind;
+mod lenient;
+pub mod metrics;
+pub mod mfa;
+#[cfg(feature = "moka")]
+pub mod moka;
+mod options;
+#[cfg(feature = "paseto")]
+pub mod paseto;
+mod platform;
+#[cfg(feature = "poem")]
+pub mod poem;
+pub mod policy;
+#[cfg(test)]
+mod proptests;
+pub mod purpose;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod ratelimit;
+#[cfg(feature = "redis")]
+pub mod redis;
+mod report;
+pub mod revocation;
+mod roles;
+#[cfg(feature = "salvo")]
+pub mod salvo;
+mod secret;
+pub mod session;
+pub mod shared;
+pub mod signer;
+#[cfg(feature = "sqlx")]
+pub mod sqlx;
+pub mod store;
+pub mod tenant;
+mod token;
+#[cfg(test)]
+mod vectors;
+#[cfg(feature = "tonic")]
+pub mod tonic;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "vault")]
+pub mod vault;
+#[cfg(feature = "warp")]
+pub mod warp;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::future::Future;
 use std::str;
-use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use audit::AuditEvent;
+
+pub use account_id::AccountId;
+pub use accumulator::{TokenAccumulator, DEFAULT_MAX_LENGTH};
+pub use audit::AuditSink;
+pub use builder::TokenizeBuilder;
+pub use clock::Clock;
+pub use config::StaticConfig;
+pub use context::ValidationContext;
+pub use encoding::{Codec, Encoding, SignatureFormat};
+#[cfg(feature = "arrayvec")]
+pub use inline::{InlineToken, INLINE_TOKEN_CAPACITY};
+pub use error::{ConfigError, Result, StoreError, TokenizeError};
+pub use keyring::Keyring;
+pub use kind::TokenKind;
+pub use lenient::{ValidatedWith, ValidationWarning};
+pub use metrics::MetricsSink;
+pub use options::ValidationOptions;
+pub use ratelimit::FailurePolicy;
+pub use report::{CheckOutcome, ValidationCheck, ValidationReport};
+pub use revocation::{RevocationStore, UsageStore};
+pub use roles::{TokenIssuer, TokenVerifier};
+pub use secret::SecretKey;
+pub use signer::Signer;
+pub use store::{AccountStore, AsyncAccountStore, BatchAccountStore, ShardedAccountStore};
+pub use token::{RedactedToken, TimestampPrecision, Token, TokenRef, TokenVersion, UnverifiedToken};
+#[cfg(feature = "derive")]
+pub use tokenize_derive::{Account, MutableAccount};
+
+// Instances are meant to be shared across threads without locking, this fails to compile otherwise
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Tokenize>();
+    assert_send_sync::<TokenizeBuilder>();
+    assert_send_sync::<StaticConfig>();
+    assert_send_sync::<ValidationOptions>();
+    assert_send_sync::<Keyring>();
+};
 
 pub const TOKENIZE_VERSION: u32 = 1;
 pub const TOKENIZE_EPOCH: i64 = 1546300800000;
 
 pub struct Tokenize {
-    secret: Vec<u8>,
-    prefix: Option<String>
+    secret: SecretKey,
+    old_secrets: Vec<SecretKey>,
+    keyring: Option<Keyring>,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<SecretKey>,
+    prefixes: Vec<String>,
+    audience: Option<String>,
+    version: TokenVersion,
+    precision: TimestampPrecision,
+    encoding: Encoding,
+    accept_any_encoding: bool,
+    signature_format: SignatureFormat,
+    accept_any_signature_format: bool,
+    unique_tokens: bool,
+    signer: Box<dyn Signer>,
+    clock: Box<dyn Clock>,
+    revocation_store: Option<Box<dyn RevocationStore>>,
+    usage_store: Option<Box<dyn UsageStore>>,
+    metrics: Option<Box<dyn MetricsSink>>,
+    failure_policy: Option<Box<dyn FailurePolicy>>,
+    audit_sink: Option<Box<dyn AuditSink>>,
+    policy: Option<Box<dyn policy::Policy>>,
+    epoch: i64,
+    options: ValidationOptions
+}
+
+impl fmt::Debug for Tokenize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokenize")
+            .field("secret", &format_args!("{}", token::REDACTED))
+            .field("key_id", &self.keyring.as_ref().map(Keyring::primary_id))
+            .field("prefix", &self.prefixes.first())
+            .field("audience", &self.audience)
+            .field("version", &self.version)
+            .field("encoding", &self.encoding)
+            .field("signature_format", &self.signature_format)
+            .field("epoch", &self.epoch)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Tokenize {
-    pub fn new(secret: Vec<u8>) -> Tokenize {
+    pub fn new<K: Into<SecretKey>>(secret: K) -> Tokenize {
+        Tokenize {
+            secret: secret.into(),
+            old_secrets: Vec::new(),
+            keyring: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            prefixes: Vec::new(),
+            audience: None,
+            version: TokenVersion::default(),
+            precision: TimestampPrecision::default(),
+            encoding: Encoding::default(),
+            accept_any_encoding: false,
+            signature_format: SignatureFormat::default(),
+            accept_any_signature_format: false,
+            unique_tokens: false,
+            signer: Box::new(signer::HmacSha256),
+            clock: Box::new(clock::SystemClock),
+            revocation_store: None,
+            usage_store: None,
+            metrics: None,
+            failure_policy: None,
+            audit_sink: None,
+            policy: None,
+            epoch: TOKENIZE_EPOCH,
+            options: ValidationOptions::default()
+        }
+    }
+
+    /// Creates a new instance signing tokens with a secret derived from `master` and `context`, such
+    /// as `"auth-tokens-v1"`. See [`SecretKey::derive`].
+    /// 
+    /// A single master key can back several kinds of tokens without reusing the same secret, as long
+    /// as each kind uses its own context.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// 
+    /// let auth = Tokenize::from_master_key("master key", "auth-tokens-v1");
+    /// let reset = Tokenize::from_master_key("master key", "password-reset-v1");
+    /// 
+    /// let token = auth.generate("326359466171826176").expect("Couldn't generate new token");
+    /// assert_eq!(reset.parse(token).err(), Some(TokenizeError::InvalidSignature));
+    /// ```
+    pub fn from_master_key<K: Into<SecretKey>>(master: K, context: &str) -> Tokenize {
+        Tokenize::new(master.into().derive(context))
+    }
+
+    /// Creates a new instance signing tokens with a secret read from an environment variable, such as
+    /// `TOKENIZE_SECRET`. See [`SecretKey::from_env`].
+    pub fn from_env(var: &str) -> Result<Tokenize, ConfigError> {
+        Ok(Tokenize::new(SecretKey::from_env(var)?))
+    }
+
+    /// Creates a [`TokenizeBuilder`], validating the configuration when building.
+    pub fn builder() -> TokenizeBuilder {
+        TokenizeBuilder::new()
+    }
+
+    /// Creates a new instance signing tokens with `primary`, and additionally accepting tokens
+    /// signed with any of `old_secrets`.
+    /// 
+    /// This allows rotating the secret without invalidating every token at once: new tokens are
+    /// issued with the new secret while tokens signed with the previous ones remain valid until
+    /// they're removed from the list.
+    pub fn with_secrets<K, I>(primary: K, old_secrets: I) -> Tokenize where
+        K: Into<SecretKey>,
+        I: IntoIterator,
+        I::Item: Into<SecretKey> {
+        Tokenize {
+            old_secrets: old_secrets.into_iter().map(Into::into).collect(),
+            ..Tokenize::new(primary)
+        }
+    }
+
+    /// Creates a new instance backed by a [`Keyring`].
+    /// 
+    /// Tokens are signed with the primary key of the keyring and carry its id, which is used during
+    /// validation to pick the key to verify them with. Tokens that don't carry any key id are rejected.
+    pub fn with_keyring(keyring: Keyring) -> Tokenize {
         Tokenize {
-            secret,
-            prefix: None
+            keyring: Some(keyring),
+            ..Tokenize::new(SecretKey::new(Vec::new()))
+        }
+    }
+
+    /// Sets the prefix of the tokens.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if the prefix is invalid, see [`Tokenize::try_set_prefix`].
+    pub fn set_prefix<S: Into<String>>(self, prefix: S) -> Self {
+        self.try_set_prefix(prefix).expect("Invalid prefix")
+    }
+
+    /// Sets the prefix of the tokens, failing with [`ConfigError::InvalidPrefix`] if it's empty or
+    /// contains anything but printable ASCII characters other than `.` and `:`, which would corrupt the
+    /// token format.
+    pub fn try_set_prefix<S: Into<String>>(self, prefix: S) -> Result<Self, ConfigError> {
+        self.try_set_prefixes([prefix])
+    }
+
+    /// Sets several prefixes, accepting tokens carrying any of them during validation. The prefix a
+    /// token was issued with is available through [`Token::prefix`].
+    /// 
+    /// Tokens are generated with the first prefix, [`Tokenize::generate_with_prefix`] generates tokens
+    /// with the other ones.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if there are no prefixes or if one of them is invalid, see [`Tokenize::try_set_prefix`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// 
+    /// let tokenize = Tokenize::new("uwu").set_prefixes(["mfa", "bot"]);
+    /// let token = tokenize.generate_with_prefix("bot", "326359466171826176").expect("Couldn't generate new token");
+    /// 
+    /// assert_eq!(tokenize.parse(token).expect("Couldn't parse token").prefix(), Some("bot"));
+    /// ```
+    pub fn set_prefixes<I>(self, prefixes: I) -> Self where
+        I: IntoIterator,
+        I::Item: Into<String> {
+        self.try_set_prefixes(prefixes).expect("Invalid prefix")
+    }
+
+    /// Sets several prefixes, failing with [`ConfigError::InvalidPrefix`] if there are none or if one
+    /// of them is invalid. See [`Tokenize::set_prefixes`].
+    pub fn try_set_prefixes<I>(mut self, prefixes: I) -> Result<Self, ConfigError> where
+        I: IntoIterator,
+        I::Item: Into<String> {
+        let prefixes = prefixes.into_iter().map(Into::into).collect::<Vec<String>>();
+        if prefixes.is_empty() || !prefixes.iter().all(|prefix| token::is_valid_prefix(prefix)) {
+            return Err(ConfigError::InvalidPrefix)
         }
+
+        self.prefixes = prefixes;
+        Ok(self)
     }
 
-    pub fn set_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
-        self.prefix = Some(prefix.into());
+    /// Sets the audience new tokens are issued for, such as `"public-api"`. The audience is signed
+    /// along with the rest of the token.
+    /// 
+    /// Services sharing a secret can set [`ValidationOptions::expected_audience`] to reject the tokens
+    /// issued for the other ones.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError, ValidationOptions};
+    /// 
+    /// let public = Tokenize::new("uwu").set_audience("public-api");
+    /// let admin = Tokenize::new("uwu").set_validation_options(ValidationOptions::new().expected_audience("admin-api"));
+    /// 
+    /// let token = public.generate("326359466171826176").expect("Couldn't generate new token");
+    /// assert_eq!(admin.parse(token).err(), Some(TokenizeError::AudienceMismatch));
+    /// ```
+    pub fn set_audience<S: Into<String>>(mut self, audience: S) -> Self {
+        self.audience = Some(audience.into());
         self
     }
 
-    pub fn generate<S: Into<String>>(&self, account_id: S) -> Result<String> {
-        let current_time = Self::current_token_time().to_string();
-        let account_part = base64::encode_config(account_id.into(), base64::STANDARD_NO_PAD);
-        let time_part = base64::encode_config(current_time, base64::STANDARD_NO_PAD);
-        let prefix_part = if let Some(prefix) = self.prefix.as_ref() {
-            format!("{}.", prefix)
-        } else { String::new() };
-        
-        let token = format!("{}{}.{}", prefix_part, account_part, time_part);
-        let signature = Self::compute_hmac(&token, &self.secret);
-        let signature_part = base64::encode_config(signature, base64::STANDARD_NO_PAD);
+    /// Sets the version of the format new tokens are issued in. Defaults to [`TokenVersion::V1`].
+    /// 
+    /// Tokens of every version are accepted during validation.
+    pub fn set_version(mut self, version: TokenVersion) -> Self {
+        self.version = version;
+        self
+    }
 
-        Ok(format!("{}.{}", token, signature_part))
+    /// Sets the precision of the generation time of new tokens. Defaults to [`TimestampPrecision::Seconds`].
+    /// 
+    /// With [`TimestampPrecision::Milliseconds`], tokens generated for the same account within the same
+    /// second differ, and [`Tokenize::invalidate_all`] only invalidates the tokens issued before the
+    /// exact millisecond. Tokens of either precision are accepted during validation.
+    pub fn set_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
     }
 
-    /// Validates a token.
+    /// Sets the base64 alphabet used to encode tokens. Defaults to [`Encoding::Standard`].
     /// 
-    /// # Arguments
+    /// Only tokens using this alphabet are accepted during validation, unless
+    /// [`Tokenize::accept_any_encoding`] is enabled. Another [`Codec`] can be plugged in with
+    /// [`Encoding::Custom`].
+    pub fn set_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Accepts tokens encoded with any supported alphabet during validation, regardless of the
+    /// configured encoding. This is meant to be used while migrating from one encoding to another.
+    pub fn accept_any_encoding(mut self) -> Self {
+        self.accept_any_encoding = true;
+        self
+    }
+
+    /// Sets how the signature segment of tokens is encoded. Defaults to [`SignatureFormat::Base64`].
     /// 
-    /// * `token` - The provided token
-    /// * `account_fetcher` - The closure used to fetch the account. It'll receive the account id as a string
-    /// and should return a struct that implements [`Account`].
+    /// Only signatures in this format are accepted during validation, unless
+    /// [`Tokenize::accept_any_signature_format`] is enabled.
     /// 
     /// # Examples
     /// 
     /// ```
-    /// use tokenize::{Tokenize, Account};
+    /// use tokenize::{SignatureFormat, Tokenize};
     /// 
-    /// pub struct TestAccount;
+    /// let legacy = Tokenize::new("uwu").set_signature_format(SignatureFormat::Hex);
+    /// let token = legacy.generate_token("326359466171826176").expect("Couldn't generate new token");
+    /// assert_eq!(token.signature().len(), 64);
     /// 
-    /// impl Account for TestAccount {
-    ///     fn last_token_reset(&self) -> u64 {
-    ///         0 // retrieve last token reset here
-    ///     }
-    /// }
+    /// let migrating = Tokenize::new("uwu").accept_any_signature_format();
+    /// assert!(migrating.parse(token.to_string()).is_ok());
+    /// ```
+    pub fn set_signature_format(mut self, format: SignatureFormat) -> Self {
+        self.signature_format = format;
+        self
+    }
+
+    /// Accepts signatures in any [`SignatureFormat`] during validation, regardless of the configured
+    /// format. This is meant to be used while migrating from one format to another.
+    pub fn accept_any_signature_format(mut self) -> Self {
+        self.accept_any_signature_format = true;
+        self
+    }
+
+    /// Appends a random 64 bits nonce to every new token as a signed extension, so tokens generated
+    /// for the same account at the same time never collide. Each token then has its own
+    /// [`Token::revocation_key`], and revoking one doesn't revoke its twins.
+    pub fn unique_tokens(mut self) -> Self {
+        self.unique_tokens = true;
+        self
+    }
+
+    /// Sets the algorithm used to sign and verify tokens. Defaults to [`signer::HmacSha256`].
+    pub fn set_signer<S: Signer + 'static>(mut self, signer: S) -> Self {
+        self.signer = Box::new(signer);
+        self
+    }
+
+    /// Sets the epoch token timestamps are relative to, as a unix timestamp in milliseconds. Defaults
+    /// to [`TOKENIZE_EPOCH`], as defined by the specification.
     /// 
-    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// Tokens issued with a different epoch are misdated, so every party has to agree on it.
+    pub fn set_epoch(mut self, epoch: i64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Sets the source of the current time. Defaults to [`clock::SystemClock`].
+    pub fn set_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Sets the store revoked tokens are recorded in. Tokens are checked against it during validation,
+    /// right after their signature has been verified.
+    pub fn set_revocation_store<R: RevocationStore + 'static>(mut self, store: R) -> Self {
+        self.revocation_store = Some(Box::new(store));
+        self
+    }
+
+    /// Sets the store consumed single-use tokens are recorded in. See [`Tokenize::validate_single_use`].
+    pub fn set_usage_store<U: UsageStore + 'static>(mut self, store: U) -> Self {
+        self.usage_store = Some(Box::new(store));
+        self
+    }
+
+    /// Sets the sink the outcome and latency of validations are reported to.
+    pub fn set_metrics_sink<M: MetricsSink + 'static>(mut self, sink: M) -> Self {
+        self.metrics = Some(Box::new(sink));
+        self
+    }
+
+    /// Sets the policy throttling peers presenting forged tokens to [`Tokenize::validate_from`].
+    pub fn set_failure_policy<P: FailurePolicy + 'static>(mut self, policy: P) -> Self {
+        self.failure_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Sets the policy authorizing tokens validated through [`Tokenize::validate_with_context`].
+    pub fn set_policy<P: policy::Policy + 'static>(mut self, policy: P) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Sets the additional checks performed when validating tokens.
+    pub fn set_validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn generate<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        self.generate_token(account_id).map(|token| token.to_string())
+    }
+
+    /// Generates a new token, returning it as a [`Token`].
+    /// 
+    /// Account ids can be given as strings, bytes or integers, see [`AccountId`].
+    /// 
+    /// The token can be turned into its string form through its [`Display`](std::fmt::Display) implementation.
+    pub fn generate_token<S: Into<AccountId>>(&self, account_id: S) -> Result<Token> {
+        Ok(self.sign(self.unsigned_token(account_id.into())))
+    }
+
+    /// Generates a new token like [`Tokenize::generate`], appending it to `buffer` instead of
+    /// allocating a new string. Buffers can be cleared and reused across calls.
+    /// 
+    /// The segments and signature are encoded straight into `buffer`, which is grown at most once, by
+    /// [`Tokenize::token_len_hint`].
+    /// 
+    /// # Examples
     /// 
-    /// tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
-    ///     Some(TestAccount)
-    /// }).expect("Couldn't validate token");
     /// ```
-    pub fn validate<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where 
-        S: Into<String>,
-        F: FnMut(String) -> Option<A>,
-        A: Account {
-        let token = token.into();
-        let splitted = token.split(".").collect::<Vec<&str>>();
+    /// use tokenize::Tokenize;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let mut buffer = String::with_capacity(tokenize.token_len_hint(18));
+    /// for account_id in ["326359466171826176", "326359466171826177"] {
+    ///     buffer.clear();
+    ///     tokenize.generate_into(account_id, &mut buffer).expect("Couldn't generate new token");
+    ///     assert!(buffer.len() <= buffer.capacity());
+    ///     assert!(tokenize.parse(&buffer).is_ok());
+    /// }
+    /// ```
+    pub fn generate_into<S: Into<AccountId>>(&self, account_id: S, buffer: &mut String) -> Result<()> {
+        let account_id = account_id.into();
+        buffer.reserve(self.token_len_hint(account_id.as_bytes().len()));
+        self.write_token(&self.unsigned_token(account_id), None, buffer).map_err(|_| TokenizeError::TokenTooLong)?;
 
-        let max_len = if self.prefix.is_some() { 4 } else { 3 };
-        if splitted.len() < 3 || splitted.len() > max_len { bail!("Token is invalid") }
+        Ok(())
+    }
 
-        let signature_string;
+    /// Upper bound of the length of the tokens [`Tokenize::generate`] issues right now for account
+    /// ids of `account_id_len` bytes, to preallocate buffers. Scopes, claims and other extensions
+    /// only some tokens carry aren't accounted for.
+    pub fn token_len_hint(&self, account_id_len: usize) -> usize {
+        let elapsed = ((self.clock.now_millis() - self.epoch) / 1000).max(0) as u64;
+        let time_len = elapsed.checked_ilog10().map_or(1, |digits| digits as usize + 1);
+        // Extensions are written as `.t:payload`, with one character long tags
+        let extension_len = |payload_len| 3 + self.encoding.encoded_len(payload_len);
 
-        if let Some(prefix) = &self.prefix {
-            if prefix != splitted[0] {
-                bail!("Token prefix doesn't match")
-            }
+        let mut len = self.encoding.encoded_len(account_id_len) + 1 + self.encoding.encoded_len(time_len) + 1;
+        len += self.prefixes.first().map_or(0, |prefix| prefix.len() + 1);
+        len += match self.version {
+            TokenVersion::V1 => 0,
+            TokenVersion::V2 => token::V2_HEADER.len() + 1
+        };
 
-            signature_string = format!("{}.{}.{}", prefix, splitted[1], splitted[2]);
-        } else {
-            signature_string = format!("{}.{}", splitted[0], splitted[1]);
+        if self.unique_tokens {
+            len += extension_len(16);
+        }
+
+        if let Some(audience) = &self.audience {
+            len += extension_len(audience.len());
         }
 
-        let signature = Self::compute_hmac(&signature_string, &self.secret);
+        if self.precision == TimestampPrecision::Milliseconds {
+            len += extension_len(3);
+        }
 
-        if !crypto::util::fixed_time_eq(base64::encode_config(signature, base64::STANDARD_NO_PAD).as_bytes(), splitted[max_len - 1].as_bytes()) {
-            bail!("Token signature doesn't match")
+        if let Some(keyring) = &self.keyring {
+            len += keyring.primary_id().len() + 1;
         }
 
-        let account_id: String = str::from_utf8(&base64::decode_config(splitted[max_len - 3], base64::STANDARD_NO_PAD)?)?.to_string();
-        let timestamp: u64 = str::from_utf8(&base64::decode_config(splitted[max_len - 2], base64::STANDARD_NO_PAD)?)?.parse()?;
+        len + self.signature_format.encoding(self.encoding).encoded_len(self.signer.signature_len())
+    }
 
-        let account_opt = account_fetcher(account_id);
-        
-        let account = if let Some(account) = account_opt {
-            account
-        } else { bail!("No account is tied to this id") };
+    /// Generates a new token with one of the prefixes set through [`Tokenize::set_prefixes`], failing
+    /// with [`TokenizeError::PrefixMismatch`] if it isn't one of them.
+    pub fn generate_with_prefix<P, S>(&self, prefix: P, account_id: S) -> Result<String> where
+        P: AsRef<str>,
+        S: Into<AccountId> {
+        let prefix = self.prefixes.iter().find(|p| *p == prefix.as_ref()).ok_or(TokenizeError::PrefixMismatch)?;
+        let mut token = self.unsigned_token(account_id.into());
+        token.prefix = Some(prefix.clone());
 
-        let last_token_reset = account.last_token_reset();
-        if last_token_reset as i64 > ((timestamp as i64 * 1000) + TOKENIZE_EPOCH) {
-            bail!("Token was invalidated")
+        Ok(self.sign(token).to_string())
+    }
+
+    /// Generates a new token for a binary account id, such as a raw UUID or a big-endian integer.
+    /// 
+    /// Such tokens are validated through [`Tokenize::validate_bytes`]; the other validation methods
+    /// reject ids which aren't valid UTF-8 with [`TokenizeError::Decode`].
+    pub fn generate_bytes<B: AsRef<[u8]>>(&self, account_id: B) -> Result<String> {
+        Ok(self.sign(self.unsigned_token(AccountId::from(account_id.as_ref()))).to_string())
+    }
+
+    /// Generates a new token carrying `scopes`, which are signed along with the rest of the token.
+    /// 
+    /// Scopes can be checked after validation through [`Token::require_scope`].
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Malformed`] if a scope is empty or contains a `,`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// let token = tokenize.generate_with_scopes("326359466171826176", ["read", "write"]).expect("Couldn't generate new token");
+    /// let token = tokenize.parse(token).expect("Couldn't parse token");
+    /// 
+    /// assert!(token.require_scope("write").is_ok());
+    /// assert_eq!(token.require_scope("admin"), Err(TokenizeError::MissingScope("admin".to_string())));
+    /// ```
+    pub fn generate_with_scopes<S, I>(&self, account_id: S, scopes: I) -> Result<String> where
+        S: Into<AccountId>,
+        I: IntoIterator,
+        I::Item: Into<String> {
+        self.generate_token_with_scopes(account_id, scopes).map(|token| token.to_string())
+    }
+
+    /// Generates a new token carrying `scopes`, returning it as a [`Token`]. See [`Tokenize::generate_with_scopes`].
+    pub fn generate_token_with_scopes<S, I>(&self, account_id: S, scopes: I) -> Result<Token> where
+        S: Into<AccountId>,
+        I: IntoIterator,
+        I::Item: Into<String> {
+        let scopes = scopes.into_iter().map(Into::into).collect::<Vec<String>>();
+        if scopes.iter().any(|scope| scope.is_empty() || scope.contains(token::SCOPE_SEPARATOR)) {
+            return Err(TokenizeError::Malformed)
         }
 
-        Ok(account)
+        let mut token = self.unsigned_token(account_id.into());
+        token.scopes = scopes;
+
+        Ok(self.sign(token))
     }
 
-    pub fn current_token_time() -> i64 {
-        (Utc::now().timestamp_millis() - TOKENIZE_EPOCH) / 1000
+    /// Generates a new token bound to `device_id`, such as an id stored in a cookie or derived from
+    /// client hints. The device id is signed along with the rest of the token.
+    /// 
+    /// [`Tokenize::validate_for_device`] only accepts the token when it's presented along with the same
+    /// device id, so a stolen token can't be used from another device. Other validation methods ignore
+    /// the binding, it can be checked afterwards through [`Token::require_device`].
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Malformed`] if the device id is empty.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.generate_for_device("326359466171826176", "laptop-4f2a").expect("Couldn't generate new token");
+    /// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+    /// 
+    /// assert!(tokenize.validate_for_device(&*token, "laptop-4f2a", account).is_ok());
+    /// assert_eq!(tokenize.validate_for_device(token, "phone-91c7", account).err(), Some(TokenizeError::DeviceMismatch));
+    /// ```
+    pub fn generate_for_device<S, D>(&self, account_id: S, device_id: D) -> Result<String> where
+        S: Into<AccountId>,
+        D: Into<String> {
+        self.generate_token_for_device(account_id, device_id).map(|token| token.to_string())
     }
 
-    fn compute_hmac(token: &str, secret: &Vec<u8>) -> [u8; 32] {
-        let input = format!("TTF.{}.{}", TOKENIZE_VERSION, token);
+    /// Generates a new token bound to `device_id`, returning it as a [`Token`]. See [`Tokenize::generate_for_device`].
+    pub fn generate_token_for_device<S, D>(&self, account_id: S, device_id: D) -> Result<Token> where
+        S: Into<AccountId>,
+        D: Into<String> {
+        let device_id = device_id.into();
+        if device_id.is_empty() {
+            return Err(TokenizeError::Malformed)
+        }
+
+        let mut token = self.unsigned_token(account_id.into());
+        token.device_id = Some(device_id);
 
-        HMAC::mac(input.as_bytes(), secret)
+        Ok(self.sign(token))
     }
-}
 
-pub trait Account {
-    fn last_token_reset(&self) -> u64;
-}
+    /// Generates a token issued at `timestamp`, a unix timestamp in milliseconds, instead of the time
+    /// given by the [`Clock`]. This is meant for test suites and migration scripts needing tokens with
+    /// exact timestamps.
+    /// 
+    /// With the default settings, tokens generated for the same account and timestamp are identical.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Malformed`] if `timestamp` is before the epoch, which tokens can't
+    /// represent.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.generate_at("326359466171826176", 1641635607000).expect("Couldn't generate new token");
+    /// 
+    /// assert_eq!(token, "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc");
+    /// ```
+    pub fn generate_at<S: Into<AccountId>>(&self, account_id: S, timestamp: i64) -> Result<String> {
+        self.generate_token_at(account_id, timestamp).map(|token| token.to_string())
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::{Tokenize, Account};
+    /// Generates a token issued at `timestamp`, returning it as a [`Token`]. See [`Tokenize::generate_at`].
+    pub fn generate_token_at<S: Into<AccountId>>(&self, account_id: S, timestamp: i64) -> Result<Token> {
+        if timestamp < self.epoch {
+            return Err(TokenizeError::Malformed)
+        }
 
-    pub struct TestAccount {
-        last_token_reset: u64
+        Ok(self.sign(self.unsigned_token_at(account_id.into(), timestamp)))
     }
 
-    impl Account for TestAccount {
-        fn last_token_reset(&self) -> u64 {
-            self.last_token_reset
+    /// Generates a new token carrying the given nonce instead of a random one, such as the one of a
+    /// token being reissued. See [`Tokenize::unique_tokens`].
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Malformed`] if the nonce is empty.
+    pub fn generate_with_nonce<S, N>(&self, account_id: S, nonce: N) -> Result<String> where
+        S: Into<AccountId>,
+        N: Into<String> {
+        let nonce = nonce.into();
+        if nonce.is_empty() {
+            return Err(TokenizeError::Malformed)
         }
+
+        let mut token = self.unsigned_token(account_id.into());
+        token.nonce = Some(nonce);
+
+        Ok(self.sign(token).to_string())
     }
 
-    #[test]
-    fn generate_token() {
-        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
-        tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+    /// Generates a new token carrying `claims`, which are serialized as JSON and signed along with the
+    /// rest of the token. Available with the `claims` feature.
+    /// 
+    /// This is meant for small pieces of data such as session or device ids: claims aren't encrypted,
+    /// anyone holding the token can read them.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use serde::{Deserialize, Serialize};
+    /// use tokenize::{Account, Tokenize};
+    /// 
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Session { id: u64 }
+    /// 
+    /// pub struct TestAccount;
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// let token = tokenize.generate_with_claims("326359466171826176", &Session { id: 42 }).expect("Couldn't generate new token");
+    /// 
+    /// let (_account, session) = tokenize.validate_with_claims::<_, _, _, Session>(token, |_id| Some(TestAccount))
+    ///     .expect("Couldn't validate token");
+    /// assert_eq!(session.id, 42);
+    /// ```
+    #[cfg(feature = "claims")]
+    pub fn generate_with_claims<S, C>(&self, account_id: S, claims: &C) -> Result<String> where
+        S: Into<AccountId>,
+        C: serde::Serialize + ?Sized {
+        self.generate_token_with_claims(account_id, claims).map(|token| token.to_string())
     }
 
-    #[test]
-    fn generate_token_with_prefix() {
-        let prefix = "prefix";
+    /// Generates a new token carrying `claims`, returning it as a [`Token`]. See [`Tokenize::generate_with_claims`].
+    #[cfg(feature = "claims")]
+    pub fn generate_token_with_claims<S, C>(&self, account_id: S, claims: &C) -> Result<Token> where
+        S: Into<AccountId>,
+        C: serde::Serialize + ?Sized {
+        let claims = serde_json::to_string(claims).map_err(|_| TokenizeError::InvalidClaims)?;
 
-        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix(prefix);
-        assert!(tokenize.generate("326359466171826176").expect("Couldn't generate new token").starts_with(prefix));
+        let mut token = self.unsigned_token(account_id.into());
+        token.claims = Some(claims);
+
+        Ok(self.sign(token))
     }
 
-    #[test]
-    fn validate_token() {
-        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
-        tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
-            Some(TestAccount { last_token_reset: 0 })
-        }).expect("Couldn't validate token");
+    /// Creates a token for `account_id` issued now, without any extension. It has to go through
+    /// [`Tokenize::sign`] once its extensions are set.
+    pub(crate) fn unsigned_token(&self, account_id: AccountId) -> Token {
+        self.unsigned_token_at(account_id, self.clock.now_millis())
     }
 
-    #[test]
-    fn validate_token_with_prefix() {
-        let prefix = "prefix";
+    /// Creates an unsigned token issued at `timestamp`, in milliseconds. See [`Tokenize::unsigned_token`].
+    fn unsigned_token_at(&self, account_id: AccountId, timestamp: i64) -> Token {
+        let elapsed = timestamp - self.epoch;
 
-        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix(prefix);
-        tokenize.validate("prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI", |_id| {
-            Some(TestAccount { last_token_reset: 0 })
-        }).expect("Couldn't validate token");
+        Token {
+            version: self.version,
+            prefix: self.prefixes.first().cloned(),
+            account_id,
+            generated_at: (elapsed / 1000) as u64,
+            key_id: None,
+            signature: String::new(),
+            encoding: self.encoding,
+            signature_format: self.signature_format,
+            scopes: Vec::new(),
+            claims: None,
+            nonce: self.unique_tokens.then(token::nonce),
+            single_use: false,
+            device_id: None,
+            audience: self.audience.clone(),
+            millis: (self.precision == TimestampPrecision::Milliseconds).then(|| elapsed.rem_euclid(1000) as u16),
+            ttl_millis: None,
+            epoch: self.epoch
+        }
     }
 
-    #[test]
-    fn validate_invalidated_token() {
-        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
-        assert!(tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
-            Some(TestAccount { last_token_reset: 1641641228500 })
-        }).is_err());
+    /// Signs a token created by [`Tokenize::unsigned_token`].
+    pub(crate) fn sign(&self, token: Token) -> Token {
+        self.sign_scoped(token, None)
     }
 
-    #[test]
+    /// Signs a token with the key narrowed down to `scope` if there's one.
+    pub(crate) fn sign_scoped(&self, mut token: Token, scope: Option<KeyScope<'_>>) -> Token {
+        let mut encoded = String::with_capacity(self.token_len_hint(token.account_id.as_bytes().len()));
+        let signature_start = self.write_token(&token, scope, &mut encoded).expect("Writing to a String can't fail");
+        token.key_id = self.keyring.as_ref().map(|keyring| keyring.primary_id().to_string());
+        token.signature = encoded.split_off(signature_start);
+
+        token
+    }
+
+    /// Writes `token` signed with the key narrowed down to `scope` if there's one at the end of
+    /// `buffer`, returning where its signature starts. This is where every token gets issued.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.generate", level = "debug", skip_all))]
+    pub(crate) fn write_token<W>(&self, token: &Token, scope: Option<KeyScope<'_>>, buffer: &mut W) -> std::result::Result<usize, fmt::Error> where
+        W: Write + std::ops::Deref<Target = str> {
+        if let Some(prefix) = &token.prefix {
+            write!(buffer, "{}.", prefix)?;
+        }
+
+        if token.version == TokenVersion::V2 {
+            write!(buffer, "{}.", token::V2_HEADER)?;
+        }
+
+        let segments_start = buffer.len();
+        token.write_segments(buffer)?;
+
+        let (key_id, secret) = match &self.keyring {
+            Some(keyring) => (Some(keyring.primary_id()), keyring.primary_secret()),
+            None => (None, self.secret.expose())
+        };
+        let scoped_key;
+        let secret = match scope {
+            Some(scope) => {
+                scoped_key = scope.key(secret);
+                scoped_key.expose()
+            },
+            None => secret
+        };
+
+        // The signed data is `[prefix.]account.time[.extensions]`, which excludes the version header
+        let segments = buffer[segments_start..].as_bytes();
+        let header: &[u8] = match token.version {
+            TokenVersion::V1 => b"TTF.1.",
+            TokenVersion::V2 => b"TTF.2."
+        };
+        let parts = match &token.prefix {
+            Some(prefix) => [header, prefix.as_bytes(), b".", segments],
+            None => [header, b"", b"", segments]
+        };
+        let signature = self.signer.sign_parts(secret, &parts);
+
+        buffer.write_char('.')?;
+        if let Some(key_id) = key_id {
+            write!(buffer, "{}{}", key_id, keyring::KEY_ID_SEPARATOR)?;
+        }
+
+        let signature_start = buffer.len();
+        write!(buffer, "{}", self.signature_format.encoding(self.encoding).display(&signature))?;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(account = %AccountId::trace_hash(token.account_id.as_bytes()), version = ?token.version, "token generated");
+
+        if self.audit_sink.is_some() {
+            self.audit(AuditEvent::TokenIssued { account_id: token.account_id.clone() }, &buffer[signature_start..]);
+        }
+
+        Ok(signature_start)
+    }
+
+    /// Generates a new token signed with a key derived from the secret and the salt of `account`, see
+    /// [`AccountSecret`]. Such tokens can only be validated by [`Tokenize::validate_salted`].
+    /// 
+    /// Leaking the key of one account doesn't compromise the others, and rotating the salt of an
+    /// account revokes its tokens only.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Account, AccountSecret, Tokenize, TokenizeError};
+    /// 
+    /// struct User { salt: Vec<u8> }
+    /// 
+    /// impl Account for User {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    /// 
+    /// impl AccountSecret for User {
+    ///     fn secret_salt(&self) -> &[u8] {
+    ///         &self.salt
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.generate_salted("326359466171826176", &User { salt: b"1".to_vec() }).expect("Couldn't generate new token");
+    /// 
+    /// assert!(tokenize.validate_salted(token.clone(), |_id| Some(User { salt: b"1".to_vec() })).is_ok());
+    /// assert_eq!(tokenize.validate_salted(token, |_id| Some(User { salt: b"2".to_vec() })).err(), Some(TokenizeError::InvalidSignature));
+    /// ```
+    pub fn generate_salted<S, A>(&self, account_id: S, account: &A) -> Result<String> where
+        S: Into<AccountId>,
+        A: AccountSecret + ?Sized {
+        Ok(self.sign_scoped(self.unsigned_token(account_id.into()), Some(KeyScope::Account(account.secret_salt()))).to_string())
+    }
+
+    /// Validates a token generated by [`Tokenize::generate_salted`].
+    /// 
+    /// The account has to be fetched before the signature can be verified, so forged tokens can reach
+    /// `account_fetcher`. To bound these lookups, malformed, expired and revoked tokens are rejected
+    /// beforehand. Tokens of unknown accounts fail with [`TokenizeError::AccountNotFound`].
+    pub fn validate_salted<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: AccountSecret {
+        let token = token.into();
+        self.measured(&token, || {
+            if self.options.max_length.is_some_and(|max_length| token.len() > max_length) {
+                return Err(TokenizeError::TokenTooLong)
+            }
+
+            // Only the signature needs the salt, the checks covering the rest of the token come first
+            let unverified = token.parse::<Token>()?.set_epoch(self.epoch);
+            self.check_token(&unverified)?;
+            let account = account_fetcher(unverified.account_id_str()?.to_string()).ok_or(TokenizeError::AccountNotFound)?;
+            let token = self.parse_scoped(&token, Some(KeyScope::Account(account.secret_salt())))?;
+
+            self.check_account(Some(account), token.issued_at_millis())
+        })
+    }
+
+    /// Validates a token.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `token` - The provided token
+    /// * `account_fetcher` - The closure used to fetch the account. It'll receive the account id as a string
+    ///   and should return a struct that implements [`Account`]. That same value is handed back on success,
+    ///   so the concrete account type is preserved.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, Account};
+    /// 
+    /// pub struct TestAccount;
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0 // retrieve last token reset here
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// 
+    /// tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
+    ///     Some(TestAccount)
+    /// }).expect("Couldn't validate token");
+    /// ```
+    pub fn validate<S, F, A>(&self, token: S, account_fetcher: F) -> Result<A> where 
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.validate_token(token, account_fetcher).map(|(_, account)| account)
+    }
+
+    /// Validates a token, returning it along with the account.
+    /// 
+    /// This behaves exactly like [`Tokenize::validate`], and gives access to the information carried by
+    /// the token, such as its scopes.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, Account};
+    /// 
+    /// pub struct TestAccount;
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// let token = tokenize.generate_with_scopes("326359466171826176", ["read"]).expect("Couldn't generate new token");
+    /// 
+    /// let (token, _account) = tokenize.validate_token(token, |_id| Some(TestAccount)).expect("Couldn't validate token");
+    /// token.require_scope("read").expect("Missing scope");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_token<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<(Token, A)> where 
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            let account = self.check_account(account, token.issued_at_millis())?;
+
+            Ok((token, account))
+        })
+    }
+
+    /// Validates a token presented with the given context, returning it along with the account. See
+    /// [`ValidationContext`].
+    /// 
+    /// Once the token and its account have been checked, the token has to be authorized by the
+    /// [`Policy`](policy::Policy) of the instance, if there's one. When the context carries a peer, it's
+    /// throttled by the [`FailurePolicy`] like with [`Tokenize::validate_from`]. The context is sent to
+    /// the audit sink along with the outcome.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_with_context<S, F, A>(&self, token: S, context: &ValidationContext, mut account_fetcher: F) -> Result<(Token, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        let throttled = self.failure_policy.as_ref().zip(context.peer());
+        if let Some((failure_policy, peer)) = throttled {
+            if !failure_policy.allow_in(peer, context) {
+                return self.measured_in(&token, Some(context), || Err(TokenizeError::RateLimited))
+            }
+        }
+
+        let result = self.measured_in(&token, Some(context), || {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            let account = self.check_account(account, token.issued_at_millis())?;
+            if let Some(policy) = &self.policy {
+                policy.evaluate(&token, &account, context)?;
+            }
+
+            Ok((token, account))
+        });
+
+        if let (Some((failure_policy, peer)), Err(TokenizeError::InvalidSignature | TokenizeError::UnknownKey)) = (throttled, &result) {
+            failure_policy.record_failure_in(peer, context);
+        }
+
+        result
+    }
+
+    /// Validates a token like [`Tokenize::validate`], reporting the outcome of every check instead of
+    /// stopping at the first error, to explain why a token was rejected.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, CheckOutcome, ValidationCheck};
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+    /// 
+    /// let report = tokenize.validate_verbose(token, |_id| Some(MemoryAccount { data: (), last_token_reset: i64::MAX as u64 }));
+    /// assert_eq!(report.failed_check(), Some(ValidationCheck::Reset));
+    /// assert_eq!(report.outcome(ValidationCheck::Signature), &CheckOutcome::Passed);
+    /// println!("{}", report);
+    /// ```
+    pub fn validate_verbose<S, F, A>(&self, token: S, mut account_fetcher: F) -> ValidationReport where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        let mut parsed = None;
+        let result = self.measured(&token, || {
+            let token = parsed.insert(self.parse_str(&token)?);
+            self.check_token(token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            self.check_account(account, token.issued_at_millis()).map(drop)
+        });
+
+        ValidationReport::new(parsed, result.err(), |check| match check {
+            ValidationCheck::Prefix => !self.prefixes.is_empty(),
+            ValidationCheck::Audience => self.options.expected_audience.is_some(),
+            ValidationCheck::Ttl => self.options.max_age.is_some() || self.options.max_clock_skew.is_some(),
+            ValidationCheck::Revocation => self.revocation_store.is_some(),
+            _ => true
+        })
+    }
+
+    /// Validates a token presented from `device_id`, failing with [`TokenizeError::DeviceMismatch`] if
+    /// it isn't bound to that device. See [`Tokenize::generate_for_device`].
+    /// 
+    /// The device is checked before the account is fetched.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_for_device<S, F, A>(&self, token: S, device_id: &str, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            token.require_device(device_id)?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+
+            self.check_account(account, token.issued_at_millis())
+        })
+    }
+
+    /// Validates a token presented by `peer`, such as the IP address of the client, checking it against
+    /// the [`FailurePolicy`] first. See [`Tokenize::validate`].
+    /// 
+    /// Peers the policy rejects get [`TokenizeError::RateLimited`] without their token being verified.
+    /// Tokens whose signature doesn't match, or which were signed with an unknown key, are recorded as
+    /// failures of `peer`. Without a policy, this behaves exactly like [`Tokenize::validate`].
+    pub fn validate_from<S, F, A>(&self, peer: &str, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let policy = match &self.failure_policy {
+            Some(policy) => policy,
+            None => return self.validate(token, account_fetcher)
+        };
+
+        let token = token.into();
+        if !policy.allow(peer) {
+            return self.measured(&token, || Err(TokenizeError::RateLimited))
+        }
+
+        let result = self.validate(token, account_fetcher);
+        if let Err(TokenizeError::InvalidSignature | TokenizeError::UnknownKey) = result {
+            policy.record_failure(peer);
+        }
+
+        result
+    }
+
+    /// Validates a token, handing the raw bytes of the account id to `account_fetcher`.
+    /// 
+    /// This behaves exactly like [`Tokenize::validate`], and supports ids which aren't valid UTF-8.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, Account};
+    /// 
+    /// pub struct TestAccount;
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// let token = tokenize.generate_bytes(42u64.to_be_bytes()).expect("Couldn't generate new token");
+    /// 
+    /// tokenize.validate_bytes(token, |id| {
+    ///     assert_eq!(id, 42u64.to_be_bytes());
+    ///     Some(TestAccount)
+    /// }).expect("Couldn't validate token");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_bytes<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(&[u8]) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id.as_bytes());
+
+            self.check_account(account, token.issued_at_millis())
+        })
+    }
+
+    /// Generates a new single-use token, such as an email verification or password reset token.
+    /// 
+    /// Single-use tokens are rejected by the other validation methods with [`TokenizeError::SingleUse`],
+    /// and have to be validated through [`Tokenize::validate_single_use`].
+    pub fn generate_single_use<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        let mut token = self.unsigned_token(account_id.into());
+        token.nonce = Some(token::nonce());
+        token.single_use = true;
+
+        Ok(self.sign(token).to_string())
+    }
+
+    /// Validates a single-use token, marking it as consumed in the configured [`UsageStore`].
+    /// 
+    /// The token is only consumed once it has been fully validated; validating it again fails with
+    /// [`TokenizeError::AlreadyUsed`]. When a maximum age is configured, the usage is only kept until
+    /// the token would have expired anyway.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Store`] if no [`UsageStore`] is configured or if it failed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// use tokenize::revocation::MemoryRevocationStore;
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu").set_usage_store(MemoryRevocationStore::new());
+    /// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+    /// let token = tokenize.generate_single_use("326359466171826176").expect("Couldn't generate new token");
+    /// 
+    /// tokenize.validate_single_use(&*token, account).expect("Couldn't validate token");
+    /// assert_eq!(tokenize.validate_single_use(token, account).err(), Some(TokenizeError::AlreadyUsed));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_single_use<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            if !token.single_use {
+                return Err(TokenizeError::Malformed)
+            }
+
+            self.check_validity(token.issued_at_millis(), token.expires_at_millis(), || token.revocation_key())?;
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            let account = self.check_account(account, token.issued_at_millis())?;
+
+            let store = self.usage_store.as_ref().ok_or_else(|| StoreError::new("No usage store is configured"))?;
+            let ttl = self.remaining_validity(&token).map(|ttl| ttl.max(Duration::from_millis(1)));
+            if !store.consume(&token.revocation_key(), ttl)? {
+                return Err(TokenizeError::AlreadyUsed)
+            }
+
+            Ok(account)
+        })
+    }
+
+    /// Validates a token, returning the account along with the claims carried by the token. Available
+    /// with the `claims` feature. See [`Tokenize::generate_with_claims`].
+    /// 
+    /// Fails with [`TokenizeError::InvalidClaims`] if the token doesn't carry claims of type `D`.
+    #[cfg(feature = "claims")]
+    pub fn validate_with_claims<S, F, A, D>(&self, token: S, account_fetcher: F) -> Result<(A, D)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account,
+        D: serde::de::DeserializeOwned {
+        let (token, account) = self.validate_token(token, account_fetcher)?;
+
+        Ok((account, token.claims()?))
+    }
+
+    /// Validates a token, fetching the account asynchronously.
+    /// 
+    /// This behaves exactly like [`Tokenize::validate`], except `account_fetcher` returns a future
+    /// which is awaited once the signature has been verified. This makes it possible to look accounts
+    /// up through async database drivers.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, Account};
+    /// 
+    /// pub struct TestAccount;
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    /// 
+    /// # futures::executor::block_on(async {
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// 
+    /// tokenize.validate_async("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| async {
+    ///     Some(TestAccount) // await your database here
+    /// }).await.expect("Couldn't validate token");
+    /// # });
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub async fn validate_async<S, F, Fut, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = Option<A>>,
+        A: Account {
+        let token = token.into();
+        self.measured_async(&token, async {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string()).await;
+
+            self.check_account(account, token.issued_at_millis())
+        }).await
+    }
+
+    /// Validates a token with a fallible account fetcher, for fetchers which can't be expressed as an
+    /// [`AccountStore`].
+    /// 
+    /// This behaves like [`Tokenize::validate`], except errors returned by `account_fetcher` are
+    /// reported as [`TokenizeError::Store`] rather than being mistaken for a missing account, so a
+    /// database outage isn't answered like an invalid token.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+    /// 
+    /// tokenize.try_validate(token, |_id| Ok::<_, std::io::Error>(Some(MemoryAccount { data: (), last_token_reset: 0 })))
+    ///     .expect("Couldn't validate token");
+    /// 
+    /// let result = tokenize.try_validate(token, |_id| Err::<Option<MemoryAccount>, _>("connection refused"));
+    /// assert!(matches!(result, Err(TokenizeError::Store(_))));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn try_validate<S, F, A, E>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> std::result::Result<Option<A>, E>,
+        A: Account,
+        E: fmt::Display {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string()).map_err(StoreError::new)?;
+
+            self.check_account(account, token.issued_at_millis())
+        })
+    }
+
+    /// Asynchronous counterpart of [`Tokenize::try_validate`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub async fn try_validate_async<S, F, Fut, A, E>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = std::result::Result<Option<A>, E>>,
+        A: Account,
+        E: fmt::Display {
+        let token = token.into();
+        self.measured_async(&token, async {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = account_fetcher(token.account_id_str()?.to_string()).await.map_err(StoreError::new)?;
+
+            self.check_account(account, token.issued_at_millis())
+        }).await
+    }
+
+    /// Validates a token, fetching the account from an [`AccountStore`].
+    /// 
+    /// This behaves like [`Tokenize::validate`], except failures of the store are reported as
+    /// [`TokenizeError::Store`] rather than being mistaken for a missing account.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Account, AccountStore, StoreError, Tokenize};
+    /// 
+    /// pub struct TestAccount;
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    /// 
+    /// pub struct Database;
+    /// 
+    /// impl AccountStore<TestAccount> for Database {
+    ///     fn fetch(&self, _id: &str) -> Result<Option<TestAccount>, StoreError> {
+    ///         Ok(Some(TestAccount)) // query your database here
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// 
+    /// tokenize.validate_with_store("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", &Database)
+    ///     .expect("Couldn't validate token");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_with_store<S, St, A>(&self, token: S, store: &St) -> Result<A> where
+        S: Into<String>,
+        St: AccountStore<A> + ?Sized,
+        A: Account {
+        let token = token.into();
+        self.measured(&token, || {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = store.fetch(token.account_id_str()?)?;
+
+            self.check_account(account, token.issued_at_millis())
+        })
+    }
+
+    /// Validates many tokens at once, fetching their accounts from a [`BatchAccountStore`] in a
+    /// single call.
+    /// 
+    /// Signatures are verified first, then the account ids of the valid tokens are deduplicated and
+    /// fetched together. Results are returned in the order of `tokens`, tokens belonging to the same
+    /// account each get a clone of it. If the store fails, every token which passed the signature
+    /// checks fails with [`TokenizeError::Store`].
+    /// 
+    /// Accounts split across shards are fetched with one call per shard through
+    /// [`Sharded`](store::Sharded).
+    /// 
+    /// With the `rayon` feature, signatures of large batches are verified in parallel on the global
+    /// rayon thread pool.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// use tokenize::store::MemoryAccountStore;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let store = MemoryAccountStore::new();
+    /// store.insert("326359466171826176", "owo");
+    /// 
+    /// let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+    /// let results = tokenize.validate_batch(&[&*token, &*token, "owo"], &store);
+    /// 
+    /// assert_eq!(results[1].as_ref().expect("Couldn't validate token").data, "owo");
+    /// assert_eq!(results[2].as_ref().err(), Some(&TokenizeError::Malformed));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate_batch", level = "debug", skip_all, fields(tokens = tokens.len())))]
+    pub fn validate_batch<S, St, A>(&self, tokens: &[S], store: &St) -> Vec<Result<A>> where
+        S: AsRef<str>,
+        St: BatchAccountStore<A> + ?Sized,
+        A: Account + Clone {
+        let stopwatch = platform::Stopwatch::start();
+        let raw_tokens = tokens.iter().map(AsRef::as_ref).collect::<Vec<&str>>();
+        let tokens = self.verify_batch(&raw_tokens);
+
+        let mut seen = HashSet::new();
+        let ids = tokens.iter()
+            .filter_map(|token| token.as_ref().ok()?.account_id_str().ok())
+            .filter(|id| seen.insert(*id))
+            .collect::<Vec<&str>>();
+
+        let accounts = if ids.is_empty() { Ok(HashMap::new()) } else { store.fetch_many(&ids) };
+
+        let results = tokens.iter()
+            .map(|token| {
+                let token = token.as_ref().map_err(Clone::clone)?;
+                let accounts = accounts.as_ref().map_err(|err| TokenizeError::Store(err.clone()))?;
+
+                self.check_account(accounts.get(token.account_id_str()?).cloned(), token.issued_at_millis())
+            })
+            .collect::<Vec<Result<A>>>();
+
+        if let Some(metrics) = &self.metrics {
+            // Tokens are verified together, each of them is assumed to take the same time
+            let elapsed = stopwatch.elapsed() / results.len().max(1) as u32;
+            results.iter().for_each(|result| metrics.record_validation(result.as_ref().map(|_| ()), elapsed));
+        }
+
+        raw_tokens.iter().zip(&results).for_each(|(token, result)| self.audit_validation(token, result, None));
+
+        results
+    }
+
+    /// Validates a token, reusing the result of a previous validation held by `cache`. See
+    /// [`ValidationCache`](cache::ValidationCache).
+    /// 
+    /// On a hit, the signature isn't verified again and `account_fetcher` isn't called, but the age and
+    /// revocation of the token are still checked, as well as the [`Account::last_token_reset`] of the
+    /// cached account.
+    pub fn validate_cached<S, F, A>(&self, token: S, cache: &cache::ValidationCache<A>, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account + Clone {
+        let token = token.into();
+        let key = cache::key(&token);
+        let now = self.clock.now_millis();
+        if let Some(hit) = cache.get(&key, now) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("validation cache hit");
+
+            return self.measured(&token, || {
+                self.check_validity(hit.issued_at_millis, hit.expires_at_millis, || hit.revocation_key)?;
+                self.check_account(Some(hit.account), hit.issued_at_millis)
+            })
+        }
+
+        let (token, account) = self.validate_token(token, account_fetcher)?;
+        cache.insert(key, &token, account.clone(), now);
+
+        Ok(account)
+    }
+
+    /// Validates a token, fetching the account from an [`AsyncAccountStore`].
+    /// 
+    /// This behaves like [`Tokenize::validate_with_store`], except the account is fetched
+    /// asynchronously once the signature has been verified.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub async fn validate_with_store_async<S, St, A>(&self, token: S, store: &St) -> Result<A> where
+        S: Into<String>,
+        St: AsyncAccountStore<A> + ?Sized,
+        A: Account {
+        let token = token.into();
+        self.measured_async(&token, async {
+            let token = self.parse_str(&token)?;
+            self.check_token(&token)?;
+            let account = store.fetch(token.account_id_str()?).await?;
+
+            self.check_account(account, token.issued_at_millis())
+        }).await
+    }
+
+    /// Parses a token and verifies its signature, without looking up the account it belongs to.
+    /// 
+    /// This is useful for middlewares that only need the account id, deferring the account lookup
+    /// (and the [`Account::last_token_reset`] check) to a later layer.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// let token = tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc")
+    ///     .expect("Couldn't parse token");
+    /// 
+    /// assert_eq!(token.account_id(), "326359466171826176");
+    /// ```
+    pub fn parse<S: Into<String>>(&self, token: S) -> Result<Token> {
+        self.parse_str(&token.into())
+    }
+
+    fn parse_str(&self, token: &str) -> Result<Token> {
+        self.parse_scoped(token, None)
+    }
+
+    /// Parses a token, verifying its signature with the key narrowed down to `scope` if there's one.
+    pub(crate) fn parse_scoped(&self, token: &str, scope: Option<KeyScope<'_>>) -> Result<Token> {
+        if self.options.max_length.is_some_and(|max_length| token.len() > max_length) {
+            return Err(TokenizeError::TokenTooLong)
+        }
+
+        let mut splitted = token.split('.').collect::<Vec<&str>>();
+        let extensions = token::take_extensions(&mut splitted)?;
+
+        let prefix_len = if self.prefixes.is_empty() { 0 } else { 1 };
+        let version = match splitted.len() - prefix_len {
+            3 => TokenVersion::V1,
+            4 if splitted[prefix_len] == token::V2_HEADER => TokenVersion::V2,
+            _ => return Err(TokenizeError::Malformed)
+        };
+
+        if version == TokenVersion::V2 {
+            splitted.remove(prefix_len);
+        }
+
+        let max_len = prefix_len + 3;
+        if let Some(max_account_id_length) = self.options.max_account_id_length {
+            // Base64 encodes every 3 bytes into 4 characters
+            if splitted[max_len - 3].len() > max_account_id_length.div_ceil(3).saturating_mul(4) {
+                return Err(TokenizeError::TokenTooLong)
+            }
+        }
+
+        let prefix = (prefix_len == 1).then(|| splitted[0]);
+        let mut signature_string = if let Some(prefix) = prefix {
+            if !self.prefixes.iter().any(|p| p == prefix) {
+                return Err(TokenizeError::PrefixMismatch)
+            }
+
+            format!("{}.{}.{}", prefix, splitted[1], splitted[2])
+        } else {
+            format!("{}.{}", splitted[0], splitted[1])
+        };
+
+        for extension in &extensions {
+            signature_string.push('.');
+            signature_string.push_str(extension);
+        }
+
+        let (key_id, signature_part) = token::split_key_id(splitted[max_len - 1]);
+        let signature_format = self.detect_signature_format(signature_part)?;
+        let mut segments = vec![splitted[max_len - 3], splitted[max_len - 2]];
+        segments.extend(token::extension_payloads(&extensions));
+        let base64_signature = (signature_format == SignatureFormat::Base64).then_some(signature_part);
+        let encoding = self.segment_encoding(segments.iter().copied().chain(base64_signature))?;
+        let signature_encoding = signature_format.encoding(encoding);
+
+        if self.options.strict && !(segments.iter().all(|segment| encoding.is_canonical(segment)) && signature_encoding.is_canonical(signature_part)) {
+            return Err(TokenizeError::Malformed)
+        }
+
+        let signature_matches = |secret: &[u8]| match scope {
+            Some(scope) => self.signature_matches(version, &signature_string, scope.key(secret).expose(), signature_part, signature_encoding),
+            None => self.signature_matches(version, &signature_string, secret, signature_part, signature_encoding)
+        };
+
+        self.verify_signature(key_id, signature_matches)?;
+
+        let mut token = Token::from_segments(version, prefix, splitted[max_len - 3], splitted[max_len - 2], &extensions, splitted[max_len - 1], encoding)?;
+        token.epoch = self.epoch;
+        token.signature_format = signature_format;
+
+        if self.options.expected_audience.is_some() && token.audience != self.options.expected_audience {
+            return Err(TokenizeError::AudienceMismatch)
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("account", AccountId::trace_hash(token.account_id.as_bytes()));
+
+        Ok(token)
+    }
+
+    /// Parses a token and verifies its signature without allocating, borrowing its segments from
+    /// `token`.
+    /// 
+    /// This performs the same checks as [`Tokenize::parse`]. Extension payloads aren't decoded, use
+    /// [`TokenRef::to_token`] to access them. Custom signers allocate while verifying signatures unless
+    /// they override [`Signer::verify`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.parse_ref("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc")
+    ///     .expect("Couldn't parse token");
+    /// 
+    /// let mut buffer = [0; 32];
+    /// assert_eq!(token.decode_account_id(&mut buffer), Ok(&b"326359466171826176"[..]));
+    /// ```
+    pub fn parse_ref<'a>(&self, token: &'a str) -> Result<TokenRef<'a>> {
+        if self.options.max_length.is_some_and(|max_length| token.len() > max_length) {
+            return Err(TokenizeError::TokenTooLong)
+        }
+
+        let (head, signature_part) = token.rsplit_once('.').ok_or(TokenizeError::Malformed)?;
+        let (prefix, body) = if self.prefixes.is_empty() {
+            (None, head)
+        } else {
+            let (prefix, body) = head.split_once('.').ok_or(TokenizeError::Malformed)?;
+            (Some(prefix), body)
+        };
+
+        if signature_part.contains(token::EXTENSION_SEPARATOR) || prefix.is_some_and(|prefix| prefix.contains(token::EXTENSION_SEPARATOR)) {
+            return Err(TokenizeError::Malformed)
+        }
+
+        // Extension segments start with the first segment containing a separator, and must all contain one
+        let (segments, extensions) = match body.find(token::EXTENSION_SEPARATOR) {
+            Some(index) => match body[..index].rfind('.') {
+                Some(dot) => (&body[..dot], &body[dot + 1..]),
+                None => return Err(TokenizeError::Malformed)
+            },
+            None => (body, "")
+        };
+
+        if !extensions.is_empty() && !extensions.split('.').all(|extension| extension.contains(token::EXTENSION_SEPARATOR)) {
+            return Err(TokenizeError::Malformed)
+        }
+
+        let (version, segments) = match segments.split_once('.') {
+            Some((token::V2_HEADER, rest)) if rest.matches('.').count() == 1 => (TokenVersion::V2, rest),
+            _ => (TokenVersion::V1, segments)
+        };
+
+        let (account_part, time_part) = segments.split_once('.')
+            .filter(|(_, time_part)| !time_part.contains('.'))
+            .ok_or(TokenizeError::Malformed)?;
+
+        if let Some(max_account_id_length) = self.options.max_account_id_length {
+            // Base64 encodes every 3 bytes into 4 characters
+            if account_part.len() > max_account_id_length.div_ceil(3).saturating_mul(4) {
+                return Err(TokenizeError::TokenTooLong)
+            }
+        }
+
+        if prefix.is_some_and(|prefix| !self.prefixes.iter().any(|p| p == prefix)) {
+            return Err(TokenizeError::PrefixMismatch)
+        }
+
+        let (key_id, signature) = token::split_key_id(signature_part);
+        let payloads = extensions.split('.').filter_map(|extension| extension.split_once(token::EXTENSION_SEPARATOR).map(|(_, payload)| payload));
+        let signature_format = self.detect_signature_format(signature)?;
+        let segments = [account_part, time_part].into_iter().chain(payloads);
+        let base64_signature = (signature_format == SignatureFormat::Base64).then_some(signature);
+        let encoding = self.segment_encoding(segments.clone().chain(base64_signature))?;
+        let signature_encoding = signature_format.encoding(encoding);
+
+        if self.options.strict && !(segments.clone().all(|segment| encoding.is_canonical(segment)) && signature_encoding.is_canonical(signature)) {
+            return Err(TokenizeError::Malformed)
+        }
+
+        // The signed data is `[prefix.]account.time[.extensions]`, which excludes the version header
+        let signed = match version {
+            TokenVersion::V1 => body,
+            TokenVersion::V2 => &body[token::V2_HEADER.len() + 1..]
+        };
+        let header: &[u8] = match version {
+            TokenVersion::V1 => b"TTF.1.",
+            TokenVersion::V2 => b"TTF.2."
+        };
+
+        let parts = match prefix {
+            Some(prefix) => [header, prefix.as_bytes(), b".", signed.as_bytes()],
+            None => [header, b"", b"", signed.as_bytes()]
+        };
+
+        let signature_matches = |secret: &[u8]| self.signer.verify(secret, &parts, signature, signature_encoding);
+        self.verify_signature(key_id, signature_matches)?;
+
+        let mut buffer = [0; 32];
+        let generated_at = str::from_utf8(encoding.decode_slice(time_part, &mut buffer)?)?.parse()?;
+
+        let (mut scopes, mut claims, mut nonce, mut single_use, mut device) = (false, false, false, false, false);
+        let (mut audience, mut millis, mut ttl_millis) = (None, None, None);
+        for extension in extensions.split('.').filter(|extension| !extension.is_empty()) {
+            let seen = match extension.split_once(token::EXTENSION_SEPARATOR) {
+                Some((token::SCOPES_TAG, _)) => &mut scopes,
+                Some((token::CLAIMS_TAG, _)) => &mut claims,
+                Some((tag @ (token::NONCE_TAG | token::SINGLE_USE_TAG), _)) => {
+                    single_use = tag == token::SINGLE_USE_TAG;
+                    &mut nonce
+                },
+                Some((token::DEVICE_TAG, _)) => &mut device,
+                Some((token::AUDIENCE_TAG, payload)) if audience.is_none() => {
+                    audience = Some(payload);
+                    continue
+                },
+                Some((token::MILLIS_TAG, payload)) if millis.is_none() => {
+                    let mut buffer = [0; 8];
+                    millis = Some(token::parse_millis(encoding.decode_slice(payload, &mut buffer)?)?);
+                    continue
+                },
+                Some((token::EXPIRY_TAG, payload)) if ttl_millis.is_none() => {
+                    let mut buffer = [0; 32];
+                    ttl_millis = Some(str::from_utf8(encoding.decode_slice(payload, &mut buffer)?)?.parse()?);
+                    continue
+                },
+                _ => return Err(TokenizeError::Malformed)
+            };
+
+            if std::mem::replace(seen, true) {
+                return Err(TokenizeError::Malformed)
+            }
+        }
+
+        if let Some(expected_audience) = &self.options.expected_audience {
+            if !audience.is_some_and(|audience| encoding.matches(expected_audience.as_bytes(), audience)) {
+                return Err(TokenizeError::AudienceMismatch)
+            }
+        }
+
+        Ok(TokenRef {
+            version,
+            prefix,
+            account_part,
+            time_part,
+            extensions,
+            signature_part,
+            encoding,
+            signature_format,
+            generated_at,
+            millis: millis.unwrap_or(0),
+            ttl_millis,
+            single_use,
+            epoch: self.epoch
+        })
+    }
+
+    /// Validates a token without allocating, returning it along with the account. See
+    /// [`Tokenize::validate_token`] and [`Tokenize::parse_ref`].
+    /// 
+    /// The account id is decoded on the stack and handed to `account_fetcher` as a string slice.
+    /// Lookups in a [`RevocationStore`] still allocate.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+    /// 
+    /// let (token, _) = tokenize.validate_ref(token, |id| {
+    ///     assert_eq!(id, "326359466171826176");
+    ///     Some(MemoryAccount { data: (), last_token_reset: 0 })
+    /// }).expect("Couldn't validate token");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_ref<'a, F, A>(&self, token: &'a str, account_fetcher: F) -> Result<(TokenRef<'a>, A)> where
+        F: FnOnce(&str) -> Option<A>,
+        A: Account {
+        self.measured(token, || {
+            let token = self.parse_ref(token)?;
+            if token.single_use {
+                return Err(TokenizeError::SingleUse)
+            }
+
+            self.check_validity(token.issued_at_millis(), token.expires_at_millis(), || token.revocation_key())?;
+
+            let mut buffer = [0; 96];
+            let decoded;
+            let account_id = if Encoding::max_decoded_len(token.account_part.len()) <= buffer.len() {
+                token.decode_account_id(&mut buffer)?
+            } else {
+                decoded = token.account_id()?;
+                decoded.as_bytes()
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::Span::current().record("account", AccountId::trace_hash(account_id));
+
+            let account = self.check_account(account_fetcher(str::from_utf8(account_id)?), token.issued_at_millis())?;
+            Ok((token, account))
+        })
+    }
+
+    /// Revokes a single token, leaving the other tokens of its account valid.
+    /// 
+    /// The token's signature is verified first. When a maximum age is configured, the revocation is
+    /// only kept until the token would have expired anyway.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Store`] if no [`RevocationStore`] is configured or if it failed.
+    pub fn revoke<S: Into<String>>(&self, token: S) -> Result<()> {
+        self.revoke_token(&self.parse(token)?)
+    }
+
+    /// Revokes a token whose signature was already verified.
+    pub(crate) fn revoke_token(&self, token: &Token) -> Result<()> {
+        let store = self.revocation_store.as_ref().ok_or_else(|| StoreError::new("No revocation store is configured"))?;
+        let ttl = self.remaining_validity(token);
+        if ttl.is_some_and(|ttl| ttl.is_zero()) {
+            return Ok(())
+        }
+
+        store.revoke(&token.revocation_key(), ttl)?;
+        self.audit(AuditEvent::TokenRevoked, &token.signature);
+        Ok(())
+    }
+
+    /// Atomically revokes a token whose signature was already verified, returning `false` if it
+    /// already was revoked. See [`RevocationStore::try_revoke`].
+    pub(crate) fn claim_token(&self, token: &Token) -> Result<bool> {
+        let store = self.revocation_store.as_ref().ok_or_else(|| StoreError::new("No revocation store is configured"))?;
+        let ttl = self.remaining_validity(token).map(|ttl| ttl.max(Duration::from_millis(1)));
+        if !store.try_revoke(&token.revocation_key(), ttl)? {
+            return Ok(false)
+        }
+
+        self.audit(AuditEvent::TokenRevoked, &token.signature);
+        Ok(true)
+    }
+
+    /// How long the token remains valid for according to the maximum age, if one is configured.
+    fn remaining_validity(&self, token: &Token) -> Option<Duration> {
+        let max_age = self.options.max_age?;
+        let age = self.clock.now_millis().saturating_sub(token.issued_at_millis()).max(0);
+
+        Some(max_age.saturating_sub(Duration::from_millis(age as u64)))
+    }
+
+    /// Invalidates every token issued for `account` until now, by setting its last token reset to the
+    /// current time according to the configured [`Clock`]. Returns the new last token reset.
+    /// 
+    /// Tokens only carry the second they were issued at, so tokens generated during the same second
+    /// as the reset are invalidated as well.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Account, MutableAccount, Tokenize, TokenizeError};
+    /// 
+    /// pub struct TestAccount { last_token_reset: u64 }
+    /// 
+    /// impl Account for TestAccount {
+    ///     fn last_token_reset(&self) -> u64 {
+    ///         self.last_token_reset
+    ///     }
+    /// }
+    /// 
+    /// impl MutableAccount for TestAccount {
+    ///     fn set_last_token_reset(&mut self, last_token_reset: u64) {
+    ///         self.last_token_reset = last_token_reset; // update your database here
+    ///     }
+    /// }
+    /// 
+    /// let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+    /// let mut account = TestAccount { last_token_reset: 0 };
+    /// let token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+    /// 
+    /// tokenize.invalidate_all(&mut account);
+    /// let last_token_reset = account.last_token_reset;
+    /// assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset })).err(), Some(TokenizeError::Invalidated));
+    /// ```
+    pub fn invalidate_all<A: MutableAccount + ?Sized>(&self, account: &mut A) -> u64 {
+        let now = self.clock.now_millis().max(0) as u64;
+        account.set_last_token_reset(now);
+
+        now
+    }
+
+    /// Performs the checks which don't require the account: [`ValidationOptions`] and revocations.
+    fn check_token(&self, token: &Token) -> Result<()> {
+        if token.single_use {
+            return Err(TokenizeError::SingleUse)
+        }
+
+        self.check_validity(token.issued_at_millis(), token.expires_at_millis(), || token.revocation_key())
+    }
+
+    /// Performs the checks from [`Tokenize::check_token`] which apply to single-use tokens as well.
+    /// 
+    /// `revocation_key` is only called when a revocation store is configured.
+    fn check_validity(&self, issued_at_millis: i64, expires_at_millis: Option<i64>, revocation_key: impl FnOnce() -> String) -> Result<()> {
+        self.check_age(issued_at_millis, expires_at_millis)?;
+        self.check_revocation(revocation_key)
+    }
+
+    /// Checks the age of a token against the maximum age and clock skew, as well as its own lifetime
+    /// if it was issued with one.
+    fn check_age(&self, issued_at_millis: i64, expires_at_millis: Option<i64>) -> Result<()> {
+        let now = self.clock.now_millis();
+        if expires_at_millis.is_some_and(|expires_at| now > expires_at) {
+            return Err(TokenizeError::Expired)
+        }
+
+        let age = now.saturating_sub(issued_at_millis);
+        if let Some(max_age) = self.options.max_age {
+            if age > 0 && age as u128 > max_age.as_millis() {
+                return Err(TokenizeError::Expired)
+            }
+        }
+
+        if let Some(max_clock_skew) = self.options.max_clock_skew {
+            if age < 0 && age.unsigned_abs() as u128 > max_clock_skew.as_millis() {
+                return Err(TokenizeError::IssuedInFuture)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a token wasn't revoked, if a revocation store is configured.
+    fn check_revocation(&self, revocation_key: impl FnOnce() -> String) -> Result<()> {
+        if let Some(store) = &self.revocation_store {
+            if store.is_revoked(&revocation_key())? {
+                return Err(TokenizeError::Revoked)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the validation of `token`, reporting its outcome and latency to the metrics and audit sinks.
+    fn measured<T>(&self, token: &str, validate: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.measured_in(token, None, validate)
+    }
+
+    /// Runs the validation of `token`, reporting its outcome and latency to the metrics and audit
+    /// sinks along with the context of the request, if there's one.
+    fn measured_in<T>(&self, token: &str, context: Option<&ValidationContext>, validate: impl FnOnce() -> Result<T>) -> Result<T> {
+        let stopwatch = platform::Stopwatch::start();
+        let result = validate();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_validation(result.as_ref().map(|_| ()), stopwatch.elapsed());
+        }
+
+        self.audit_validation(token, &result, context);
+
+        result
+    }
+
+    /// Runs the asynchronous validation of `token`, reporting its outcome and latency to the metrics
+    /// and audit sinks.
+    async fn measured_async<T>(&self, token: &str, validate: impl Future<Output = Result<T>>) -> Result<T> {
+        let stopwatch = platform::Stopwatch::start();
+        let result = validate.await;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_validation(result.as_ref().map(|_| ()), stopwatch.elapsed());
+        }
+
+        self.audit_validation(token, &result, None);
+
+        result
+    }
+
+    /// Parses and checks each token of a batch, before their accounts are fetched.
+    #[cfg(not(feature = "rayon"))]
+    fn verify_batch(&self, tokens: &[&str]) -> Vec<Result<Token>> {
+        tokens.iter().map(|token| self.verify_batch_token(token)).collect()
+    }
+
+    /// Parses and checks each token of a batch, before their accounts are fetched.
+    #[cfg(feature = "rayon")]
+    fn verify_batch(&self, tokens: &[&str]) -> Vec<Result<Token>> {
+        use rayon::prelude::*;
+
+        // Below this size, spreading the work across threads costs more than it saves
+        const PARALLEL_THRESHOLD: usize = 256;
+
+        if tokens.len() < PARALLEL_THRESHOLD {
+            return tokens.iter().map(|token| self.verify_batch_token(token)).collect()
+        }
+
+        tokens.par_iter().map(|token| self.verify_batch_token(token)).collect()
+    }
+
+    fn verify_batch_token(&self, token: &str) -> Result<Token> {
+        let token = self.parse_str(token)?;
+        self.check_token(&token)?;
+        token.account_id_str()?;
+
+        Ok(token)
+    }
+
+    /// Checks a signature against the keyring key it was made with, or against the secrets.
+    /// 
+    /// In hardened mode, every secret is tried and signatures made with unknown keys are checked
+    /// against the primary key, so the time taken doesn't reveal which secret matched or which keys
+    /// exist.
+    fn verify_signature(&self, key_id: Option<&str>, signature_matches: impl Fn(&[u8]) -> bool) -> Result<()> {
+        let signature_matches = if let Some(keyring) = &self.keyring {
+            match keyring.get(key_id.ok_or(TokenizeError::Malformed)?) {
+                Some(secret) => signature_matches(secret),
+                None => {
+                    if self.options.hardened {
+                        std::hint::black_box(signature_matches(keyring.primary_secret()));
+                    }
+
+                    return Err(TokenizeError::UnknownKey)
+                }
+            }
+        } else {
+            let mut secrets = std::iter::once(&self.secret).chain(&self.old_secrets);
+            if self.options.hardened {
+                key_id.is_none() & secrets.fold(false, |matches, secret| signature_matches(secret.expose()) | matches)
+            } else {
+                key_id.is_none() && secrets.any(|secret| signature_matches(secret.expose()))
+            }
+        };
+
+        if !signature_matches {
+            return Err(TokenizeError::InvalidSignature)
+        }
+
+        Ok(())
+    }
+
+    fn check_account<A: Account>(&self, account: Option<A>, issued_at_millis: i64) -> Result<A> {
+        self.check_account_with(account, issued_at_millis, true)
+    }
+
+    /// Checks the account of a token like [`Tokenize::check_account`], ignoring its last token reset
+    /// unless `check_reset` is set.
+    fn check_account_with<A: Account>(&self, account: Option<A>, issued_at_millis: i64, check_reset: bool) -> Result<A> {
+        let account = match account {
+            Some(account) => account,
+            None => {
+                if self.options.hardened {
+                    // Missing accounts go through the same comparison as existing ones
+                    std::hint::black_box(std::hint::black_box(0u64) as i64 > issued_at_millis);
+                }
+
+                return Err(TokenizeError::AccountNotFound)
+            }
+        };
+
+        let reset_grace = self.options.reset_grace.map_or(0, |grace| i64::try_from(grace.as_millis()).unwrap_or(i64::MAX));
+        let last_token_reset = account.last_token_reset();
+        if check_reset && (last_token_reset as i64).saturating_sub(reset_grace) > issued_at_millis {
+            return Err(TokenizeError::Invalidated)
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(outcome = "valid", "token validated");
+
+        Ok(account)
+    }
+
+    pub fn current_token_time() -> i64 {
+        (platform::now_millis() - TOKENIZE_EPOCH) / 1000
+    }
+
+    /// The encoding of the segments of a token. Tokens using another base64 alphabet than this
+    /// instance are rejected unless any encoding is accepted, and custom codecs are used as-is.
+    fn segment_encoding<'a, I: IntoIterator<Item = &'a str>>(&self, segments: I) -> Result<Encoding> {
+        if let Encoding::Custom(_) = self.encoding {
+            return Ok(self.encoding)
+        }
+
+        match Encoding::detect(segments)? {
+            Some(encoding) if encoding != self.encoding && (!self.accept_any_encoding || self.options.strict) => Err(TokenizeError::Malformed),
+            Some(encoding) => Ok(encoding),
+            None => Ok(self.encoding)
+        }
+    }
+
+    /// The format of `signature`, which must be the one of this instance unless any format is
+    /// accepted. Signatures of tokens using a custom codec are taken to be in the configured format.
+    fn detect_signature_format(&self, signature: &str) -> Result<SignatureFormat> {
+        if let Encoding::Custom(_) = self.encoding {
+            return Ok(self.signature_format)
+        }
+
+        match SignatureFormat::detect(signature) {
+            format if format != self.signature_format && !self.accept_any_signature_format => Err(TokenizeError::Malformed),
+            format => Ok(format)
+        }
+    }
+
+    /// Whether `signature`, encoded with `encoding`, is the signature of `token` made with `secret`.
+    fn signature_matches(&self, version: TokenVersion, token: &str, secret: &[u8], signature: &str, encoding: Encoding) -> bool {
+        let header = format!("TTF.{}.", version.number());
+
+        self.signer.verify(secret, &[header.as_bytes(), token.as_bytes()], signature, encoding)
+    }
+}
+
+/// Narrows a secret down to a key only signing some tokens, so they can't be replayed as other ones.
+#[derive(Clone, Copy)]
+pub(crate) enum KeyScope<'a> {
+    /// The tokens of an account with the given salt, see [`AccountSecret`].
+    Account(&'a [u8]),
+    /// The tokens issued for a purpose, see [`purpose::PurposeToken`].
+    Purpose(&'a str)
+}
+
+impl KeyScope<'_> {
+    /// The key derived from `secret` for this scope.
+    fn key(self, secret: &[u8]) -> SecretKey {
+        let context = match self {
+            KeyScope::Account(salt) => [b"tokenize account key ".as_slice(), salt].concat(),
+            KeyScope::Purpose(purpose) => [b"tokenize purpose key ".as_slice(), purpose.as_bytes()].concat()
+        };
+
+        SecretKey::from(secret).derive_bytes(&context)
+    }
+}
+
+/// An account tokens are generated for.
+/// 
+/// With the `derive` feature, it can be derived for structs marking the field holding the last token
+/// reset with `#[token_reset]`, as can [`MutableAccount`].
+pub trait Account {
+    fn last_token_reset(&self) -> u64;
+}
+
+impl<A: Account + ?Sized> Account for &A {
+    fn last_token_reset(&self) -> u64 {
+        (**self).last_token_reset()
+    }
+}
+
+impl<A: Account + ?Sized> Account for &mut A {
+    fn last_token_reset(&self) -> u64 {
+        (**self).last_token_reset()
+    }
+}
+
+impl<A: Account + ?Sized> Account for Box<A> {
+    fn last_token_reset(&self) -> u64 {
+        (**self).last_token_reset()
+    }
+}
+
+impl<A: Account + ?Sized> Account for Arc<A> {
+    fn last_token_reset(&self) -> u64 {
+        (**self).last_token_reset()
+    }
+}
+
+/// An account and its last token reset, such as an `(id, last_token_reset)` row.
+impl<T> Account for (T, u64) {
+    fn last_token_reset(&self) -> u64 {
+        self.1
+    }
+}
+
+/// An [`Account`] with a salt of its own, from which the key signing its tokens is derived. See
+/// [`Tokenize::generate_salted`].
+pub trait AccountSecret: Account {
+    /// The salt of the account, stored alongside it. Changing it invalidates every token of the
+    /// account, without affecting other accounts.
+    fn secret_salt(&self) -> &[u8];
+}
+
+impl<A: AccountSecret + ?Sized> AccountSecret for &A {
+    fn secret_salt(&self) -> &[u8] {
+        (**self).secret_salt()
+    }
+}
+
+impl<A: AccountSecret + ?Sized> AccountSecret for &mut A {
+    fn secret_salt(&self) -> &[u8] {
+        (**self).secret_salt()
+    }
+}
+
+impl<A: AccountSecret + ?Sized> AccountSecret for Box<A> {
+    fn secret_salt(&self) -> &[u8] {
+        (**self).secret_salt()
+    }
+}
+
+impl<A: AccountSecret + ?Sized> AccountSecret for Arc<A> {
+    fn secret_salt(&self) -> &[u8] {
+        (**self).secret_salt()
+    }
+}
+
+/// An [`Account`] whose last token reset can be updated, see [`Tokenize::invalidate_all`].
+pub trait MutableAccount: Account {
+    /// Sets the last token reset, in milliseconds. This is the place to persist it.
+    fn set_last_token_reset(&mut self, last_token_reset: u64);
+}
+
+impl<A: MutableAccount + ?Sized> MutableAccount for &mut A {
+    fn set_last_token_reset(&mut self, last_token_reset: u64) {
+        (**self).set_last_token_reset(last_token_reset)
+    }
+}
+
+impl<A: MutableAccount + ?Sized> MutableAccount for Box<A> {
+    fn set_last_token_reset(&mut self, last_token_reset: u64) {
+        (**self).set_last_token_reset(last_token_reset)
+    }
+}
+
+impl<T> MutableAccount for (T, u64) {
+    fn set_last_token_reset(&mut self, last_token_reset: u64) {
+        self.1 = last_token_reset;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use crate::api_key::{ApiKeyManager, ApiKeyStore, MemoryApiKeyStore};
+    use crate::audit::{AuditEvent, MemoryAuditSink};
+    use crate::cache::ValidationCache;
+    use crate::clock::{FixedClock, MockClock};
+    use crate::keyloading::KeySource;
+    use crate::metrics::MemoryMetrics;
+    use crate::mfa::{MemoryChallengeStore, MfaManager};
+    use crate::policy::{All, Any, MaxAge, Not, Policy, RequirePrefix, RequireScope};
+    use crate::purpose::{PurposeToken, EMAIL_VERIFICATION, PASSWORD_RESET};
+    use crate::ratelimit::TokenBucket;
+    use crate::revocation::MemoryRevocationStore;
+    use crate::session::{MemorySessionStore, SessionManager};
+    use crate::shared::SharedTokenize;
+    use crate::store::{MemoryAccount, MemoryAccountStore, Sharded};
+    use crate::tenant::TenantRegistry;
+    use crate::TOKENIZE_EPOCH;
+    use crate::{AccountId, TokenAccumulator, TokenKind, Tokenize, TokenizeError, ConfigError, StaticConfig, CheckOutcome, ValidationCheck, Token, TimestampPrecision, TokenVersion, ValidationContext, ValidationWarning, Account, AccountSecret, AccountStore, BatchAccountStore, ShardedAccountStore, MutableAccount, RevocationStore, StoreError, ValidationOptions, Keyring, Codec, Encoding, SecretKey, SignatureFormat, TokenVerifier, signer};
+
+    pub struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    impl MutableAccount for TestAccount {
+        fn set_last_token_reset(&mut self, last_token_reset: u64) {
+            self.last_token_reset = last_token_reset;
+        }
+    }
+
+    #[test]
+    fn generate_token() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+        tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+    }
+
+    #[test]
+    fn generate_token_with_prefix() {
+        let prefix = "prefix";
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix(prefix);
+        assert!(tokenize.generate("326359466171826176").expect("Couldn't generate new token").starts_with(prefix));
+    }
+
+    #[test]
+    fn validate_token() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+        tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
+            Some(TestAccount { last_token_reset: 0 })
+        }).expect("Couldn't validate token");
+    }
+
+    #[test]
+    fn validate_token_with_prefix() {
+        let prefix = "prefix";
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix(prefix);
+        tokenize.validate("prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI", |_id| {
+            Some(TestAccount { last_token_reset: 0 })
+        }).expect("Couldn't validate token");
+    }
+
+    #[test]
+    fn validate_token_returns_concrete_account() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+        let account = tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
+            Some(TestAccount { last_token_reset: 42 })
+        }).expect("Couldn't validate token");
+        assert_eq!(account.last_token_reset, 42);
+    }
+
+    #[test]
+    fn validate_token_async() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+        let account = futures::executor::block_on(tokenize.validate_async("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |id| async move {
+            assert_eq!(id, "326359466171826176");
+            Some(TestAccount { last_token_reset: 0 })
+        }));
+        assert!(account.is_ok());
+    }
+
+    #[test]
+    fn parse_token_with_prefix() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix("prefix");
+        let token = tokenize.parse("prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI")
+            .expect("Couldn't parse token");
+        assert_eq!(token.prefix(), Some("prefix"));
+        assert_eq!(token.account_id(), "326359466171826176");
+        assert_eq!(token.generated_at(), 95341441);
+    }
+
+    #[test]
+    fn parse_token_missing_prefix() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix("prefix");
+        assert_eq!(tokenize.parse("prefix.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI").err(), Some(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn generated_token_round_trips() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix("prefix");
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        let parsed = tokenize.parse(token.to_string()).expect("Couldn't parse token");
+        assert_eq!(parsed, token);
+        assert_eq!(token.to_string().parse::<Token>(), Ok(token));
+    }
+
+    #[test]
+    fn token_issued_at() {
+        let token: Token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc".parse().expect("Couldn't decode token");
+        assert_eq!(token.issued_at().timestamp(), 1641635607);
+        assert_eq!(token.signature(), "ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc");
+    }
+
+    #[test]
+    fn validate_expired_token() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(30 * 24 * 60 * 60)));
+        assert_eq!(tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
+            Some(TestAccount { last_token_reset: 0 })
+        }).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn validate_fresh_token_with_max_age() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).is_ok());
+    }
+
+    #[test]
+    fn validate_token_with_old_secret() {
+        let tokenize = Tokenize::with_secrets("owo".as_bytes().to_vec(), vec!["uwu".as_bytes().to_vec()]);
+        assert!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").is_ok());
+
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(Tokenize::new("owo".as_bytes().to_vec()).parse(&token).is_ok());
+        assert_eq!(Tokenize::new("uwu".as_bytes().to_vec()).parse(token).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn validate_token_with_keyring() {
+        let keyring = Keyring::new("2022", "uwu".as_bytes().to_vec());
+        let old = Tokenize::with_keyring(keyring.clone());
+        let old_token = old.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(old_token.contains(".2022~"));
+
+        let tokenize = Tokenize::with_keyring(keyring.rotate("2023", "owo".as_bytes().to_vec()));
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(token.key_id(), Some("2023"));
+        assert_eq!(tokenize.parse(&old_token).expect("Couldn't parse token").key_id(), Some("2022"));
+        assert_eq!(old.parse(token.to_string()).err(), Some(TokenizeError::UnknownKey));
+        assert_eq!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").err(), Some(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn validate_url_safe_token() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_encoding(Encoding::UrlSafe);
+        let token = tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII-Wc").expect("Couldn't parse token");
+        assert_eq!(token.encoding(), Encoding::UrlSafe);
+        assert!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").is_err());
+
+        let tokenize = tokenize.accept_any_encoding();
+        let token = tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").expect("Couldn't parse token");
+        assert_eq!(token.encoding(), Encoding::Standard);
+    }
+
+    #[test]
+    fn validate_non_canonical_token_strictly() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec())
+            .accept_any_encoding()
+            .set_validation_options(ValidationOptions::new().strict());
+        assert!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").is_ok());
+        assert_eq!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc=.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").err(), Some(TokenizeError::Malformed));
+        assert_eq!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII-Wc").err(), Some(TokenizeError::Malformed));
+        assert_eq!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDd.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").err(), Some(TokenizeError::Malformed));
+        assert_eq!(tokenize.parse("MzI2MzU5NDY2MTcx ODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").err(), Some(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn validate_token_with_custom_codec() {
+        struct Hex;
+
+        impl Codec for Hex {
+            fn name(&self) -> &'static str {
+                "hex"
+            }
+
+            fn encode(&self, input: &[u8]) -> String {
+                input.iter().map(|b| format!("{:02x}", b)).collect()
+            }
+
+            fn decode(&self, input: &str) -> Result<Vec<u8>, TokenizeError> {
+                (0..input.len()).step_by(2)
+                    .map(|i| input.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or(TokenizeError::Decode)
+            }
+        }
+
+        static HEX: Hex = Hex;
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec())
+            .set_encoding(Encoding::Custom(&HEX))
+            .set_validation_options(ValidationOptions::new().strict());
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(token.starts_with("333236333539343636313731383236313736."));
+        assert_eq!(tokenize.parse(&token).expect("Couldn't parse token").encoding(), Encoding::Custom(&HEX));
+        assert_eq!(tokenize.parse_ref(&token).expect("Couldn't parse token").account_id(), Ok("326359466171826176".into()));
+        assert_eq!(tokenize.parse(token.to_uppercase()).err(), Some(TokenizeError::Malformed));
+        assert!(tokenize.parse("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").is_err());
+    }
+
+    #[test]
+    fn validate_hex_signatures() {
+        let legacy = Tokenize::new("uwu".as_bytes().to_vec()).set_signature_format(SignatureFormat::Hex);
+        let token = legacy.generate_token("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(token.signature().len(), 64);
+        assert!(token.signature().bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+        assert!(legacy.parse(token.to_string()).is_ok());
+        assert!(legacy.parse_ref(&token.to_string()).is_ok());
+
+        let current = Tokenize::new("uwu".as_bytes().to_vec());
+        let base64_token = current.generate("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(current.parse(token.to_string()).err(), Some(TokenizeError::Malformed));
+        assert_eq!(legacy.parse(&base64_token).err(), Some(TokenizeError::Malformed));
+
+        let migrating = current.accept_any_signature_format().set_validation_options(ValidationOptions::new().strict());
+        assert!(migrating.parse(token.to_string()).is_ok());
+        assert!(migrating.parse_ref(&token.to_string()).is_ok());
+        assert!(migrating.parse(&base64_token).is_ok());
+
+        let forged = format!("{}0000", &token.to_string()[..token.to_string().len() - 4]);
+        assert_eq!(migrating.parse(forged).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn revoke_every_signature_format() {
+        let as_hex = |token: &str| {
+            let (head, signature) = token.rsplit_once('.').unwrap();
+            let signature = Encoding::Standard.decode(signature).unwrap().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+            format!("{}.{}", head, signature)
+        };
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).accept_any_signature_format().set_revocation_store(MemoryRevocationStore::new());
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(tokenize.validate(as_hex(&token), account).is_ok());
+        tokenize.revoke(&*token).expect("Couldn't revoke token");
+        assert_eq!(tokenize.validate(as_hex(&token), account).err(), Some(TokenizeError::Revoked));
+        assert_eq!(tokenize.validate_ref(&as_hex(&token), |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Revoked));
+
+        let token = tokenize.generate("326359466171826177").expect("Couldn't generate new token");
+        tokenize.revoke(as_hex(&token)).expect("Couldn't revoke token");
+        assert_eq!(tokenize.validate(token, account).err(), Some(TokenizeError::Revoked));
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).accept_any_signature_format().set_usage_store(MemoryRevocationStore::new());
+        let token = tokenize.generate_single_use("326359466171826176").expect("Couldn't generate new token");
+        tokenize.validate_single_use(&*token, account).expect("Couldn't validate token");
+        assert_eq!(tokenize.validate_single_use(as_hex(&token), account).err(), Some(TokenizeError::AlreadyUsed));
+    }
+
+    #[test]
+    fn generate_into_buffer() {
+        let configs = [
+            Tokenize::new("uwu".as_bytes().to_vec()),
+            Tokenize::new("uwu".as_bytes().to_vec()).set_prefix("bot").set_version(TokenVersion::V2).unique_tokens(),
+            Tokenize::with_keyring(Keyring::new("2023", "uwu".as_bytes().to_vec())).set_audience("api").set_signer(signer::HmacSha512),
+            Tokenize::new("uwu".as_bytes().to_vec()).set_precision(TimestampPrecision::Milliseconds).set_signature_format(SignatureFormat::Hex)
+        ];
+
+        for tokenize in configs {
+            let mut buffer = String::from("Bearer ");
+            tokenize.generate_into("326359466171826176", &mut buffer).expect("Couldn't generate new token");
+            let token = buffer.strip_prefix("Bearer ").expect("Buffer was overwritten");
+            assert!(tokenize.parse(token).is_ok());
+            assert!(token.len() <= tokenize.token_len_hint(18));
+            assert!(token.len() + 3 > tokenize.token_len_hint(18));
+
+            let mut buffer = String::with_capacity(tokenize.token_len_hint(18));
+            let capacity = buffer.capacity();
+            tokenize.generate_into("326359466171826176", &mut buffer).expect("Couldn't generate new token");
+            assert_eq!(buffer.capacity(), capacity);
+        }
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_clock(FixedClock(TOKENIZE_EPOCH + 953348071000));
+        assert_eq!(tokenize.token_len_hint(18), tokenize.generate("326359466171826176").unwrap().len());
+
+        let tokenize = Tokenize::with_keyring(Keyring::new("2023", "uwu".as_bytes().to_vec()))
+            .set_clock(FixedClock(TOKENIZE_EPOCH + 953348071042))
+            .set_prefix("bot")
+            .set_version(TokenVersion::V2)
+            .set_audience("api")
+            .set_precision(TimestampPrecision::Milliseconds);
+        let mut buffer = String::new();
+        tokenize.generate_into("326359466171826176", &mut buffer).expect("Couldn't generate new token");
+        assert_eq!(Ok(buffer), tokenize.generate("326359466171826176"));
+    }
+
+    #[cfg(feature = "arrayvec")]
+    #[test]
+    fn generate_inline_tokens() {
+        use crate::{InlineToken, INLINE_TOKEN_CAPACITY};
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix("bot").set_signer(signer::HmacSha512).unique_tokens();
+        let token = tokenize.generate_inline([7; 64].to_vec()).expect("Couldn't generate new token");
+        assert!(token.len() <= tokenize.token_len_hint(64));
+        assert_eq!(tokenize.parse_ref(&token).expect("Couldn't parse token").account_id(), Ok(AccountId::new([7; 64].to_vec())));
+        assert_eq!(token.as_str().parse::<InlineToken>(), Ok(token));
+        assert!(!format!("{:?}", token).contains(token.as_str()));
+
+        assert_eq!(tokenize.generate_inline([7; 192].to_vec()).err(), Some(TokenizeError::TokenTooLong));
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_clock(FixedClock(1641635607000));
+        let token = tokenize.generate_inline("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(Ok(token.to_string()), tokenize.generate("326359466171826176"));
+        assert_eq!("a".repeat(INLINE_TOKEN_CAPACITY + 1).parse::<InlineToken>().err(), Some(TokenizeError::TokenTooLong));
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_decoding_matches_engine() {
+        let inputs = [
+            "", "MzI2MzU5NDY2MTcxODI2MTc2", "OTUzMzQ4MDc", "OTUzMzQ4MDd", "OTUzMzQ4MDc=", "OTUzMzQ4M",
+            "ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", "ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII-Wc", "dXd1 ",
+            &"BwcH".repeat(32), &"BwcH".repeat(31)
+        ];
+
+        for encoding in [Encoding::Standard, Encoding::UrlSafe] {
+            for input in inputs {
+                let decoded = encoding.codec().decode(input);
+                assert_eq!(encoding.decode(input), decoded, "{}", input);
+
+                let mut buffer = [0; 128];
+                assert_eq!(encoding.decode_slice(input, &mut buffer).map(<[u8]>::to_vec), decoded, "{}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn validate_token_with_signer() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_signer(signer::HmacSha512);
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(token.signature().len(), 86);
+        assert!(tokenize.parse(token.to_string()).is_ok());
+        assert_eq!(Tokenize::new("uwu".as_bytes().to_vec()).parse(token.to_string()).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn validate_token_with_blake3_signer() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_signer(signer::Blake3);
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(tokenize.parse(token).is_ok());
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn validate_token_with_ed25519_signer() {
+        // RFC 8032 test 1
+        let private_key = SecretKey::decode("hex:9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+        let public_key = SecretKey::decode("hex:d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a").unwrap();
+        assert_eq!(signer::Ed25519::public_key(private_key.expose()).as_ref().map(|key| &key[..]), Some(public_key.expose()));
+
+        let issuer = Tokenize::with_keyring(Keyring::new("k1", private_key.expose())).set_signer(signer::Ed25519::signing());
+        let token = issuer.generate_with_scopes("326359466171826176", ["read"]).expect("Couldn't generate new token");
+        assert!(issuer.parse(token.as_str()).is_ok());
+
+        let validator = Tokenize::with_keyring(Keyring::new("k1", public_key.expose())).set_signer(signer::Ed25519::verifying());
+        assert!(validator.parse(token.as_str()).is_ok());
+        assert!(validator.parse_ref(&token).is_ok());
+
+        let other_key = signer::Ed25519::public_key(&[7; 32]).unwrap();
+        let validator = Tokenize::with_keyring(Keyring::new("k1", &other_key[..])).set_signer(signer::Ed25519::verifying());
+        assert_eq!(validator.parse(token.as_str()).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(validator.parse_ref(&token).err(), Some(TokenizeError::InvalidSignature));
+
+        let issuer = issuer.set_revocation_store(MemoryRevocationStore::new());
+        issuer.revoke(token.as_str()).expect("Couldn't revoke token");
+        assert_eq!(issuer.parse(format!("{}==", token)).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(issuer.parse_ref(&format!("{}==", token)).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(issuer.validate(format!("{}==", token), |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::InvalidSignature));
+
+        let issuer = issuer.set_signature_format(SignatureFormat::Hex);
+        let token = issuer.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(issuer.parse_ref(&token).is_ok());
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    #[should_panic(expected = "Ed25519 verifying signers can't sign tokens")]
+    fn generate_token_with_ed25519_verifying_signer() {
+        let public_key = signer::Ed25519::public_key(&[7; 32]).unwrap();
+        let _ = Tokenize::new(&public_key[..]).set_signer(signer::Ed25519::verifying()).generate("326359466171826176");
+    }
+
+    #[test]
+    fn validate_v2_token() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_prefix("prefix").set_version(TokenVersion::V2);
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(token.starts_with("prefix.v2."));
+
+        let parsed = tokenize.parse(&token).expect("Couldn't parse token");
+        assert_eq!(parsed.version(), TokenVersion::V2);
+        assert_eq!(token.parse::<Token>(), Ok(parsed));
+        assert!(tokenize.parse("prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI").is_ok());
+
+        let downgraded = token.replace("prefix.v2.", "prefix.");
+        assert_eq!(tokenize.parse(downgraded).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn secret_key_is_redacted() {
+        let keyring = Keyring::new("2022", "uwu");
+        assert_eq!(format!("{:?}", SecretKey::from("uwu")), "SecretKey([REDACTED])");
+        assert!(!format!("{:?}", keyring).contains("uwu"));
+    }
+
+    #[test]
+    fn build_tokenize() {
+        let tokenize = Tokenize::builder().secret("uwu").prefix("prefix").build().expect("Invalid configuration");
+        assert!(tokenize.parse("prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI").is_ok());
+
+        assert_eq!(Tokenize::builder().build().err(), Some(ConfigError::MissingSecret));
+        assert_eq!(Tokenize::builder().secret("").build().err(), Some(ConfigError::EmptySecret));
+        assert_eq!(Tokenize::builder().secret("uwu").prefix("pre.fix").build().err(), Some(ConfigError::InvalidPrefix));
+        assert_eq!(Tokenize::builder().secret("uwu").prefix("pré").build().err(), Some(ConfigError::InvalidPrefix));
+        assert!(matches!(Tokenize::new("uwu").try_set_prefix(""), Err(ConfigError::InvalidPrefix)));
+        assert!(matches!(Tokenize::new("uwu").try_set_prefix("a b"), Err(ConfigError::InvalidPrefix)));
+        assert!(Tokenize::new("uwu").try_set_prefix("mfa").is_ok());
+        assert_eq!(Tokenize::builder().secret("uwu").keyring(Keyring::new("2022", "owo")).build().err(), Some(ConfigError::ConflictingSecrets));
+    }
+
+    #[test]
+    fn generate_token_with_fixed_clock() {
+        let tokenize = Tokenize::new("uwu").set_clock(FixedClock(1641635607000));
+        assert_eq!(tokenize.generate("326359466171826176"), Ok("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc".to_string()));
+    }
+
+    #[test]
+    fn validate_expired_token_with_mock_clock() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(tokenize.parse(&token).and_then(|token| tokenize.check_token(&token)).is_ok());
+
+        clock.advance(61_000);
+        assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Expired));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_token_and_error() {
+        let token: Token = "prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI".parse().expect("Couldn't decode token");
+        let json = serde_json::to_string(&token).expect("Couldn't serialize token");
+        assert_eq!(serde_json::from_str::<Token>(&json).expect("Couldn't deserialize token"), token);
+
+        assert_eq!(serde_json::to_string(&TokenizeError::Expired).expect("Couldn't serialize error"), "\"Expired\"");
+        let options = ValidationOptions::new().max_age(Duration::from_secs(60));
+        assert_eq!(serde_json::from_str::<ValidationOptions>(&serde_json::to_string(&options).unwrap()).unwrap(), options);
+    }
+
+    #[test]
+    fn validate_invalidated_token() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+        assert_eq!(tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", |_id| {
+            Some(TestAccount { last_token_reset: 1641641228500 })
+        }).err(), Some(TokenizeError::Invalidated));
+    }
+
+    #[test]
     fn validate_token_with_invalid_signature() {
         let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
-        assert!(tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid", |_id| {
+        assert_eq!(tokenize.validate("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid", |_id| {
+            Some(TestAccount { last_token_reset: 0 })
+        }).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn validate_token_with_store() {
+        struct TestStore {
+            available: bool
+        }
+
+        impl AccountStore<TestAccount> for TestStore {
+            fn fetch(&self, id: &str) -> Result<Option<TestAccount>, StoreError> {
+                if !self.available {
+                    return Err(StoreError::new("connection refused"))
+                }
+
+                Ok((id == "326359466171826176").then_some(TestAccount { last_token_reset: 0 }))
+            }
+        }
+
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec());
+        let token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+        tokenize.validate_with_store(token, &TestStore { available: true }).expect("Couldn't validate token");
+        assert_eq!(tokenize.validate_with_store(token, &TestStore { available: false }).err(), Some(TokenizeError::Store(StoreError::new("connection refused"))));
+
+        let token = tokenize.generate("0").expect("Couldn't generate new token");
+        assert_eq!(tokenize.validate_with_store(token, &TestStore { available: true }).err(), Some(TokenizeError::AccountNotFound));
+    }
+
+    #[test]
+    fn validate_token_with_memory_store() {
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_clock(FixedClock(1641635607000));
+        let store = MemoryAccountStore::new();
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(tokenize.validate_with_store(&*token, &store).err(), Some(TokenizeError::AccountNotFound));
+
+        store.insert("326359466171826176", "owo");
+        assert_eq!(tokenize.validate_with_store(&*token, &store).expect("Couldn't validate token").data, "owo");
+
+        assert!(store.reset_tokens("326359466171826176"));
+        assert_eq!(tokenize.validate_with_store(&*token, &store).err(), Some(TokenizeError::Invalidated));
+        assert!(!store.reset_tokens("0"));
+    }
+
+    #[test]
+    fn validate_revoked_token() {
+        #[derive(Default)]
+        struct TestRevocationStore {
+            revoked: Mutex<HashSet<String>>
+        }
+
+        impl RevocationStore for TestRevocationStore {
+            fn is_revoked(&self, key: &str) -> Result<bool, StoreError> {
+                Ok(self.revoked.lock().unwrap().contains(key))
+            }
+
+            fn revoke(&self, key: &str, _ttl: Option<Duration>) -> Result<(), StoreError> {
+                self.revoked.lock().unwrap().insert(key.to_string());
+                Ok(())
+            }
+        }
+
+        let store = Arc::new(TestRevocationStore::default());
+        let tokenize = Tokenize::new("uwu".as_bytes().to_vec()).set_revocation_store(store.clone());
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        tokenize.revoke(&*token).expect("Couldn't revoke token");
+        assert_eq!(store.revoked.lock().unwrap().len(), 1);
+        assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Revoked));
+        assert!(Tokenize::new("uwu").revoke("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").is_err());
+    }
+
+    #[test]
+    fn revoke_token_with_ttl() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let store = Arc::new(MemoryRevocationStore::with_clock(clock.clone()));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_revocation_store(store.clone())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        clock.advance(30_000);
+        tokenize.revoke(&*token).expect("Couldn't revoke token");
+        assert_eq!(tokenize.validate(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Revoked));
+
+        clock.advance(31_000);
+        store.purge_expired();
+        assert!(store.is_empty());
+        assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn invalidate_all_tokens() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone());
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let mut account = TestAccount { last_token_reset: 0 };
+
+        clock.advance(1_000);
+        assert_eq!(tokenize.invalidate_all(&mut account), 1641635608000);
+        assert_eq!(account.last_token_reset, 1641635608000);
+        assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: account.last_token_reset })).err(), Some(TokenizeError::Invalidated));
+    }
+
+    #[test]
+    fn validate_token_with_scopes() {
+        let tokenize = Tokenize::new("uwu").set_prefix("prefix").set_version(TokenVersion::V2).set_encoding(Encoding::UrlSafe);
+        let token = tokenize.generate_with_scopes("326359466171826176", ["read", "write"]).expect("Couldn't generate new token");
+        assert_eq!(token.split('.').count(), 6);
+
+        let (parsed, _) = tokenize.validate_token(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+        assert_eq!(parsed.scopes(), ["read", "write"]);
+        assert_eq!(parsed.to_string(), token);
+        assert_eq!(token.parse::<Token>().expect("Couldn't decode token").scopes(), parsed.scopes());
+        assert!(parsed.require_scopes(&["read", "write"]).is_ok());
+        assert_eq!(parsed.require_scope("admin"), Err(TokenizeError::MissingScope("admin".to_string())));
+
+        let mut segments = token.split('.').collect::<Vec<_>>();
+        let escalated = format!("s:{}", Encoding::UrlSafe.encode("read,write,admin"));
+        segments[4] = &escalated;
+        assert_eq!(tokenize.parse(segments.join(".")).err(), Some(TokenizeError::InvalidSignature));
+
+        segments.swap(3, 4);
+        assert_eq!(tokenize.parse(segments.join(".")).err(), Some(TokenizeError::Malformed));
+        assert_eq!(tokenize.generate_with_scopes("326359466171826176", ["read,write"]).err(), Some(TokenizeError::Malformed));
+        assert!(tokenize.parse(tokenize.generate("326359466171826176").unwrap()).unwrap().scopes().is_empty());
+    }
+
+    #[cfg(feature = "claims")]
+    #[test]
+    fn validate_token_with_claims() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Claims {
+            session: String,
+            device: u32
+        }
+
+        let tokenize = Tokenize::new("uwu").set_prefix("prefix");
+        let claims = Claims { session: "abc".to_string(), device: 7 };
+        let token = tokenize.generate_with_claims("326359466171826176", &claims).expect("Couldn't generate new token");
+
+        let (_, decoded) = tokenize.validate_with_claims::<_, _, _, Claims>(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+        assert_eq!(decoded, claims);
+        assert_eq!(tokenize.parse(&*token).unwrap().to_string(), token);
+        assert_eq!(tokenize.validate_with_claims::<_, _, _, u32>(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::InvalidClaims));
+
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(tokenize.validate_with_claims::<_, _, _, Claims>(token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::InvalidClaims));
+    }
+
+    #[test]
+    fn refresh_token_pair() {
+        let access = Tokenize::new("uwu").set_prefix("access");
+        let refresh = Tokenize::new("owo").set_prefix("refresh").set_revocation_store(MemoryRevocationStore::new());
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let sessions = SessionManager::new(access, refresh).expect("Invalid configuration");
+
+        let pair = sessions.issue("326359466171826176").expect("Couldn't issue tokens");
+        assert_ne!(sessions.issue("326359466171826176").unwrap().refresh_token, pair.refresh_token);
+        assert_eq!(sessions.refresh(&*pair.access_token, account).err(), Some(TokenizeError::PrefixMismatch));
+
+        let renewed = sessions.refresh(&*pair.refresh_token, account).expect("Couldn't refresh tokens");
+        sessions.validate(renewed.access_token, account).expect("Couldn't validate token");
+        assert_eq!(sessions.refresh(pair.refresh_token, account).err(), Some(TokenizeError::Revoked));
+        sessions.refresh(renewed.refresh_token, account).expect("Couldn't refresh tokens");
+
+        assert_eq!(SessionManager::new(Tokenize::new("uwu"), Tokenize::new("owo").set_revocation_store(MemoryRevocationStore::new())).err(), Some(ConfigError::SharedPrefix));
+        assert_eq!(SessionManager::new(Tokenize::new("uwu"), Tokenize::new("owo").set_prefix("refresh")).err(), Some(ConfigError::MissingRevocationStore));
+    }
+
+    #[test]
+    fn track_sessions() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let access = Tokenize::new("uwu").set_prefix("access").set_clock(clock.clone());
+        let refresh = Tokenize::new("owo").set_prefix("refresh").set_clock(clock.clone()).set_revocation_store(MemoryRevocationStore::new());
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let sessions = SessionManager::new(access, refresh).expect("Invalid configuration");
+        assert!(matches!(sessions.login("326359466171826176", None), Err(TokenizeError::Store(_))));
+
+        let sessions = sessions.set_session_store(MemorySessionStore::new());
+        let (laptop, laptop_pair) = sessions.login("326359466171826176", Some("laptop")).expect("Couldn't log in");
+        clock.advance(1000);
+        let (phone, phone_pair) = sessions.login("326359466171826176", Some("phone")).expect("Couldn't log in");
+        sessions.login("326359466171826177", None).expect("Couldn't log in");
+        assert_eq!(laptop.created_at, 1641635607000);
+        assert_eq!(sessions.sessions("326359466171826176").unwrap(), vec![laptop.clone(), phone.clone()]);
+
+        clock.advance(1000);
+        let (session, _) = sessions.validate_session(&*laptop_pair.access_token, account).expect("Couldn't validate token");
+        assert_eq!((session.id.as_str(), session.last_seen), (laptop.id.as_str(), 1641635609000));
+        assert_eq!(sessions.sessions("326359466171826176").unwrap()[0].last_seen, 1641635609000);
+
+        // Refreshing keeps the session
+        let renewed = sessions.refresh(&*laptop_pair.refresh_token, account).expect("Couldn't refresh tokens");
+        assert_eq!(sessions.validate_session(&*renewed.access_token, account).unwrap().0.id, laptop.id);
+
+        assert_eq!(sessions.terminate_others("326359466171826176", &phone.id), Ok(1));
+        assert_eq!(sessions.validate(&*renewed.access_token, account).err(), Some(TokenizeError::Revoked));
+        assert_eq!(sessions.refresh(&*renewed.refresh_token, account).err(), Some(TokenizeError::Revoked));
+        assert!(sessions.validate(&*phone_pair.access_token, account).is_ok());
+
+        // Tokens issued outside of a session aren't tied to one
+        let pair = sessions.issue("326359466171826176").expect("Couldn't issue tokens");
+        assert!(sessions.validate(&*pair.access_token, account).is_ok());
+        assert_eq!(sessions.validate_session(&*pair.access_token, account).err(), Some(TokenizeError::Revoked));
+
+        assert_eq!(sessions.terminate(&phone.id), Ok(true));
+        assert_eq!(sessions.terminate(&phone.id), Ok(false));
+        assert_eq!(sessions.validate(&*phone_pair.access_token, account).err(), Some(TokenizeError::Revoked));
+    }
+
+    #[test]
+    fn reuse_refresh_token() {
+        // Lets every token through the revocation check, as if concurrent exchanges raced past it
+        struct RacingStore(MemoryRevocationStore);
+
+        impl RevocationStore for RacingStore {
+            fn is_revoked(&self, _key: &str) -> Result<bool, StoreError> {
+                Ok(false)
+            }
+
+            fn revoke(&self, key: &str, ttl: Option<Duration>) -> Result<(), StoreError> {
+                self.0.revoke(key, ttl)
+            }
+
+            fn try_revoke(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+                self.0.try_revoke(key, ttl)
+            }
+        }
+
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        for store in [Arc::new(MemoryRevocationStore::new()) as Arc<dyn RevocationStore>, Arc::new(RacingStore(MemoryRevocationStore::new()))] {
+            let access = Tokenize::new("uwu").set_prefix("access");
+            let refresh = Tokenize::new("owo").set_prefix("refresh").set_revocation_store(store);
+            let sessions = SessionManager::new(access, refresh).expect("Invalid configuration")
+                .set_session_store(MemorySessionStore::new());
+
+            let (_, pair) = sessions.login("326359466171826176", Some("laptop")).expect("Couldn't log in");
+            let renewed = sessions.refresh(&*pair.refresh_token, account).expect("Couldn't refresh tokens");
+            assert_eq!(sessions.refresh(&*pair.refresh_token, account).err(), Some(TokenizeError::Revoked));
+
+            assert!(sessions.sessions("326359466171826176").unwrap().is_empty());
+            assert_eq!(sessions.validate(&*renewed.access_token, account).err(), Some(TokenizeError::Revoked));
+            assert_eq!(sessions.refresh(&*renewed.refresh_token, account).err(), Some(TokenizeError::Revoked));
+        }
+    }
+
+    #[test]
+    fn revoke_every_encoding_of_a_token() {
+        let twin = |token: &str| token.replace('+', "-").replace('/', "_");
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let tokenize = Tokenize::new("uwu").accept_any_encoding().set_revocation_store(MemoryRevocationStore::new());
+        let token = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+        assert!(tokenize.validate(twin(token), account).is_ok());
+        tokenize.revoke(token).expect("Couldn't revoke token");
+        assert_eq!(tokenize.validate(twin(token), account).err(), Some(TokenizeError::Revoked));
+        assert_eq!(tokenize.validate_ref(&twin(token), |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Revoked));
+
+        let tokenize = Tokenize::new("uwu").accept_any_encoding().set_usage_store(MemoryRevocationStore::new());
+        let token = std::iter::repeat_with(|| tokenize.generate_single_use("326359466171826176").unwrap())
+            .find(|token| token.contains(['+', '/']))
+            .unwrap();
+        tokenize.validate_single_use(&*token, account).expect("Couldn't validate token");
+        assert_eq!(tokenize.validate_single_use(twin(&token), account).err(), Some(TokenizeError::AlreadyUsed));
+
+        let access = Tokenize::new("uwu").set_prefix("access");
+        let refresh = Tokenize::new("owo").set_prefix("refresh").accept_any_encoding().set_revocation_store(MemoryRevocationStore::new());
+        let sessions = SessionManager::new(access, refresh).expect("Invalid configuration");
+        let pair = std::iter::repeat_with(|| sessions.issue("326359466171826176").unwrap())
+            .find(|pair| pair.refresh_token.contains(['+', '/']))
+            .unwrap();
+        sessions.refresh(&*pair.refresh_token, account).expect("Couldn't refresh tokens");
+        assert_eq!(sessions.refresh(twin(&pair.refresh_token), account).err(), Some(TokenizeError::Revoked));
+    }
+
+    #[test]
+    fn validate_single_use_token() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let store = Arc::new(MemoryRevocationStore::with_clock(clock.clone()));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_usage_store(store.clone())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let token = tokenize.generate_single_use("326359466171826176").expect("Couldn't generate new token");
+        assert_ne!(tokenize.generate_single_use("326359466171826176").unwrap(), token);
+        assert!(tokenize.parse(&*token).unwrap().is_single_use());
+        assert_eq!(tokenize.validate(&*token, account).err(), Some(TokenizeError::SingleUse));
+        assert_eq!(tokenize.validate_single_use(&*token, |_id| None::<TestAccount>).err(), Some(TokenizeError::AccountNotFound));
+
+        tokenize.validate_single_use(&*token, account).expect("Couldn't validate token");
+        assert_eq!(tokenize.validate_single_use(&*token, account).err(), Some(TokenizeError::AlreadyUsed));
+        assert_eq!(tokenize.validate_single_use(tokenize.generate("326359466171826176").unwrap(), account).err(), Some(TokenizeError::Malformed));
+
+        clock.advance(61_000);
+        store.purge_expired();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn validate_binary_account_id() {
+        let tokenize = Tokenize::new("uwu");
+        let id = [0xff, 0x00, 0xfe, 0x80];
+        let token = tokenize.generate_bytes(id).expect("Couldn't generate new token");
+        assert_eq!(tokenize.parse(&*token).expect("Couldn't parse token").account_id().as_bytes(), id);
+
+        tokenize.validate_bytes(&*token, |account_id| {
+            assert_eq!(account_id, id);
             Some(TestAccount { last_token_reset: 0 })
-        }).is_err());
+        }).expect("Couldn't validate token");
+        assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Decode));
+    }
+
+    #[test]
+    fn generate_token_with_numeric_id() {
+        let tokenize = Tokenize::new("uwu").set_clock(FixedClock(1641635607000));
+        assert_eq!(tokenize.generate(326359466171826176u64), tokenize.generate("326359466171826176"));
+
+        let token = tokenize.parse(tokenize.generate(326359466171826176u64).unwrap()).expect("Couldn't parse token");
+        assert_eq!(token.account_id().to_u64(), Some(326359466171826176));
+        assert_eq!(AccountId::from("abc").to_u64(), None);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn generate_token_with_uuid() {
+        let tokenize = Tokenize::new("uwu");
+        let uuid = uuid::Uuid::from_u128(0x936da01f_9abd_4d9d_80c7_02af85c822a8);
+        let token = tokenize.parse(tokenize.generate(uuid).unwrap()).expect("Couldn't parse token");
+        assert_eq!(token.account_id().as_bytes().len(), 16);
+        assert_eq!(token.account_id().to_uuid(), Some(uuid));
+        assert_eq!(AccountId::from(uuid.to_string()).to_uuid(), Some(uuid));
+    }
+
+    #[test]
+    fn validate_token_with_custom_epoch() {
+        const DISCORD_EPOCH: i64 = 1420070400000;
+
+        let tokenize = Tokenize::new("uwu").set_epoch(DISCORD_EPOCH).set_clock(FixedClock(1641635607000));
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(token.generated_at(), (1641635607000 - DISCORD_EPOCH) as u64 / 1000);
+        assert_eq!(token.issued_at_millis(), 1641635607000);
+
+        let parsed = tokenize.parse(token.to_string()).expect("Couldn't parse token");
+        assert_eq!(parsed.issued_at_millis(), 1641635607000);
+        assert_eq!(tokenize.validate(token.to_string(), |_id| Some(TestAccount { last_token_reset: 1641635608000 })).err(), Some(TokenizeError::Invalidated));
+        tokenize.validate(token.to_string(), |_id| Some(TestAccount { last_token_reset: 1641635606000 })).expect("Couldn't validate token");
+    }
+
+    #[test]
+    fn validate_token_issued_in_future() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_validation_options(ValidationOptions::new().max_clock_skew(Duration::from_secs(5)));
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        clock.set(1641635603000);
+        tokenize.validate(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+
+        clock.set(1641635601000);
+        assert_eq!(tokenize.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::IssuedInFuture));
+    }
+
+    #[test]
+    fn validate_token_length_limits() {
+        let tokenize = Tokenize::new("uwu").set_validation_options(ValidationOptions::new().max_length(128).max_account_id_length(18));
+        tokenize.parse(tokenize.generate("326359466171826176").unwrap()).expect("Couldn't parse token");
+        assert_eq!(tokenize.parse(tokenize.generate("3263594661718261760").unwrap()).err(), Some(TokenizeError::TokenTooLong));
+        assert_eq!(tokenize.parse("a".repeat(129)).err(), Some(TokenizeError::TokenTooLong));
+    }
+
+    #[test]
+    fn validate_token_with_several_prefixes() {
+        let tokenize = Tokenize::new("uwu").set_prefixes(["mfa", "bot"]);
+        let mfa = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let bot = tokenize.generate_with_prefix("bot", "326359466171826176").expect("Couldn't generate new token");
+        assert!(mfa.starts_with("mfa."));
+
+        let (token, _) = tokenize.validate_token(bot, |_id| Some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+        assert_eq!(token.prefix(), Some("bot"));
+        assert_eq!(tokenize.parse(mfa.replacen("mfa", "user", 1)).err(), Some(TokenizeError::PrefixMismatch));
+        assert_eq!(tokenize.parse(mfa.replacen("mfa", "bot", 1)).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(tokenize.generate_with_prefix("user", "326359466171826176").err(), Some(TokenizeError::PrefixMismatch));
+    }
+
+    #[test]
+    fn validate_token_of_tenant() {
+        let mut registry = TenantRegistry::new();
+        let acme = registry.insert(Tokenize::new("uwu").set_prefix("acme")).expect("Invalid tenant");
+        let globex = registry.insert(Tokenize::new("owo").set_prefixes(["globex", "initech"])).expect("Invalid tenant");
+        assert!(matches!(registry.insert(Tokenize::new("owo").set_prefix("initech")), Err(ConfigError::SharedPrefix)));
+        assert!(matches!(registry.insert(Tokenize::new("owo")), Err(ConfigError::MissingPrefix)));
+
+        let fetcher = |_id| Some(TestAccount { last_token_reset: 0 });
+        let token = registry.generate(&acme, "326359466171826176").expect("Couldn't generate new token");
+        registry.validate(&*token, fetcher).expect("Couldn't validate token");
+
+        let initech = registry.tenant("initech").expect("Unknown tenant");
+        let (parsed, _) = registry.validate_token(registry.generate(&initech, "326359466171826176").expect("Couldn't generate new token"), fetcher).expect("Couldn't validate token");
+        assert_eq!(parsed.prefix(), Some("initech"));
+        assert!(registry.generate(&globex, "326359466171826176").expect("Couldn't generate new token").starts_with("globex."));
+
+        assert_eq!(registry.parse(token.replacen("acme", "globex", 1)).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(registry.parse(token.replacen("acme", "hooli", 1)).err(), Some(TokenizeError::PrefixMismatch));
+    }
+
+    #[test]
+    fn validate_batch_fetches_accounts_once() {
+        struct Accounts(std::cell::RefCell<Vec<Vec<String>>>);
+
+        impl BatchAccountStore<MemoryAccount> for Accounts {
+            fn fetch_many(&self, ids: &[&str]) -> std::result::Result<HashMap<String, MemoryAccount>, StoreError> {
+                self.0.borrow_mut().push(ids.iter().map(|id| id.to_string()).collect());
+                Ok(ids.iter().filter(|&&id| id != "1").map(|&id| (id.to_owned(), MemoryAccount { data: (), last_token_reset: 0 })).collect())
+            }
+        }
+
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let missing = tokenize.generate("1").expect("Couldn't generate new token");
+        let store = Accounts(std::cell::RefCell::new(Vec::new()));
+
+        let results = tokenize.validate_batch(&[&*token, "owo", &*missing, &*token], &store);
+        assert!(results[0].is_ok() && results[3].is_ok());
+        assert!(matches!(results[1], Err(TokenizeError::Malformed)));
+        assert!(matches!(results[2], Err(TokenizeError::AccountNotFound)));
+        assert_eq!(*store.0.borrow(), vec![vec!["326359466171826176".to_owned(), "1".to_owned()]]);
+
+        assert!(tokenize.validate_batch(&["owo"], &store)[0].is_err());
+        assert_eq!(store.0.borrow().len(), 1);
+    }
+
+    #[test]
+    fn validate_batch_per_shard() {
+        struct Shards(std::cell::RefCell<Vec<(u8, Vec<String>)>>);
+
+        impl ShardedAccountStore<MemoryAccount> for Shards {
+            type Shard = u8;
+
+            fn route(&self, id: &str) -> u8 {
+                id.as_bytes()[0]
+            }
+
+            fn fetch_shard(&self, shard: &u8, ids: &[&str]) -> std::result::Result<HashMap<String, MemoryAccount>, StoreError> {
+                self.0.borrow_mut().push((*shard, ids.iter().map(|id| id.to_string()).collect()));
+                Ok(ids.iter().map(|&id| (id.to_owned(), MemoryAccount { data: (), last_token_reset: 0 })).collect())
+            }
+        }
+
+        let tokenize = Tokenize::new("uwu");
+        let tokens = ["10", "20", "11", "21", "12"].map(|id| tokenize.generate(id).expect("Couldn't generate new token"));
+        let store = Sharded(Shards(std::cell::RefCell::new(Vec::new())));
+
+        assert!(tokenize.validate_batch(&tokens, &store).iter().all(Result::is_ok));
+        let mut queries = store.0.0.take();
+        queries.sort();
+        assert_eq!(queries, vec![
+            (b'1', vec!["10".to_owned(), "11".to_owned(), "12".to_owned()]),
+            (b'2', vec!["20".to_owned(), "21".to_owned()])
+        ]);
+
+        tokenize.validate_with_store(&*tokens[1], &store).expect("Couldn't validate token");
+        assert_eq!(store.0.0.take(), vec![(b'2', vec!["20".to_owned()])]);
+    }
+
+    #[test]
+    fn validate_large_batch() {
+        let tokenize = Tokenize::new("uwu");
+        let store = MemoryAccountStore::new();
+        store.insert("326359466171826176", ());
+
+        let mut tokens = vec![tokenize.generate("326359466171826176").expect("Couldn't generate new token"); 1000];
+        tokens[500] = "owo".to_owned();
+
+        let results = tokenize.validate_batch(&tokens, &store);
+        assert_eq!(results.len(), 1000);
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 999);
+        assert!(matches!(results[500], Err(TokenizeError::Malformed)));
+    }
+
+    #[test]
+    fn parse_ref_matches_parse() {
+        let instances = [
+            Tokenize::new("uwu"),
+            Tokenize::new("uwu").set_prefixes(["mfa", "bot"]).set_version(TokenVersion::V2),
+            Tokenize::new("uwu").set_encoding(Encoding::UrlSafe).set_signer(signer::HmacSha512),
+            Tokenize::with_keyring(Keyring::new("k1", "uwu")).set_validation_options(ValidationOptions::new().strict())
+        ];
+
+        for tokenize in &instances {
+            let mut tokens = vec![
+                tokenize.generate("326359466171826176").expect("Couldn't generate new token"),
+                tokenize.generate_with_scopes("326359466171826176", ["read", "write"]).expect("Couldn't generate new token"),
+                tokenize.generate_single_use("326359466171826176").expect("Couldn't generate new token"),
+                "".to_owned(), "owo".to_owned(), "a.b".to_owned(), "a.b.c.d.e.f".to_owned(), "s:eA.a.b".to_owned(),
+                "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc".to_owned(),
+                "prefix.MzI2MzU5NDY2MTcxODI2MTc2.OTUzNDE0NDE.JMOWr0OOZqbqqTkQp5LvvzBmsvu5JWbAPp4UpwzyJKI".to_owned()
+            ];
+            tokens.push(tokens[1].replacen(".s:", ".x:", 1));
+            tokens.push(tokens[0].replacen('.', ".s:eA.", 1));
+            tokens.push(format!("{}A", tokens[0]));
+
+            for token in &tokens {
+                let expected = tokenize.parse(&**token);
+                assert_eq!(tokenize.parse_ref(token).and_then(|token| token.to_token()), expected, "{}", token);
+            }
+        }
+    }
+
+    #[test]
+    fn validate_ref() {
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        let (parsed, _) = tokenize.validate_ref(&token, |id| (id == "326359466171826176").then_some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+        assert_eq!(parsed.account_id(), Ok(AccountId::from("326359466171826176")));
+        assert!(matches!(tokenize.validate_ref(&token, |_id| Some(TestAccount { last_token_reset: u64::MAX / 2 })), Err(TokenizeError::Invalidated)));
+
+        let long = tokenize.generate("a".repeat(200)).expect("Couldn't generate new token");
+        assert!(tokenize.validate_ref(&long, |id| (id.len() == 200).then_some(TestAccount { last_token_reset: 0 })).is_ok());
+
+        let single_use = tokenize.generate_single_use("326359466171826176").expect("Couldn't generate new token");
+        assert!(matches!(tokenize.validate_ref(&single_use, |_id| Some(TestAccount { last_token_reset: 0 })), Err(TokenizeError::SingleUse)));
+    }
+
+    #[test]
+    fn validate_cached() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone()).set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(90)));
+        let cache = ValidationCache::new(2, Duration::from_secs(60));
+        let fetches = std::cell::Cell::new(0);
+        let fetcher = |_id| {
+            fetches.set(fetches.get() + 1);
+            Some(MemoryAccount { data: (), last_token_reset: 0 })
+        };
+
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        for _ in 0..3 {
+            tokenize.validate_cached(&*token, &cache, fetcher).expect("Couldn't validate token");
+        }
+        assert_eq!((fetches.get(), cache.hits(), cache.misses()), (1, 2, 1));
+
+        clock.advance(61_000);
+        tokenize.validate_cached(&*token, &cache, fetcher).expect("Couldn't validate token");
+        assert_eq!(fetches.get(), 2);
+
+        cache.invalidate_account("326359466171826176");
+        assert!(cache.is_empty());
+        assert!(tokenize.validate_cached(&*token, &cache, |_id| None::<MemoryAccount>).is_err());
+
+        for id in ["1", "2", "3"] {
+            tokenize.validate_cached(tokenize.generate(id).expect("Couldn't generate new token"), &cache, fetcher).expect("Couldn't validate token");
+        }
+        assert_eq!(cache.len(), 2);
+
+        assert!(tokenize.validate_cached(format!("{}A", token), &cache, fetcher).is_err());
+        tokenize.validate_cached(&*token, &cache, fetcher).expect("Couldn't validate token");
+        clock.advance(30_000);
+        assert_eq!(tokenize.validate_cached(&*token, &cache, fetcher).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn validate_cached_after_reset() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone());
+        let cache = ValidationCache::new(10, Duration::from_secs(60));
+        let account = std::cell::RefCell::new(MemoryAccount { data: (), last_token_reset: 0 });
+        let fetcher = |_id| Some(account.borrow().clone());
+
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        tokenize.validate_cached(&*token, &cache, fetcher).expect("Couldn't validate token");
+        tokenize.validate_cached(&*token, &cache, fetcher).expect("Couldn't validate token");
+        assert_eq!(cache.hits(), 1);
+
+        clock.advance(1_000);
+        tokenize.invalidate_all(&mut *account.borrow_mut());
+        clock.advance(1_000);
+
+        // Caching a token validated against the reset account evicts the ones issued before
+        let fresh = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        tokenize.validate_cached(&*fresh, &cache, fetcher).expect("Couldn't validate token");
+        assert_eq!(cache.len(), 1);
+        assert_eq!(tokenize.validate_cached(&*token, &cache, fetcher).err(), Some(TokenizeError::Invalidated));
+        assert_eq!(cache.hits(), 1);
+
+        cache.invalidate_account("326359466171826176");
+        assert!(cache.is_empty());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn trace_validation() {
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Output {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let tokenize = Tokenize::new("uwu");
+        let token = tracing::subscriber::with_default(subscriber, || {
+            let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+            tokenize.validate(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+            assert!(tokenize.validate(format!("{}A", token), |_id| Some(TestAccount { last_token_reset: 0 })).is_err());
+            token
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("token generated") && output.contains("token validated") && output.contains("Token signature doesn't match"));
+        assert!(output.contains(&AccountId::trace_hash(b"326359466171826176")));
+        assert!(!output.contains("326359466171826176") && !output.contains(&token[..token.len() - 10]));
+    }
+
+    #[test]
+    fn record_metrics() {
+        let metrics = Arc::new(MemoryMetrics::new());
+        let tokenize = Tokenize::new("uwu").set_metrics_sink(metrics.clone());
+        let store = MemoryAccountStore::new();
+        store.insert("326359466171826176", ());
+
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let missing = tokenize.generate("1").expect("Couldn't generate new token");
+        tokenize.validate_batch(&[&*token, &*missing, "owo", &*token], &store);
+        assert!(tokenize.validate_ref(&token, |_id| None::<TestAccount>).is_err());
+
+        assert_eq!(metrics.counts(), HashMap::from([("valid", 2), ("account_not_found", 2), ("malformed", 1)]));
+        assert_eq!(metrics.validations(), 5);
+        assert_eq!(metrics.latency_histogram().iter().map(|(_, count)| count).sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn validate_hardened() {
+        let keyring = Keyring::new("k1", "uwu").add_key("k2", "owo");
+        let tokenize = Tokenize::with_keyring(keyring).set_validation_options(ValidationOptions::new().hardened());
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        assert!(tokenize.validate(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).is_ok());
+        assert_eq!(tokenize.validate(&*token, |_id| None::<TestAccount>).err(), Some(TokenizeError::AccountNotFound));
+        assert_eq!(tokenize.parse(token.replacen("k1~", "k3~", 1)).err(), Some(TokenizeError::UnknownKey));
+
+        let tokenize = Tokenize::with_secrets("uwu", ["owo", "nya"]).set_validation_options(ValidationOptions::new().hardened());
+        assert!(tokenize.parse(Tokenize::new("nya").generate("326359466171826176").expect("Couldn't generate new token")).is_ok());
+        assert_eq!(tokenize.parse(Tokenize::new("rawr").generate("326359466171826176").expect("Couldn't generate new token")).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn derive_secret() {
+        // RFC 5869, test case 3
+        let derived = SecretKey::new(vec![0x0b; 22]).derive("");
+        let expected = "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d";
+        assert_eq!(derived.expose().iter().map(|byte| format!("{:02x}", byte)).collect::<String>(), expected);
+
+        assert_ne!(SecretKey::from("uwu").derive("auth-tokens-v1"), SecretKey::from("uwu").derive("auth-tokens-v2"));
+        let token = Tokenize::new(SecretKey::from("uwu").derive("auth-tokens-v1")).generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(Tokenize::from_master_key("uwu", "auth-tokens-v1").parse(token).is_ok());
+    }
+
+    #[test]
+    fn load_secret() {
+        assert_eq!(SecretKey::decode("base64:dXd1"), Ok(SecretKey::from("uwu")));
+        assert_eq!(SecretKey::decode("base64:dXd1dQ=="), Ok(SecretKey::from("uwuu")));
+        assert_eq!(SecretKey::decode("hex:757775"), Ok(SecretKey::from("uwu")));
+        assert_eq!(SecretKey::decode("uwu"), Ok(SecretKey::from("uwu")));
+        assert_eq!(SecretKey::decode("hex:7577a"), Err(ConfigError::InvalidSecret));
+        assert_eq!(SecretKey::decode("hex:75zz"), Err(ConfigError::InvalidSecret));
+        assert_eq!(SecretKey::decode("base64:d?d1"), Err(ConfigError::InvalidSecret));
+        assert_eq!(SecretKey::decode("base64:"), Err(ConfigError::EmptySecret));
+
+        std::env::set_var("TOKENIZE_TEST_SECRET", "hex:757775");
+        let token = Tokenize::new("uwu").generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(Tokenize::from_env("TOKENIZE_TEST_SECRET").expect("Couldn't load secret").parse(token).is_ok());
+        assert_eq!(Tokenize::from_env("TOKENIZE_TEST_MISSING_SECRET").err(), Some(ConfigError::MissingSecret));
+    }
+
+    #[test]
+    fn shared_tokenize() {
+        let shared = SharedTokenize::new(Tokenize::new("uwu"));
+        let token = shared.generate("326359466171826176").expect("Couldn't generate new token");
+        let snapshot = shared.load();
+
+        let previous = shared.swap(Tokenize::new("owo"));
+        assert!(previous.parse(&*token).is_ok());
+        assert!(snapshot.parse(&*token).is_ok());
+        assert_eq!(shared.parse(&*token).err(), Some(TokenizeError::InvalidSignature));
+
+        shared.clone().store(Tokenize::with_secrets("owo", ["uwu"]));
+        assert!(shared.validate(token, |_id| Some(TestAccount { last_token_reset: 0 })).is_ok());
+    }
+
+    #[test]
+    fn refresh_shared_tokenize() {
+        struct TestKeySource(Arc<Mutex<SecretKey>>);
+
+        impl KeySource for TestKeySource {
+            fn load(&self) -> Result<SecretKey, StoreError> {
+                Ok(self.0.lock().unwrap().clone())
+            }
+        }
+
+        let secret = Arc::new(Mutex::new(SecretKey::from("owo")));
+        let shared = SharedTokenize::new(Tokenize::new("uwu"));
+        let refresher = shared.refresh_from(TestKeySource(secret.clone()), Duration::from_millis(5), Tokenize::new);
+
+        let token = Tokenize::new("owo").generate("326359466171826176").expect("Couldn't generate new token");
+        let refreshed = (0..200).any(|_| {
+            std::thread::sleep(Duration::from_millis(5));
+            shared.parse(&*token).is_ok()
+        });
+        assert!(refreshed);
+
+        refresher.stop();
+        *secret.lock().unwrap() = SecretKey::from("nya");
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(shared.parse(token).is_ok());
+    }
+
+    #[test]
+    fn decode_with_epoch() {
+        let tokenize = Tokenize::new("uwu").set_epoch(1600000000000);
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        let decoded = token.to_string().parse::<Token>().expect("Couldn't decode token");
+
+        assert_ne!(decoded.issued_at_millis(), token.issued_at_millis());
+        assert_eq!(decoded.set_epoch(1600000000000).issued_at_millis(), token.issued_at_millis());
+    }
+
+    #[test]
+    fn split_issuer_and_verifier() {
+        let (issuer, verifier) = Tokenize::new("uwu").set_prefix("bot").split();
+        let token = issuer.generate_with_scopes("326359466171826176", ["read"]).expect("Couldn't generate new token");
+
+        let (token, _) = verifier.validate_token(token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 })).expect("Couldn't validate token");
+        assert!(token.has_scope("read"));
+        assert_eq!(verifier.parse(issuer.generate_single_use("326359466171826176").unwrap()).map(|token| token.is_single_use()), Ok(true));
+
+        let verifier = TokenVerifier::from(Tokenize::new("owo").set_prefix("bot"));
+        assert_eq!(verifier.parse(token.to_string()).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn validate_token_for_device() {
+        let tokenize = Tokenize::new("uwu").set_prefix("bot");
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let token = tokenize.generate_for_device("326359466171826176", "laptop").expect("Couldn't generate new token");
+        assert_eq!(tokenize.generate_for_device("326359466171826176", ""), Err(TokenizeError::Malformed));
+
+        let parsed = tokenize.parse(&*token).expect("Couldn't parse token");
+        assert_eq!(parsed.device_id(), Some("laptop"));
+        assert_eq!(parsed.to_string(), token);
+        assert_eq!(tokenize.parse_ref(&token).and_then(|token| token.to_token()), Ok(parsed));
+
+        assert!(tokenize.validate_for_device(&*token, "laptop", account).is_ok());
+        assert_eq!(tokenize.validate_for_device(&*token, "phone", account).err(), Some(TokenizeError::DeviceMismatch));
+        assert!(tokenize.validate(&*token, account).is_ok());
+
+        let unbound = tokenize.generate("326359466171826176").unwrap();
+        assert_eq!(tokenize.validate_for_device(unbound, "laptop", account).err(), Some(TokenizeError::DeviceMismatch));
+
+        // The device id is signed
+        let tampered = token.replacen(&Encoding::Standard.encode("laptop"), &Encoding::Standard.encode("phone"), 1);
+        assert_eq!(tokenize.validate_for_device(tampered, "phone", account).err(), Some(TokenizeError::InvalidSignature));
+    }
+
+    #[test]
+    fn validate_token_audience() {
+        let public = Tokenize::new("uwu").set_audience("public-api");
+        let admin = Tokenize::builder().secret("uwu").audience("admin-api")
+            .validation_options(ValidationOptions::new().expected_audience("admin-api"))
+            .build()
+            .expect("Invalid configuration");
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let token = admin.generate_with_scopes("326359466171826176", ["write"]).expect("Couldn't generate new token");
+        let parsed = admin.parse(&*token).expect("Couldn't parse token");
+        assert_eq!(parsed.audience(), Some("admin-api"));
+        assert_eq!(parsed.to_string(), token);
+        assert!(admin.validate_ref(&token, |_id| account(String::new())).is_ok());
+        assert!(public.validate(&*token, account).is_ok());
+
+        let token = public.generate("326359466171826176").unwrap();
+        assert_eq!(admin.validate(&*token, account).err(), Some(TokenizeError::AudienceMismatch));
+        assert_eq!(admin.parse_ref(&token).err(), Some(TokenizeError::AudienceMismatch));
+
+        let token = Tokenize::new("uwu").generate("326359466171826176").unwrap();
+        assert_eq!(admin.validate(&*token, account).err(), Some(TokenizeError::AudienceMismatch));
+        assert_eq!(admin.parse_ref(&token).err(), Some(TokenizeError::AudienceMismatch));
+    }
+
+    #[test]
+    fn throttle_forged_tokens() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let policy = Arc::new(TokenBucket::with_clock(2, Duration::from_secs(60), clock.clone()));
+        let metrics = Arc::new(MemoryMetrics::new());
+        let tokenize = Tokenize::with_keyring(Keyring::new("k1", "uwu")).set_failure_policy(policy.clone()).set_metrics_sink(metrics.clone());
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let token = tokenize.generate("326359466171826176").unwrap();
+        let forged = Tokenize::with_keyring(Keyring::new("k1", "owo")).generate("326359466171826176").unwrap();
+        let unknown_key = Tokenize::with_keyring(Keyring::new("k2", "uwu")).generate("326359466171826176").unwrap();
+
+        assert_eq!(tokenize.validate_from("203.0.113.7", &*forged, account).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(tokenize.validate_from("203.0.113.7", "not a token", account).err(), Some(TokenizeError::Malformed));
+        assert!(tokenize.validate_from("203.0.113.7", &*token, account).is_ok());
+        assert_eq!(policy.remaining("203.0.113.7"), 1);
+
+        assert_eq!(tokenize.validate_from("203.0.113.7", &*unknown_key, account).err(), Some(TokenizeError::UnknownKey));
+        assert_eq!(tokenize.validate_from("203.0.113.7", &*token, account).err(), Some(TokenizeError::RateLimited));
+        assert!(tokenize.validate_from("198.51.100.2", &*token, account).is_ok());
+        assert_eq!(metrics.count("rate_limited"), 1);
+
+        // One failure is forgiven every minute
+        clock.advance(60_000);
+        assert!(tokenize.validate_from("203.0.113.7", &*token, account).is_ok());
+        assert_eq!(tokenize.validate_from("203.0.113.7", &*forged, account).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(tokenize.validate_from("203.0.113.7", &*token, account).err(), Some(TokenizeError::RateLimited));
+
+        clock.advance(120_000);
+        assert_eq!(policy.remaining("203.0.113.7"), 2);
+        policy.purge_full();
+        assert!(tokenize.validate_from("203.0.113.7", &*token, account).is_ok());
+    }
+
+    #[test]
+    fn audit_token_lifecycle() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let sink = Arc::new(MemoryAuditSink::new());
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_audit_sink(sink.clone())
+            .set_revocation_store(MemoryRevocationStore::with_clock(clock.clone()));
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        clock.advance(1000);
+        tokenize.validate(token.to_string(), account).expect("Couldn't validate token");
+        tokenize.revoke(token.to_string()).expect("Couldn't revoke token");
+        assert_eq!(tokenize.validate_ref(&token.to_string(), |_id| account(String::new())).err(), Some(TokenizeError::Revoked));
+        assert!(tokenize.validate("not a token", account).is_err());
+
+        let fingerprint = token.revocation_key();
+        let records = sink.records();
+        assert_eq!(records.iter().map(|record| record.event.name()).collect::<Vec<&str>>(), ["token_issued", "token_validated", "token_revoked", "token_rejected", "token_rejected"]);
+        assert_eq!(records[0].event, AuditEvent::TokenIssued { account_id: AccountId::from("326359466171826176") });
+        assert_eq!((records[0].timestamp, records[1].timestamp), (1641635607000, 1641635608000));
+        assert!(records[..4].iter().all(|record| record.fingerprint == fingerprint));
+        assert_eq!(records[3].event, AuditEvent::TokenRejected { reason: TokenizeError::Revoked });
+        assert_eq!(records[4].event, AuditEvent::TokenRejected { reason: TokenizeError::Malformed });
+    }
+
+    #[test]
+    fn token_fingerprint() {
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        let raw = token.to_string();
+        let fingerprint = token.fingerprint();
+
+        assert_eq!(fingerprint.len(), 16);
+        assert!(fingerprint.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert!(token.revocation_key().starts_with(&fingerprint));
+        assert_eq!(raw.parse::<Token>().unwrap().fingerprint(), fingerprint);
+        assert_eq!(tokenize.parse_ref(&raw).unwrap().fingerprint(), fingerprint);
+        assert!(!raw.contains(&fingerprint));
+
+        let other = tokenize.generate_token("326359466171826177").expect("Couldn't generate new token");
+        assert_ne!(other.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn redact_debug_output() {
+        let tokenize = Tokenize::new("uwu").set_prefix("mfa");
+        let debug = format!("{:?}", tokenize);
+        assert!(debug.starts_with(r#"Tokenize { secret: [REDACTED], key_id: None, prefix: Some("mfa"), "#), "{}", debug);
+        assert!(!debug.contains("uwu"));
+
+        let keyring = Keyring::new("k1", "owo");
+        assert!(!format!("{:?}", Tokenize::with_keyring(keyring)).contains("owo"));
+
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        let raw = token.to_string();
+        let signature = token.signature().to_string();
+        assert!(format!("{:?}", token).contains("signature: [REDACTED]"));
+        assert!(!format!("{:?}", token).contains(&signature));
+        assert!(!format!("{:?}", tokenize.parse_ref(&raw).unwrap()).contains(&signature));
+
+        let redacted = token.redacted().to_string();
+        assert_eq!(redacted, raw.replace(&signature, "[REDACTED]"));
+        assert!(redacted.starts_with("mfa."));
+    }
+
+    #[test]
+    fn validate_token_with_account_secret() {
+        struct SaltedAccount {
+            salt: Vec<u8>
+        }
+
+        impl Account for SaltedAccount {
+            fn last_token_reset(&self) -> u64 {
+                0
+            }
+        }
+
+        impl AccountSecret for SaltedAccount {
+            fn secret_salt(&self) -> &[u8] {
+                &self.salt
+            }
+        }
+
+        let tokenize = Tokenize::new("uwu");
+        let salts = Mutex::new(HashMap::from([("1".to_string(), b"a".to_vec()), ("2".to_string(), b"b".to_vec())]));
+        let fetcher = |id: String| salts.lock().unwrap().get(&id).map(|salt| SaltedAccount { salt: salt.clone() });
+
+        let first = tokenize.generate_salted("1", &fetcher("1".to_string()).unwrap()).expect("Couldn't generate new token");
+        let second = tokenize.generate_salted("2", &fetcher("2".to_string()).unwrap()).expect("Couldn't generate new token");
+        assert!(tokenize.validate_salted(first.clone(), fetcher).is_ok());
+        assert_eq!(tokenize.validate(first.clone(), |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(tokenize.validate_salted(tokenize.generate("1").unwrap(), fetcher).err(), Some(TokenizeError::InvalidSignature));
+
+        // Rotating the salt of one account leaves the others alone
+        salts.lock().unwrap().insert("1".to_string(), b"c".to_vec());
+        assert_eq!(tokenize.validate_salted(first, fetcher).err(), Some(TokenizeError::InvalidSignature));
+        assert!(tokenize.validate_salted(second, fetcher).is_ok());
+        assert_eq!(tokenize.validate_salted(tokenize.generate("3").unwrap(), fetcher).err(), Some(TokenizeError::AccountNotFound));
+
+        // Tokens which can't be valid whatever their signature don't trigger lookups
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let token = tokenize.generate_salted("2", &fetcher("2".to_string()).unwrap()).expect("Couldn't generate new token");
+        let lookups = Mutex::new(0);
+        let counting = |id: String| {
+            *lookups.lock().unwrap() += 1;
+            fetcher(id)
+        };
+        assert!(tokenize.validate_salted(token.clone(), counting).is_ok());
+        assert_eq!(tokenize.validate_salted("uwu", counting).err(), Some(TokenizeError::Malformed));
+        clock.advance(61_000);
+        assert_eq!(tokenize.validate_salted(token, counting).err(), Some(TokenizeError::Expired));
+        assert_eq!(*lookups.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn validate_token_within_reset_grace() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone());
+        let graceful = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_validation_options(ValidationOptions::new().reset_grace(Duration::from_millis(500)));
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        // The reset landed 400ms after the token was issued
+        let account = |_id| Some(TestAccount { last_token_reset: 1641635607400 });
+        assert_eq!(tokenize.validate(token.clone(), account).err(), Some(TokenizeError::Invalidated));
+        assert!(graceful.validate(token.clone(), account).is_ok());
+
+        let account = |_id| Some(TestAccount { last_token_reset: 1641635607600 });
+        assert_eq!(graceful.validate(token, account).err(), Some(TokenizeError::Invalidated));
+    }
+
+    #[test]
+    fn generate_token_with_millisecond_precision() {
+        let clock = Arc::new(MockClock::new(1641635607123));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone());
+        let precise = Tokenize::new("uwu").set_clock(clock.clone()).set_precision(TimestampPrecision::Milliseconds);
+
+        let first = precise.generate_token("326359466171826176").expect("Couldn't generate new token");
+        clock.advance(5);
+        let second = precise.generate_token("326359466171826176").expect("Couldn't generate new token");
+        assert_ne!(first.to_string(), second.to_string());
+        assert_eq!(first.generated_at(), second.generated_at());
+        assert_eq!((first.issued_at_millis(), second.issued_at_millis()), (1641635607123, 1641635607128));
+        assert_eq!(first.precision(), TimestampPrecision::Milliseconds);
+        assert_eq!(first.to_string().parse::<Token>(), Ok(first.clone()));
+
+        // Both precisions are accepted, resets are compared to the millisecond
+        let raw = first.to_string();
+        let account = |_id| Some(TestAccount { last_token_reset: 1641635607100 });
+        assert_eq!(tokenize.validate_token(raw.clone(), account).map(|(token, _)| token.issued_at_millis()), Ok(1641635607123));
+        assert_eq!(tokenize.validate_ref(&raw, |_id| account(String::new())).map(|(token, _)| token.issued_at_millis()), Ok(1641635607123));
+        assert_eq!(tokenize.validate(tokenize.generate("326359466171826176").unwrap(), account).err(), Some(TokenizeError::Invalidated));
+    }
+
+    #[test]
+    fn generate_unique_tokens() {
+        let tokenize = Tokenize::new("uwu").set_clock(FixedClock(1641635607000));
+        let unique = Tokenize::new("uwu")
+            .set_clock(FixedClock(1641635607000))
+            .unique_tokens()
+            .set_revocation_store(MemoryRevocationStore::new());
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        assert_eq!(tokenize.generate("326359466171826176"), tokenize.generate("326359466171826176"));
+        let first = unique.generate_token("326359466171826176").expect("Couldn't generate new token");
+        let second = unique.generate_token("326359466171826176").expect("Couldn't generate new token");
+        assert_ne!(first.to_string(), second.to_string());
+        assert_eq!(first.nonce().map(str::len), Some(16));
+        assert!(tokenize.validate(first.to_string(), account).is_ok());
+
+        unique.revoke(first.to_string()).expect("Couldn't revoke token");
+        assert_eq!(unique.validate(first.to_string(), account).err(), Some(TokenizeError::Revoked));
+        assert!(unique.validate(second.to_string(), account).is_ok());
+    }
+
+    #[test]
+    fn generate_deterministic_tokens() {
+        let tokenize = Tokenize::new("uwu").set_precision(TimestampPrecision::Milliseconds);
+
+        let token = tokenize.generate_token_at("326359466171826176", 1641635607123).expect("Couldn't generate new token");
+        assert_eq!(token.issued_at_millis(), 1641635607123);
+        assert_eq!(tokenize.generate_at("326359466171826176", 1641635607123), Ok(token.to_string()));
+        assert_eq!(tokenize.generate_at("326359466171826176", TOKENIZE_EPOCH - 1), Err(TokenizeError::Malformed));
+
+        let token = tokenize.generate_with_nonce("326359466171826176", "reissued").expect("Couldn't generate new token");
+        assert_eq!(tokenize.parse(token).unwrap().nonce(), Some("reissued"));
+        assert_eq!(tokenize.generate_with_nonce("326359466171826176", ""), Err(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn validate_verbose_report() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu")
+            .set_clock(clock.clone())
+            .set_prefix("tk")
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let report = tokenize.validate_verbose(token.to_string(), account);
+        assert!(report.is_valid());
+        assert_eq!(report.token(), Some(&token));
+        assert_eq!(report.outcome(ValidationCheck::Ttl), &CheckOutcome::Passed);
+        assert_eq!(report.outcome(ValidationCheck::Audience), &CheckOutcome::Skipped);
+        assert_eq!(report.outcome(ValidationCheck::Revocation), &CheckOutcome::Skipped);
+
+        let forged = format!("{}A", token);
+        let report = tokenize.validate_verbose(forged, account);
+        assert_eq!(report.failed_check(), Some(ValidationCheck::Signature));
+        assert_eq!(report.error(), Some(&TokenizeError::InvalidSignature));
+        assert_eq!(report.outcome(ValidationCheck::Prefix), &CheckOutcome::Passed);
+        assert_eq!(report.outcome(ValidationCheck::Account), &CheckOutcome::Skipped);
+        assert_eq!(report.token(), None);
+
+        let report = tokenize.validate_verbose(token.to_string(), |_id| Some(TestAccount { last_token_reset: 1641635608000 }));
+        assert_eq!(report.failed_check(), Some(ValidationCheck::Reset));
+        assert_eq!(report.outcome(ValidationCheck::Account), &CheckOutcome::Passed);
+
+        clock.advance(61 * 1000);
+        let report = tokenize.validate_verbose(token.to_string(), account);
+        assert_eq!(report.to_string(), "format: passed\nprefix: passed\nsignature: passed\naudience: skipped\n\
+            ttl: failed (Token has expired)\nrevocation: skipped\naccount: skipped\nreset: skipped\n");
+
+        let report = tokenize.validate_verbose("thisisinvalid", account);
+        assert_eq!(report.failed_check(), Some(ValidationCheck::Format));
+        assert!(report.checks()[1..].iter().all(|(_, outcome)| *outcome == CheckOutcome::Skipped));
+    }
+
+    #[test]
+    fn decode_unverified_token() {
+        let tokenize = Tokenize::new("uwu").set_prefix("tk").set_epoch(0);
+        let token = tokenize.generate_token("326359466171826176").expect("Couldn't generate new token");
+
+        let unverified = Token::decode_unverified(token.to_string()).expect("Couldn't decode token").set_epoch(0);
+        assert_eq!(unverified.account_id(), "326359466171826176");
+        assert_eq!(unverified.prefix(), Some("tk"));
+        assert_eq!(unverified.issued_at_millis(), token.issued_at_millis());
+        assert!(!format!("{:?}", unverified).contains(token.signature()));
+        assert_eq!(unverified.verify(&tokenize), Ok(token.clone()));
+
+        let forged = Token::decode_unverified(format!("{}A", token)).expect("Couldn't decode token");
+        assert_eq!(forged.account_id(), "326359466171826176");
+        assert_eq!(forged.verify(&tokenize), Err(TokenizeError::InvalidSignature));
+        assert_eq!(Token::decode_unverified("thisisinvalid"), Err(TokenizeError::Malformed));
+    }
+
+    #[test]
+    #[cfg(feature = "derive")]
+    fn derive_account() {
+        #[derive(Account, MutableAccount)]
+        struct User {
+            #[allow(dead_code)]
+            name: String,
+            #[token_reset]
+            reset: u64
+        }
+
+        #[derive(Account)]
+        struct Session(#[allow(dead_code)] String, #[token_reset] u32);
+
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let mut user = User { name: "cynthia".to_owned(), reset: 0 };
+
+        assert_eq!(Session("abc".to_owned(), 42).last_token_reset(), 42);
+        assert!(tokenize.validate(&*token, |_id| Some(User { name: "cynthia".to_owned(), reset: 0 })).is_ok());
+        tokenize.invalidate_all(&mut user);
+        assert!(user.last_token_reset() > 0);
+        let mut user = Some(user);
+        assert_eq!(tokenize.validate(token, |_id| user.take()).err(), Some(TokenizeError::Invalidated));
+    }
+
+    #[test]
+    fn validate_account_wrappers() {
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let account = Arc::new(TestAccount { last_token_reset: 0 });
+
+        assert!(tokenize.validate(&*token, |_id| Some(account.clone())).is_ok());
+        assert!(tokenize.validate(&*token, |_id| Some(&*account)).is_ok());
+        assert!(tokenize.validate(&*token, |_id| Some(Box::new(TestAccount { last_token_reset: 0 }) as Box<dyn Account>)).is_ok());
+
+        let mut row = ("326359466171826176".to_owned(), 0);
+        assert!(tokenize.validate(&*token, |_id| Some(row.clone())).is_ok());
+        tokenize.invalidate_all(&mut row);
+        assert_eq!(tokenize.validate(&*token, |_id| Some(row.clone())).err(), Some(TokenizeError::Invalidated));
+    }
+
+    #[test]
+    fn try_validate_reports_store_errors() {
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        assert!(tokenize.try_validate(&*token, |_id| Ok::<_, StoreError>(Some(TestAccount { last_token_reset: 0 }))).is_ok());
+        assert_eq!(tokenize.try_validate(&*token, |_id| Ok::<Option<TestAccount>, StoreError>(None)).err(), Some(TokenizeError::AccountNotFound));
+        assert_eq!(
+            tokenize.try_validate(&*token, |_id| Err::<Option<TestAccount>, _>("database is down")).err(),
+            Some(TokenizeError::Store(StoreError::new("database is down")))
+        );
+
+        let result = futures::executor::block_on(tokenize.try_validate_async(&*token, |_id| async { Err::<Option<TestAccount>, _>("timed out") }));
+        assert_eq!(result.err().map(|err| err.kind()), Some("store"));
+    }
+
+    #[test]
+    fn share_static_instance_across_threads() {
+        static CONFIG: StaticConfig = StaticConfig::from_secret(b"uwu").prefix("api").max_age(Duration::from_secs(3600));
+        static TOKENIZE: std::sync::LazyLock<Tokenize> = std::sync::LazyLock::new(|| CONFIG.build().expect("Invalid configuration"));
+
+        let token = TOKENIZE.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(token.starts_with("api."));
+        assert!(!format!("{:?}", CONFIG).contains("uwu"));
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        TOKENIZE.validate(&*token, |_id| Some(TestAccount { last_token_reset: 0 })).expect("Couldn't validate token");
+                    }
+                });
+            }
+        });
+
+        assert_eq!(StaticConfig::from_env("TOKENIZE_TEST_UNSET_SECRET").build().err(), Some(ConfigError::MissingSecret));
+    }
+
+    #[test]
+    fn accumulate_token_chunks() {
+        let tokenize = Tokenize::new("uwu").set_validation_options(ValidationOptions::new().max_length(128));
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let mut accumulator = tokenize.accumulator();
+        for chunk in token.as_bytes().chunks(7) {
+            accumulator.push(chunk).expect("Invalid chunk");
+        }
+        assert_eq!(accumulator.len(), token.len());
+        assert!(accumulator.validate(&tokenize, account).is_ok());
+
+        let mut accumulator = tokenize.accumulator();
+        accumulator.push(&[b'A'; 100]).expect("Invalid chunk");
+        assert_eq!(accumulator.push(&[b'A'; 29]), Err(TokenizeError::TokenTooLong));
+        assert!(accumulator.is_empty());
+        assert_eq!(accumulator.push(b"A"), Err(TokenizeError::TokenTooLong));
+        assert_eq!(accumulator.finish(), Err(TokenizeError::TokenTooLong));
+
+        let mut accumulator = TokenAccumulator::new(1024);
+        assert_eq!(accumulator.push(b"abc def"), Err(TokenizeError::Malformed));
+        assert_eq!(accumulator.validate(&tokenize, account).err(), Some(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn mfa_step_up() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone());
+        let mfa = MfaManager::new(Tokenize::new("uwu").set_clock(clock.clone()), MemoryChallengeStore::with_clock(clock.clone()));
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let (challenge, token) = mfa.begin("326359466171826176").expect("Couldn't begin challenge");
+        assert!(token.starts_with("mfa."));
+        assert_eq!(tokenize.validate(&*token, account).err(), Some(TokenizeError::Malformed));
+        assert_eq!(mfa.tokens().validate(tokenize.generate("326359466171826176").unwrap(), account).err(), Some(TokenizeError::Malformed));
+
+        // Step-up tokens without a pending challenge are rejected
+        let forged = mfa.tokens().generate("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(mfa.complete(forged, account).err(), Some(TokenizeError::Revoked));
+
+        assert_eq!(mfa.pending(&*token, account).map(|(challenge, _)| challenge), Ok(challenge.clone()));
+        assert_eq!(mfa.complete(&*token, account).map(|(challenge, _)| challenge), Ok(challenge));
+        assert_eq!(mfa.complete(&*token, account).err(), Some(TokenizeError::Revoked));
+
+        let (_, token) = mfa.begin("326359466171826176").expect("Couldn't begin challenge");
+        assert_eq!(mfa.cancel(&*token), Ok(true));
+        assert_eq!(mfa.pending(&*token, account).err(), Some(TokenizeError::Revoked));
+
+        let (_, token) = mfa.begin("326359466171826176").expect("Couldn't begin challenge");
+        clock.advance(5 * 60 * 1000 + 1);
+        assert_eq!(mfa.complete(token, account).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn validate_purpose_tokens() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone());
+        let tokens = PurposeToken::new(Tokenize::new("uwu").set_clock(clock.clone()));
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+
+        let token = tokens.generate(PASSWORD_RESET, "326359466171826176", Duration::from_secs(60)).expect("Couldn't generate new token");
+        assert_eq!(tokens.tokens().parse(&*token).expect_err("Parsed purpose token").kind(), "invalid_signature");
+        assert_eq!(tokens.tokens().parse_ref(&token).err(), Some(TokenizeError::InvalidSignature));
+
+        // Both parsers accept the lifetime of tokens, which every validation enforces
+        let mut unscoped = tokenize.unsigned_token("326359466171826176".into());
+        unscoped.ttl_millis = Some(60 * 1000);
+        let unscoped = tokenize.sign(unscoped).to_string();
+        let parsed = tokenize.parse(&*unscoped).expect("Couldn't parse token");
+        assert_eq!(parsed.expires_at_millis(), Some(1641635667000));
+        assert_eq!(tokenize.parse_ref(&unscoped).and_then(|token| token.to_token()), Ok(parsed));
+        assert_eq!(tokenize.parse_ref(&unscoped).map(|token| token.expires_at_millis()), Ok(Some(1641635667000)));
+        assert!(tokenize.validate(&*unscoped, account).is_ok());
+        assert_eq!(tokens.validate_for(EMAIL_VERIFICATION, &*token, account).err(), Some(TokenizeError::InvalidSignature));
+        assert!(tokens.validate_for(PASSWORD_RESET, &*token, account).is_ok());
+
+        // Regular tokens can't be passed off as purpose tokens either
+        let regular = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(tokens.validate_for(PASSWORD_RESET, regular, account).err(), Some(TokenizeError::InvalidSignature));
+
+        let reset = |_id| Some(TestAccount { last_token_reset: 1641635608000 });
+        assert_eq!(tokens.validate_for(PASSWORD_RESET, &*token, reset).err(), Some(TokenizeError::Invalidated));
+
+        clock.advance(60 * 1000 + 1);
+        assert_eq!(tokens.validate_for(PASSWORD_RESET, &*token, account).err(), Some(TokenizeError::Expired));
+        assert_eq!(tokenize.validate(&*unscoped, account).err(), Some(TokenizeError::Expired));
+        assert_eq!(tokenize.validate_ref(&unscoped, |_id| Some(TestAccount { last_token_reset: 0 })).err(), Some(TokenizeError::Expired));
+
+        assert_eq!(tokens.generate("", "326359466171826176", Duration::from_secs(60)).err(), Some(TokenizeError::Malformed));
+        assert_eq!(tokens.generate(PASSWORD_RESET, "326359466171826176", Duration::ZERO).err(), Some(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn manage_api_keys() {
+        let tokenize = Tokenize::new("uwu").set_revocation_store(MemoryRevocationStore::new());
+        let store = Arc::new(MemoryApiKeyStore::new());
+        let keys = ApiKeyManager::new(tokenize, store.clone()).expect("Invalid configuration");
+        let account = |_id| Some(TestAccount { last_token_reset: i64::MAX as u64 });
+        assert_eq!(ApiKeyManager::new(Tokenize::new("uwu"), MemoryApiKeyStore::new()).err(), Some(ConfigError::MissingRevocationStore));
+
+        // Keys survive the last token reset of their account
+        let (ci, token) = keys.issue("326359466171826176", Some("ci")).expect("Couldn't issue key");
+        assert!(token.starts_with("key."));
+        assert!(keys.validate(&*token, account).is_ok());
+        assert_eq!(keys.validate(&*token, |_id| None::<TestAccount>).err(), Some(TokenizeError::AccountNotFound));
+
+        let (deploy, deploy_token) = keys.issue("326359466171826176", Some("deploy")).expect("Couldn't issue key");
+        let listed = keys.keys("326359466171826176").expect("Couldn't list keys");
+        assert!(listed.len() == 2 && listed.contains(&ci) && listed.contains(&deploy));
+
+        let (rotated, rotated_token) = keys.rotate(&ci.id).expect("Couldn't rotate key");
+        assert_eq!(rotated.name.as_deref(), Some("ci"));
+        assert_eq!(keys.validate(&*token, account).err(), Some(TokenizeError::Revoked));
+        assert!(keys.validate(&*rotated_token, account).is_ok());
+        assert_eq!(keys.rotate(&ci.id).err(), Some(TokenizeError::Revoked));
+
+        assert_eq!(keys.revoke(&rotated.id), Ok(true));
+        assert_eq!(keys.validate(rotated_token, account).err(), Some(TokenizeError::Revoked));
+        assert_eq!(keys.keys("326359466171826176"), Ok(vec![deploy.clone()]));
+
+        // Keys deleted from the store without being revoked are rejected too
+        assert!(keys.validate(&*deploy_token, account).is_ok());
+        assert!(store.remove(&deploy.id).expect("Couldn't delete key").is_some());
+        assert_eq!(keys.validate(deploy_token, account).err(), Some(TokenizeError::Revoked));
+
+        // Regular tokens don't carry a key id
+        let regular = keys.tokens().generate("326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(keys.validate(regular, account).err(), Some(TokenizeError::Malformed));
+    }
+
+    #[test]
+    fn validate_token_kinds() {
+        let store = Arc::new(MemoryRevocationStore::new());
+        let tokenize = Tokenize::new("uwu").set_token_kinds().set_revocation_store(store.clone());
+        let reset = |_id| Some(TestAccount { last_token_reset: i64::MAX as u64 });
+
+        let user = tokenize.generate_kind(TokenKind::User, "326359466171826176").expect("Couldn't generate new token");
+        let bot = tokenize.generate_kind(TokenKind::Bot, "326359466171826176").expect("Couldn't generate new token");
+        assert!(user.starts_with("user.") && bot.starts_with("bot."));
+        assert_eq!(tokenize.parse(&*bot).expect("Couldn't parse token").kind(), Some(TokenKind::Bot));
+
+        // Only user tokens are invalidated by resets
+        assert_eq!(tokenize.validate_kind(&*user, reset).err(), Some(TokenizeError::Invalidated));
+        assert_eq!(tokenize.validate_kind(&*bot, reset).map(|(kind, _)| kind), Ok(TokenKind::Bot));
+        assert_eq!(tokenize.validate_kind(&*bot, |_id| None::<TestAccount>).err(), Some(TokenizeError::AccountNotFound));
+
+        tokenize.revoke(&*bot).expect("Couldn't revoke token");
+        assert_eq!(tokenize.validate_kind(&*bot, reset).err(), Some(TokenizeError::Revoked));
+
+        // Bot tokens can't be revoked without a revocation store
+        let tokenize = Tokenize::new("uwu").set_token_kinds();
+        let bot = tokenize.generate_kind(TokenKind::Bot, "326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(tokenize.validate_kind(bot, reset).err().map(|err| err.kind()), Some("store"));
+        assert_eq!(Tokenize::new("uwu").generate_kind(TokenKind::Bot, "326359466171826176").err(), Some(TokenizeError::PrefixMismatch));
+
+        let other = Tokenize::new("uwu").set_prefixes(["user", "mfa"]);
+        let token = other.generate_with_prefix("mfa", "326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(other.validate_kind(token, reset).err(), Some(TokenizeError::PrefixMismatch));
+    }
+
+    #[test]
+    fn validate_leniently() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let options = ValidationOptions::new().max_age(Duration::from_secs(60)).expiry_grace(Duration::from_secs(60)).reset_grace(Duration::from_secs(1));
+        let tokenize = Tokenize::new("uwu").set_clock(clock.clone()).set_validation_options(options);
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+
+        let validated = tokenize.validate_lenient(&*token, account).expect("Couldn't validate token");
+        assert!(validated.is_clean());
+
+        let reset = |_id| Some(TestAccount { last_token_reset: 1641635607500 });
+        assert_eq!(tokenize.validate_lenient(&*token, reset).map(|validated| validated.warnings), Ok(vec![ValidationWarning::WithinResetGrace]));
+
+        clock.advance(61 * 1000);
+        assert_eq!(tokenize.validate(&*token, account).err(), Some(TokenizeError::Expired));
+        assert_eq!(tokenize.validate_lenient(&*token, account).map(|validated| validated.warnings), Ok(vec![ValidationWarning::Expired(Duration::from_secs(1))]));
+
+        clock.advance(60 * 1000);
+        assert_eq!(tokenize.validate_lenient(&*token, account).err(), Some(TokenizeError::Expired));
+
+        let url_safe = Tokenize::new("uwu").set_encoding(Encoding::UrlSafe).generate_at("326359466171826176", 1641635607000).expect("Couldn't generate new token");
+        let migrating = Tokenize::new("uwu").accept_any_encoding();
+        let validated = migrating.validate_lenient(url_safe, account).expect("Couldn't validate token");
+        assert_eq!(validated.warnings, vec![ValidationWarning::DeprecatedEncoding(Encoding::UrlSafe)]);
+    }
+
+    #[test]
+    fn validate_tokens_with_policy() {
+        let clock = Arc::new(MockClock::new(1641635607000));
+        let in_region = |_token: &Token, _account: &dyn Account, context: &ValidationContext| match context.get("region") {
+            Some("eu") => Ok(()),
+            _ => Err(TokenizeError::PolicyViolation("outside of the region".to_string()))
+        };
+        let policy = All::new()
+            .and(RequirePrefix::new("user"))
+            .and(MaxAge::with_clock(Duration::from_secs(60), clock.clone()))
+            .and(Any::new().or(RequireScope::new("admin")).or(Not::new(in_region)));
+        let tokenize = Tokenize::new("uwu").set_prefixes(["user", "bot"]).set_clock(clock.clone()).set_policy(policy);
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let eu = ValidationContext::new().with("region", "eu");
+        let us = ValidationContext::new().with("region", "us");
+
+        let token = tokenize.generate_with_scopes("326359466171826176", ["read"]).expect("Couldn't generate new token");
+        assert!(tokenize.validate_with_context(&*token, &us, account).is_ok());
+        assert_eq!(tokenize.validate_with_context(&*token, &eu, account).err(), Some(TokenizeError::PolicyViolation("a negated policy authorizes the token".to_string())));
+
+        let admin = tokenize.generate_with_scopes("326359466171826176", ["admin"]).expect("Couldn't generate new token");
+        assert!(tokenize.validate_with_context(&*admin, &eu, account).is_ok());
+
+        let bot = tokenize.generate_with_prefix("bot", "326359466171826176").expect("Couldn't generate new token");
+        assert_eq!(tokenize.validate_with_context(bot, &us, account).err(), Some(TokenizeError::PrefixMismatch));
+
+        clock.advance(61 * 1000);
+        assert!(tokenize.validate(&*admin, account).is_ok());
+        assert_eq!(tokenize.validate_with_context(admin, &eu, account).err(), Some(TokenizeError::Expired));
+        assert_eq!(Any::new().evaluate(&tokenize.parse(token).unwrap(), &TestAccount { last_token_reset: 0 }, &eu).map_err(|err| err.kind()), Err("policy_violation"));
+    }
+
+    #[test]
+    fn pass_context_through_validation() {
+        let sink = Arc::new(MemoryAuditSink::new());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let routes = seen.clone();
+        let record_route = move |_token: &Token, _account: &dyn Account, context: &ValidationContext| {
+            routes.lock().unwrap().push(context.route().map(str::to_string));
+            Ok(())
+        };
+        let tokenize = Tokenize::new("uwu")
+            .set_audit_sink(sink.clone())
+            .set_policy(record_route)
+            .set_failure_policy(TokenBucket::new(1, Duration::from_secs(60)));
+        let account = |_id| Some(TestAccount { last_token_reset: 0 });
+        let context = ValidationContext::new()
+            .with(ValidationContext::PEER, "203.0.113.7")
+            .with(ValidationContext::USER_AGENT, "curl/8.0")
+            .with(ValidationContext::ROUTE, "/users/@me");
+
+        let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+        assert!(tokenize.validate_with_context(&*token, &context, account).is_ok());
+        assert_eq!(*seen.lock().unwrap(), vec![Some("/users/@me".to_string())]);
+
+        let records = sink.records();
+        assert_eq!(records[1].event, AuditEvent::TokenValidated);
+        assert_eq!(records[1].context.user_agent(), Some("curl/8.0"));
+
+        // The peer is throttled like with validate_from
+        let forged = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid";
+        assert_eq!(tokenize.validate_with_context(forged, &context, account).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(tokenize.validate_with_context(&*token, &context, account).err(), Some(TokenizeError::RateLimited));
+        assert!(tokenize.validate_with_context(&*token, &ValidationContext::new(), account).is_ok());
+        assert_eq!(sink.records().last().map(|record| record.context.is_empty()), Some(true));
     }
 }
@@ -0,0 +1,140 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Metrics about token validations.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use crate::TokenizeError;
+
+/// A sink receiving the outcome and latency of every validation, such as a Prometheus exporter.
+/// 
+/// Sinks are set through [`Tokenize::set_metrics_sink`](crate::Tokenize::set_metrics_sink). They're
+/// called on the validation path, so they should only update counters.
+pub trait MetricsSink: Send + Sync {
+    /// Records a validation which ended with `outcome` after `elapsed`.
+    fn record_validation(&self, outcome: Result<(), &TokenizeError>, elapsed: Duration);
+}
+
+impl<M: MetricsSink + ?Sized> MetricsSink for Arc<M> {
+    fn record_validation(&self, outcome: Result<(), &TokenizeError>, elapsed: Duration) {
+        (**self).record_validation(outcome, elapsed)
+    }
+}
+
+/// Upper bounds of the latency histogram buckets of [`MemoryMetrics`], in microseconds.
+const LATENCY_BUCKETS: [u64; 9] = [10, 25, 50, 100, 250, 500, 1_000, 10_000, 100_000];
+
+/// A [`MetricsSink`] keeping counters in memory, to be exported by the application.
+/// 
+/// Validations are counted per outcome: `valid`, or the [`TokenizeError::kind`] of the error.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::sync::Arc;
+/// use tokenize::Tokenize;
+/// use tokenize::metrics::MemoryMetrics;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let metrics = Arc::new(MemoryMetrics::new());
+/// let tokenize = Tokenize::new("uwu").set_metrics_sink(metrics.clone());
+/// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+/// 
+/// let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+/// tokenize.validate(&*token, account).expect("Couldn't validate token");
+/// assert!(tokenize.validate(format!("{}A", token), account).is_err());
+/// 
+/// assert_eq!(metrics.count("valid"), 1);
+/// assert_eq!(metrics.signature_failures(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryMetrics {
+    state: Mutex<MetricsState>
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    outcomes: HashMap<&'static str, u64>,
+    /// Validations per latency bucket, the last one counting validations slower than every bound.
+    latencies: [u64; LATENCY_BUCKETS.len() + 1]
+}
+
+impl MemoryMetrics {
+    pub fn new() -> MemoryMetrics {
+        MemoryMetrics::default()
+    }
+
+    /// The number of validations which ended with `outcome`.
+    pub fn count(&self, outcome: &str) -> u64 {
+        self.lock().outcomes.get(outcome).copied().unwrap_or(0)
+    }
+
+    /// The number of validations, per outcome.
+    pub fn counts(&self) -> HashMap<&'static str, u64> {
+        self.lock().outcomes.clone()
+    }
+
+    /// The number of validations rejected because of their signature.
+    pub fn signature_failures(&self) -> u64 {
+        self.count("invalid_signature")
+    }
+
+    /// The total number of validations.
+    pub fn validations(&self) -> u64 {
+        self.lock().outcomes.values().sum()
+    }
+
+    /// The latency histogram, as the upper bound of each bucket along with its number of validations.
+    /// The last bucket is unbounded, and has an upper bound of [`Duration::MAX`].
+    pub fn latency_histogram(&self) -> Vec<(Duration, u64)> {
+        let state = self.lock();
+        LATENCY_BUCKETS.iter()
+            .map(|&bound| Duration::from_micros(bound))
+            .chain(std::iter::once(Duration::MAX))
+            .zip(state.latencies)
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MetricsState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl MetricsSink for MemoryMetrics {
+    fn record_validation(&self, outcome: Result<(), &TokenizeError>, elapsed: Duration) {
+        let outcome = outcome.map_or_else(TokenizeError::kind, |_| "valid");
+        let bucket = LATENCY_BUCKETS.iter()
+            .position(|&bound| elapsed <= Duration::from_micros(bound))
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        let mut state = self.lock();
+        *state.outcomes.entry(outcome).or_insert(0) += 1;
+        state.latencies[bucket] += 1;
+    }
+}
@@ -0,0 +1,256 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Step-up tokens for two-step logins, issued once the first factor is verified and exchanged for the
+//! account once the second one is.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use crate::clock::{Clock, SystemClock};
+use crate::{token, Account, AccountId, Result, StoreError, Token, Tokenize, TokenizeError};
+
+/// Prefix of step-up tokens.
+pub const MFA_PREFIX: &str = "mfa";
+
+/// How long a challenge stays pending, unless the instance has a maximum age of its own.
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Issues step-up tokens tied to a pending second-factor challenge.
+/// 
+/// Step-up tokens are prefixed with `mfa.` and short-lived. On top of their signature, they're only
+/// accepted while their challenge is pending in the [`ChallengeStore`]: completing or cancelling a
+/// challenge rejects its token with [`TokenizeError::Revoked`], so each one is exchanged at most once.
+/// Verifying the second factor itself, such as a TOTP code, is up to the application.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Tokenize, TokenizeError};
+/// use tokenize::mfa::{MemoryChallengeStore, MfaManager};
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let mfa = MfaManager::new(Tokenize::new("uwu"), MemoryChallengeStore::new());
+/// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+/// 
+/// // The password was checked, the client is asked for its second factor
+/// let (_, token) = mfa.begin("326359466171826176").expect("Couldn't begin challenge");
+/// assert!(token.starts_with("mfa."));
+/// 
+/// // The client answered with the token and a code, checked against the account before completing
+/// let (challenge, _account) = mfa.pending(&*token, account).expect("Invalid challenge");
+/// assert_eq!(challenge.account_id, "326359466171826176");
+/// mfa.complete(&*token, account).expect("Couldn't complete challenge");
+/// 
+/// assert_eq!(mfa.complete(token, account).err(), Some(TokenizeError::Revoked));
+/// ```
+pub struct MfaManager {
+    tokenize: Tokenize,
+    challenges: Box<dyn ChallengeStore>
+}
+
+impl MfaManager {
+    /// Creates a manager signing step-up tokens with `tokenize`, recording pending challenges in `store`.
+    /// 
+    /// The prefix of `tokenize` is replaced with [`MFA_PREFIX`], and its maximum age defaults to
+    /// [`DEFAULT_CHALLENGE_TTL`].
+    pub fn new<S: ChallengeStore + 'static>(tokenize: Tokenize, store: S) -> MfaManager {
+        let mut tokenize = tokenize.set_prefix(MFA_PREFIX);
+        tokenize.options.max_age.get_or_insert(DEFAULT_CHALLENGE_TTL);
+
+        MfaManager { tokenize, challenges: Box::new(store) }
+    }
+
+    /// The instance step-up tokens are issued with.
+    pub fn tokens(&self) -> &Tokenize {
+        &self.tokenize
+    }
+
+    /// Opens a challenge for `account_id`, returning it along with its step-up token.
+    pub fn begin<S: Into<AccountId>>(&self, account_id: S) -> Result<(Challenge, String)> {
+        let account_id = account_id.into();
+        let challenge = Challenge {
+            id: token::nonce(),
+            account_id: account_id.as_str().ok_or(TokenizeError::Decode)?.to_string(),
+            created_at: self.tokenize.clock.now_millis()
+        };
+
+        self.challenges.create(&challenge, self.ttl())?;
+        let mut token = self.tokenize.unsigned_token(account_id);
+        token.nonce = Some(challenge.id.clone());
+
+        Ok((challenge, self.tokenize.sign(token).to_string()))
+    }
+
+    /// Validates a step-up token whose challenge is still pending, without completing it. This lets
+    /// the second factor be checked against the account.
+    pub fn pending<S, F, A>(&self, token: S, account_fetcher: F) -> Result<(Challenge, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let (token, account) = self.tokenize.validate_token(token, account_fetcher)?;
+        let challenge = self.challenges.get(challenge_id(&token)?)?;
+
+        Ok((matching(challenge, &token)?, account))
+    }
+
+    /// Validates a step-up token and completes its challenge, once the second factor is verified.
+    /// Tokens whose challenge isn't pending anymore are rejected with [`TokenizeError::Revoked`].
+    pub fn complete<S, F, A>(&self, token: S, account_fetcher: F) -> Result<(Challenge, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let (token, account) = self.tokenize.validate_token(token, account_fetcher)?;
+        let challenge = self.challenges.remove(challenge_id(&token)?)?;
+
+        Ok((matching(challenge, &token)?, account))
+    }
+
+    /// Cancels the challenge of a step-up token, for instance after too many wrong codes, returning
+    /// whether it was pending.
+    pub fn cancel<S: Into<String>>(&self, token: S) -> Result<bool> {
+        let token = self.tokenize.parse(token)?;
+
+        Ok(self.challenges.remove(challenge_id(&token)?)?.is_some())
+    }
+
+    fn ttl(&self) -> Duration {
+        self.tokenize.options.max_age.unwrap_or(DEFAULT_CHALLENGE_TTL)
+    }
+}
+
+/// The challenge id carried by a step-up token.
+fn challenge_id(token: &Token) -> Result<&str> {
+    token.nonce().ok_or(TokenizeError::Revoked)
+}
+
+/// Checks that a challenge is pending and belongs to the account of `token`.
+fn matching(challenge: Option<Challenge>, token: &Token) -> Result<Challenge> {
+    match challenge {
+        Some(challenge) if token.account_id() == challenge.account_id.as_str() => Ok(challenge),
+        _ => Err(TokenizeError::Revoked)
+    }
+}
+
+/// A pending second-factor challenge, opened through [`MfaManager::begin`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Challenge {
+    pub id: String,
+    pub account_id: String,
+    /// Unix timestamp in milliseconds at which the challenge was opened.
+    pub created_at: i64
+}
+
+/// A store recording the challenges pending through a [`MfaManager`].
+pub trait ChallengeStore: Send + Sync {
+    /// Records a pending challenge. It can be forgotten after `ttl`, once its token is expired.
+    fn create(&self, challenge: &Challenge, ttl: Duration) -> Result<(), StoreError>;
+
+    fn get(&self, id: &str) -> Result<Option<Challenge>, StoreError>;
+
+    /// Atomically removes a challenge, returning it if it was pending.
+    fn remove(&self, id: &str) -> Result<Option<Challenge>, StoreError>;
+}
+
+impl<S: ChallengeStore + ?Sized> ChallengeStore for Arc<S> {
+    fn create(&self, challenge: &Challenge, ttl: Duration) -> Result<(), StoreError> {
+        (**self).create(challenge, ttl)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Challenge>, StoreError> {
+        (**self).get(id)
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<Challenge>, StoreError> {
+        (**self).remove(id)
+    }
+}
+
+/// A [`ChallengeStore`] keeping challenges in memory. Expired challenges are dropped whenever one is
+/// created.
+pub struct MemoryChallengeStore {
+    challenges: Mutex<HashMap<String, (Challenge, i64)>>,
+    clock: Box<dyn Clock>
+}
+
+impl MemoryChallengeStore {
+    pub fn new() -> MemoryChallengeStore {
+        MemoryChallengeStore::with_clock(SystemClock)
+    }
+
+    /// Creates a store measuring ttls with `clock`.
+    pub fn with_clock<C: Clock + 'static>(clock: C) -> MemoryChallengeStore {
+        MemoryChallengeStore {
+            challenges: Mutex::new(HashMap::new()),
+            clock: Box::new(clock)
+        }
+    }
+
+    /// Number of challenges held, including expired ones that weren't dropped yet.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, (Challenge, i64)>> {
+        self.challenges.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Default for MemoryChallengeStore {
+    fn default() -> Self {
+        MemoryChallengeStore::new()
+    }
+}
+
+impl ChallengeStore for MemoryChallengeStore {
+    fn create(&self, challenge: &Challenge, ttl: Duration) -> Result<(), StoreError> {
+        let now = self.clock.now_millis();
+        let expires_at = now.saturating_add(ttl.as_millis().try_into().unwrap_or(i64::MAX));
+
+        let mut challenges = self.lock();
+        challenges.retain(|_, (_, expires_at)| *expires_at > now);
+        challenges.insert(challenge.id.clone(), (challenge.clone(), expires_at));
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Challenge>, StoreError> {
+        let now = self.clock.now_millis();
+
+        Ok(self.lock().get(id).filter(|(_, expires_at)| *expires_at > now).map(|(challenge, _)| challenge.clone()))
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<Challenge>, StoreError> {
+        let now = self.clock.now_millis();
+
+        Ok(self.lock().remove(id).filter(|(_, expires_at)| *expires_at > now).map(|(challenge, _)| challenge))
+    }
+}
@@ -0,0 +1,207 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! [moka] integration, available with the `moka` feature.
+//! 
+//! [moka]: https://docs.rs/moka
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use ::moka::sync::Cache;
+use crate::{Account, AccountStore, AsyncAccountStore, BatchAccountStore, StoreError};
+
+/// Wraps an account store, caching the accounts it returns so hot accounts don't hit the underlying
+/// store on every validation.
+/// 
+/// It implements [`AccountStore`], [`AsyncAccountStore`] and [`BatchAccountStore`] when the wrapped
+/// store does. Missing accounts and failures aren't cached.
+/// 
+/// Cached accounts, and their last token reset, can be stale for up to the TTL. Call
+/// [`CachedAccountStore::invalidate`] when resetting the tokens of an account so they're rejected
+/// right away.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::time::Duration;
+/// use tokenize::Tokenize;
+/// use tokenize::moka::CachedAccountStore;
+/// use tokenize::store::MemoryAccountStore;
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let accounts = MemoryAccountStore::new();
+/// accounts.insert("326359466171826176", ());
+/// 
+/// let store = CachedAccountStore::new(&accounts, 10_000, Duration::from_secs(60));
+/// let token = tokenize.generate("326359466171826176").expect("Couldn't generate new token");
+/// tokenize.validate_with_store(&*token, &store).expect("Couldn't validate token");
+/// 
+/// accounts.reset_tokens("326359466171826176");
+/// store.invalidate("326359466171826176");
+/// assert!(tokenize.validate_with_store(token, &store).is_err());
+/// ```
+#[derive(Clone)]
+pub struct CachedAccountStore<S, A> {
+    store: S,
+    cache: Cache<String, A>
+}
+
+impl<S, A: Clone + Send + Sync + 'static> CachedAccountStore<S, A> {
+    /// Caches up to `capacity` accounts of `store`, each for up to `ttl`.
+    pub fn new(store: S, capacity: u64, ttl: Duration) -> CachedAccountStore<S, A> {
+        CachedAccountStore::with_cache(store, Cache::builder().max_capacity(capacity).time_to_live(ttl).build())
+    }
+
+    /// Caches the accounts of `store` in `cache`, for caches configured beyond a capacity and a TTL.
+    pub fn with_cache(store: S, cache: Cache<String, A>) -> CachedAccountStore<S, A> {
+        CachedAccountStore { store, cache }
+    }
+
+    /// Evicts the account tied to `id`, for instance after resetting its tokens.
+    pub fn invalidate(&self, id: &str) {
+        self.cache.invalidate(id);
+    }
+
+    /// Evicts every account.
+    pub fn invalidate_all(&self) {
+        self.cache.invalidate_all();
+    }
+
+    /// The wrapped store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The underlying cache.
+    pub fn cache(&self) -> &Cache<String, A> {
+        &self.cache
+    }
+
+    fn cached(&self, id: &str, account: Option<A>) -> Option<A> {
+        if let Some(account) = &account {
+            self.cache.insert(id.to_string(), account.clone());
+        }
+
+        account
+    }
+}
+
+impl<S, A> AccountStore<A> for CachedAccountStore<S, A> where
+    S: AccountStore<A>,
+    A: Account + Clone + Send + Sync + 'static {
+    fn fetch(&self, id: &str) -> Result<Option<A>, StoreError> {
+        match self.cache.get(id) {
+            Some(account) => Ok(Some(account)),
+            None => Ok(self.cached(id, self.store.fetch(id)?))
+        }
+    }
+}
+
+impl<S, A> AsyncAccountStore<A> for CachedAccountStore<S, A> where
+    S: AsyncAccountStore<A> + Sync,
+    A: Account + Clone + Send + Sync + 'static {
+    fn fetch(&self, id: &str) -> impl Future<Output = Result<Option<A>, StoreError>> + Send {
+        let id = id.to_string();
+        async move {
+            match self.cache.get(&id) {
+                Some(account) => Ok(Some(account)),
+                None => Ok(self.cached(&id, self.store.fetch(&id).await?))
+            }
+        }
+    }
+}
+
+impl<S, A> BatchAccountStore<A> for CachedAccountStore<S, A> where
+    S: BatchAccountStore<A>,
+    A: Account + Clone + Send + Sync + 'static {
+    /// Fetches the accounts which aren't cached with a single call to the wrapped store.
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        let mut accounts = HashMap::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        for &id in ids {
+            match self.cache.get(id) {
+                Some(account) => { accounts.insert(id.to_string(), account); },
+                None => missing.push(id)
+            }
+        }
+
+        if !missing.is_empty() {
+            for (id, account) in self.store.fetch_many(&missing)? {
+                self.cache.insert(id.clone(), account.clone());
+                accounts.insert(id, account);
+            }
+        }
+
+        Ok(accounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use super::CachedAccountStore;
+    use crate::{AccountStore, BatchAccountStore, StoreError};
+    use crate::store::MemoryAccount;
+
+    struct Accounts(Cell<usize>);
+
+    impl AccountStore<MemoryAccount> for Accounts {
+        fn fetch(&self, id: &str) -> Result<Option<MemoryAccount>, StoreError> {
+            self.0.set(self.0.get() + 1);
+            Ok((id != "1").then_some(MemoryAccount { data: (), last_token_reset: 0 }))
+        }
+    }
+
+    impl BatchAccountStore<MemoryAccount> for Accounts {
+        fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, MemoryAccount>, StoreError> {
+            self.0.set(self.0.get() + ids.len());
+            Ok(ids.iter().map(|&id| (id.to_owned(), MemoryAccount { data: (), last_token_reset: 0 })).collect())
+        }
+    }
+
+    #[test]
+    fn cache_accounts() {
+        let store = CachedAccountStore::new(Accounts(Cell::new(0)), 100, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert!(AccountStore::fetch(&store, "326359466171826176").unwrap().is_some());
+            assert_eq!(AccountStore::fetch(&store, "1").unwrap(), None);
+        }
+        assert_eq!(store.store().0.get(), 4);
+
+        store.invalidate("326359466171826176");
+        AccountStore::fetch(&store, "326359466171826176").unwrap();
+        assert_eq!(store.store().0.get(), 5);
+
+        let accounts = store.fetch_many(&["326359466171826176", "2", "3"]).unwrap();
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(store.store().0.get(), 7);
+    }
+}
@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use std::time::Duration;
+
+/// Additional checks performed by [`Tokenize::validate`](crate::Tokenize::validate) on top of the
+/// ones mandated by the specification.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationOptions {
+    /// Maximum age of a token. Older tokens are rejected with [`TokenizeError::Expired`](crate::TokenizeError::Expired).
+    pub max_age: Option<Duration>,
+    /// How far in the future a token can have been issued, to tolerate issuers whose clock is ahead.
+    /// Tokens issued later than that are rejected with [`TokenizeError::IssuedInFuture`](crate::TokenizeError::IssuedInFuture).
+    pub max_clock_skew: Option<Duration>,
+    /// Maximum length of a token, in bytes. Longer tokens are rejected with
+    /// [`TokenizeError::TokenTooLong`](crate::TokenizeError::TokenTooLong) before anything is decoded.
+    pub max_length: Option<usize>,
+    /// Maximum length of the account id carried by a token, in bytes. Checked against the length of
+    /// the encoded segment before it's decoded, so ids up to the next multiple of 3 bytes get through.
+    pub max_account_id_length: Option<usize>,
+    /// Rejects tokens whose segments aren't in the canonical form of the configured encoding (padding,
+    /// whitespace, characters from another alphabet or non-zero trailing bits), so two textually
+    /// different tokens can never verify to the same account.
+    pub strict: bool,
+    /// Makes the time taken by a validation independent of secret state: every secret is tried,
+    /// tokens signed with an unknown [`Keyring`](crate::Keyring) key go through a dummy signature
+    /// verification, and tokens of missing accounts go through the same checks as tokens of existing
+    /// ones before being rejected with [`TokenizeError::AccountNotFound`](crate::TokenizeError::AccountNotFound).
+    /// 
+    /// The time taken by the account fetcher itself isn't covered, it should take as long whether the
+    /// account exists or not.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hardened: bool,
+    /// Audience tokens must have been issued for, see [`Tokenize::set_audience`](crate::Tokenize::set_audience).
+    /// Tokens issued for another audience or for none are rejected with
+    /// [`TokenizeError::AudienceMismatch`](crate::TokenizeError::AudienceMismatch), so tokens issued
+    /// for one API can't be replayed against another one sharing the same secret.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expected_audience: Option<String>,
+    /// How long before the last token reset of an account a token can have been issued and still be
+    /// accepted, tolerating resets persisted a bit after a token was issued. Tokens issued within that
+    /// window before a reset survive it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reset_grace: Option<Duration>,
+    /// How long past their maximum age tokens are still accepted by
+    /// [`Tokenize::validate_lenient`](crate::Tokenize::validate_lenient), with a warning. Other
+    /// validation methods ignore it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expiry_grace: Option<Duration>
+}
+
+impl ValidationOptions {
+    pub fn new() -> ValidationOptions {
+        ValidationOptions::default()
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn max_clock_skew(mut self, max_clock_skew: Duration) -> Self {
+        self.max_clock_skew = Some(max_clock_skew);
+        self
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub fn max_account_id_length(mut self, max_account_id_length: usize) -> Self {
+        self.max_account_id_length = Some(max_account_id_length);
+        self
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn hardened(mut self) -> Self {
+        self.hardened = true;
+        self
+    }
+
+    pub fn expected_audience<S: Into<String>>(mut self, audience: S) -> Self {
+        self.expected_audience = Some(audience.into());
+        self
+    }
+
+    pub fn reset_grace(mut self, reset_grace: Duration) -> Self {
+        self.reset_grace = Some(reset_grace);
+        self
+    }
+
+    pub fn expiry_grace(mut self, expiry_grace: Duration) -> Self {
+        self.expiry_grace = Some(expiry_grace);
+        self
+    }
+}
@@ -0,0 +1,248 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Issuance of encrypted [PASETO](https://paseto.io) v4.local tokens, available with the `paseto`
+//! feature.
+//! 
+//! PASETO tokens carry the same account id and generation time as Tokenize tokens, in the `sub` and
+//! `iat` claims, but their payload is encrypted. They're validated against the same accounts, with the
+//! same [`ValidationOptions`](crate::ValidationOptions), so services can accept both formats during a
+//! transition.
+//! 
+//! The 32 bytes PASETO key is derived from the secret with [`SecretKey::derive`] and the
+//! [`KEY_CONTEXT`] context, so other implementations can decrypt the tokens by deriving it the same
+//! way. With a [`Keyring`](crate::Keyring), the key id is written to the footer as `{"kid":"..."}`.
+
+use std::cell::RefCell;
+use chrono::{DateTime, SecondsFormat};
+use pasetors::keys::SymmetricKey;
+use pasetors::token::UntrustedToken;
+use pasetors::version4::{LocalToken, V4};
+use pasetors::Local;
+use serde_json::{json, Value};
+use crate::audit::AuditEvent;
+use crate::{Account, AccountId, AccountStore, Result, SecretKey, Tokenize, TokenizeError};
+
+/// Context the PASETO key is derived from the secret with.
+pub const KEY_CONTEXT: &str = "tokenize paseto v4.local";
+
+impl Tokenize {
+    /// Generates a new PASETO v4.local token for the given account id.
+    /// 
+    /// Fails with [`TokenizeError::InvalidClaims`] if the account id isn't valid UTF-8.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let tokenize = Tokenize::new("uwu");
+    /// let token = tokenize.generate_paseto("326359466171826176").expect("Couldn't generate new token");
+    /// assert!(token.starts_with("v4.local."));
+    /// 
+    /// let account = tokenize.validate_paseto(token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 }))
+    ///     .expect("Couldn't validate token");
+    /// ```
+    pub fn generate_paseto<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        let account_id = account_id.into();
+        let issued_at = DateTime::from_timestamp(self.clock.now_millis().div_euclid(1000), 0).ok_or(TokenizeError::InvalidClaims)?;
+        let claims = json!({
+            "sub": account_id.as_str().ok_or(TokenizeError::InvalidClaims)?,
+            "iat": issued_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        });
+
+        let (key_id, secret) = match &self.keyring {
+            Some(keyring) => (Some(keyring.primary_id()), keyring.primary_secret()),
+            None => (None, self.secret.expose())
+        };
+        let footer = key_id.map(|key_id| json!({ "kid": key_id }).to_string());
+
+        let token = LocalToken::encrypt(&key(secret)?, claims.to_string().as_bytes(), footer.as_deref().map(str::as_bytes), None)
+            .map_err(|_| TokenizeError::InvalidClaims)?;
+        self.audit(AuditEvent::TokenIssued { account_id }, token.rsplit('.').next().unwrap_or_default());
+
+        Ok(token)
+    }
+
+    /// Validates a PASETO token generated by [`Tokenize::generate_paseto`], returning the account it
+    /// belongs to. See [`Tokenize::validate`].
+    /// 
+    /// Tokens which can't be decrypted with any secret fail with [`TokenizeError::InvalidSignature`].
+    /// The `exp` claim is enforced if the token carries one.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_paseto<S, F, A>(&self, token: S, mut account_fetcher: F) -> Result<A> where
+        S: AsRef<str>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.measured(token.as_ref(), || {
+            let (account_id, issued_at_millis) = self.parse_paseto(token.as_ref())?;
+            let account = account_fetcher(account_id);
+
+            self.check_account(account, issued_at_millis)
+        })
+    }
+
+    /// Validates a PASETO token, fetching its account from `store`. See [`Tokenize::validate_paseto`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "tokenize.validate", level = "debug", skip_all, fields(account = tracing::field::Empty), err(level = "debug")))]
+    pub fn validate_paseto_with_store<S, St, A>(&self, token: S, store: &St) -> Result<A> where
+        S: AsRef<str>,
+        St: AccountStore<A> + ?Sized,
+        A: Account {
+        self.measured(token.as_ref(), || {
+            let (account_id, issued_at_millis) = self.parse_paseto(token.as_ref())?;
+            let account = store.fetch(&account_id)?;
+
+            self.check_account(account, issued_at_millis)
+        })
+    }
+
+    /// Decrypts a PASETO token and performs the checks which don't involve its account, returning its
+    /// account id and generation time.
+    fn parse_paseto(&self, token: &str) -> Result<(String, i64)> {
+        if self.options.max_length.is_some_and(|max_length| token.len() > max_length) {
+            return Err(TokenizeError::TokenTooLong)
+        }
+
+        let untrusted = UntrustedToken::<Local, V4>::try_from(token).map_err(|_| TokenizeError::Malformed)?;
+        let key_id = match untrusted.untrusted_footer() {
+            [] => None,
+            footer => match serde_json::from_slice::<Value>(footer) {
+                Ok(Value::Object(footer)) => Some(footer.get("kid").and_then(Value::as_str).ok_or(TokenizeError::Malformed)?.to_string()),
+                _ => return Err(TokenizeError::Malformed)
+            }
+        };
+
+        let payload = RefCell::new(None);
+        self.verify_signature(key_id.as_deref(), |secret| {
+            match key(secret).and_then(|key| LocalToken::decrypt(&key, &untrusted, None, None).map_err(|_| TokenizeError::InvalidSignature)) {
+                Ok(trusted) => {
+                    payload.borrow_mut().get_or_insert_with(|| trusted.payload().to_string());
+                    true
+                },
+                Err(_) => false
+            }
+        })?;
+
+        let payload = payload.into_inner().ok_or(TokenizeError::InvalidSignature)?;
+        let claims = serde_json::from_str::<Value>(&payload).map_err(|_| TokenizeError::InvalidClaims)?;
+        let account_id = claims["sub"].as_str().ok_or(TokenizeError::InvalidClaims)?;
+        let issued_at_millis = parse_time(&claims["iat"])?;
+
+        if !claims["exp"].is_null() && self.clock.now_millis() >= parse_time(&claims["exp"])? {
+            return Err(TokenizeError::Expired)
+        }
+
+        self.check_validity(issued_at_millis, None, || crate::token::signature_hash(token))?;
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("account", AccountId::trace_hash(account_id.as_bytes()));
+
+        Ok((account_id.to_string(), issued_at_millis))
+    }
+}
+
+/// Derives the PASETO key from `secret`.
+fn key(secret: &[u8]) -> Result<SymmetricKey<V4>> {
+    SymmetricKey::from(SecretKey::from(secret).derive(KEY_CONTEXT).expose()).map_err(|_| TokenizeError::InvalidSignature)
+}
+
+/// Parses an RFC 3339 timestamp claim to a unix timestamp in milliseconds.
+fn parse_time(claim: &Value) -> Result<i64> {
+    let time = claim.as_str().ok_or(TokenizeError::InvalidClaims)?;
+
+    DateTime::parse_from_rfc3339(time).map(|time| time.timestamp_millis()).map_err(|_| TokenizeError::InvalidClaims)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use pasetors::keys::SymmetricKey;
+    use pasetors::version4::LocalToken;
+    use crate::clock::MockClock;
+    use crate::store::{MemoryAccount, MemoryAccountStore};
+    use crate::{Keyring, SecretKey, Tokenize, TokenizeError, ValidationOptions};
+    use super::KEY_CONTEXT;
+
+    fn account(_id: String) -> Option<MemoryAccount> {
+        Some(MemoryAccount { data: (), last_token_reset: 0 })
+    }
+
+    #[test]
+    fn validate_paseto() {
+        let clock = Arc::new(MockClock::new(1641731696000));
+        let tokenize = Tokenize::with_secrets("owo", ["uwu"])
+            .set_clock(clock.clone())
+            .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60)));
+        let token = Tokenize::new("uwu").set_clock(clock.clone()).generate_paseto("326359466171826176").expect("Couldn't generate new token");
+
+        assert!(tokenize.validate_paseto(&token, account).is_ok());
+        assert_eq!(Tokenize::new("nya").validate_paseto(&token, account).err(), Some(TokenizeError::InvalidSignature));
+        assert_eq!(tokenize.validate_paseto(&token, |_id| Some(MemoryAccount { data: (), last_token_reset: 1641731697000 })).err(), Some(TokenizeError::Invalidated));
+        assert_eq!(tokenize.validate_paseto(token.replacen("v4.local.", "v4.public.", 1), account).err(), Some(TokenizeError::Malformed));
+
+        let store = MemoryAccountStore::new();
+        assert_eq!(tokenize.validate_paseto_with_store(&token, &store).err(), Some(TokenizeError::AccountNotFound));
+        store.insert("326359466171826176", ());
+        assert!(tokenize.validate_paseto_with_store(&token, &store).is_ok());
+
+        clock.advance(61_000);
+        assert_eq!(tokenize.validate_paseto(&token, account).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn decrypt_paseto() {
+        // Other implementations decrypt the tokens with the derived key
+        let tokenize = Tokenize::new("uwu").set_clock(MockClock::new(1641731696000));
+        let token = tokenize.generate_paseto("326359466171826176").expect("Couldn't generate new token");
+
+        let key = SymmetricKey::from(SecretKey::from("uwu").derive(KEY_CONTEXT).expose()).unwrap();
+        let untrusted = token.as_str().try_into().unwrap();
+        let trusted = LocalToken::decrypt(&key, &untrusted, None, None).expect("Couldn't decrypt token");
+        assert_eq!(trusted.payload(), r#"{"iat":"2022-01-09T12:34:56Z","sub":"326359466171826176"}"#);
+
+        // Tokens with an expiry from other issuers
+        let payload = r#"{"sub":"326359466171826176","iat":"2022-01-09T12:34:56Z","exp":"2022-01-09T12:35:56Z"}"#;
+        let token = LocalToken::encrypt(&key, payload.as_bytes(), None, None).unwrap();
+        assert!(tokenize.validate_paseto(&token, account).is_ok());
+        let tokenize = tokenize.set_clock(MockClock::new(1641731756000));
+        assert_eq!(tokenize.validate_paseto(&token, account).err(), Some(TokenizeError::Expired));
+    }
+
+    #[test]
+    fn validate_keyring_paseto() {
+        let tokenize = Tokenize::with_keyring(Keyring::new("k1", "uwu").add_key("k2", "owo"));
+        let token = tokenize.generate_paseto("326359466171826176").expect("Couldn't generate new token");
+        assert!(token.ends_with(".eyJraWQiOiJrMSJ9"));
+        assert!(tokenize.validate_paseto(&token, account).is_ok());
+
+        let rotated = Tokenize::with_keyring(Keyring::new("k2", "owo"));
+        assert_eq!(rotated.validate_paseto(&token, account).err(), Some(TokenizeError::UnknownKey));
+        assert_eq!(Tokenize::new("uwu").validate_paseto(&token, account).err(), Some(TokenizeError::InvalidSignature));
+    }
+}
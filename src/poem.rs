@@ -0,0 +1,186 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! [Poem] integration, available with the `poem` feature.
+//! 
+//! [Poem]: https://docs.rs/poem
+
+use std::future::Future;
+use std::sync::Arc;
+use ::poem::http::StatusCode;
+use ::poem::{Endpoint, Middleware, Request};
+use crate::extract::{TokenExtractor, TokenSource};
+use crate::{Account, Tokenize};
+
+/// Middleware validating the token of incoming requests.
+/// 
+/// On success, the account is stored in the request extensions, where handlers can retrieve it through
+/// [`Data`](poem::web::Data). Requests without a valid token are rejected with a `401 Unauthorized`
+/// error.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use poem::{get, handler, EndpointExt, Route};
+/// use poem::web::Data;
+/// use tokenize::{Account, Tokenize};
+/// use tokenize::extract::TokenSource;
+/// use tokenize::poem::TokenizeMiddleware;
+/// 
+/// #[derive(Clone)]
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// #[handler]
+/// fn me(user: Data<&User>) -> String {
+///     user.last_token_reset.to_string()
+/// }
+/// 
+/// let middleware = TokenizeMiddleware::new(Tokenize::new("uwu"), |_id| async {
+///     Some(User { last_token_reset: 0 }) // query your database here
+/// }).fallback_source(TokenSource::Cookie("token".to_string()));
+/// 
+/// let app = Route::new().at("/me", get(me)).with(middleware);
+/// ```
+pub struct TokenizeMiddleware<F> {
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Arc<F>,
+    extractor: TokenExtractor
+}
+
+impl<F> TokenizeMiddleware<F> {
+    /// Creates a middleware validating tokens from the `Authorization` header.
+    pub fn new<T: Into<Arc<Tokenize>>>(tokenize: T, account_fetcher: F) -> TokenizeMiddleware<F> {
+        TokenizeMiddleware {
+            tokenize: tokenize.into(),
+            account_fetcher: Arc::new(account_fetcher),
+            extractor: TokenExtractor::new()
+        }
+    }
+
+    /// Replaces the places the token is looked for.
+    pub fn source(mut self, source: TokenSource) -> Self {
+        self.extractor = self.extractor.source(source);
+        self
+    }
+
+    /// Adds a place the token is looked for. Sources are tried in the order they were added.
+    pub fn fallback_source(mut self, source: TokenSource) -> Self {
+        self.extractor = self.extractor.fallback_source(source);
+        self
+    }
+}
+
+impl<E: Endpoint, F> Middleware<E> for TokenizeMiddleware<F> where
+    TokenizeEndpoint<E, F>: Endpoint {
+    type Output = TokenizeEndpoint<E, F>;
+
+    fn transform(&self, inner: E) -> Self::Output {
+        TokenizeEndpoint {
+            inner,
+            tokenize: self.tokenize.clone(),
+            account_fetcher: self.account_fetcher.clone(),
+            extractor: self.extractor.clone()
+        }
+    }
+}
+
+/// Endpoint created by [`TokenizeMiddleware`].
+pub struct TokenizeEndpoint<E, F> {
+    inner: E,
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Arc<F>,
+    extractor: TokenExtractor
+}
+
+impl<E, F, Fut, A> Endpoint for TokenizeEndpoint<E, F> where
+    E: Endpoint,
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Option<A>> + Send,
+    A: Account + Clone + Send + Sync + 'static {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> ::poem::Result<Self::Output> {
+        let token = self.extractor.extract(|name| req.headers().get(name).and_then(|value| value.to_str().ok()), req.uri().query())
+            .ok_or_else(|| ::poem::Error::from_status(StatusCode::UNAUTHORIZED))?;
+        let account = self.tokenize.validate_async(token, |id| (self.account_fetcher)(id))
+            .await
+            .map_err(|_| ::poem::Error::from_status(StatusCode::UNAUTHORIZED))?;
+
+        req.extensions_mut().insert(account);
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::poem::http::StatusCode;
+    use ::poem::web::Data;
+    use ::poem::{handler, Endpoint, EndpointExt, Request};
+    use super::TokenizeMiddleware;
+    use crate::extract::TokenSource;
+    use crate::{Account, Tokenize};
+
+    #[derive(Clone)]
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    const TOKEN: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+
+    #[handler]
+    fn index(account: Data<&TestAccount>) -> String {
+        account.last_token_reset.to_string()
+    }
+
+    #[tokio::test]
+    async fn validate_requests() {
+        let middleware = TokenizeMiddleware::new(Tokenize::new("uwu"), |_id| async { Some(TestAccount { last_token_reset: 0 }) })
+            .fallback_source(TokenSource::Query("token".to_string()));
+        let app = index.with(middleware);
+
+        let request = Request::builder().header("Authorization", format!("Bearer {}", TOKEN)).finish();
+        assert_eq!(app.get_response(request).await.status(), StatusCode::OK);
+
+        let request = Request::builder().uri_str(format!("/?token={}", TOKEN.replace('+', "%2B"))).finish();
+        assert_eq!(app.get_response(request).await.status(), StatusCode::OK);
+
+        let request = Request::builder().header("Authorization", "thisisinvalid").finish();
+        assert_eq!(app.get_response(request).await.status(), StatusCode::UNAUTHORIZED);
+    }
+}
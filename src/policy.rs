@@ -0,0 +1,230 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Authorization policies, evaluated once a token has been validated.
+
+use std::sync::Arc;
+use std::time::Duration;
+use crate::clock::{Clock, SystemClock};
+use crate::{Account, Result, Token, TokenizeError, ValidationContext};
+
+/// An authorization rule, evaluated by [`Tokenize::validate_with_context`](crate::Tokenize::validate_with_context)
+/// once the signature and the account of a token have been checked.
+/// 
+/// Policies get the validated token, its account and the context of the request, such as the route
+/// or the peer address. They're combined through [`All`], [`Any`] and [`Not`], and closures taking the
+/// same arguments are policies as well.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Account, Token, Tokenize, TokenizeError, ValidationContext};
+/// use tokenize::policy::{All, Any, Not, RequirePrefix, RequireScope};
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let admin_route = |_token: &Token, _account: &dyn Account, context: &ValidationContext| match context.route() {
+///     Some(route) if route.starts_with("/admin") => Ok(()),
+///     _ => Err(TokenizeError::PolicyViolation("not an admin route".to_string()))
+/// };
+/// let policy = All::new()
+///     .and(RequirePrefix::new("user"))
+///     .and(Any::new().or(RequireScope::new("admin")).or(Not::new(admin_route)));
+/// 
+/// let tokenize = Tokenize::new("uwu").set_prefix("user").set_policy(policy);
+/// let token = tokenize.generate_with_scopes("326359466171826176", ["read"]).expect("Couldn't generate new token");
+/// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+/// 
+/// let context = ValidationContext::new().with(ValidationContext::ROUTE, "/feed");
+/// assert!(tokenize.validate_with_context(&*token, &context, account).is_ok());
+/// 
+/// let context = ValidationContext::new().with(ValidationContext::ROUTE, "/admin/users");
+/// assert!(tokenize.validate_with_context(token, &context, account).is_err());
+/// ```
+pub trait Policy: Send + Sync {
+    /// Checks that `token` is authorized, failing with the reason it isn't.
+    fn evaluate(&self, token: &Token, account: &dyn Account, context: &ValidationContext) -> Result<()>;
+}
+
+impl<F> Policy for F where
+    F: Fn(&Token, &dyn Account, &ValidationContext) -> Result<()> + Send + Sync {
+    fn evaluate(&self, token: &Token, account: &dyn Account, context: &ValidationContext) -> Result<()> {
+        self(token, account, context)
+    }
+}
+
+impl<P: Policy + ?Sized> Policy for Arc<P> {
+    fn evaluate(&self, token: &Token, account: &dyn Account, context: &ValidationContext) -> Result<()> {
+        (**self).evaluate(token, account, context)
+    }
+}
+
+/// Authorizes tokens authorized by every one of its policies, failing with the error of the first
+/// one denying them. Authorizes every token if it's empty.
+#[derive(Default)]
+pub struct All {
+    policies: Vec<Box<dyn Policy>>
+}
+
+impl All {
+    pub fn new() -> All {
+        All::default()
+    }
+
+    pub fn and<P: Policy + 'static>(mut self, policy: P) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+}
+
+impl Policy for All {
+    fn evaluate(&self, token: &Token, account: &dyn Account, context: &ValidationContext) -> Result<()> {
+        self.policies.iter().try_for_each(|policy| policy.evaluate(token, account, context))
+    }
+}
+
+/// Authorizes tokens authorized by at least one of its policies, failing with the error of the last
+/// one otherwise. Denies every token if it's empty.
+#[derive(Default)]
+pub struct Any {
+    policies: Vec<Box<dyn Policy>>
+}
+
+impl Any {
+    pub fn new() -> Any {
+        Any::default()
+    }
+
+    pub fn or<P: Policy + 'static>(mut self, policy: P) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+}
+
+impl Policy for Any {
+    fn evaluate(&self, token: &Token, account: &dyn Account, context: &ValidationContext) -> Result<()> {
+        let mut result = Err(TokenizeError::PolicyViolation("no policy authorizes the token".to_string()));
+        for policy in &self.policies {
+            result = policy.evaluate(token, account, context);
+            if result.is_ok() {
+                break
+            }
+        }
+
+        result
+    }
+}
+
+/// Authorizes tokens denied by its policy, and denies the ones it authorizes.
+/// 
+/// Failures of stores queried by the policy are passed through rather than authorizing the token.
+pub struct Not<P> {
+    policy: P
+}
+
+impl<P: Policy> Not<P> {
+    pub fn new(policy: P) -> Not<P> {
+        Not { policy }
+    }
+}
+
+impl<P: Policy> Policy for Not<P> {
+    fn evaluate(&self, token: &Token, account: &dyn Account, context: &ValidationContext) -> Result<()> {
+        match self.policy.evaluate(token, account, context) {
+            Ok(()) => Err(TokenizeError::PolicyViolation("a negated policy authorizes the token".to_string())),
+            Err(err @ TokenizeError::Store(_)) => Err(err),
+            Err(_) => Ok(())
+        }
+    }
+}
+
+/// Denies tokens older than a maximum age with [`TokenizeError::Expired`], on top of the one of the
+/// instance. This lets sensitive operations require a recently issued token.
+pub struct MaxAge {
+    max_age: Duration,
+    clock: Box<dyn Clock>
+}
+
+impl MaxAge {
+    pub fn new(max_age: Duration) -> MaxAge {
+        MaxAge::with_clock(max_age, SystemClock)
+    }
+
+    /// Creates a policy measuring the age of tokens with `clock`.
+    pub fn with_clock<C: Clock + 'static>(max_age: Duration, clock: C) -> MaxAge {
+        MaxAge { max_age, clock: Box::new(clock) }
+    }
+}
+
+impl Policy for MaxAge {
+    fn evaluate(&self, token: &Token, _account: &dyn Account, _context: &ValidationContext) -> Result<()> {
+        let age = self.clock.now_millis().saturating_sub(token.issued_at_millis());
+        if age > 0 && age as u128 > self.max_age.as_millis() {
+            return Err(TokenizeError::Expired)
+        }
+
+        Ok(())
+    }
+}
+
+/// Denies tokens issued without a scope with [`TokenizeError::MissingScope`].
+pub struct RequireScope {
+    scope: String
+}
+
+impl RequireScope {
+    pub fn new<S: Into<String>>(scope: S) -> RequireScope {
+        RequireScope { scope: scope.into() }
+    }
+}
+
+impl Policy for RequireScope {
+    fn evaluate(&self, token: &Token, _account: &dyn Account, _context: &ValidationContext) -> Result<()> {
+        token.require_scope(&self.scope)
+    }
+}
+
+/// Denies tokens without a prefix with [`TokenizeError::PrefixMismatch`], such as `user` tokens on
+/// routes accepting `bot` tokens as well.
+pub struct RequirePrefix {
+    prefix: String
+}
+
+impl RequirePrefix {
+    pub fn new<S: Into<String>>(prefix: S) -> RequirePrefix {
+        RequirePrefix { prefix: prefix.into() }
+    }
+}
+
+impl Policy for RequirePrefix {
+    fn evaluate(&self, token: &Token, _account: &dyn Account, _context: &ValidationContext) -> Result<()> {
+        if token.prefix() != Some(self.prefix.as_str()) {
+            return Err(TokenizeError::PrefixMismatch)
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Property-based tests feeding arbitrary and mutated tokens to the parser. The `fuzz` directory has
+//! `cargo fuzz` targets for the same properties.
+
+use proptest::prelude::*;
+use crate::store::MemoryAccount;
+use crate::{Encoding, Keyring, TokenVersion, Tokenize, ValidationOptions};
+
+/// Instances covering the parser's configurations.
+fn instances() -> Vec<Tokenize> {
+    vec![
+        Tokenize::new("uwu"),
+        Tokenize::new("uwu").set_prefix("app"),
+        Tokenize::new("uwu").set_prefixes(["app", "bot"]).set_version(TokenVersion::V2),
+        Tokenize::new("uwu").set_encoding(Encoding::UrlSafe).accept_any_encoding(),
+        Tokenize::with_secrets("uwu", ["owo"]).set_validation_options(ValidationOptions::new().strict().hardened()),
+        Tokenize::with_keyring(Keyring::new("k1", "uwu").add_key("k2", "owo")),
+        Tokenize::new("uwu").set_validation_options(ValidationOptions::new().max_length(64).max_account_id_length(8)),
+        Tokenize::new("uwu").set_validation_options(ValidationOptions::new().max_length(usize::MAX).max_account_id_length(usize::MAX))
+    ]
+}
+
+/// Runs `token` through every validation entry point, asserting none of them accepts it.
+fn assert_rejected(tokenize: &Tokenize, token: &str) {
+    let account = MemoryAccount { data: (), last_token_reset: 0 };
+
+    assert!(tokenize.parse(token).is_err(), "{} was parsed", token);
+    assert!(tokenize.parse_ref(token).is_err(), "{} was parsed without allocating", token);
+    assert!(tokenize.validate(token, |_id| Some(account.clone())).is_err(), "{} was validated", token);
+    assert!(tokenize.validate_ref(token, |_id| Some(account.clone())).is_err(), "{} was validated without allocating", token);
+    assert!(tokenize.validate_single_use(token, |_id| Some(account.clone())).is_err(), "{} was validated as single-use", token);
+    assert!(tokenize.validate_bytes(token, |_id| Some(account.clone())).is_err(), "{} was validated as bytes", token);
+    let _ = token.parse::<crate::Token>();
+}
+
+/// Strings made of the characters tokens are built from, more likely to reach deep parser paths than
+/// arbitrary ones.
+fn token_like() -> impl Strategy<Value = String> {
+    proptest::string::string_regex("[A-Za-z0-9+/_=.:~-]{0,120}").expect("Invalid regex")
+}
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    Replace(usize, char),
+    Insert(usize, char),
+    Remove(usize),
+    Truncate(usize),
+    DuplicateSegment(usize),
+    SwapSegments(usize, usize)
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    let index = any::<usize>();
+    let character = prop_oneof![proptest::char::range('+', 'z'), Just('.'), Just(':'), Just('~'), any::<char>()];
+
+    prop_oneof![
+        (index, character.clone()).prop_map(|(index, character)| Mutation::Replace(index, character)),
+        (index, character).prop_map(|(index, character)| Mutation::Insert(index, character)),
+        index.prop_map(Mutation::Remove),
+        index.prop_map(Mutation::Truncate),
+        index.prop_map(Mutation::DuplicateSegment),
+        (index, index).prop_map(|(a, b)| Mutation::SwapSegments(a, b))
+    ]
+}
+
+impl Mutation {
+    fn apply(&self, token: &str) -> String {
+        if token.is_empty() {
+            return String::new()
+        }
+
+        let mut chars = token.chars().collect::<Vec<char>>();
+        let len = chars.len();
+        let mut segments = token.split('.').map(str::to_string).collect::<Vec<String>>();
+
+        match *self {
+            Mutation::Replace(index, character) => chars[index % len] = character,
+            Mutation::Insert(index, character) => chars.insert(index % (len + 1), character),
+            Mutation::Remove(index) => { chars.remove(index % len); },
+            Mutation::Truncate(index) => chars.truncate(index % len),
+            Mutation::DuplicateSegment(index) => {
+                let segment = segments[index % segments.len()].clone();
+                segments.insert(index % segments.len(), segment);
+                return segments.join(".")
+            },
+            Mutation::SwapSegments(a, b) => {
+                let len = segments.len();
+                segments.swap(a % len, b % len);
+                return segments.join(".")
+            }
+        }
+
+        chars.into_iter().collect()
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn arbitrary_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+        let token = String::from_utf8_lossy(&bytes);
+        for tokenize in instances() {
+            assert_rejected(&tokenize, &token);
+        }
+    }
+
+    #[test]
+    fn token_like_strings(token in token_like()) {
+        for tokenize in instances() {
+            assert_rejected(&tokenize, &token);
+        }
+    }
+
+    #[test]
+    fn mutated_tokens(account_id in "[0-9]{1,20}", scopes in proptest::collection::vec("[a-z]{1,8}", 0..3), single_use in any::<bool>(), mutations in proptest::collection::vec(mutation(), 1..4)) {
+        for tokenize in instances() {
+            let token = if single_use {
+                tokenize.generate_single_use(&*account_id)
+            } else {
+                tokenize.generate_with_scopes(&*account_id, scopes.clone())
+            };
+            let Ok(token) = token else { continue };
+            prop_assert!(tokenize.parse(&*token).is_ok() || token.len() > 64, "{} wasn't parsed", token);
+
+            let mutated = mutations.iter().fold(token.clone(), |token, mutation| mutation.apply(&token));
+            if mutated != token {
+                assert_rejected(&tokenize, &mutated);
+            }
+        }
+    }
+}
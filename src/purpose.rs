@@ -0,0 +1,117 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Purpose-scoped tokens, such as email verification or password reset tokens.
+
+use std::time::Duration;
+use crate::{Account, AccountId, KeyScope, Result, Tokenize, TokenizeError};
+
+/// Purpose of password reset tokens.
+pub const PASSWORD_RESET: &str = "password-reset";
+
+/// Purpose of email verification tokens.
+pub const EMAIL_VERIFICATION: &str = "email-verification";
+
+/// Issues tokens valid for a single purpose and a limited time.
+/// 
+/// The purpose is mixed into the key tokens are signed with, so a token issued for one purpose fails
+/// with [`TokenizeError::InvalidSignature`] when validated for another one, or as a regular token.
+/// Each token carries its own lifetime, checked on top of the [`ValidationOptions`](crate::ValidationOptions)
+/// of the instance.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::time::Duration;
+/// use tokenize::{Tokenize, TokenizeError};
+/// use tokenize::purpose::{PurposeToken, EMAIL_VERIFICATION, PASSWORD_RESET};
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let tokens = PurposeToken::new(Tokenize::new("uwu"));
+/// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+/// 
+/// let token = tokens.generate(PASSWORD_RESET, "326359466171826176", Duration::from_secs(15 * 60))
+///     .expect("Couldn't generate new token");
+/// 
+/// assert!(tokens.validate_for(PASSWORD_RESET, &*token, account).is_ok());
+/// assert_eq!(tokens.validate_for(EMAIL_VERIFICATION, token, account).err(), Some(TokenizeError::InvalidSignature));
+/// ```
+pub struct PurposeToken {
+    tokenize: Tokenize
+}
+
+impl PurposeToken {
+    /// Creates an issuer signing purpose-scoped tokens with keys derived from the secret of `tokenize`.
+    pub fn new(tokenize: Tokenize) -> PurposeToken {
+        PurposeToken { tokenize }
+    }
+
+    /// The instance tokens are issued with.
+    pub fn tokens(&self) -> &Tokenize {
+        &self.tokenize
+    }
+
+    /// Generates a token for `account_id`, only valid for `purpose` and expiring after `ttl`.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Malformed`] if the purpose is empty or if `ttl` is zero.
+    pub fn generate<S: Into<AccountId>>(&self, purpose: &str, account_id: S, ttl: Duration) -> Result<String> {
+        let ttl_millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        if purpose.is_empty() || ttl_millis == 0 {
+            return Err(TokenizeError::Malformed)
+        }
+
+        let mut token = self.tokenize.unsigned_token(account_id.into());
+        token.ttl_millis = Some(ttl_millis);
+
+        Ok(self.tokenize.sign_scoped(token, Some(KeyScope::Purpose(purpose))).to_string())
+    }
+
+    /// Validates a token issued for `purpose`. See [`Tokenize::validate`].
+    /// 
+    /// Tokens issued for another purpose fail with [`TokenizeError::InvalidSignature`], and tokens past
+    /// their lifetime with [`TokenizeError::Expired`]. Resetting the tokens of the account, such as
+    /// once its password was changed, invalidates its pending tokens as well.
+    pub fn validate_for<S, F, A>(&self, purpose: &str, token: S, mut account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.tokenize.measured(&token, || {
+            let token = self.tokenize.parse_scoped(&token, Some(KeyScope::Purpose(purpose)))?;
+            if token.expires_at_millis().is_none() {
+                return Err(TokenizeError::Malformed)
+            }
+
+            self.tokenize.check_token(&token)?;
+
+            let account = account_fetcher(token.account_id_str()?.to_string());
+            self.tokenize.check_account(account, token.issued_at_millis())
+        })
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Python bindings, available with the `python` feature.
+//! 
+//! The module is named `tokenize_rs` so it doesn't shadow the standard library's `tokenize`, and is
+//! built with [maturin](https://www.maturin.rs), see `pyproject.toml`:
+//! 
+//! ```python
+//! from tokenize_rs import Tokenize, TokenizeError
+//! 
+//! tokenize = Tokenize(b"uwu", prefix="app")
+//! token = tokenize.generate("326359466171826176")
+//! 
+//! try:
+//!     account_id = tokenize.validate(token, lambda account_id: 0)
+//! except TokenizeError as err:
+//!     print(err.kind)
+//! ```
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use crate::{Account, Tokenize};
+
+create_exception!(tokenize_rs, TokenizeError, PyValueError, "Raised when a token can't be generated or validated. Its `kind` attribute holds the kind of error, such as `\"expired\"`.");
+
+struct PyAccount {
+    last_token_reset: u64
+}
+
+impl Account for PyAccount {
+    fn last_token_reset(&self) -> u64 {
+        self.last_token_reset
+    }
+}
+
+/// A secret given as `bytes` or `str`.
+#[derive(FromPyObject)]
+enum PySecret {
+    Bytes(Vec<u8>),
+    Text(String)
+}
+
+/// Python wrapper around [`Tokenize`].
+#[pyclass(name = "Tokenize", module = "tokenize_rs", frozen)]
+pub struct PyTokenize {
+    inner: Tokenize
+}
+
+#[pymethods]
+impl PyTokenize {
+    #[new]
+    #[pyo3(signature = (secret, prefix = None))]
+    fn new(secret: PySecret, prefix: Option<String>) -> PyResult<PyTokenize> {
+        let mut inner = match secret {
+            PySecret::Bytes(secret) => Tokenize::new(secret),
+            PySecret::Text(secret) => Tokenize::new(secret.into_bytes())
+        };
+
+        if let Some(prefix) = prefix {
+            inner = inner.try_set_prefix(prefix).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        }
+
+        Ok(PyTokenize { inner })
+    }
+
+    /// Generates a new token for the given account id.
+    fn generate(&self, account_id: &str) -> PyResult<String> {
+        self.inner.generate(account_id).map_err(to_py_err)
+    }
+
+    /// Validates a token, returning the id of the account it belongs to.
+    /// 
+    /// `fetch_last_token_reset` is called with the account id and must return the account's last
+    /// token reset timestamp in milliseconds, or `None` if there is no such account. Exceptions it
+    /// raises are propagated.
+    fn validate(&self, token: &str, fetch_last_token_reset: &Bound<'_, PyAny>) -> PyResult<String> {
+        let mut fetch_error = None;
+        let mut account_id = String::new();
+
+        let result = self.inner.validate(token, |id| {
+            let last_token_reset = match fetch_last_token_reset.call1((id.as_str(),)).and_then(|value| value.extract::<Option<u64>>()) {
+                Ok(last_token_reset) => last_token_reset?,
+                Err(err) => {
+                    fetch_error = Some(err);
+                    return None
+                }
+            };
+
+            account_id = id;
+            Some(PyAccount { last_token_reset })
+        });
+
+        if let Some(err) = fetch_error {
+            return Err(err)
+        }
+
+        result.map_err(to_py_err)?;
+        Ok(account_id)
+    }
+
+    /// Verifies a token without looking up its account, returning the id of the account it belongs to.
+    fn parse(&self, token: &str) -> PyResult<String> {
+        Ok(self.inner.parse(token).map_err(to_py_err)?.account_id().to_string())
+    }
+}
+
+fn to_py_err(err: crate::TokenizeError) -> PyErr {
+    Python::attach(|py| {
+        let py_err = TokenizeError::new_err(err.to_string());
+        match py_err.value(py).setattr("kind", err.kind()) {
+            Ok(()) => py_err,
+            Err(err) => err
+        }
+    })
+}
+
+/// The `tokenize_rs` Python module.
+#[pymodule]
+fn tokenize_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTokenize>()?;
+    m.add("TokenizeError", m.py().get_type::<TokenizeError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::ffi::c_str;
+    use pyo3::types::PyDict;
+    use super::*;
+
+    #[test]
+    fn generate_and_validate() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "tokenize_rs").unwrap();
+            tokenize_rs(&module).unwrap();
+            let locals = PyDict::new(py);
+            locals.set_item("tokenize_rs", module).unwrap();
+
+            py.run(c_str!(r#"
+tokenize = tokenize_rs.Tokenize("uwu", prefix="app")
+token = tokenize.generate("326359466171826176")
+assert tokenize.validate(token, lambda account_id: 0) == "326359466171826176"
+assert tokenize_rs.Tokenize(b"uwu", prefix="app").parse(token) == "326359466171826176"
+
+try:
+    tokenize.validate(token, lambda account_id: None)
+    assert False
+except tokenize_rs.TokenizeError as err:
+    assert err.kind == "account_not_found"
+
+try:
+    tokenize.validate(token, lambda account_id: 1 / 0)
+    assert False
+except ZeroDivisionError:
+    pass
+"#), None, Some(&locals)).unwrap();
+        });
+    }
+}
@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Throttling of peers presenting forged tokens.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use crate::clock::{Clock, SystemClock};
+use crate::ValidationContext;
+
+/// A policy deciding whether a peer may present tokens, based on the forged tokens it presented
+/// before. Peers are identified by the caller of [`Tokenize::validate_from`](crate::Tokenize::validate_from),
+/// with an IP address or a client id for instance.
+/// 
+/// Policies are set through [`Tokenize::set_failure_policy`](crate::Tokenize::set_failure_policy).
+pub trait FailurePolicy: Send + Sync {
+    /// Whether `peer` may present a token. Peers which may not are rejected with
+    /// [`TokenizeError::RateLimited`](crate::TokenizeError::RateLimited) before anything is verified.
+    fn allow(&self, peer: &str) -> bool;
+
+    /// Records that `peer` presented a token whose signature doesn't match, or which was signed with
+    /// an unknown key.
+    fn record_failure(&self, peer: &str);
+
+    /// Whether `peer` may present a token, given the context of its request. Called by
+    /// [`Tokenize::validate_with_context`](crate::Tokenize::validate_with_context), defaults to
+    /// [`FailurePolicy::allow`].
+    fn allow_in(&self, peer: &str, context: &ValidationContext) -> bool {
+        let _ = context;
+        self.allow(peer)
+    }
+
+    /// Records a failure of `peer`, given the context of its request. Called by
+    /// [`Tokenize::validate_with_context`](crate::Tokenize::validate_with_context), defaults to
+    /// [`FailurePolicy::record_failure`].
+    fn record_failure_in(&self, peer: &str, context: &ValidationContext) {
+        let _ = context;
+        self.record_failure(peer)
+    }
+}
+
+impl<P: FailurePolicy + ?Sized> FailurePolicy for Arc<P> {
+    fn allow(&self, peer: &str) -> bool {
+        (**self).allow(peer)
+    }
+
+    fn record_failure(&self, peer: &str) {
+        (**self).record_failure(peer)
+    }
+
+    fn allow_in(&self, peer: &str, context: &ValidationContext) -> bool {
+        (**self).allow_in(peer, context)
+    }
+
+    fn record_failure_in(&self, peer: &str, context: &ValidationContext) {
+        (**self).record_failure_in(peer, context)
+    }
+}
+
+/// A [`FailurePolicy`] giving each peer a bucket of `capacity` failures, refilled with one failure
+/// every `refill` interval. Peers are rejected while their bucket is empty.
+/// 
+/// Buckets are kept in memory. Full buckets are dropped whenever a failure is recorded.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::time::Duration;
+/// use tokenize::{Tokenize, TokenizeError};
+/// use tokenize::ratelimit::TokenBucket;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let tokenize = Tokenize::new("uwu").set_failure_policy(TokenBucket::new(2, Duration::from_secs(60)));
+/// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+/// let forged = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid";
+/// 
+/// assert_eq!(tokenize.validate_from("203.0.113.7", forged, account).err(), Some(TokenizeError::InvalidSignature));
+/// assert_eq!(tokenize.validate_from("203.0.113.7", forged, account).err(), Some(TokenizeError::InvalidSignature));
+/// assert_eq!(tokenize.validate_from("203.0.113.7", forged, account).err(), Some(TokenizeError::RateLimited));
+/// ```
+pub struct TokenBucket {
+    capacity: u32,
+    refill: Duration,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    clock: Box<dyn Clock>
+}
+
+struct Bucket {
+    remaining: u32,
+    /// Time at which the last failure was refilled, in milliseconds.
+    refilled_at: i64
+}
+
+impl TokenBucket {
+    /// # Panics
+    /// 
+    /// Panics if `refill` is zero.
+    pub fn new(capacity: u32, refill: Duration) -> TokenBucket {
+        TokenBucket::with_clock(capacity, refill, SystemClock)
+    }
+
+    /// Creates a policy measuring refills with `clock`.
+    pub fn with_clock<C: Clock + 'static>(capacity: u32, refill: Duration, clock: C) -> TokenBucket {
+        assert!(!refill.is_zero(), "The refill interval can't be zero");
+
+        TokenBucket {
+            capacity,
+            refill,
+            buckets: Mutex::new(HashMap::new()),
+            clock: Box::new(clock)
+        }
+    }
+
+    /// Number of failures `peer` can still make before being rejected.
+    pub fn remaining(&self, peer: &str) -> u32 {
+        let now = self.clock.now_millis();
+
+        self.lock().get_mut(peer).map_or(self.capacity, |bucket| self.refill(bucket, now))
+    }
+
+    /// Drops the buckets which are full again.
+    pub fn purge_full(&self) {
+        let now = self.clock.now_millis();
+        self.lock().retain(|_, bucket| self.refill(bucket, now) < self.capacity);
+    }
+
+    /// Refills `bucket` with the failures recovered since it was last refilled, returning the number
+    /// of remaining failures.
+    fn refill(&self, bucket: &mut Bucket, now: i64) -> u32 {
+        let refill = self.refill.as_millis().try_into().unwrap_or(i64::MAX);
+        let recovered = now.saturating_sub(bucket.refilled_at).max(0) / refill;
+        if recovered > 0 {
+            bucket.remaining = self.capacity.min(bucket.remaining.saturating_add(recovered.try_into().unwrap_or(u32::MAX)));
+            bucket.refilled_at = if bucket.remaining == self.capacity { now } else { bucket.refilled_at + recovered * refill };
+        }
+
+        bucket.remaining
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Bucket>> {
+        self.buckets.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl FailurePolicy for TokenBucket {
+    fn allow(&self, peer: &str) -> bool {
+        self.remaining(peer) > 0
+    }
+
+    fn record_failure(&self, peer: &str) {
+        let now = self.clock.now_millis();
+
+        self.purge_full();
+        let mut buckets = self.lock();
+        let bucket = buckets.entry(peer.to_string()).or_insert(Bucket { remaining: self.capacity, refilled_at: now });
+        bucket.remaining = bucket.remaining.saturating_sub(1);
+    }
+}
@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! [redis] integration, available with the `redis` feature.
+//! 
+//! [redis]: https://docs.rs/redis
+
+use std::time::Duration;
+use ::redis::Client;
+use crate::{RevocationStore, StoreError, UsageStore};
+
+/// A [`RevocationStore`] keeping revoked tokens in Redis.
+/// 
+/// It can also be used as a [`UsageStore`], consumed tokens being recorded as revoked ones.
+/// 
+/// Each revoked token is stored under its own key, expiring along with the revocation. A connection
+/// is opened for every operation.
+/// 
+/// # Examples
+/// 
+/// ```no_run
+/// use tokenize::Tokenize;
+/// use tokenize::redis::RedisRevocationStore;
+/// 
+/// let client = redis::Client::open("redis://127.0.0.1/").expect("Invalid Redis URL");
+/// let tokenize = Tokenize::new("uwu").set_revocation_store(RedisRevocationStore::new(client));
+/// 
+/// tokenize.revoke("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc")
+///     .expect("Couldn't revoke token");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedisRevocationStore {
+    client: Client,
+    namespace: String
+}
+
+impl RedisRevocationStore {
+    pub fn new(client: Client) -> RedisRevocationStore {
+        RedisRevocationStore {
+            client,
+            namespace: "tokenize:revoked:".to_string()
+        }
+    }
+
+    /// Sets the string prepended to the keys revoked tokens are stored under. Defaults to `tokenize:revoked:`.
+    pub fn namespace<S: Into<String>>(mut self, namespace: S) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.namespace, key)
+    }
+}
+
+impl RevocationStore for RedisRevocationStore {
+    fn is_revoked(&self, key: &str) -> Result<bool, StoreError> {
+        let mut connection = self.client.get_connection().map_err(StoreError::new)?;
+
+        ::redis::cmd("EXISTS").arg(self.key(key)).query(&mut connection).map_err(StoreError::new)
+    }
+
+    fn revoke(&self, key: &str, ttl: Option<Duration>) -> Result<(), StoreError> {
+        let mut connection = self.client.get_connection().map_err(StoreError::new)?;
+        let mut command = ::redis::cmd("SET");
+        command.arg(self.key(key)).arg(1);
+
+        if let Some(ttl) = ttl {
+            command.arg("PX").arg(ttl.as_millis().max(1) as u64);
+        }
+
+        command.query(&mut connection).map_err(StoreError::new)
+    }
+
+    fn try_revoke(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        let mut connection = self.client.get_connection().map_err(StoreError::new)?;
+        let mut command = ::redis::cmd("SET");
+        command.arg(self.key(key)).arg(1).arg("NX");
+
+        if let Some(ttl) = ttl {
+            command.arg("PX").arg(ttl.as_millis().max(1) as u64);
+        }
+
+        let reply: Option<String> = command.query(&mut connection).map_err(StoreError::new)?;
+        Ok(reply.is_some())
+    }
+}
+
+impl UsageStore for RedisRevocationStore {
+    fn consume(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        self.try_revoke(key, ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::redis::Client;
+    use super::RedisRevocationStore;
+
+    #[test]
+    fn namespace_keys() {
+        let store = RedisRevocationStore::new(Client::open("redis://127.0.0.1/").unwrap());
+        assert_eq!(store.key("abc"), "tokenize:revoked:abc");
+        assert_eq!(store.namespace("app:").key("abc"), "app:abc");
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+use std::fmt;
+use crate::{Token, TokenizeError};
+
+/// A check performed while validating a token, in the order they're performed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ValidationCheck {
+    /// The token has the expected structure, length and encoding.
+    Format,
+    /// The token prefix is one of the configured ones.
+    Prefix,
+    /// The token was signed with a known secret.
+    Signature,
+    /// The token was issued for the expected audience.
+    Audience,
+    /// The token is within its maximum age and clock skew.
+    Ttl,
+    /// The token wasn't revoked.
+    Revocation,
+    /// The account fetcher returned the token's account.
+    Account,
+    /// The token was issued after the account's last token reset.
+    Reset
+}
+
+impl ValidationCheck {
+    pub const ALL: [ValidationCheck; 8] = [
+        ValidationCheck::Format,
+        ValidationCheck::Prefix,
+        ValidationCheck::Signature,
+        ValidationCheck::Audience,
+        ValidationCheck::Ttl,
+        ValidationCheck::Revocation,
+        ValidationCheck::Account,
+        ValidationCheck::Reset
+    ];
+
+    /// The check which rejects tokens with `err`.
+    pub(crate) fn failed_with(err: &TokenizeError) -> ValidationCheck {
+        match err {
+            TokenizeError::PrefixMismatch => ValidationCheck::Prefix,
+            TokenizeError::InvalidSignature | TokenizeError::UnknownKey => ValidationCheck::Signature,
+            TokenizeError::AudienceMismatch => ValidationCheck::Audience,
+            TokenizeError::Expired | TokenizeError::IssuedInFuture => ValidationCheck::Ttl,
+            TokenizeError::Revoked => ValidationCheck::Revocation,
+            TokenizeError::AccountNotFound | TokenizeError::Store(_) => ValidationCheck::Account,
+            TokenizeError::Invalidated => ValidationCheck::Reset,
+            _ => ValidationCheck::Format
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValidationCheck::Format => "format",
+            ValidationCheck::Prefix => "prefix",
+            ValidationCheck::Signature => "signature",
+            ValidationCheck::Audience => "audience",
+            ValidationCheck::Ttl => "ttl",
+            ValidationCheck::Revocation => "revocation",
+            ValidationCheck::Account => "account",
+            ValidationCheck::Reset => "reset"
+        }
+    }
+}
+
+impl fmt::Display for ValidationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The outcome of a [`ValidationCheck`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CheckOutcome {
+    Passed,
+    Failed(TokenizeError),
+    /// The check isn't configured, or wasn't reached because an earlier one failed.
+    Skipped
+}
+
+impl fmt::Display for CheckOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckOutcome::Passed => write!(f, "passed"),
+            CheckOutcome::Failed(err) => write!(f, "failed ({})", err),
+            CheckOutcome::Skipped => write!(f, "skipped")
+        }
+    }
+}
+
+/// Every check performed by [`Tokenize::validate_verbose`](crate::Tokenize::validate_verbose) and
+/// its outcome, explaining why a token was rejected.
+/// 
+/// Its [`Display`](fmt::Display) implementation lists one check per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationReport {
+    checks: Vec<(ValidationCheck, CheckOutcome)>,
+    // Serializing the token would leak its signature
+    #[cfg_attr(feature = "serde", serde(skip))]
+    token: Option<Token>
+}
+
+impl ValidationReport {
+    /// Builds the report of a validation which failed with `err`, or succeeded if it's `None`.
+    /// Checks for which `configured` returns false are skipped.
+    pub(crate) fn new(token: Option<Token>, err: Option<TokenizeError>, configured: impl Fn(ValidationCheck) -> bool) -> ValidationReport {
+        let failed = err.as_ref().map(ValidationCheck::failed_with);
+        let mut err = err;
+        let mut reached = true;
+        let checks = ValidationCheck::ALL.into_iter().map(|check| {
+            let outcome = if Some(check) == failed {
+                reached = false;
+                CheckOutcome::Failed(err.take().expect("The failed check is only reached once"))
+            } else if reached && configured(check) {
+                CheckOutcome::Passed
+            } else {
+                CheckOutcome::Skipped
+            };
+
+            (check, outcome)
+        }).collect();
+
+        ValidationReport { checks, token }
+    }
+
+    /// Whether every check passed or was skipped.
+    pub fn is_valid(&self) -> bool {
+        self.error().is_none()
+    }
+
+    /// The checks, in the order they're performed, along with their outcome.
+    pub fn checks(&self) -> &[(ValidationCheck, CheckOutcome)] {
+        &self.checks
+    }
+
+    pub fn outcome(&self, check: ValidationCheck) -> &CheckOutcome {
+        self.checks.iter()
+            .find(|(c, _)| *c == check)
+            .map(|(_, outcome)| outcome)
+            .expect("Reports contain every check")
+    }
+
+    /// The check which rejected the token, if any.
+    pub fn failed_check(&self) -> Option<ValidationCheck> {
+        self.checks.iter().find(|(_, outcome)| matches!(outcome, CheckOutcome::Failed(_))).map(|(check, _)| *check)
+    }
+
+    /// The error the token was rejected with, if any.
+    pub fn error(&self) -> Option<&TokenizeError> {
+        self.checks.iter().find_map(|(_, outcome)| match outcome {
+            CheckOutcome::Failed(err) => Some(err),
+            _ => None
+        })
+    }
+
+    /// The token, if its signature could be verified.
+    pub fn token(&self) -> Option<&Token> {
+        self.token.as_ref()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (check, outcome) in &self.checks {
+            writeln!(f, "{}: {}", check, outcome)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Revocation of individual tokens.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::clock::{Clock, SystemClock};
+use crate::StoreError;
+
+/// A store recording revoked tokens.
+/// 
+/// Resetting an account invalidates all of its tokens at once. Revocation stores make it possible to
+/// invalidate a single token instead, for instance when logging a session out. Tokens are identified
+/// by their [`Token::revocation_key`](crate::Token::revocation_key).
+pub trait RevocationStore: Send + Sync {
+    /// Whether the token identified by `key` was revoked.
+    fn is_revoked(&self, key: &str) -> Result<bool, StoreError>;
+
+    /// Records the token identified by `key` as revoked. The revocation can be forgotten after `ttl`,
+    /// once the token would be rejected anyway; `None` means it has to be kept forever.
+    fn revoke(&self, key: &str, ttl: Option<Duration>) -> Result<(), StoreError>;
+
+    /// Atomically records the token identified by `key` as revoked, returning `false` if it already
+    /// was. This is how refresh tokens are claimed by [`SessionManager::refresh`](crate::session::SessionManager::refresh).
+    /// 
+    /// The default implementation checks and records the revocation in two steps. Stores shared by
+    /// concurrent validators have to override it with a single atomic operation.
+    fn try_revoke(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        if self.is_revoked(key)? {
+            return Ok(false)
+        }
+
+        self.revoke(key, ttl)?;
+        Ok(true)
+    }
+}
+
+impl<R: RevocationStore + ?Sized> RevocationStore for Arc<R> {
+    fn is_revoked(&self, key: &str) -> Result<bool, StoreError> {
+        (**self).is_revoked(key)
+    }
+
+    fn revoke(&self, key: &str, ttl: Option<Duration>) -> Result<(), StoreError> {
+        (**self).revoke(key, ttl)
+    }
+
+    fn try_revoke(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        (**self).try_revoke(key, ttl)
+    }
+}
+
+/// A store recording consumed single-use tokens, see [`Tokenize::validate_single_use`](crate::Tokenize::validate_single_use).
+pub trait UsageStore: Send + Sync {
+    /// Atomically marks the token identified by `key` as consumed, returning `false` if it already was.
+    /// The usage can be forgotten after `ttl`; `None` means it has to be kept forever.
+    fn consume(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError>;
+}
+
+impl<U: UsageStore + ?Sized> UsageStore for Arc<U> {
+    fn consume(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        (**self).consume(key, ttl)
+    }
+}
+
+/// A [`RevocationStore`] keeping revoked tokens in memory, forgetting each of them once its ttl has elapsed.
+/// 
+/// It can also be used as a [`UsageStore`], consumed tokens being recorded as revoked ones.
+/// 
+/// Expired revocations are dropped whenever a token is revoked. [`MemoryRevocationStore::spawn_expiry`]
+/// additionally purges them periodically from a background thread.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use tokenize::{Tokenize, TokenizeError};
+/// use tokenize::revocation::MemoryRevocationStore;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let store = Arc::new(MemoryRevocationStore::new());
+/// MemoryRevocationStore::spawn_expiry(&store, Duration::from_secs(60));
+/// 
+/// let tokenize = Tokenize::new("uwu").set_revocation_store(store);
+/// let token = tokenize.generate("326359466171826176").unwrap();
+/// tokenize.revoke(&*token).unwrap();
+/// 
+/// assert_eq!(tokenize.validate(token, |_id| None::<MemoryAccount>).err(), Some(TokenizeError::Revoked));
+/// ```
+pub struct MemoryRevocationStore {
+    revoked: Mutex<HashMap<String, Option<i64>>>,
+    clock: Box<dyn Clock>
+}
+
+impl MemoryRevocationStore {
+    pub fn new() -> MemoryRevocationStore {
+        MemoryRevocationStore::with_clock(SystemClock)
+    }
+
+    /// Creates a store measuring ttls with `clock`.
+    pub fn with_clock<C: Clock + 'static>(clock: C) -> MemoryRevocationStore {
+        MemoryRevocationStore {
+            revoked: Mutex::new(HashMap::new()),
+            clock: Box::new(clock)
+        }
+    }
+
+    /// Drops the revocations whose ttl has elapsed.
+    pub fn purge_expired(&self) {
+        let now = self.clock.now_millis();
+        self.lock().retain(|_, expires_at| expires_at.is_none_or(|expires_at| expires_at > now));
+    }
+
+    /// Spawns a thread calling [`MemoryRevocationStore::purge_expired`] every `interval`. The thread
+    /// stops once the store is dropped.
+    pub fn spawn_expiry(store: &Arc<MemoryRevocationStore>, interval: Duration) -> JoinHandle<()> {
+        let store = Arc::downgrade(store);
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match Weak::upgrade(&store) {
+                Some(store) => store.purge_expired(),
+                None => break
+            }
+        })
+    }
+
+    /// Number of revocations held, including expired ones that weren't purged yet.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Option<i64>>> {
+        self.revoked.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Default for MemoryRevocationStore {
+    fn default() -> Self {
+        MemoryRevocationStore::new()
+    }
+}
+
+impl RevocationStore for MemoryRevocationStore {
+    fn is_revoked(&self, key: &str) -> Result<bool, StoreError> {
+        let now = self.clock.now_millis();
+
+        Ok(self.lock().get(key).is_some_and(|expires_at| expires_at.is_none_or(|expires_at| expires_at > now)))
+    }
+
+    fn revoke(&self, key: &str, ttl: Option<Duration>) -> Result<(), StoreError> {
+        let expires_at = ttl.map(|ttl| self.clock.now_millis().saturating_add(ttl.as_millis().try_into().unwrap_or(i64::MAX)));
+
+        self.purge_expired();
+        self.lock().insert(key.to_string(), expires_at);
+        Ok(())
+    }
+
+    fn try_revoke(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        let now = self.clock.now_millis();
+        let expires_at = ttl.map(|ttl| now.saturating_add(ttl.as_millis().try_into().unwrap_or(i64::MAX)));
+
+        self.purge_expired();
+        let mut revoked = self.lock();
+        if revoked.contains_key(key) {
+            return Ok(false)
+        }
+
+        revoked.insert(key.to_string(), expires_at);
+        Ok(true)
+    }
+}
+
+impl UsageStore for MemoryRevocationStore {
+    fn consume(&self, key: &str, ttl: Option<Duration>) -> Result<bool, StoreError> {
+        self.try_revoke(key, ttl)
+    }
+}
@@ -0,0 +1,231 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Handles restricting a [`Tokenize`] instance to either issuing or verifying tokens.
+
+use std::future::Future;
+use std::sync::Arc;
+use crate::{Account, AccountId, AccountStore, AsyncAccountStore, Result, Token, TokenRef, Tokenize};
+
+/// A handle which can only issue tokens.
+/// 
+/// Clones share the same instance. See [`Tokenize::split`].
+#[derive(Clone)]
+pub struct TokenIssuer {
+    tokenize: Arc<Tokenize>
+}
+
+impl TokenIssuer {
+    pub fn new(tokenize: Tokenize) -> TokenIssuer {
+        TokenIssuer { tokenize: Arc::new(tokenize) }
+    }
+
+    /// See [`Tokenize::generate`].
+    pub fn generate<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        self.tokenize.generate(account_id)
+    }
+
+    /// See [`Tokenize::generate_token`].
+    pub fn generate_token<S: Into<AccountId>>(&self, account_id: S) -> Result<Token> {
+        self.tokenize.generate_token(account_id)
+    }
+
+    /// See [`Tokenize::generate_with_prefix`].
+    pub fn generate_with_prefix<P, S>(&self, prefix: P, account_id: S) -> Result<String> where
+        P: AsRef<str>,
+        S: Into<AccountId> {
+        self.tokenize.generate_with_prefix(prefix, account_id)
+    }
+
+    /// See [`Tokenize::generate_bytes`].
+    pub fn generate_bytes<B: AsRef<[u8]>>(&self, account_id: B) -> Result<String> {
+        self.tokenize.generate_bytes(account_id)
+    }
+
+    /// See [`Tokenize::generate_with_scopes`].
+    pub fn generate_with_scopes<S, I>(&self, account_id: S, scopes: I) -> Result<String> where
+        S: Into<AccountId>,
+        I: IntoIterator,
+        I::Item: Into<String> {
+        self.tokenize.generate_with_scopes(account_id, scopes)
+    }
+
+    /// See [`Tokenize::generate_with_claims`].
+    #[cfg(feature = "claims")]
+    pub fn generate_with_claims<S, C>(&self, account_id: S, claims: &C) -> Result<String> where
+        S: Into<AccountId>,
+        C: serde::Serialize + ?Sized {
+        self.tokenize.generate_with_claims(account_id, claims)
+    }
+
+    /// See [`Tokenize::generate_single_use`].
+    pub fn generate_single_use<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        self.tokenize.generate_single_use(account_id)
+    }
+}
+
+impl From<Tokenize> for TokenIssuer {
+    fn from(tokenize: Tokenize) -> Self {
+        TokenIssuer::new(tokenize)
+    }
+}
+
+/// A handle which can only verify tokens, for services which must never mint them.
+/// 
+/// Verifying HMAC signatures requires the same secret as issuing them, so this only prevents minting
+/// tokens through the API. With the [`Ed25519`](crate::signer::Ed25519) signer, the verifier can be
+/// built from public keys alone, which can't be used to mint tokens at all.
+/// 
+/// Clones share the same instance. See [`Tokenize::split`].
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Tokenize, TokenVerifier};
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let (issuer, verifier) = Tokenize::new("uwu").split();
+/// let token = issuer.generate("326359466171826176").expect("Couldn't generate new token");
+/// 
+/// let verifier = TokenVerifier::new(Tokenize::new("uwu"));
+/// verifier.validate(token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 })).expect("Couldn't validate token");
+/// ```
+#[derive(Clone)]
+pub struct TokenVerifier {
+    tokenize: Arc<Tokenize>
+}
+
+impl TokenVerifier {
+    pub fn new(tokenize: Tokenize) -> TokenVerifier {
+        TokenVerifier { tokenize: Arc::new(tokenize) }
+    }
+
+    /// See [`Tokenize::validate`].
+    pub fn validate<S, F, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.tokenize.validate(token, account_fetcher)
+    }
+
+    /// See [`Tokenize::validate_token`].
+    pub fn validate_token<S, F, A>(&self, token: S, account_fetcher: F) -> Result<(Token, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.tokenize.validate_token(token, account_fetcher)
+    }
+
+    /// See [`Tokenize::validate_bytes`].
+    pub fn validate_bytes<S, F, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(&[u8]) -> Option<A>,
+        A: Account {
+        self.tokenize.validate_bytes(token, account_fetcher)
+    }
+
+    /// See [`Tokenize::validate_ref`].
+    pub fn validate_ref<'a, F, A>(&self, token: &'a str, account_fetcher: F) -> Result<(TokenRef<'a>, A)> where
+        F: FnOnce(&str) -> Option<A>,
+        A: Account {
+        self.tokenize.validate_ref(token, account_fetcher)
+    }
+
+    /// See [`Tokenize::validate_single_use`].
+    pub fn validate_single_use<S, F, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.tokenize.validate_single_use(token, account_fetcher)
+    }
+
+    /// See [`Tokenize::validate_with_claims`].
+    #[cfg(feature = "claims")]
+    pub fn validate_with_claims<S, F, A, D>(&self, token: S, account_fetcher: F) -> Result<(A, D)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account,
+        D: serde::de::DeserializeOwned {
+        self.tokenize.validate_with_claims(token, account_fetcher)
+    }
+
+    /// See [`Tokenize::validate_async`].
+    pub async fn validate_async<S, F, Fut, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = Option<A>>,
+        A: Account {
+        self.tokenize.validate_async(token, account_fetcher).await
+    }
+
+    /// See [`Tokenize::validate_with_store`].
+    pub fn validate_with_store<S, St, A>(&self, token: S, store: &St) -> Result<A> where
+        S: Into<String>,
+        St: AccountStore<A> + ?Sized,
+        A: Account {
+        self.tokenize.validate_with_store(token, store)
+    }
+
+    /// See [`Tokenize::validate_with_store_async`].
+    pub async fn validate_with_store_async<S, St, A>(&self, token: S, store: &St) -> Result<A> where
+        S: Into<String>,
+        St: AsyncAccountStore<A> + ?Sized,
+        A: Account {
+        self.tokenize.validate_with_store_async(token, store).await
+    }
+
+    /// See [`Tokenize::parse`].
+    pub fn parse<S: Into<String>>(&self, token: S) -> Result<Token> {
+        self.tokenize.parse(token)
+    }
+
+    /// See [`Tokenize::parse_ref`].
+    pub fn parse_ref<'a>(&self, token: &'a str) -> Result<TokenRef<'a>> {
+        self.tokenize.parse_ref(token)
+    }
+
+    /// See [`Tokenize::revoke`].
+    pub fn revoke<S: Into<String>>(&self, token: S) -> Result<()> {
+        self.tokenize.revoke(token)
+    }
+}
+
+impl From<Tokenize> for TokenVerifier {
+    fn from(tokenize: Tokenize) -> Self {
+        TokenVerifier::new(tokenize)
+    }
+}
+
+impl Tokenize {
+    /// Splits the instance into a handle which can only issue tokens and one which can only verify
+    /// them, sharing the same configuration.
+    pub fn split(self) -> (TokenIssuer, TokenVerifier) {
+        let tokenize = Arc::new(self);
+
+        (TokenIssuer { tokenize: tokenize.clone() }, TokenVerifier { tokenize })
+    }
+}
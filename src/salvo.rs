@@ -0,0 +1,166 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! [Salvo] integration, available with the `salvo` feature.
+//! 
+//! [Salvo]: https://docs.rs/salvo
+
+use std::future::Future;
+use std::sync::Arc;
+use salvo_core::http::StatusCode;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+use crate::extract::{TokenExtractor, TokenSource};
+use crate::{Account, Tokenize};
+
+/// Handler validating the token of incoming requests, to be used as a hoop.
+/// 
+/// On success, the account is injected in the [`Depot`], where handlers can retrieve it through
+/// [`Depot::obtain`]. Requests without a valid token are answered with a `401 Unauthorized` response,
+/// and the rest of the handlers are skipped.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use salvo_core::{handler, Depot, Router};
+/// use tokenize::{Account, Tokenize};
+/// use tokenize::extract::TokenSource;
+/// use tokenize::salvo::TokenizeHandler;
+/// 
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// #[handler]
+/// async fn me(depot: &mut Depot) -> String {
+///     depot.obtain::<User>().unwrap().last_token_reset.to_string()
+/// }
+/// 
+/// let handler = TokenizeHandler::new(Tokenize::new("uwu"), |_id| async {
+///     Some(User { last_token_reset: 0 }) // query your database here
+/// }).fallback_source(TokenSource::Cookie("token".to_string()));
+/// 
+/// let router = Router::new().hoop(handler).push(Router::with_path("me").get(me));
+/// ```
+pub struct TokenizeHandler<F> {
+    tokenize: Arc<Tokenize>,
+    account_fetcher: F,
+    extractor: TokenExtractor
+}
+
+impl<F> TokenizeHandler<F> {
+    /// Creates a handler validating tokens from the `Authorization` header.
+    pub fn new<T: Into<Arc<Tokenize>>>(tokenize: T, account_fetcher: F) -> TokenizeHandler<F> {
+        TokenizeHandler {
+            tokenize: tokenize.into(),
+            account_fetcher,
+            extractor: TokenExtractor::new()
+        }
+    }
+
+    /// Replaces the places the token is looked for.
+    pub fn source(mut self, source: TokenSource) -> Self {
+        self.extractor = self.extractor.source(source);
+        self
+    }
+
+    /// Adds a place the token is looked for. Sources are tried in the order they were added.
+    pub fn fallback_source(mut self, source: TokenSource) -> Self {
+        self.extractor = self.extractor.fallback_source(source);
+        self
+    }
+}
+
+#[async_trait]
+impl<F, Fut, A> Handler for TokenizeHandler<F> where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<A>> + Send,
+    A: Account + Send + Sync + 'static {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let token = self.extractor.extract(|name| req.headers().get(name).and_then(|value| value.to_str().ok()), req.uri().query());
+        let account = match token {
+            Some(token) => self.tokenize.validate_async(token, |id| (self.account_fetcher)(id)).await.ok(),
+            None => None
+        };
+
+        match account {
+            Some(account) => {
+                depot.inject(account);
+            },
+            None => {
+                res.status_code(StatusCode::UNAUTHORIZED);
+                ctrl.skip_rest();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::http::StatusCode;
+    use salvo_core::{Depot, FlowCtrl, Handler, Request, Response};
+    use super::TokenizeHandler;
+    use crate::extract::TokenSource;
+    use crate::{Account, Tokenize};
+
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    const TOKEN: &str = "MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc";
+
+    async fn handle(header: Option<(&'static str, &str)>) -> (Option<StatusCode>, bool) {
+        let handler = TokenizeHandler::new(Tokenize::new("uwu"), |_id| async { Some(TestAccount { last_token_reset: 0 }) })
+            .fallback_source(TokenSource::Cookie("token".to_string()));
+
+        let mut req = Request::new();
+        if let Some((name, value)) = header {
+            req.headers_mut().insert(name, value.parse().unwrap());
+        }
+
+        let (mut depot, mut res, mut ctrl) = (Depot::new(), Response::new(), FlowCtrl::new(Vec::new()));
+        handler.handle(&mut req, &mut depot, &mut res, &mut ctrl).await;
+        (res.status_code, depot.obtain::<TestAccount>().is_ok())
+    }
+
+    #[tokio::test]
+    async fn validate_requests() {
+        assert_eq!(handle(Some(("authorization", &format!("Bearer {}", TOKEN)))).await, (None, true));
+        assert_eq!(handle(Some(("cookie", &format!("token={}", TOKEN)))).await, (None, true));
+        assert_eq!(handle(Some(("authorization", "thisisinvalid"))).await, (Some(StatusCode::UNAUTHORIZED), false));
+        assert_eq!(handle(None).await, (Some(StatusCode::UNAUTHORIZED), false));
+    }
+}
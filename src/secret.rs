@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use std::fmt;
+use crate::{crypto, ConfigError, Encoding};
+
+/// Key material used to sign tokens.
+/// 
+/// The key never shows up in [`Debug`] output, and doesn't implement [`Display`](fmt::Display). With the
+/// `zeroize` feature enabled, the key is wiped from memory when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretKey(Vec<u8>);
+
+impl SecretKey {
+    pub fn new(key: Vec<u8>) -> SecretKey {
+        SecretKey(key)
+    }
+
+    /// Exposes the key material.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Decodes a secret stored as text, such as in an environment variable.
+    /// 
+    /// Values starting with `base64:` or `hex:` are decoded accordingly, other values are used as-is.
+    /// Fails with [`ConfigError::InvalidSecret`] if the value can't be decoded, and with
+    /// [`ConfigError::EmptySecret`] if the secret is empty.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::SecretKey;
+    /// 
+    /// assert_eq!(SecretKey::decode("hex:757775"), Ok(SecretKey::from("uwu")));
+    /// assert_eq!(SecretKey::decode("base64:dXd1"), Ok(SecretKey::from("uwu")));
+    /// assert_eq!(SecretKey::decode("uwu"), Ok(SecretKey::from("uwu")));
+    /// ```
+    pub fn decode(value: &str) -> Result<SecretKey, ConfigError> {
+        let key = if let Some(encoded) = value.strip_prefix("base64:") {
+            Encoding::Standard.decode(encoded).map_err(|_| ConfigError::InvalidSecret)?
+        } else if let Some(encoded) = value.strip_prefix("hex:") {
+            if encoded.len() % 2 != 0 {
+                return Err(ConfigError::InvalidSecret)
+            }
+
+            (0..encoded.len()).step_by(2)
+                .map(|i| encoded.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or(ConfigError::InvalidSecret)?
+        } else {
+            value.as_bytes().to_vec()
+        };
+
+        if key.is_empty() {
+            return Err(ConfigError::EmptySecret)
+        }
+
+        Ok(SecretKey(key))
+    }
+
+    /// Reads a secret from an environment variable, decoding it with [`SecretKey::decode`].
+    /// 
+    /// Fails with [`ConfigError::MissingSecret`] if the variable isn't set.
+    pub fn from_env(var: &str) -> Result<SecretKey, ConfigError> {
+        let value = std::env::var(var).map_err(|_| ConfigError::MissingSecret)?;
+
+        SecretKey::decode(&value)
+    }
+
+    /// Derives a 32 bytes key from this one using HKDF-SHA256 ([RFC 5869](https://www.rfc-editor.org/rfc/rfc5869)),
+    /// with `context` as the info parameter and no salt. Each context yields an independent key.
+    pub fn derive(&self, context: &str) -> SecretKey {
+        self.derive_bytes(context.as_bytes())
+    }
+
+    /// Derives a key from this one with an arbitrary context. See [`SecretKey::derive`].
+    pub(crate) fn derive_bytes(&self, context: &[u8]) -> SecretKey {
+        SecretKey(crypto::hkdf_sha256(&self.0, context).to_vec())
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey([REDACTED])")
+    }
+}
+
+impl From<Vec<u8>> for SecretKey {
+    fn from(key: Vec<u8>) -> Self {
+        SecretKey(key)
+    }
+}
+
+impl From<&[u8]> for SecretKey {
+    fn from(key: &[u8]) -> Self {
+        SecretKey(key.to_vec())
+    }
+}
+
+impl From<&str> for SecretKey {
+    fn from(key: &str) -> Self {
+        SecretKey(key.as_bytes().to_vec())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
@@ -0,0 +1,414 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Issuance of access and refresh token pairs, and tracking of the sessions they belong to.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::{token, Account, AccountId, ConfigError, Result, StoreError, Token, Tokenize, TokenizeError};
+
+/// Separator between the session id and the random part of the nonce of session tokens.
+const SESSION_SEPARATOR: char = '/';
+
+/// An access token along with the refresh token used to renew it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String
+}
+
+/// Issues short-lived access tokens along with longer-lived refresh tokens.
+/// 
+/// Access and refresh tokens are handled by two [`Tokenize`] instances, so each kind can have its own
+/// secret, prefix and maximum age. Refresh tokens are single-use: exchanging one records it in the
+/// [`RevocationStore`](crate::RevocationStore) of the refresh instance, and exchanging it again fails
+/// with [`TokenizeError::Revoked`](crate::TokenizeError::Revoked), hinting that it leaked. The session
+/// it belongs to, if any, is then terminated.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::time::Duration;
+/// use tokenize::{Tokenize, TokenizeError, ValidationOptions};
+/// use tokenize::revocation::MemoryRevocationStore;
+/// use tokenize::session::SessionManager;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let access = Tokenize::new("uwu")
+///     .set_prefix("access")
+///     .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(15 * 60)));
+/// let refresh = Tokenize::new("owo")
+///     .set_prefix("refresh")
+///     .set_revocation_store(MemoryRevocationStore::new())
+///     .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(30 * 24 * 60 * 60)));
+/// 
+/// let sessions = SessionManager::new(access, refresh).expect("Invalid configuration");
+/// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+/// 
+/// let pair = sessions.issue("326359466171826176").expect("Couldn't issue tokens");
+/// let renewed = sessions.refresh(&*pair.refresh_token, account).expect("Couldn't refresh tokens");
+/// sessions.validate(renewed.access_token, account).expect("Couldn't validate token");
+/// 
+/// assert_eq!(sessions.refresh(pair.refresh_token, account).err(), Some(TokenizeError::Revoked));
+/// ```
+pub struct SessionManager {
+    access: Tokenize,
+    refresh: Tokenize,
+    sessions: Option<Box<dyn SessionStore>>
+}
+
+impl SessionManager {
+    /// Creates a session manager issuing access tokens with `access` and refresh tokens with `refresh`.
+    /// 
+    /// # Errors
+    /// 
+    /// Fails if both instances accept a common prefix, or if `refresh` has no revocation store.
+    pub fn new(access: Tokenize, refresh: Tokenize) -> Result<SessionManager, ConfigError> {
+        if access.prefixes.first() == refresh.prefixes.first() || access.prefixes.iter().any(|prefix| refresh.prefixes.contains(prefix)) {
+            return Err(ConfigError::SharedPrefix)
+        }
+
+        if refresh.revocation_store.is_none() {
+            return Err(ConfigError::MissingRevocationStore)
+        }
+
+        Ok(SessionManager { access, refresh, sessions: None })
+    }
+
+    /// The instance access tokens are issued with.
+    pub fn access_tokens(&self) -> &Tokenize {
+        &self.access
+    }
+
+    /// The instance refresh tokens are issued with.
+    pub fn refresh_tokens(&self) -> &Tokenize {
+        &self.refresh
+    }
+
+    /// Sets the store sessions are recorded in, enabling [`SessionManager::login`].
+    pub fn set_session_store<S: SessionStore + 'static>(mut self, store: S) -> Self {
+        self.sessions = Some(Box::new(store));
+        self
+    }
+
+    /// Issues a new token pair for `account_id`, which isn't tied to any session.
+    pub fn issue<S: Into<AccountId>>(&self, account_id: S) -> Result<TokenPair> {
+        self.issue_pair(account_id.into(), None)
+    }
+
+    /// Opens a new session for `account_id` on `device`, such as a user agent, recording it in the
+    /// session store and issuing its token pair.
+    /// 
+    /// Tokens of a session are rejected with [`TokenizeError::Revoked`] once it's terminated.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`TokenizeError::Store`] if no [`SessionStore`] is configured or if it failed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use tokenize::{Tokenize, TokenizeError};
+    /// use tokenize::revocation::MemoryRevocationStore;
+    /// use tokenize::session::{MemorySessionStore, SessionManager};
+    /// use tokenize::store::MemoryAccount;
+    /// 
+    /// let access = Tokenize::new("uwu").set_prefix("access");
+    /// let refresh = Tokenize::new("owo").set_prefix("refresh").set_revocation_store(MemoryRevocationStore::new());
+    /// let sessions = SessionManager::new(access, refresh).expect("Invalid configuration")
+    ///     .set_session_store(MemorySessionStore::new());
+    /// let account = |_id| Some(MemoryAccount { data: (), last_token_reset: 0 });
+    /// 
+    /// let (laptop, _) = sessions.login("326359466171826176", Some("laptop")).expect("Couldn't log in");
+    /// let (phone, pair) = sessions.login("326359466171826176", Some("phone")).expect("Couldn't log in");
+    /// assert_eq!(sessions.sessions("326359466171826176").unwrap().len(), 2);
+    /// 
+    /// // Log out other devices
+    /// sessions.terminate_others("326359466171826176", &phone.id).expect("Couldn't terminate sessions");
+    /// assert_eq!(sessions.sessions("326359466171826176").unwrap(), vec![phone]);
+    /// sessions.validate(pair.access_token, account).expect("Couldn't validate token");
+    /// ```
+    pub fn login<S: Into<AccountId>>(&self, account_id: S, device: Option<&str>) -> Result<(Session, TokenPair)> {
+        let store = self.session_store()?;
+        let account_id = account_id.into();
+        let now = self.access.clock.now_millis();
+        let session = Session {
+            id: token::nonce(),
+            account_id: account_id.as_str().ok_or(TokenizeError::Decode)?.to_string(),
+            device: device.map(str::to_string),
+            created_at: now,
+            last_seen: now
+        };
+
+        store.create(&session)?;
+        let pair = self.issue_pair(account_id, Some(&session.id))?;
+
+        Ok((session, pair))
+    }
+
+    fn issue_pair(&self, account_id: AccountId, session_id: Option<&str>) -> Result<TokenPair> {
+        let nonce = |session_id: Option<&str>| match session_id {
+            Some(session_id) => format!("{}{}{}", session_id, SESSION_SEPARATOR, token::nonce()),
+            None => token::nonce()
+        };
+
+        let mut access_token = self.access.unsigned_token(account_id.clone());
+        if let Some(session_id) = session_id {
+            access_token.nonce = Some(nonce(Some(session_id)));
+        }
+
+        // Refresh tokens are revoked once exchanged, they must never collide with a previous one
+        let mut refresh_token = self.refresh.unsigned_token(account_id);
+        refresh_token.nonce = Some(nonce(session_id));
+
+        Ok(TokenPair {
+            access_token: self.access.sign(access_token).to_string(),
+            refresh_token: self.refresh.sign(refresh_token).to_string()
+        })
+    }
+
+    /// Validates an access token. See [`Tokenize::validate`].
+    /// 
+    /// Tokens tied to a terminated session are rejected with [`TokenizeError::Revoked`], and the
+    /// last activity of the session is updated otherwise.
+    pub fn validate<S, F, A>(&self, access_token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let (token, account) = self.access.validate_token(access_token, account_fetcher)?;
+        self.touch(&token)?;
+
+        Ok(account)
+    }
+
+    /// Validates an access token, returning the session it belongs to along with the account. Tokens
+    /// which aren't tied to any session are rejected with [`TokenizeError::Revoked`].
+    pub fn validate_session<S, F, A>(&self, access_token: S, account_fetcher: F) -> Result<(Session, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let (token, account) = self.access.validate_token(access_token, account_fetcher)?;
+        let session = self.touch(&token)?.ok_or(TokenizeError::Revoked)?;
+
+        Ok((session, account))
+    }
+
+    /// Exchanges a refresh token for a new token pair.
+    /// 
+    /// The account is fetched to make sure the refresh token wasn't invalidated, then the refresh
+    /// token is claimed with [`RevocationStore::try_revoke`](crate::RevocationStore::try_revoke), so
+    /// concurrent exchanges of the same token can't both succeed. The new pair belongs to the same
+    /// session.
+    /// 
+    /// Refresh tokens that were already exchanged are rejected with [`TokenizeError::Revoked`], and
+    /// the session they belong to is terminated as they most likely leaked. Refresh tokens tied to a
+    /// terminated session are rejected with [`TokenizeError::Revoked`] as well.
+    pub fn refresh<S, F, A>(&self, refresh_token: S, account_fetcher: F) -> Result<TokenPair> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let refresh_token = refresh_token.into();
+        let token = match self.refresh.validate_token(&*refresh_token, account_fetcher) {
+            Ok((token, _)) => token,
+            Err(TokenizeError::Revoked) => {
+                self.terminate_reused(&self.refresh.parse(refresh_token)?)?;
+                return Err(TokenizeError::Revoked)
+            },
+            Err(err) => return Err(err)
+        };
+
+        if !self.refresh.claim_token(&token)? {
+            self.terminate_reused(&token)?;
+            return Err(TokenizeError::Revoked)
+        }
+
+        let session = self.touch(&token)?;
+        self.issue_pair(token.account_id, session.as_ref().map(|session| session.id.as_str()))
+    }
+
+    /// Terminates the session of a refresh token which was exchanged again.
+    fn terminate_reused(&self, token: &Token) -> Result<()> {
+        if let Some(session_id) = session_id(token) {
+            self.session_store()?.remove(session_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// The open sessions of `account_id`.
+    pub fn sessions(&self, account_id: &str) -> Result<Vec<Session>> {
+        Ok(self.session_store()?.list(account_id)?)
+    }
+
+    /// Terminates a session, rejecting its tokens from now on. Returns whether it was open.
+    pub fn terminate(&self, session_id: &str) -> Result<bool> {
+        Ok(self.session_store()?.remove(session_id)?)
+    }
+
+    /// Terminates every session of `account_id` except `current_session_id`, logging out the other
+    /// devices. Returns the number of terminated sessions.
+    pub fn terminate_others(&self, account_id: &str, current_session_id: &str) -> Result<usize> {
+        let store = self.session_store()?;
+        let mut terminated = 0;
+        for session in store.list(account_id)? {
+            if session.id != current_session_id && store.remove(&session.id)? {
+                terminated += 1;
+            }
+        }
+
+        Ok(terminated)
+    }
+
+    /// Checks that the session `token` is tied to is still open and records its activity, returning
+    /// it. Tokens which aren't tied to any session are let through.
+    fn touch(&self, token: &Token) -> Result<Option<Session>> {
+        let session_id = match session_id(token) {
+            Some(session_id) => session_id,
+            None => return Ok(None)
+        };
+
+        let store = self.session_store()?;
+        let mut session = match store.get(session_id)? {
+            Some(session) if token.account_id.as_str() == Some(&session.account_id) => session,
+            _ => return Err(TokenizeError::Revoked)
+        };
+
+        session.last_seen = self.access.clock.now_millis();
+        store.touch(session_id, session.last_seen)?;
+
+        Ok(Some(session))
+    }
+
+    fn session_store(&self) -> Result<&dyn SessionStore> {
+        Ok(self.sessions.as_deref().ok_or_else(|| StoreError::new("No session store is configured"))?)
+    }
+}
+
+/// The id of the session `token` is tied to, if there's one.
+fn session_id(token: &Token) -> Option<&str> {
+    let (session_id, _) = token.nonce.as_deref()?.split_once(SESSION_SEPARATOR)?;
+    Some(session_id)
+}
+
+/// A session opened through [`SessionManager::login`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    pub id: String,
+    pub account_id: String,
+    /// The device the session was opened on, as given to [`SessionManager::login`].
+    pub device: Option<String>,
+    /// Unix timestamp in milliseconds at which the session was opened.
+    pub created_at: i64,
+    /// Unix timestamp in milliseconds at which one of the session tokens was last validated.
+    pub last_seen: i64
+}
+
+/// A store recording the sessions opened through a [`SessionManager`].
+pub trait SessionStore: Send + Sync {
+    fn create(&self, session: &Session) -> Result<(), StoreError>;
+
+    fn get(&self, id: &str) -> Result<Option<Session>, StoreError>;
+
+    /// Updates the last activity of a session. Sessions that don't exist anymore are ignored.
+    fn touch(&self, id: &str, last_seen: i64) -> Result<(), StoreError>;
+
+    /// The sessions of an account, in any order.
+    fn list(&self, account_id: &str) -> Result<Vec<Session>, StoreError>;
+
+    /// Removes a session, returning whether it existed.
+    fn remove(&self, id: &str) -> Result<bool, StoreError>;
+}
+
+impl<S: SessionStore + ?Sized> SessionStore for Arc<S> {
+    fn create(&self, session: &Session) -> Result<(), StoreError> {
+        (**self).create(session)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Session>, StoreError> {
+        (**self).get(id)
+    }
+
+    fn touch(&self, id: &str, last_seen: i64) -> Result<(), StoreError> {
+        (**self).touch(id, last_seen)
+    }
+
+    fn list(&self, account_id: &str) -> Result<Vec<Session>, StoreError> {
+        (**self).list(account_id)
+    }
+
+    fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        (**self).remove(id)
+    }
+}
+
+/// A [`SessionStore`] keeping sessions in memory, listing them from the oldest to the newest.
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, Session>>
+}
+
+impl MemorySessionStore {
+    pub fn new() -> MemorySessionStore {
+        MemorySessionStore::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Session>> {
+        self.sessions.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn create(&self, session: &Session) -> Result<(), StoreError> {
+        self.lock().insert(session.id.clone(), session.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Session>, StoreError> {
+        Ok(self.lock().get(id).cloned())
+    }
+
+    fn touch(&self, id: &str, last_seen: i64) -> Result<(), StoreError> {
+        if let Some(session) = self.lock().get_mut(id) {
+            session.last_seen = last_seen;
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, account_id: &str) -> Result<Vec<Session>, StoreError> {
+        let mut sessions = self.lock().values().filter(|session| session.account_id == account_id).cloned().collect::<Vec<Session>>();
+        sessions.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+        Ok(sessions)
+    }
+
+    fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        Ok(self.lock().remove(id).is_some())
+    }
+}
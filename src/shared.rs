@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! A [`Tokenize`] instance shared across threads, which can be replaced at runtime.
+
+use std::sync::Arc;
+use std::time::Duration;
+use arc_swap::ArcSwap;
+use crate::keyloading::{KeyRefresher, KeySource};
+use crate::{Account, AccountId, Result, SecretKey, Token, Tokenize};
+
+/// A handle to a [`Tokenize`] instance that can be atomically replaced, e.g. after rotating its
+/// secret.
+/// 
+/// Clones share the same instance. Each call works on a snapshot of the instance taken when it
+/// starts, so validations in flight while it's replaced finish with the configuration they started
+/// with, and never see a mix of the old and new ones. Taking a snapshot doesn't lock.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::Tokenize;
+/// use tokenize::shared::SharedTokenize;
+/// use tokenize::store::MemoryAccount;
+/// 
+/// let shared = SharedTokenize::new(Tokenize::new("uwu"));
+/// let token = shared.generate("326359466171826176").expect("Couldn't generate new token");
+/// 
+/// shared.store(Tokenize::with_secrets("owo", ["uwu"]));
+/// shared.validate(token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 })).expect("Couldn't validate token");
+/// ```
+#[derive(Clone)]
+pub struct SharedTokenize {
+    current: Arc<ArcSwap<Tokenize>>
+}
+
+impl SharedTokenize {
+    pub fn new(tokenize: Tokenize) -> SharedTokenize {
+        SharedTokenize { current: Arc::new(ArcSwap::from_pointee(tokenize)) }
+    }
+
+    /// Returns a snapshot of the current instance, unaffected by later replacements.
+    pub fn load(&self) -> Arc<Tokenize> {
+        self.current.load_full()
+    }
+
+    /// Replaces the current instance. Calls already running keep using the previous one.
+    pub fn store(&self, tokenize: Tokenize) {
+        self.current.store(Arc::new(tokenize));
+    }
+
+    /// Replaces the current instance, returning the previous one.
+    pub fn swap(&self, tokenize: Tokenize) -> Arc<Tokenize> {
+        self.current.swap(Arc::new(tokenize))
+    }
+
+    /// Reloads the secret from `source` every `interval`, replacing the current instance with the
+    /// one `build` creates from it whenever it changes. See [`KeyRefresher`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use tokenize::Tokenize;
+    /// use tokenize::keyloading::EnvKeySource;
+    /// use tokenize::shared::SharedTokenize;
+    /// 
+    /// let shared = SharedTokenize::new(Tokenize::from_env("TOKENIZE_SECRET").expect("Invalid secret"));
+    /// let refresher = shared.refresh_from(EnvKeySource::new("TOKENIZE_SECRET"), Duration::from_secs(300), |secret| {
+    ///     Tokenize::new(secret).set_prefix("app")
+    /// });
+    /// ```
+    pub fn refresh_from<K, F>(&self, source: K, interval: Duration, build: F) -> KeyRefresher where
+        K: KeySource + 'static,
+        F: Fn(SecretKey) -> Tokenize + Send + 'static {
+        let current = self.current.clone();
+        KeyRefresher::spawn(source, interval, move |secret| current.store(Arc::new(build(secret))))
+    }
+
+    /// Generates a new token with the current instance. See [`Tokenize::generate`].
+    pub fn generate<S: Into<AccountId>>(&self, account_id: S) -> Result<String> {
+        self.current.load().generate(account_id)
+    }
+
+    /// Validates a token with the current instance. See [`Tokenize::validate`].
+    pub fn validate<S, F, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.current.load().validate(token, account_fetcher)
+    }
+
+    /// Validates a token with the current instance, returning it along with the account. See
+    /// [`Tokenize::validate_token`].
+    pub fn validate_token<S, F, A>(&self, token: S, account_fetcher: F) -> Result<(Token, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.current.load().validate_token(token, account_fetcher)
+    }
+
+    /// Parses a token with the current instance. See [`Tokenize::parse`].
+    pub fn parse<S: Into<String>>(&self, token: S) -> Result<Token> {
+        self.current.load().parse(token)
+    }
+}
+
+impl From<Tokenize> for SharedTokenize {
+    fn from(tokenize: Tokenize) -> Self {
+        SharedTokenize::new(tokenize)
+    }
+}
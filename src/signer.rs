@@ -0,0 +1,238 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! Algorithms used to sign tokens.
+
+use crate::{crypto, Encoding};
+
+/// An algorithm used to compute the signature of a token.
+/// 
+/// The message handed to the signer always starts with the `TTF.<version>.` header mandated by the
+/// specification, whatever the algorithm, so the version field stays consistent on the wire.
+pub trait Signer: Send + Sync {
+    fn sign(&self, secret: &[u8], message: &[u8]) -> Vec<u8>;
+
+    /// Length of the signatures in bytes, used to size buffers. Defaults to 64, the length of the
+    /// longest built-in signatures.
+    fn signature_len(&self) -> usize {
+        64
+    }
+
+    /// Signs the concatenation of `parts`.
+    /// 
+    /// The default implementation concatenates the parts and signs them with [`Signer::sign`]. The
+    /// built-in HMAC and BLAKE3 signers override it to sign tokens without allocating.
+    fn sign_parts(&self, secret: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+        self.sign(secret, &parts.concat())
+    }
+
+    /// Checks in constant time that `signature`, encoded with `encoding`, is the signature of the
+    /// concatenation of `parts`.
+    /// 
+    /// The default implementation concatenates the parts and signs them with [`Signer::sign`]. The
+    /// built-in signers override it to verify signatures without allocating.
+    fn verify(&self, secret: &[u8], parts: &[&[u8]], signature: &str, encoding: Encoding) -> bool {
+        encoding.matches(&self.sign(secret, &parts.concat()), signature)
+    }
+}
+
+/// HMAC-SHA256, the algorithm mandated by the specification. This is the default signer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HmacSha256;
+
+impl Signer for HmacSha256 {
+    fn signature_len(&self) -> usize {
+        32
+    }
+
+    fn sign(&self, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        crypto::hmac_sha256(secret, message).to_vec()
+    }
+
+    fn sign_parts(&self, secret: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+        let mut hmac = hmac_sha256::HMAC::new(secret);
+        parts.iter().for_each(|part| hmac.update(part));
+
+        hmac.finalize().to_vec()
+    }
+
+    fn verify(&self, secret: &[u8], parts: &[&[u8]], signature: &str, encoding: Encoding) -> bool {
+        let mut hmac = hmac_sha256::HMAC::new(secret);
+        parts.iter().for_each(|part| hmac.update(part));
+
+        encoding.matches(&hmac.finalize(), signature)
+    }
+}
+
+/// HMAC-SHA512.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HmacSha512;
+
+impl Signer for HmacSha512 {
+    fn signature_len(&self) -> usize {
+        64
+    }
+
+    fn sign(&self, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        hmac_sha512::HMAC::mac(message, secret).to_vec()
+    }
+
+    fn sign_parts(&self, secret: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+        let mut hmac = hmac_sha512::HMAC::new(secret);
+        parts.iter().for_each(|part| hmac.update(part));
+
+        hmac.finalize().to_vec()
+    }
+
+    fn verify(&self, secret: &[u8], parts: &[&[u8]], signature: &str, encoding: Encoding) -> bool {
+        let mut hmac = hmac_sha512::HMAC::new(secret);
+        parts.iter().for_each(|part| hmac.update(part));
+
+        encoding.matches(&hmac.finalize(), signature)
+    }
+}
+
+/// Keyed BLAKE3. The 32 bytes key is derived from the secret using BLAKE3's key derivation mode.
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3;
+
+#[cfg(feature = "blake3")]
+impl Signer for Blake3 {
+    fn signature_len(&self) -> usize {
+        32
+    }
+
+    fn sign(&self, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        let key = blake3::derive_key("tokenize-rs 2022-01-01 token signing key", secret);
+        blake3::keyed_hash(&key, message).as_bytes().to_vec()
+    }
+
+    fn sign_parts(&self, secret: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+        let key = blake3::derive_key("tokenize-rs 2022-01-01 token signing key", secret);
+        let mut hasher = blake3::Hasher::new_keyed(&key);
+        parts.iter().for_each(|part| { hasher.update(part); });
+
+        hasher.finalize().as_bytes().to_vec()
+    }
+
+    fn verify(&self, secret: &[u8], parts: &[&[u8]], signature: &str, encoding: Encoding) -> bool {
+        let key = blake3::derive_key("tokenize-rs 2022-01-01 token signing key", secret);
+        let mut hasher = blake3::Hasher::new_keyed(&key);
+        parts.iter().for_each(|part| { hasher.update(part); });
+
+        encoding.matches(hasher.finalize().as_bytes(), signature)
+    }
+}
+
+/// Ed25519 signatures, available with the `ed25519` feature.
+/// 
+/// The secrets handed to this signer are 32 bytes Ed25519 keys: private keys for instances built
+/// with [`Ed25519::signing`], which issue and verify tokens, and public keys for instances built with
+/// [`Ed25519::verifying`], which can only verify them. Services validating tokens thus don't need
+/// to hold anything allowing them to issue tokens. Keyrings and old secrets work the same way, with
+/// keys of the matching kind.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::Tokenize;
+/// use tokenize::signer::Ed25519;
+/// 
+/// let private_key = [7; 32];
+/// let issuer = Tokenize::new(&private_key[..]).set_signer(Ed25519::signing());
+/// let token = issuer.generate("326359466171826176").expect("Couldn't generate new token");
+/// 
+/// let public_key = Ed25519::public_key(&private_key).expect("Invalid private key");
+/// let validator = Tokenize::new(&public_key[..]).set_signer(Ed25519::verifying());
+/// assert!(validator.parse(token).is_ok());
+/// ```
+#[cfg(feature = "ed25519")]
+#[derive(Debug, Clone, Copy)]
+pub struct Ed25519 {
+    signing: bool
+}
+
+#[cfg(feature = "ed25519")]
+impl Ed25519 {
+    /// Signer whose secrets are private keys.
+    pub fn signing() -> Ed25519 {
+        Ed25519 { signing: true }
+    }
+
+    /// Signer whose secrets are public keys. It can only verify tokens.
+    pub fn verifying() -> Ed25519 {
+        Ed25519 { signing: false }
+    }
+
+    /// The public key matching a private key, or `None` if it isn't 32 bytes long.
+    pub fn public_key(private_key: &[u8]) -> Option<[u8; 32]> {
+        let private_key = ed25519_dalek::SecretKey::try_from(private_key).ok()?;
+
+        Some(ed25519_dalek::SigningKey::from_bytes(&private_key).verifying_key().to_bytes())
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl Signer for Ed25519 {
+    fn signature_len(&self) -> usize {
+        64
+    }
+
+    /// # Panics
+    /// 
+    /// Panics if the signer only verifies tokens, or if the secret isn't 32 bytes long.
+    fn sign(&self, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        use ed25519_dalek::Signer as _;
+
+        assert!(self.signing, "Ed25519 verifying signers can't sign tokens");
+        let private_key = ed25519_dalek::SecretKey::try_from(secret).expect("Ed25519 private keys are 32 bytes long");
+
+        ed25519_dalek::SigningKey::from_bytes(&private_key).sign(message).to_bytes().to_vec()
+    }
+
+    fn verify(&self, secret: &[u8], parts: &[&[u8]], signature: &str, encoding: Encoding) -> bool {
+        let public_key = match self.signing {
+            true => Ed25519::public_key(secret),
+            false => secret.try_into().ok()
+        };
+
+        let mut buffer = [0; 66];
+        let verifying_key = public_key.and_then(|public_key| ed25519_dalek::VerifyingKey::from_bytes(&public_key).ok());
+        // Only the canonical encoding is accepted, padded or otherwise re-encoded signatures would be
+        // distinct tokens with the same signature
+        let signature = encoding.decode_slice(signature, &mut buffer).ok()
+            .filter(|decoded| encoding.matches(decoded, signature))
+            .and_then(|signature| ed25519_dalek::Signature::from_slice(signature).ok());
+
+        match (verifying_key, signature) {
+            (Some(verifying_key), Some(signature)) => verifying_key.verify_strict(&parts.concat(), &signature).is_ok(),
+            _ => false
+        }
+    }
+}
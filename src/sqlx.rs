@@ -0,0 +1,165 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! [sqlx] integration, available with the `sqlx` feature.
+//! 
+//! [sqlx]: https://docs.rs/sqlx
+
+use ::sqlx::{ColumnIndex, Database, Decode, Encode, Executor, IntoArguments, Pool, Type};
+use crate::{Account, AsyncAccountStore, StoreError};
+
+/// An account fetched by a [`SqlxAccountStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlxAccount {
+    pub id: String,
+    pub last_token_reset: u64
+}
+
+impl Account for SqlxAccount {
+    fn last_token_reset(&self) -> u64 {
+        self.last_token_reset
+    }
+}
+
+/// An [`AsyncAccountStore`] fetching accounts from a Postgres, MySQL or SQLite database.
+/// 
+/// Accounts are read from the `accounts` table by default, matching the token's account id against
+/// the `id` column and reading the last token reset, in milliseconds, from the `last_token_reset`
+/// column. The id is bound as text, and the last token reset is read as a 64-bit integer.
+/// 
+/// Table and column names are inserted in the query as is, so they must never come from user input.
+/// 
+/// # Examples
+/// 
+/// ```no_run
+/// use sqlx::SqlitePool;
+/// use tokenize::Tokenize;
+/// use tokenize::sqlx::SqlxAccountStore;
+/// 
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = SqlitePool::connect("sqlite://app.db").await?;
+/// let store = SqlxAccountStore::new(pool).table("users").last_token_reset_column("tokens_reset_at");
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let account = tokenize.validate_with_store_async("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", &store).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SqlxAccountStore<DB: Database> {
+    pool: Pool<DB>,
+    table: String,
+    id_column: String,
+    last_token_reset_column: String
+}
+
+impl<DB: Database> SqlxAccountStore<DB> {
+    pub fn new(pool: Pool<DB>) -> SqlxAccountStore<DB> {
+        SqlxAccountStore {
+            pool,
+            table: "accounts".to_string(),
+            id_column: "id".to_string(),
+            last_token_reset_column: "last_token_reset".to_string()
+        }
+    }
+
+    /// Sets the table accounts are read from. Defaults to `accounts`.
+    pub fn table<S: Into<String>>(mut self, table: S) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Sets the column holding account ids. Defaults to `id`.
+    pub fn id_column<S: Into<String>>(mut self, column: S) -> Self {
+        self.id_column = column.into();
+        self
+    }
+
+    /// Sets the column holding last token resets. Defaults to `last_token_reset`.
+    pub fn last_token_reset_column<S: Into<String>>(mut self, column: S) -> Self {
+        self.last_token_reset_column = column.into();
+        self
+    }
+
+    pub fn pool(&self) -> &Pool<DB> {
+        &self.pool
+    }
+
+    fn query(&self) -> String {
+        // Postgres only understands numbered placeholders, which MySQL doesn't support
+        let placeholder = if DB::NAME == "PostgreSQL" { "$1" } else { "?" };
+
+        format!("SELECT {} FROM {} WHERE {} = {}", self.last_token_reset_column, self.table, self.id_column, placeholder)
+    }
+}
+
+impl<DB> AsyncAccountStore<SqlxAccount> for SqlxAccountStore<DB> where
+    DB: Database,
+    for<'c> &'c Pool<DB>: Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    for<'q> String: Encode<'q, DB>,
+    String: Type<DB>,
+    for<'r> i64: Decode<'r, DB>,
+    i64: Type<DB>,
+    usize: ColumnIndex<DB::Row> {
+    async fn fetch(&self, id: &str) -> Result<Option<SqlxAccount>, StoreError> {
+        let query = self.query();
+        let last_token_reset = ::sqlx::query_scalar::<DB, i64>(&query)
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(StoreError::new)?;
+
+        Ok(last_token_reset.map(|last_token_reset| SqlxAccount { id: id.to_string(), last_token_reset: last_token_reset as u64 }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::sqlx::SqlitePool;
+    use super::SqlxAccountStore;
+    use crate::{Tokenize, TokenizeError};
+
+    #[tokio::test]
+    async fn fetch_accounts() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        ::sqlx::query("CREATE TABLE users (snowflake TEXT PRIMARY KEY, last_token_reset INTEGER NOT NULL)").execute(&pool).await.unwrap();
+        ::sqlx::query("INSERT INTO users VALUES ('326359466171826176', 1641641228500), ('1', 0)").execute(&pool).await.unwrap();
+
+        let store = SqlxAccountStore::new(pool).table("users").id_column("snowflake");
+        let tokenize = Tokenize::new("uwu");
+        assert_eq!(tokenize.validate_with_store_async("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc", &store).await.err(), Some(TokenizeError::Invalidated));
+
+        let account = tokenize.validate_with_store_async(tokenize.generate("1").unwrap(), &store).await.expect("Couldn't validate token");
+        assert_eq!(account.id, "1");
+        assert_eq!(tokenize.validate_with_store_async(tokenize.generate("2").unwrap(), &store).await.err(), Some(TokenizeError::AccountNotFound));
+
+        store.pool().close().await;
+        assert!(matches!(tokenize.validate_with_store_async(tokenize.generate("1").unwrap(), &store).await, Err(TokenizeError::Store(_))));
+    }
+}
@@ -0,0 +1,343 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Sources of the accounts tokens belong to.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+use crate::{platform, Account, MutableAccount, StoreError};
+
+/// A source of accounts, such as a database, a cache or an in-memory map.
+/// 
+/// Stores are used through [`Tokenize::validate_with_store`](crate::Tokenize::validate_with_store).
+/// Unlike the closures accepted by [`Tokenize::validate`](crate::Tokenize::validate), they can report
+/// failures separately from missing accounts.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::collections::HashMap;
+/// use tokenize::{Account, AccountStore, StoreError};
+/// 
+/// #[derive(Clone)]
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// struct Users(HashMap<String, User>);
+/// 
+/// impl AccountStore<User> for Users {
+///     fn fetch(&self, id: &str) -> Result<Option<User>, StoreError> {
+///         Ok(self.0.get(id).cloned())
+///     }
+/// }
+/// ```
+pub trait AccountStore<A: Account> {
+    /// Fetches the account tied to `id`, returning `Ok(None)` if there's none.
+    fn fetch(&self, id: &str) -> Result<Option<A>, StoreError>;
+}
+
+impl<A: Account, S: AccountStore<A> + ?Sized> AccountStore<A> for &S {
+    fn fetch(&self, id: &str) -> Result<Option<A>, StoreError> {
+        (**self).fetch(id)
+    }
+}
+
+impl<A: Account, S: AccountStore<A> + ?Sized> AccountStore<A> for Box<S> {
+    fn fetch(&self, id: &str) -> Result<Option<A>, StoreError> {
+        (**self).fetch(id)
+    }
+}
+
+impl<A: Account, S: AccountStore<A> + ?Sized> AccountStore<A> for Arc<S> {
+    fn fetch(&self, id: &str) -> Result<Option<A>, StoreError> {
+        (**self).fetch(id)
+    }
+}
+
+/// Asynchronous counterpart of [`AccountStore`], for stores backed by async drivers.
+/// 
+/// Stores are used through [`Tokenize::validate_with_store_async`](crate::Tokenize::validate_with_store_async).
+pub trait AsyncAccountStore<A: Account> {
+    /// Fetches the account tied to `id`, returning `Ok(None)` if there's none.
+    fn fetch(&self, id: &str) -> impl Future<Output = Result<Option<A>, StoreError>> + Send;
+}
+
+impl<A: Account, S: AsyncAccountStore<A> + ?Sized> AsyncAccountStore<A> for &S {
+    fn fetch(&self, id: &str) -> impl Future<Output = Result<Option<A>, StoreError>> + Send {
+        (**self).fetch(id)
+    }
+}
+
+impl<A: Account, S: AsyncAccountStore<A> + ?Sized> AsyncAccountStore<A> for Arc<S> {
+    fn fetch(&self, id: &str) -> impl Future<Output = Result<Option<A>, StoreError>> + Send {
+        (**self).fetch(id)
+    }
+}
+
+/// A source of accounts able to fetch many of them at once, such as a database queried with a
+/// single `IN` clause.
+/// 
+/// Stores are used through [`Tokenize::validate_batch`](crate::Tokenize::validate_batch).
+pub trait BatchAccountStore<A: Account> {
+    /// Fetches the accounts tied to `ids`, keyed by id. Ids without an account are left out.
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, A>, StoreError>;
+}
+
+impl<A: Account, S: BatchAccountStore<A> + ?Sized> BatchAccountStore<A> for &S {
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        (**self).fetch_many(ids)
+    }
+}
+
+impl<A: Account, S: BatchAccountStore<A> + ?Sized> BatchAccountStore<A> for Box<S> {
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        (**self).fetch_many(ids)
+    }
+}
+
+impl<A: Account, S: BatchAccountStore<A> + ?Sized> BatchAccountStore<A> for Arc<S> {
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        (**self).fetch_many(ids)
+    }
+}
+
+/// A source of accounts split across shards, the shard holding an account being determined by its id.
+/// 
+/// Wrapped in [`Sharded`], it's used through [`Tokenize::validate_batch`](crate::Tokenize::validate_batch),
+/// which then queries each shard once with the ids it holds.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::collections::HashMap;
+/// use tokenize::{ShardedAccountStore, StoreError, Tokenize};
+/// use tokenize::store::{MemoryAccount, Sharded};
+/// 
+/// struct Users;
+/// 
+/// impl ShardedAccountStore<MemoryAccount> for Users {
+///     type Shard = u64;
+/// 
+///     fn route(&self, id: &str) -> u64 {
+///         id.parse::<u64>().unwrap_or(0) % 4
+///     }
+/// 
+///     fn fetch_shard(&self, shard: &u64, ids: &[&str]) -> Result<HashMap<String, MemoryAccount>, StoreError> {
+///         // query the database of `shard` here
+///         Ok(ids.iter().map(|&id| (id.to_owned(), MemoryAccount { data: (), last_token_reset: 0 })).collect())
+///     }
+/// }
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let tokens = [tokenize.generate("326359466171826176").unwrap(), tokenize.generate("1").unwrap()];
+/// assert!(tokenize.validate_batch(&tokens, &Sharded(Users)).iter().all(Result::is_ok));
+/// ```
+pub trait ShardedAccountStore<A: Account> {
+    /// Identifies a shard.
+    type Shard: Eq + Hash;
+
+    /// The shard holding the account tied to `id`.
+    fn route(&self, id: &str) -> Self::Shard;
+
+    /// Fetches the accounts tied to `ids` from `shard`, keyed by id. Ids without an account are left out.
+    fn fetch_shard(&self, shard: &Self::Shard, ids: &[&str]) -> Result<HashMap<String, A>, StoreError>;
+}
+
+impl<A: Account, S: ShardedAccountStore<A> + ?Sized> ShardedAccountStore<A> for &S {
+    type Shard = S::Shard;
+
+    fn route(&self, id: &str) -> S::Shard {
+        (**self).route(id)
+    }
+
+    fn fetch_shard(&self, shard: &S::Shard, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        (**self).fetch_shard(shard, ids)
+    }
+}
+
+impl<A: Account, S: ShardedAccountStore<A> + ?Sized> ShardedAccountStore<A> for Arc<S> {
+    type Shard = S::Shard;
+
+    fn route(&self, id: &str) -> S::Shard {
+        (**self).route(id)
+    }
+
+    fn fetch_shard(&self, shard: &S::Shard, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        (**self).fetch_shard(shard, ids)
+    }
+}
+
+/// Adapts a [`ShardedAccountStore`] to [`BatchAccountStore`] and [`AccountStore`], grouping the ids
+/// of a batch per shard.
+/// 
+/// Shards are queried one after the other, and a batch fails as soon as one of them does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sharded<S>(pub S);
+
+impl<A: Account, S: ShardedAccountStore<A>> BatchAccountStore<A> for Sharded<S> {
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, A>, StoreError> {
+        let mut shards = HashMap::<S::Shard, Vec<&str>>::new();
+        for &id in ids {
+            shards.entry(self.0.route(id)).or_default().push(id);
+        }
+
+        let mut accounts = HashMap::with_capacity(ids.len());
+        for (shard, ids) in shards {
+            accounts.extend(self.0.fetch_shard(&shard, &ids)?);
+        }
+
+        Ok(accounts)
+    }
+}
+
+impl<A: Account, S: ShardedAccountStore<A>> AccountStore<A> for Sharded<S> {
+    fn fetch(&self, id: &str) -> Result<Option<A>, StoreError> {
+        Ok(self.0.fetch_shard(&self.0.route(id), &[id])?.remove(id))
+    }
+}
+
+/// An account held by a [`MemoryAccountStore`], along with its last token reset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAccount<A = ()> {
+    pub data: A,
+    pub last_token_reset: u64
+}
+
+impl<A> Account for MemoryAccount<A> {
+    fn last_token_reset(&self) -> u64 {
+        self.last_token_reset
+    }
+}
+
+impl<A> MutableAccount for MemoryAccount<A> {
+    fn set_last_token_reset(&mut self, last_token_reset: u64) {
+        self.last_token_reset = last_token_reset;
+    }
+}
+
+/// An [`AccountStore`] keeping accounts in memory, for tests and prototypes.
+/// 
+/// Accounts can be inserted and their tokens reset through a shared reference, so the store can
+/// keep being used after handing it to a server.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Tokenize, TokenizeError};
+/// use tokenize::store::MemoryAccountStore;
+/// 
+/// let tokenize = Tokenize::new("uwu");
+/// let store = MemoryAccountStore::new();
+/// store.insert("326359466171826176", "owo");
+/// 
+/// let token = tokenize.generate("326359466171826176").unwrap();
+/// assert_eq!(tokenize.validate_with_store(&*token, &store).unwrap().data, "owo");
+/// 
+/// store.set_last_token_reset("326359466171826176", 4102444800000);
+/// assert_eq!(tokenize.validate_with_store(token, &store).err(), Some(TokenizeError::Invalidated));
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryAccountStore<A = ()> {
+    accounts: RwLock<HashMap<String, MemoryAccount<A>>>
+}
+
+impl<A> MemoryAccountStore<A> {
+    pub fn new() -> MemoryAccountStore<A> {
+        MemoryAccountStore { accounts: RwLock::new(HashMap::new()) }
+    }
+
+    /// Inserts an account whose tokens have never been reset, replacing any account with the same id.
+    pub fn insert<S: Into<String>>(&self, id: S, data: A) {
+        self.insert_account(id, MemoryAccount { data, last_token_reset: 0 });
+    }
+
+    /// Inserts an account, replacing any account with the same id.
+    pub fn insert_account<S: Into<String>>(&self, id: S, account: MemoryAccount<A>) {
+        self.write().insert(id.into(), account);
+    }
+
+    pub fn remove(&self, id: &str) -> Option<MemoryAccount<A>> {
+        self.write().remove(id)
+    }
+
+    /// Invalidates every token of an account issued until now. Returns `false` if there's no such account.
+    pub fn reset_tokens(&self, id: &str) -> bool {
+        self.set_last_token_reset(id, platform::now_millis() as u64)
+    }
+
+    /// Sets the last token reset of an account, in milliseconds. Returns `false` if there's no such account.
+    pub fn set_last_token_reset(&self, id: &str, last_token_reset: u64) -> bool {
+        match self.write().get_mut(id) {
+            Some(account) => {
+                account.last_token_reset = last_token_reset;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.read().unwrap_or_else(|err| err.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, MemoryAccount<A>>> {
+        self.accounts.write().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<A: Clone> AccountStore<MemoryAccount<A>> for MemoryAccountStore<A> {
+    fn fetch(&self, id: &str) -> Result<Option<MemoryAccount<A>>, StoreError> {
+        let accounts = self.accounts.read().map_err(|_| StoreError::new("Account store is poisoned"))?;
+
+        Ok(accounts.get(id).cloned())
+    }
+}
+
+impl<A: Clone + Send + Sync> AsyncAccountStore<MemoryAccount<A>> for MemoryAccountStore<A> {
+    async fn fetch(&self, id: &str) -> Result<Option<MemoryAccount<A>>, StoreError> {
+        AccountStore::fetch(self, id)
+    }
+}
+
+impl<A: Clone> BatchAccountStore<MemoryAccount<A>> for MemoryAccountStore<A> {
+    fn fetch_many(&self, ids: &[&str]) -> Result<HashMap<String, MemoryAccount<A>>, StoreError> {
+        let accounts = self.accounts.read().map_err(|_| StoreError::new("Account store is poisoned"))?;
+
+        Ok(ids.iter().filter_map(|&id| Some((id.to_owned(), accounts.get(id)?.clone()))).collect())
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Validation of tokens issued for several tenants.
+
+use std::collections::HashMap;
+
+use crate::{Account, AccountId, ConfigError, Result, Token, Tokenize, TokenizeError};
+
+/// A handle to a tenant of a [`TenantRegistry`], used to generate its tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tenant {
+    prefix: String
+}
+
+impl Tenant {
+    /// The prefix the tenant's tokens are generated with.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+/// Maps token prefixes to the [`Tokenize`] instance of the tenant they belong to.
+/// 
+/// Each tenant has its own secret, epoch and validation options. Validating a token reads its
+/// prefix and hands it to the matching tenant, tokens with an unknown prefix are rejected with
+/// [`TokenizeError::PrefixMismatch`].
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::time::Duration;
+/// use tokenize::{Tokenize, ValidationOptions};
+/// use tokenize::store::MemoryAccount;
+/// use tokenize::tenant::TenantRegistry;
+/// 
+/// let mut registry = TenantRegistry::new();
+/// let acme = registry.insert(Tokenize::new("uwu").set_prefix("acme")).expect("Invalid tenant");
+/// registry.insert(Tokenize::new("owo")
+///     .set_prefix("globex")
+///     .set_epoch(1600000000000)
+///     .set_validation_options(ValidationOptions::new().max_age(Duration::from_secs(60 * 60))))
+///     .expect("Invalid tenant");
+/// 
+/// let token = registry.generate(&acme, "326359466171826176").expect("Couldn't generate new token");
+/// registry.validate(token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 })).expect("Couldn't validate token");
+/// ```
+#[derive(Default)]
+pub struct TenantRegistry {
+    tenants: Vec<Tokenize>,
+    prefixes: HashMap<String, usize>
+}
+
+impl TenantRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> TenantRegistry {
+        TenantRegistry::default()
+    }
+
+    /// Registers a tenant, returning a handle to generate tokens with its first prefix.
+    /// 
+    /// # Errors
+    /// 
+    /// Fails with [`ConfigError::MissingPrefix`] if `tokenize` has no prefix, or with
+    /// [`ConfigError::SharedPrefix`] if one of its prefixes belongs to another tenant.
+    pub fn insert(&mut self, tokenize: Tokenize) -> Result<Tenant, ConfigError> {
+        let prefix = tokenize.prefixes.first().ok_or(ConfigError::MissingPrefix)?.clone();
+        if tokenize.prefixes.iter().any(|prefix| self.prefixes.contains_key(prefix)) {
+            return Err(ConfigError::SharedPrefix)
+        }
+
+        let index = self.tenants.len();
+        self.prefixes.extend(tokenize.prefixes.iter().map(|prefix| (prefix.clone(), index)));
+        self.tenants.push(tokenize);
+
+        Ok(Tenant { prefix })
+    }
+
+    /// Returns the handle of the tenant owning `prefix`, generating tokens with that prefix.
+    pub fn tenant(&self, prefix: &str) -> Option<Tenant> {
+        self.prefixes.contains_key(prefix).then(|| Tenant { prefix: prefix.to_owned() })
+    }
+
+    /// Returns the instance of the tenant owning `prefix`.
+    pub fn get(&self, prefix: &str) -> Option<&Tokenize> {
+        self.prefixes.get(prefix).map(|&index| &self.tenants[index])
+    }
+
+    /// The number of registered tenants.
+    pub fn len(&self) -> usize {
+        self.tenants.len()
+    }
+
+    /// Whether no tenant is registered.
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    /// Generates a new token for an account of `tenant`.
+    /// 
+    /// Fails with [`TokenizeError::PrefixMismatch`] if the tenant doesn't belong to this registry.
+    pub fn generate<S: Into<AccountId>>(&self, tenant: &Tenant, account_id: S) -> Result<String> {
+        self.get(&tenant.prefix)
+            .ok_or(TokenizeError::PrefixMismatch)?
+            .generate_with_prefix(&tenant.prefix, account_id)
+    }
+
+    /// Validates a token with the instance of the tenant it was issued for. See [`Tokenize::validate`].
+    pub fn validate<S, F, A>(&self, token: S, account_fetcher: F) -> Result<A> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        self.validate_token(token, account_fetcher).map(|(_, account)| account)
+    }
+
+    /// Validates a token with the instance of the tenant it was issued for, returning the parsed
+    /// token along with the account. See [`Tokenize::validate_token`].
+    pub fn validate_token<S, F, A>(&self, token: S, account_fetcher: F) -> Result<(Token, A)> where
+        S: Into<String>,
+        F: FnMut(String) -> Option<A>,
+        A: Account {
+        let token = token.into();
+        self.tenant_for(&token)?.validate_token(token, account_fetcher)
+    }
+
+    /// Parses a token with the instance of the tenant it was issued for. See [`Tokenize::parse`].
+    pub fn parse<S: Into<String>>(&self, token: S) -> Result<Token> {
+        let token = token.into();
+        self.tenant_for(&token)?.parse(token)
+    }
+
+    fn tenant_for(&self, token: &str) -> Result<&Tokenize> {
+        let (prefix, _) = token.split_once('.').ok_or(TokenizeError::Malformed)?;
+        self.get(prefix).ok_or(TokenizeError::PrefixMismatch)
+    }
+}
@@ -0,0 +1,862 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::{self, FromStr};
+use crate::{crypto, AccountId, Encoding, Result, SignatureFormat, Tokenize, TokenizeError, TOKENIZE_EPOCH};
+use crate::keyring::KEY_ID_SEPARATOR;
+
+/// Header segment identifying [`TokenVersion::V2`] tokens.
+pub(crate) const V2_HEADER: &str = "v2";
+
+/// Separator between the tag and the payload of extension segments.
+/// 
+/// Extension segments are optional, signed segments sitting between the time and the signature
+/// (`<tag>:<base64 payload>`).
+pub(crate) const EXTENSION_SEPARATOR: char = ':';
+
+/// Tag of the extension segment holding the scopes of a token.
+pub(crate) const SCOPES_TAG: &str = "s";
+
+/// Tag of the extension segment holding the claims of a token.
+pub(crate) const CLAIMS_TAG: &str = "c";
+
+/// Tag of the extension segment making a token unique.
+pub(crate) const NONCE_TAG: &str = "n";
+
+/// Tag of the extension segment making a token unique and marking it as single-use.
+pub(crate) const SINGLE_USE_TAG: &str = "o";
+
+/// Tag of the extension segment binding a token to a device.
+pub(crate) const DEVICE_TAG: &str = "d";
+
+/// Tag of the extension segment holding the audience of a token.
+pub(crate) const AUDIENCE_TAG: &str = "a";
+
+/// Tag of the extension segment holding the milliseconds of the generation time of a token.
+pub(crate) const MILLIS_TAG: &str = "m";
+
+/// Tag of the extension segment holding how long a token is valid for, in milliseconds.
+pub(crate) const EXPIRY_TAG: &str = "e";
+
+/// Separator between the scopes of a token.
+pub(crate) const SCOPE_SEPARATOR: char = ',';
+
+/// Placeholder written instead of secrets and signatures in logs and [`Debug`] output.
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Version of the token format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenVersion {
+    /// The format described by the specification: `[prefix.]account.time.signature`.
+    #[default]
+    V1,
+    /// Same as [`TokenVersion::V1`], with a `v2` header segment right after the prefix. The version is
+    /// part of the signed data, so a token can't be downgraded to another version.
+    V2
+}
+
+/// Precision of the generation time of new tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimestampPrecision {
+    /// Whole seconds, as mandated by the specification. Two tokens generated for the same account
+    /// within the same second are identical.
+    #[default]
+    Seconds,
+    /// Milliseconds. The time segment still holds whole seconds, the milliseconds are carried by a
+    /// signed extension segment.
+    Milliseconds
+}
+
+impl TokenVersion {
+    /// The version number used in the signed data.
+    pub fn number(self) -> u32 {
+        match self {
+            TokenVersion::V1 => 1,
+            TokenVersion::V2 => 2
+        }
+    }
+}
+
+/// A Tokenize token, split into its components.
+/// 
+/// Tokens returned by [`Tokenize::parse`](crate::Tokenize::parse) and
+/// [`Tokenize::generate_token`](crate::Tokenize::generate_token) have a verified signature. Tokens
+/// obtained through [`FromStr`] are only decoded: their signature is **not** checked.
+/// 
+/// The signature is redacted from the [`Debug`] output, use [`Token::redacted`] to log a token.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token {
+    pub(crate) version: TokenVersion,
+    pub(crate) prefix: Option<String>,
+    pub(crate) account_id: AccountId,
+    pub(crate) generated_at: u64,
+    pub(crate) key_id: Option<String>,
+    pub(crate) signature: String,
+    pub(crate) encoding: Encoding,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) signature_format: SignatureFormat,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub(crate) scopes: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub(crate) claims: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub(crate) nonce: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) single_use: bool,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub(crate) device_id: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub(crate) audience: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub(crate) millis: Option<u16>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub(crate) ttl_millis: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default = "default_epoch"))]
+    pub(crate) epoch: i64
+}
+
+impl Token {
+    /// Decodes the segments of a token. Doesn't perform any signature verification.
+    pub(crate) fn from_segments(version: TokenVersion, prefix: Option<&str>, account_part: &str, time_part: &str, extensions: &[&str], signature_part: &str, encoding: Encoding) -> Result<Token> {
+        let account_id = AccountId::new(encoding.decode(account_part)?);
+        let generated_at = str::from_utf8(&encoding.decode(time_part)?)?.parse()?;
+        let (key_id, signature) = split_key_id(signature_part);
+        let mut scopes = None;
+        let mut claims = None;
+        let mut nonce = None;
+        let mut single_use = false;
+        let mut device_id = None;
+        let mut audience = None;
+        let mut millis = None;
+        let mut ttl_millis = None;
+
+        for extension in extensions {
+            match extension.split_once(EXTENSION_SEPARATOR) {
+                Some((SCOPES_TAG, payload)) if scopes.is_none() => {
+                    let decoded = encoding.decode(payload)?;
+                    scopes = Some(str::from_utf8(&decoded)?.split(SCOPE_SEPARATOR).map(str::to_string).collect());
+                },
+                Some((CLAIMS_TAG, payload)) if claims.is_none() => {
+                    claims = Some(str::from_utf8(&encoding.decode(payload)?)?.to_string());
+                },
+                Some((tag @ (NONCE_TAG | SINGLE_USE_TAG), payload)) if nonce.is_none() => {
+                    nonce = Some(str::from_utf8(&encoding.decode(payload)?)?.to_string());
+                    single_use = tag == SINGLE_USE_TAG;
+                },
+                Some((DEVICE_TAG, payload)) if device_id.is_none() => {
+                    device_id = Some(str::from_utf8(&encoding.decode(payload)?)?.to_string());
+                },
+                Some((AUDIENCE_TAG, payload)) if audience.is_none() => {
+                    audience = Some(str::from_utf8(&encoding.decode(payload)?)?.to_string());
+                },
+                Some((MILLIS_TAG, payload)) if millis.is_none() => {
+                    millis = Some(parse_millis(&encoding.decode(payload)?)?);
+                },
+                Some((EXPIRY_TAG, payload)) if ttl_millis.is_none() => {
+                    ttl_millis = Some(str::from_utf8(&encoding.decode(payload)?)?.parse()?);
+                },
+                _ => return Err(TokenizeError::Malformed)
+            }
+        }
+
+        Ok(Token {
+            version,
+            prefix: prefix.map(str::to_string),
+            account_id,
+            generated_at,
+            key_id: key_id.map(str::to_string),
+            signature: signature.to_string(),
+            encoding,
+            signature_format: SignatureFormat::default(),
+            scopes: scopes.unwrap_or_default(),
+            claims,
+            nonce,
+            single_use,
+            device_id,
+            audience,
+            millis,
+            ttl_millis,
+            epoch: TOKENIZE_EPOCH
+        })
+    }
+
+    /// The version of the format the token is in.
+    pub fn version(&self) -> TokenVersion {
+        self.version
+    }
+
+    /// The prefix of the token, if any.
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// The id of the account this token was generated for.
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// The id of the account as a string, failing with [`TokenizeError::Decode`] if it isn't valid UTF-8.
+    pub(crate) fn account_id_str(&self) -> Result<&str> {
+        self.account_id.as_str().ok_or(TokenizeError::Decode)
+    }
+
+    /// The time at which the token was generated, in seconds since the epoch of the instance it was
+    /// issued by, [`TOKENIZE_EPOCH`](crate::TOKENIZE_EPOCH) by default.
+    pub fn generated_at(&self) -> u64 {
+        self.generated_at
+    }
+
+    /// Sets the epoch the generation time is relative to, for tokens decoded through [`FromStr`] that
+    /// were issued by an instance with a custom epoch.
+    pub fn set_epoch(mut self, epoch: i64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// The time at which the token was generated, as a unix timestamp in milliseconds.
+    pub fn issued_at_millis(&self) -> i64 {
+        issued_at_millis(self.generated_at, self.epoch).saturating_add(self.millis.unwrap_or(0).into())
+    }
+
+    /// The time at which the token was generated.
+    pub fn issued_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.issued_at_millis()).unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// The time after which the token is expired, as a unix timestamp in milliseconds, if it was issued
+    /// with its own lifetime. See [`PurposeToken`](crate::purpose::PurposeToken).
+    pub fn expires_at_millis(&self) -> Option<i64> {
+        expires_at_millis(self.issued_at_millis(), self.ttl_millis)
+    }
+
+    /// The precision of the generation time of the token.
+    pub fn precision(&self) -> TimestampPrecision {
+        match self.millis {
+            Some(_) => TimestampPrecision::Milliseconds,
+            None => TimestampPrecision::Seconds
+        }
+    }
+
+    /// The id of the [`Keyring`](crate::Keyring) key the token was signed with, if any.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// The base64 alphabet the token is encoded with.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// How the signature of the token is encoded.
+    pub fn signature_format(&self) -> SignatureFormat {
+        self.signature_format
+    }
+
+    /// The scopes the token was issued with. Empty if it wasn't issued with any.
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// Whether the token was issued with `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Checks that the token was issued with `scope`, failing with [`TokenizeError::MissingScope`] otherwise.
+    pub fn require_scope(&self, scope: &str) -> Result<()> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(TokenizeError::MissingScope(scope.to_string()))
+        }
+    }
+
+    /// Checks that the token was issued with every scope of `scopes`, failing with the first missing one.
+    pub fn require_scopes(&self, scopes: &[&str]) -> Result<()> {
+        scopes.iter().try_for_each(|scope| self.require_scope(scope))
+    }
+
+    /// The serialized claims the token was issued with, if any.
+    pub fn raw_claims(&self) -> Option<&str> {
+        self.claims.as_deref()
+    }
+
+    /// Deserializes the claims the token was issued with.
+    /// 
+    /// Fails with [`TokenizeError::InvalidClaims`] if the token doesn't carry claims or if they don't
+    /// match `D`.
+    #[cfg(feature = "claims")]
+    pub fn claims<D: serde::de::DeserializeOwned>(&self) -> Result<D> {
+        let claims = self.claims.as_deref().ok_or(TokenizeError::InvalidClaims)?;
+
+        serde_json::from_str(claims).map_err(|_| TokenizeError::InvalidClaims)
+    }
+
+    /// The nonce making the token unique, if any. See
+    /// [`Tokenize::unique_tokens`](crate::Tokenize::unique_tokens).
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    /// Whether the token can only be validated once, through
+    /// [`Tokenize::validate_single_use`](crate::Tokenize::validate_single_use).
+    pub fn is_single_use(&self) -> bool {
+        self.single_use
+    }
+
+    /// The id of the device the token is bound to, if any. See
+    /// [`Tokenize::generate_for_device`](crate::Tokenize::generate_for_device).
+    pub fn device_id(&self) -> Option<&str> {
+        self.device_id.as_deref()
+    }
+
+    /// Checks that the token is bound to `device_id`, failing with [`TokenizeError::DeviceMismatch`]
+    /// if it's bound to another device or to none.
+    pub fn require_device(&self, device_id: &str) -> Result<()> {
+        if self.device_id.as_deref() == Some(device_id) {
+            Ok(())
+        } else {
+            Err(TokenizeError::DeviceMismatch)
+        }
+    }
+
+    /// The audience the token was issued for, if any. See [`Tokenize::set_audience`](crate::Tokenize::set_audience).
+    pub fn audience(&self) -> Option<&str> {
+        self.audience.as_deref()
+    }
+
+    /// Writes the segments of the token covered by the signature after its prefix:
+    /// `account.time[.extensions]`.
+    pub(crate) fn write_segments<W: fmt::Write>(&self, buffer: &mut W) -> fmt::Result {
+        let mut digits = [0; 20];
+        write!(
+            buffer, "{}.{}",
+            self.encoding.display(self.account_id.as_bytes()),
+            self.encoding.display(decimal(self.generated_at, &mut digits))
+        )?;
+
+        let mut extension = |tag, payload: &[u8]| write!(buffer, ".{}{}{}", tag, EXTENSION_SEPARATOR, self.encoding.display(payload));
+        if !self.scopes.is_empty() {
+            extension(SCOPES_TAG, self.scopes.join(",").as_bytes())?;
+        }
+
+        if let Some(claims) = &self.claims {
+            extension(CLAIMS_TAG, claims.as_bytes())?;
+        }
+
+        if let Some(nonce) = &self.nonce {
+            extension(if self.single_use { SINGLE_USE_TAG } else { NONCE_TAG }, nonce.as_bytes())?;
+        }
+
+        if let Some(device_id) = &self.device_id {
+            extension(DEVICE_TAG, device_id.as_bytes())?;
+        }
+
+        if let Some(audience) = &self.audience {
+            extension(AUDIENCE_TAG, audience.as_bytes())?;
+        }
+
+        if let Some(millis) = self.millis {
+            extension(MILLIS_TAG, decimal(millis.into(), &mut digits))?;
+        }
+
+        if let Some(ttl_millis) = self.ttl_millis {
+            extension(EXPIRY_TAG, decimal(ttl_millis, &mut digits))?;
+        }
+
+        Ok(())
+    }
+
+    /// The encoded signature of the token, see [`SignatureFormat`].
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    /// Hex-encoded SHA-256 hash of the key id and signature, identifying the token in a
+    /// [`RevocationStore`](crate::RevocationStore) without storing the token itself. Tokens only
+    /// differing by the alphabet or format of their signature share the same key.
+    pub fn revocation_key(&self) -> String {
+        revocation_key(self.key_id.as_deref(), &self.signature, self.signature_format.encoding(self.encoding))
+    }
+
+    /// Short hex-encoded hash of the signature, safe to log in place of the token: it's stable for a
+    /// given token but can't be turned back into it.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.signature)
+    }
+
+    /// Displays the token with its signature replaced by `[REDACTED]`, so it can be logged without
+    /// leaking a usable credential.
+    /// 
+    /// ```
+    /// use tokenize::Tokenize;
+    /// 
+    /// let token = Tokenize::new("uwu").generate_token("326359466171826176").unwrap();
+    /// assert!(token.redacted().to_string().ends_with(".[REDACTED]"));
+    /// ```
+    pub fn redacted(&self) -> RedactedToken<'_> {
+        RedactedToken(self)
+    }
+
+    /// Decodes a token **without verifying its signature**, to inspect it before the secret is
+    /// available, e.g. to route a request to the shard owning its account.
+    /// 
+    /// Nothing read from an [`UnverifiedToken`] can be trusted, and it can't be used where a [`Token`]
+    /// is expected: [`UnverifiedToken::verify`] turns it into one.
+    /// 
+    /// ```
+    /// use tokenize::{Token, Tokenize};
+    /// 
+    /// let unverified = Token::decode_unverified("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc").unwrap();
+    /// assert_eq!(unverified.account_id(), "326359466171826176");
+    /// 
+    /// let token = unverified.verify(&Tokenize::new("uwu")).expect("Couldn't verify token");
+    /// ```
+    pub fn decode_unverified<S: Into<String>>(token: S) -> Result<UnverifiedToken> {
+        let raw = token.into();
+        let token = raw.parse()?;
+
+        Ok(UnverifiedToken { token, raw })
+    }
+
+    /// Writes the token with `signature` in place of its own.
+    fn write(&self, f: &mut fmt::Formatter<'_>, signature: &str) -> fmt::Result {
+        if let Some(prefix) = &self.prefix {
+            write!(f, "{}.", prefix)?;
+        }
+
+        if self.version == TokenVersion::V2 {
+            write!(f, "{}.", V2_HEADER)?;
+        }
+
+        self.write_segments(f)?;
+        f.write_str(".")?;
+        if let Some(key_id) = &self.key_id {
+            write!(f, "{}{}", key_id, KEY_ID_SEPARATOR)?;
+        }
+
+        write!(f, "{}", signature)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &self.signature)
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("version", &self.version)
+            .field("prefix", &self.prefix)
+            .field("account_id", &self.account_id)
+            .field("generated_at", &self.generated_at)
+            .field("key_id", &self.key_id)
+            .field("signature", &format_args!("{}", REDACTED))
+            .field("encoding", &self.encoding)
+            .field("signature_format", &self.signature_format)
+            .field("scopes", &self.scopes)
+            .field("claims", &self.claims)
+            .field("nonce", &self.nonce)
+            .field("single_use", &self.single_use)
+            .field("device_id", &self.device_id)
+            .field("audience", &self.audience)
+            .field("millis", &self.millis)
+            .field("ttl_millis", &self.ttl_millis)
+            .field("epoch", &self.epoch)
+            .finish()
+    }
+}
+
+/// A [`Token`] displayed with its signature redacted, returned by [`Token::redacted`].
+#[derive(Clone, Copy)]
+pub struct RedactedToken<'a>(&'a Token);
+
+impl fmt::Display for RedactedToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(f, REDACTED)
+    }
+}
+
+impl fmt::Debug for RedactedToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+/// A token whose signature wasn't verified, returned by [`Token::decode_unverified`].
+/// 
+/// Its signature isn't exposed, and its [`Debug`](fmt::Debug) output is redacted like a [`Token`]'s.
+#[derive(Clone, PartialEq, Eq)]
+pub struct UnverifiedToken {
+    token: Token,
+    /// The token as it was presented, so it's verified exactly as it was signed.
+    raw: String
+}
+
+impl UnverifiedToken {
+    /// The version of the format the token claims to be in.
+    pub fn version(&self) -> TokenVersion {
+        self.token.version
+    }
+
+    /// The prefix of the token, if any.
+    pub fn prefix(&self) -> Option<&str> {
+        self.token.prefix()
+    }
+
+    /// The id of the account the token claims to belong to.
+    pub fn account_id(&self) -> &AccountId {
+        &self.token.account_id
+    }
+
+    /// The claimed generation time, in seconds since the epoch of the instance it was issued by.
+    pub fn generated_at(&self) -> u64 {
+        self.token.generated_at
+    }
+
+    /// Sets the epoch the generation time is relative to, see [`Token::set_epoch`].
+    pub fn set_epoch(mut self, epoch: i64) -> Self {
+        self.token.epoch = epoch;
+        self
+    }
+
+    /// The claimed generation time, as a unix timestamp in milliseconds.
+    pub fn issued_at_millis(&self) -> i64 {
+        self.token.issued_at_millis()
+    }
+
+    /// The claimed generation time.
+    pub fn issued_at(&self) -> DateTime<Utc> {
+        self.token.issued_at()
+    }
+
+    /// The id of the [`Keyring`](crate::Keyring) key the token claims to be signed with, if any.
+    pub fn key_id(&self) -> Option<&str> {
+        self.token.key_id()
+    }
+
+    /// The audience the token claims to be issued for, if any.
+    pub fn audience(&self) -> Option<&str> {
+        self.token.audience()
+    }
+
+    /// Verifies the token with `tokenize`, see [`Tokenize::parse`].
+    pub fn verify(&self, tokenize: &Tokenize) -> Result<Token> {
+        tokenize.parse(self.raw.as_str())
+    }
+}
+
+impl fmt::Debug for UnverifiedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UnverifiedToken").field(&self.token).finish()
+    }
+}
+
+impl FromStr for Token {
+    type Err = TokenizeError;
+
+    /// Decodes a token **without verifying its signature**. Use [`Tokenize::parse`](crate::Tokenize::parse)
+    /// to obtain a verified token.
+    /// 
+    /// The token is assumed to use [`TOKENIZE_EPOCH`](crate::TOKENIZE_EPOCH).
+    fn from_str(token: &str) -> Result<Token> {
+        let mut splitted = token.split('.').collect::<Vec<&str>>();
+        let extensions = take_extensions(&mut splitted)?;
+
+        let (version, prefix, account_part, time_part, signature_part) = match splitted[..] {
+            [account_part, time_part, signature_part] => (TokenVersion::V1, None, account_part, time_part, signature_part),
+            [V2_HEADER, account_part, time_part, signature_part] => (TokenVersion::V2, None, account_part, time_part, signature_part),
+            [prefix, account_part, time_part, signature_part] => (TokenVersion::V1, Some(prefix), account_part, time_part, signature_part),
+            [prefix, V2_HEADER, account_part, time_part, signature_part] => (TokenVersion::V2, Some(prefix), account_part, time_part, signature_part),
+            _ => return Err(TokenizeError::Malformed)
+        };
+
+        let signature_format = SignatureFormat::detect(split_key_id(signature_part).1);
+        let mut segments = vec![account_part, time_part];
+        segments.extend((signature_format == SignatureFormat::Base64).then(|| split_key_id(signature_part).1));
+        segments.extend(extension_payloads(&extensions));
+        let encoding = Encoding::detect(segments.iter().copied())?.unwrap_or_default();
+        let mut token = Token::from_segments(version, prefix, account_part, time_part, &extensions, signature_part, encoding)?;
+        token.signature_format = signature_format;
+
+        Ok(token)
+    }
+}
+
+/// A token borrowing the string it was parsed from, verified without allocating.
+/// 
+/// Returned by [`Tokenize::parse_ref`](crate::Tokenize::parse_ref) and
+/// [`Tokenize::validate_ref`](crate::Tokenize::validate_ref). The account id and extensions are only
+/// decoded on demand, [`TokenRef::to_token`] decodes everything into an owned [`Token`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TokenRef<'a> {
+    pub(crate) version: TokenVersion,
+    pub(crate) prefix: Option<&'a str>,
+    pub(crate) account_part: &'a str,
+    pub(crate) time_part: &'a str,
+    /// The extension segments, separated by dots. Empty if there are none.
+    pub(crate) extensions: &'a str,
+    pub(crate) signature_part: &'a str,
+    pub(crate) encoding: Encoding,
+    pub(crate) signature_format: SignatureFormat,
+    pub(crate) generated_at: u64,
+    pub(crate) millis: u16,
+    pub(crate) ttl_millis: Option<u64>,
+    pub(crate) single_use: bool,
+    pub(crate) epoch: i64
+}
+
+impl<'a> TokenRef<'a> {
+    /// The version of the format the token is in.
+    pub fn version(&self) -> TokenVersion {
+        self.version
+    }
+
+    /// The prefix of the token, if any.
+    pub fn prefix(&self) -> Option<&'a str> {
+        self.prefix
+    }
+
+    /// Decodes the id of the account this token was generated for into `buffer`.
+    /// 
+    /// Fails with [`TokenizeError::Decode`] if `buffer` is too small, it must hold three bytes for
+    /// every four characters of the account segment.
+    pub fn decode_account_id<'b>(&self, buffer: &'b mut [u8]) -> Result<&'b [u8]> {
+        self.encoding.decode_slice(self.account_part, buffer)
+    }
+
+    /// Decodes the id of the account this token was generated for.
+    pub fn account_id(&self) -> Result<AccountId> {
+        Ok(AccountId::new(self.encoding.decode(self.account_part)?))
+    }
+
+    /// The time at which the token was generated, in seconds since the epoch of the instance it was
+    /// issued by.
+    pub fn generated_at(&self) -> u64 {
+        self.generated_at
+    }
+
+    /// The time at which the token was generated, as a unix timestamp in milliseconds.
+    pub fn issued_at_millis(&self) -> i64 {
+        issued_at_millis(self.generated_at, self.epoch).saturating_add(self.millis.into())
+    }
+
+    /// The time after which the token is expired, as a unix timestamp in milliseconds, if it was issued
+    /// with its own lifetime. See [`Token::expires_at_millis`].
+    pub fn expires_at_millis(&self) -> Option<i64> {
+        expires_at_millis(self.issued_at_millis(), self.ttl_millis)
+    }
+
+    /// The id of the [`Keyring`](crate::Keyring) key the token was signed with, if any.
+    pub fn key_id(&self) -> Option<&'a str> {
+        split_key_id(self.signature_part).0
+    }
+
+    /// The base64 alphabet the token is encoded with.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Whether the token can only be validated once.
+    pub fn is_single_use(&self) -> bool {
+        self.single_use
+    }
+
+    /// The encoded signature of the token, see [`SignatureFormat`].
+    pub fn signature(&self) -> &'a str {
+        split_key_id(self.signature_part).1
+    }
+
+    /// Short hex-encoded hash of the signature. See [`Token::fingerprint`].
+    pub fn fingerprint(&self) -> String {
+        fingerprint(self.signature())
+    }
+
+    /// Hash of the key id and signature. See [`Token::revocation_key`].
+    pub fn revocation_key(&self) -> String {
+        revocation_key(self.key_id(), self.signature(), self.signature_format.encoding(self.encoding))
+    }
+
+    /// Decodes every segment into an owned [`Token`].
+    pub fn to_token(&self) -> Result<Token> {
+        let extensions = self.extension_segments().collect::<Vec<&str>>();
+        let mut token = Token::from_segments(self.version, self.prefix, self.account_part, self.time_part, &extensions, self.signature_part, self.encoding)?;
+        token.epoch = self.epoch;
+        token.signature_format = self.signature_format;
+
+        Ok(token)
+    }
+
+    /// The extension segments of the token.
+    pub(crate) fn extension_segments(&self) -> impl Iterator<Item = &'a str> {
+        self.extensions.split('.').filter(|extension| !extension.is_empty())
+    }
+}
+
+impl fmt::Debug for TokenRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenRef")
+            .field("version", &self.version)
+            .field("prefix", &self.prefix)
+            .field("account_part", &self.account_part)
+            .field("time_part", &self.time_part)
+            .field("extensions", &self.extensions)
+            .field("key_id", &self.key_id())
+            .field("signature", &format_args!("{}", REDACTED))
+            .field("encoding", &self.encoding)
+            .field("signature_format", &self.signature_format)
+            .field("generated_at", &self.generated_at)
+            .field("millis", &self.millis)
+            .field("ttl_millis", &self.ttl_millis)
+            .field("single_use", &self.single_use)
+            .field("epoch", &self.epoch)
+            .finish()
+    }
+}
+
+/// Whether `prefix` can be used as a token prefix: it must be made of printable ASCII characters, and
+/// can't contain the segment or extension separators.
+pub(crate) fn is_valid_prefix(prefix: &str) -> bool {
+    !prefix.is_empty() && prefix.bytes().all(|b| b.is_ascii_graphic() && b != b'.' && b as char != EXTENSION_SEPARATOR)
+}
+
+/// Splits the key id from the signature segment of a token, if there's one.
+pub(crate) fn split_key_id(signature_part: &str) -> (Option<&str>, &str) {
+    match signature_part.split_once(KEY_ID_SEPARATOR) {
+        Some((key_id, signature)) => (Some(key_id), signature),
+        None => (None, signature_part)
+    }
+}
+
+/// Removes the extension segments from the segments of a token, returning them.
+/// 
+/// Extension segments have to sit right before the signature; any other segment containing an
+/// [`EXTENSION_SEPARATOR`] makes the token malformed.
+pub(crate) fn take_extensions<'a>(segments: &mut Vec<&'a str>) -> Result<Vec<&'a str>> {
+    let signature_part = segments.pop().ok_or(TokenizeError::Malformed)?;
+    let start = segments.iter().rposition(|segment| !segment.contains(EXTENSION_SEPARATOR)).map_or(0, |index| index + 1);
+    let extensions = segments.split_off(start);
+    segments.push(signature_part);
+
+    if segments.iter().any(|segment| segment.contains(EXTENSION_SEPARATOR)) {
+        return Err(TokenizeError::Malformed)
+    }
+
+    Ok(extensions)
+}
+
+/// The base64 payloads of extension segments.
+pub(crate) fn extension_payloads<'a>(extensions: &[&'a str]) -> Vec<&'a str> {
+    extensions.iter().filter_map(|extension| extension.split_once(EXTENSION_SEPARATOR).map(|(_, payload)| payload)).collect()
+}
+
+/// The time at which a token was generated, as a unix timestamp in milliseconds.
+pub(crate) fn issued_at_millis(generated_at: u64, epoch: i64) -> i64 {
+    i64::try_from(generated_at).unwrap_or(i64::MAX)
+        .saturating_mul(1000)
+        .saturating_add(epoch)
+}
+
+/// The time after which a token issued with a lifetime of `ttl_millis` is expired.
+fn expires_at_millis(issued_at_millis: i64, ttl_millis: Option<u64>) -> Option<i64> {
+    let ttl_millis = i64::try_from(ttl_millis?).unwrap_or(i64::MAX);
+
+    Some(issued_at_millis.saturating_add(ttl_millis))
+}
+
+/// Parses the decoded payload of a [`MILLIS_TAG`] extension, which must be below 1000.
+pub(crate) fn parse_millis(payload: &[u8]) -> Result<u16> {
+    let millis = str::from_utf8(payload)?.parse()?;
+    if millis >= 1000 {
+        return Err(TokenizeError::Malformed)
+    }
+
+    Ok(millis)
+}
+
+/// Hex-encoded SHA-256 hash of a signature segment. See [`Token::revocation_key`].
+/// 
+/// The signature is decoded and re-encoded in the standard alphabet first, so the same signature
+/// gets the same key whichever encoding it was sent in.
+pub(crate) fn revocation_key(key_id: Option<&str>, signature: &str, encoding: Encoding) -> String {
+    let signature = match encoding.decode(signature) {
+        Ok(decoded) => Encoding::Standard.encode(decoded),
+        Err(_) => signature.to_string()
+    };
+
+    match key_id {
+        Some(key_id) => signature_hash(&format!("{}{}{}", key_id, KEY_ID_SEPARATOR, signature)),
+        None => signature_hash(&signature)
+    }
+}
+
+/// Hex-encoded SHA-256 hash of a signature, as sent.
+pub(crate) fn signature_hash(signature: &str) -> String {
+    crypto::sha256(signature.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The first 8 bytes of the SHA-256 hash of a signature, hex-encoded. See [`Token::fingerprint`].
+pub(crate) fn fingerprint(signature: &str) -> String {
+    crypto::sha256(signature.as_bytes())[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(feature = "serde")]
+fn default_epoch() -> i64 {
+    TOKENIZE_EPOCH
+}
+
+/// Generates a random 64 bits value making a token unique, hex-encoded.
+/// 
+/// Nonces come from the operating system's CSPRNG, as they're also used as unguessable identifiers,
+/// such as the ids of sessions and API keys.
+pub(crate) fn nonce() -> String {
+    let mut bytes = [0; 8];
+    getrandom::getrandom(&mut bytes).expect("Couldn't get random bytes from the operating system");
+
+    format!("{:016x}", u64::from_be_bytes(bytes))
+}
+
+/// Writes the decimal digits of `value` at the end of `buffer`, returning them.
+fn decimal(mut value: u64, buffer: &mut [u8; 20]) -> &[u8] {
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            return &buffer[start..]
+        }
+    }
+}
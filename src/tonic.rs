@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! [tonic] integration, available with the `tonic` feature.
+//! 
+//! [tonic]: https://docs.rs/tonic
+
+use std::sync::Arc;
+use ::tonic::service::Interceptor;
+use ::tonic::{Request, Status};
+use crate::{extract, Account, Tokenize, TokenizeError};
+
+/// Interceptor validating the token from the `authorization` metadata of incoming calls.
+/// 
+/// Both raw tokens and `Bearer` tokens are accepted. On success, the account is stored in the
+/// request extensions. Calls without a valid token are rejected with an `UNAUTHENTICATED` status,
+/// or `PERMISSION_DENIED` for authentic tokens that were rejected (invalidated, expired, revoked or
+/// tied to no account).
+/// 
+/// Interceptors are synchronous, so accounts have to be fetched without awaiting. Use the
+/// [`tower`](crate::tower) integration for async lookups.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Account, Tokenize};
+/// use tokenize::tonic::TokenizeInterceptor;
+/// 
+/// #[derive(Clone)]
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// let interceptor = TokenizeInterceptor::new(Tokenize::new("uwu"), |_id: String| {
+///     Some(User { last_token_reset: 0 }) // query your cache here
+/// });
+/// 
+/// // GreeterServer::with_interceptor(greeter, interceptor)
+/// ```
+pub struct TokenizeInterceptor<F> {
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Arc<F>
+}
+
+impl<F> TokenizeInterceptor<F> {
+    /// Creates an interceptor fetching accounts through `account_fetcher`.
+    pub fn new<T: Into<Arc<Tokenize>>>(tokenize: T, account_fetcher: F) -> TokenizeInterceptor<F> {
+        TokenizeInterceptor {
+            tokenize: tokenize.into(),
+            account_fetcher: Arc::new(account_fetcher)
+        }
+    }
+}
+
+impl<F> Clone for TokenizeInterceptor<F> {
+    fn clone(&self) -> Self {
+        TokenizeInterceptor {
+            tokenize: self.tokenize.clone(),
+            account_fetcher: self.account_fetcher.clone()
+        }
+    }
+}
+
+impl<F, A> Interceptor for TokenizeInterceptor<F> where
+    F: Fn(String) -> Option<A>,
+    A: Account + Clone + Send + Sync + 'static {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let token = request.metadata().get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(extract::bearer)
+            .ok_or_else(|| Status::unauthenticated("Missing token"))?;
+
+        let account = self.tokenize.validate(token, |id| (self.account_fetcher)(id)).map_err(|err| match err {
+            TokenizeError::Invalidated | TokenizeError::Expired | TokenizeError::Revoked | TokenizeError::AccountNotFound => Status::permission_denied(err.to_string()),
+            _ => Status::unauthenticated(err.to_string())
+        })?;
+
+        request.extensions_mut().insert(account);
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tonic::service::Interceptor;
+    use ::tonic::{Code, Request};
+    use super::TokenizeInterceptor;
+    use crate::{Account, Tokenize};
+
+    #[derive(Clone)]
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    fn call(token: Option<&str>) -> Result<Request<()>, Code> {
+        let mut interceptor = TokenizeInterceptor::new(Tokenize::new("uwu"), |id: String| {
+            (id == "326359466171826176").then_some(TestAccount { last_token_reset: 1641641228500 })
+        });
+
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request.metadata_mut().insert("authorization", token.parse().unwrap());
+        }
+
+        interceptor.call(request).map_err(|status| status.code())
+    }
+
+    #[test]
+    fn intercept_calls() {
+        assert_eq!(call(Some("Bearer MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc")).unwrap_err(), Code::PermissionDenied);
+        assert_eq!(call(Some("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid")).unwrap_err(), Code::Unauthenticated);
+        assert_eq!(call(None).unwrap_err(), Code::Unauthenticated);
+
+        let tokenize = Tokenize::new("uwu");
+        let token = tokenize.generate("326359466171826176").unwrap();
+        let request = call(Some(&token)).unwrap_or_else(|_| panic!("Call was rejected"));
+        assert!(request.extensions().get::<TestAccount>().is_some());
+    }
+}
@@ -0,0 +1,224 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! [tower] integration, available with the `tower` feature.
+//! 
+//! [tower]: https://docs.rs/tower
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use http::header::{HeaderName, AUTHORIZATION};
+use http::{Request, Response, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+use crate::{extract, Account, Tokenize};
+
+/// Layer validating the token of incoming requests.
+/// 
+/// On success, the account is stored in the request extensions. Requests without a valid token are
+/// rejected with an empty `401 Unauthorized` response without reaching the inner service.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use tokenize::{Account, Tokenize};
+/// use tokenize::tower::TokenizeLayer;
+/// 
+/// #[derive(Clone)]
+/// struct User { last_token_reset: u64 }
+/// 
+/// impl Account for User {
+///     fn last_token_reset(&self) -> u64 {
+///         self.last_token_reset
+///     }
+/// }
+/// 
+/// let layer = TokenizeLayer::new(Tokenize::new("uwu"), |_id: String| async {
+///     Some(User { last_token_reset: 0 }) // query your database here
+/// }).header("x-token");
+/// ```
+pub struct TokenizeLayer<F> {
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Arc<F>,
+    header: HeaderName
+}
+
+impl<F> TokenizeLayer<F> {
+    /// Creates a layer validating tokens from the `Authorization` header.
+    pub fn new<T: Into<Arc<Tokenize>>>(tokenize: T, account_fetcher: F) -> TokenizeLayer<F> {
+        TokenizeLayer {
+            tokenize: tokenize.into(),
+            account_fetcher: Arc::new(account_fetcher),
+            header: AUTHORIZATION
+        }
+    }
+
+    /// Sets the header the token is read from. `Bearer` tokens are accepted as well as raw tokens.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if `header` isn't a valid header name.
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = HeaderName::from_bytes(header.as_bytes()).expect("Invalid header name");
+        self
+    }
+}
+
+impl<F> Clone for TokenizeLayer<F> {
+    fn clone(&self) -> Self {
+        TokenizeLayer {
+            tokenize: self.tokenize.clone(),
+            account_fetcher: self.account_fetcher.clone(),
+            header: self.header.clone()
+        }
+    }
+}
+
+impl<S, F> Layer<S> for TokenizeLayer<F> {
+    type Service = TokenizeService<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TokenizeService {
+            inner,
+            tokenize: self.tokenize.clone(),
+            account_fetcher: self.account_fetcher.clone(),
+            header: self.header.clone()
+        }
+    }
+}
+
+/// Service created by [`TokenizeLayer`].
+pub struct TokenizeService<S, F> {
+    inner: S,
+    tokenize: Arc<Tokenize>,
+    account_fetcher: Arc<F>,
+    header: HeaderName
+}
+
+impl<S: Clone, F> Clone for TokenizeService<S, F> {
+    fn clone(&self) -> Self {
+        TokenizeService {
+            inner: self.inner.clone(),
+            tokenize: self.tokenize.clone(),
+            account_fetcher: self.account_fetcher.clone(),
+            header: self.header.clone()
+        }
+    }
+}
+
+impl<S, F, Fut, A, ReqBody, ResBody> Service<Request<ReqBody>> for TokenizeService<S, F> where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<A>> + Send,
+    A: Account + Clone + Send + Sync + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default {
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // The ready service has to be the one being called, leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let tokenize = self.tokenize.clone();
+        let account_fetcher = self.account_fetcher.clone();
+        let token = req.headers().get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .and_then(extract::bearer)
+            .map(str::to_string);
+
+        Box::pin(async move {
+            let account = match token {
+                Some(token) => tokenize.validate_async(token, |id| account_fetcher(id)).await.ok(),
+                None => None
+            };
+
+            match account {
+                Some(account) => {
+                    req.extensions_mut().insert(account);
+                    inner.call(req).await
+                },
+                None => {
+                    let mut response = Response::new(ResBody::default());
+                    *response.status_mut() = StatusCode::UNAUTHORIZED;
+                    Ok(response)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use http::{Request, Response, StatusCode};
+    use tower::{service_fn, Layer, ServiceExt};
+    use super::TokenizeLayer;
+    use crate::{Account, Tokenize};
+
+    #[derive(Clone)]
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    async fn status(token: Option<&str>) -> StatusCode {
+        let layer = TokenizeLayer::new(Tokenize::new("uwu"), |_id| async { Some(TestAccount { last_token_reset: 0 }) });
+        let service = layer.layer(service_fn(|req: Request<()>| async move {
+            assert!(req.extensions().get::<TestAccount>().is_some());
+            Ok::<_, Infallible>(Response::new(()))
+        }));
+
+        let mut request = Request::builder();
+        if let Some(token) = token {
+            request = request.header("Authorization", token);
+        }
+
+        service.oneshot(request.body(()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn validate_requests() {
+        assert_eq!(status(Some("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.ucU3pXWOg2L6w5ErFLraknIOjzQLuI0HqhBDpdII+Wc")).await, StatusCode::OK);
+        assert_eq!(status(Some("MzI2MzU5NDY2MTcxODI2MTc2.OTUzMzQ4MDc.thisisinvalid")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,135 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Loading of secrets from [HashiCorp Vault](https://www.vaultproject.io).
+
+use crate::keyloading::KeySource;
+use crate::{SecretKey, StoreError};
+
+/// A [`KeySource`] reading a secret from Vault's key-value secrets engine (version 2).
+/// 
+/// The secret is read from the `secret` field of the entry by default, and decoded with
+/// [`SecretKey::decode`].
+/// 
+/// # Examples
+/// 
+/// ```no_run
+/// use tokenize::Tokenize;
+/// use tokenize::keyloading::KeySource;
+/// use tokenize::vault::VaultKeySource;
+/// 
+/// let source = VaultKeySource::new("https://vault.example.com:8200", "hvs.token", "tokenize/auth")
+///     .field("signing_key");
+/// let tokenize = Tokenize::new(source.load().expect("Couldn't load secret"));
+/// ```
+#[derive(Clone)]
+pub struct VaultKeySource {
+    address: String,
+    token: String,
+    mount: String,
+    path: String,
+    field: String
+}
+
+impl VaultKeySource {
+    /// Creates a source reading the entry at `path` of the `secret` mount, authenticating with `token`.
+    pub fn new<A, T, P>(address: A, token: T, path: P) -> VaultKeySource where
+        A: Into<String>,
+        T: Into<String>,
+        P: Into<String> {
+        VaultKeySource {
+            address: address.into(),
+            token: token.into(),
+            mount: "secret".to_string(),
+            path: path.into(),
+            field: "secret".to_string()
+        }
+    }
+
+    /// Sets the mount point of the key-value secrets engine. Defaults to `secret`.
+    pub fn mount<S: Into<String>>(mut self, mount: S) -> Self {
+        self.mount = mount.into();
+        self
+    }
+
+    /// Sets the field of the entry holding the secret. Defaults to `secret`.
+    pub fn field<S: Into<String>>(mut self, field: S) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    fn url(&self) -> String {
+        format!("{}/v1/{}/data/{}", self.address.trim_end_matches('/'), self.mount, self.path.trim_start_matches('/'))
+    }
+}
+
+impl std::fmt::Debug for VaultKeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultKeySource")
+            .field("address", &self.address)
+            .field("mount", &self.mount)
+            .field("path", &self.path)
+            .field("field", &self.field)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeySource for VaultKeySource {
+    fn load(&self) -> Result<SecretKey, StoreError> {
+        let response = ::ureq::get(&self.url())
+            .set("X-Vault-Token", &self.token)
+            .call()
+            .map_err(|err| StoreError::new(format_args!("Couldn't read secret from Vault: {}", err)))?
+            .into_string()
+            .map_err(StoreError::new)?;
+
+        secret_from_response(&response, &self.field)
+    }
+}
+
+/// Extracts the secret from the body of a key-value read response.
+fn secret_from_response(response: &str, field: &str) -> Result<SecretKey, StoreError> {
+    let response = serde_json::from_str::<serde_json::Value>(response).map_err(StoreError::new)?;
+    let value = response["data"]["data"][field].as_str()
+        .ok_or_else(|| StoreError::new(format_args!("Vault entry has no {} field", field)))?;
+
+    SecretKey::decode(value).map_err(StoreError::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_from_response() {
+        let response = r#"{"data": {"data": {"secret": "hex:757775"}, "metadata": {"version": 2}}}"#;
+
+        assert_eq!(super::secret_from_response(response, "secret"), Ok(SecretKey::from("uwu")));
+        assert!(super::secret_from_response(response, "key").is_err());
+        assert_eq!(VaultKeySource::new("http://vault:8200/", "t", "/tokenize").mount("kv").url(), "http://vault:8200/v1/kv/data/tokenize");
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! Compatibility tests against tokens generated by the reference JavaScript implementation.
+//! 
+//! The vectors live in `tests/vectors/tokens.json`, and are regenerated with
+//! `tests/vectors/generate.js`.
+
+use serde_json::Value;
+use crate::clock::FixedClock;
+use crate::store::MemoryAccount;
+use crate::{Tokenize, TokenizeError, TOKENIZE_EPOCH};
+
+struct Vector {
+    secret: String,
+    prefix: Option<String>,
+    account_id: String,
+    generated_at: u64,
+    token: String
+}
+
+impl Vector {
+    /// An instance with the vector's secret and prefix, its clock set to the vector's generation time.
+    fn tokenize(&self) -> Tokenize {
+        let tokenize = Tokenize::new(self.secret.as_bytes())
+            .set_clock(FixedClock(TOKENIZE_EPOCH + self.generated_at as i64 * 1000));
+
+        match &self.prefix {
+            Some(prefix) => tokenize.set_prefix(prefix),
+            None => tokenize
+        }
+    }
+}
+
+fn vectors() -> Vec<Vector> {
+    let vectors = serde_json::from_str::<Value>(include_str!("../tests/vectors/tokens.json")).expect("Invalid test vectors");
+    let field = |vector: &Value, name: &str| vector[name].as_str().map(str::to_string);
+
+    vectors.as_array().expect("Test vectors aren't an array").iter()
+        .map(|vector| Vector {
+            secret: field(vector, "secret").expect("Missing secret"),
+            prefix: field(vector, "prefix"),
+            account_id: field(vector, "account_id").expect("Missing account id"),
+            generated_at: vector["generated_at"].as_u64().expect("Missing generation time"),
+            token: field(vector, "token").expect("Missing token")
+        })
+        .collect()
+}
+
+#[test]
+fn generate() {
+    for vector in vectors() {
+        assert_eq!(vector.tokenize().generate(&*vector.account_id).as_deref(), Ok(&*vector.token), "{}", vector.token);
+    }
+}
+
+#[test]
+fn validate() {
+    for vector in vectors() {
+        let (token, _) = vector.tokenize()
+            .validate_token(&*vector.token, |_id| Some(MemoryAccount { data: (), last_token_reset: 0 }))
+            .unwrap_or_else(|err| panic!("{}: {}", vector.token, err));
+
+        assert_eq!(token.account_id().as_str(), Some(&*vector.account_id));
+        assert_eq!(token.generated_at(), vector.generated_at);
+        assert_eq!(token.prefix(), vector.prefix.as_deref());
+        assert_eq!(token.to_string(), vector.token);
+
+        let token_ref = vector.tokenize().parse_ref(&vector.token).unwrap_or_else(|err| panic!("{}: {}", vector.token, err));
+        assert_eq!(token_ref.account_id().ok().as_ref().and_then(|id| id.as_str()), Some(&*vector.account_id));
+    }
+}
+
+#[test]
+fn reject_tampered() {
+    for vector in vectors() {
+        let tokenize = vector.tokenize();
+        let (head, signature) = vector.token.rsplit_once('.').expect("Token has no signature");
+        let flipped = if signature.starts_with('A') { 'B' } else { 'A' };
+        let tampered = format!("{}.{}{}", head, flipped, &signature[1..]);
+        assert_eq!(tokenize.parse(tampered).err(), Some(TokenizeError::InvalidSignature), "{}", vector.token);
+
+        let other = Tokenize::new(format!("{}!", vector.secret).into_bytes());
+        let other = match &vector.prefix {
+            Some(prefix) => other.set_prefix(prefix),
+            None => other
+        };
+        assert_eq!(other.parse(&*vector.token).err(), Some(TokenizeError::InvalidSignature), "{}", vector.token);
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+
+//! JavaScript bindings, available with the `wasm` feature.
+//! 
+//! The `npm` directory builds them into a Node package, see its `package.json`.
+
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use crate::{Account, Tokenize, TokenizeError, ValidationOptions};
+
+struct JsAccount {
+    last_token_reset: u64
+}
+
+impl Account for JsAccount {
+    fn last_token_reset(&self) -> u64 {
+        self.last_token_reset
+    }
+}
+
+/// JavaScript wrapper around [`Tokenize`].
+#[wasm_bindgen(js_name = Tokenize)]
+pub struct WasmTokenize {
+    inner: Tokenize
+}
+
+#[wasm_bindgen(js_class = Tokenize)]
+impl WasmTokenize {
+    /// Creates an instance signing tokens with `secret`, rejecting tokens older than `max_age` seconds
+    /// if it's given.
+    #[wasm_bindgen(constructor)]
+    pub fn new(secret: &[u8], prefix: Option<String>, max_age: Option<f64>) -> Result<WasmTokenize, JsError> {
+        let mut inner = Tokenize::new(secret);
+        if let Some(prefix) = prefix {
+            inner = inner.try_set_prefix(prefix)?;
+        }
+
+        if let Some(max_age) = max_age {
+            inner = inner.set_validation_options(ValidationOptions::new().max_age(Duration::try_from_secs_f64(max_age)?));
+        }
+
+        Ok(WasmTokenize { inner })
+    }
+
+    /// Generates a new token for the given account id.
+    pub fn generate(&self, account_id: String) -> Result<String, JsValue> {
+        self.inner.generate(account_id).map_err(to_js_error)
+    }
+
+    /// Validates a token, returning the id of the account it belongs to.
+    /// 
+    /// `fetch_last_token_reset` is called with the account id and must return the account's last
+    /// token reset timestamp in milliseconds, or `null`/`undefined` if there is no such account.
+    /// Errors thrown by it are rethrown.
+    pub fn validate(&self, token: String, fetch_last_token_reset: &js_sys::Function) -> Result<String, JsValue> {
+        let mut fetch_error = None;
+        let mut account_id = String::new();
+
+        let result = self.inner.validate(token, |id| {
+            let last_token_reset = match fetch_last_token_reset.call1(&JsValue::NULL, &JsValue::from_str(&id)) {
+                Ok(value) => value.as_f64()?,
+                Err(err) => {
+                    fetch_error = Some(err);
+                    return None
+                }
+            };
+
+            account_id = id;
+            Some(JsAccount { last_token_reset: last_token_reset as u64 })
+        });
+
+        if let Some(err) = fetch_error {
+            return Err(err)
+        }
+
+        result.map_err(to_js_error)?;
+        Ok(account_id)
+    }
+
+    /// Verifies a token without looking up its account, returning the id of the account it belongs to.
+    pub fn parse(&self, token: String) -> Result<String, JsValue> {
+        Ok(self.inner.parse(token).map_err(to_js_error)?.account_id().to_string())
+    }
+}
+
+/// Converts `err` to a JavaScript `Error`, with its [`kind`](TokenizeError::kind) in a `kind` property.
+fn to_js_error(err: TokenizeError) -> JsValue {
+    let js_error = js_sys::Error::new(&err.to_string());
+    let _ = js_sys::Reflect::set(&js_error, &JsValue::from_str("kind"), &JsValue::from_str(err.kind()));
+    js_error.into()
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) 2022 Umut İnan Erdoğan
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *
+ * 1. Redistributions of source code must retain the above copyright notice, this
+ *    list of conditions and the following disclaimer.
+ * 2. Redistributions in binary form must reproduce the above copyright notice,
+ *    this list of conditions and the following disclaimer in the
+ *    documentation and/or other materials provided with the distribution.
+ * 3. Neither the name of the copyright holder nor the names of its contributors
+ *    may be used to endorse or promote products derived from this software without
+ *    specific prior written permission.
+ * 
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE. 
+ */
+
+//! WebSocket integration through [tokio-tungstenite], available with the `websocket` feature.
+//! 
+//! Clients present their token either during the handshake, as a subprotocol (see
+//! [`extract::subprotocol`]), or as the first message once the connection is open. The first way
+//! suits browsers, which can't set headers on WebSocket connections; the second one avoids tokens
+//! showing up in proxy logs.
+//! 
+//! Other WebSocket libraries can rely on [`extract::subprotocol`] directly.
+//! 
+//! See `examples/gateway.rs` for a complete gateway.
+//! 
+//! [tokio-tungstenite]: https://docs.rs/tokio-tungstenite
+
+use std::fmt;
+use std::future::Future;
+use futures_util::StreamExt;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::WebSocketStream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use crate::{extract, Account, Tokenize, TokenizeError};
+
+/// Error returned by [`accept`].
+#[derive(Debug)]
+pub enum WebSocketError {
+    /// The handshake failed, or the connection broke while waiting for the token.
+    Protocol(tungstenite::Error),
+    /// The connection was closed before a token was presented.
+    MissingToken,
+    /// The token couldn't be validated. The connection was closed with a policy violation.
+    InvalidToken(TokenizeError)
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebSocketError::Protocol(err) => write!(f, "WebSocket error: {}", err),
+            WebSocketError::MissingToken => write!(f, "Missing token"),
+            WebSocketError::InvalidToken(err) => write!(f, "{}", err)
+        }
+    }
+}
+
+impl std::error::Error for WebSocketError {}
+
+impl From<tungstenite::Error> for WebSocketError {
+    fn from(err: tungstenite::Error) -> Self {
+        WebSocketError::Protocol(err)
+    }
+}
+
+/// Accepts a WebSocket connection, validating the token presented by the client, and returns the
+/// connection along with the account the token belongs to.
+/// 
+/// The token is taken from the `Sec-WebSocket-Protocol` header if the client offered the
+/// [`SUBPROTOCOL`](extract::SUBPROTOCOL), which is then selected. Otherwise, the first text or binary
+/// message must hold the token, raw or prefixed with `Bearer `.
+// The handshake callback has to return tungstenite's error response
+#[allow(clippy::result_large_err)]
+pub async fn accept<S, F, Fut, A>(tokenize: &Tokenize, stream: S, account_fetcher: F) -> Result<(WebSocketStream<S>, A), WebSocketError> where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Option<A>>,
+    A: Account {
+    let mut token = None;
+    let mut socket = tokio_tungstenite::accept_hdr_async(stream, |req: &Request, mut res: Response| {
+        token = req.headers().get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .and_then(extract::subprotocol)
+            .map(str::to_string);
+
+        if token.is_some() {
+            res.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(extract::SUBPROTOCOL));
+        }
+
+        Ok(res)
+    }).await?;
+
+    let token = match token {
+        Some(token) => token,
+        None => first_message(&mut socket).await?
+    };
+
+    match tokenize.validate_async(token, account_fetcher).await {
+        Ok(account) => Ok((socket, account)),
+        Err(err) => {
+            let frame = CloseFrame { code: CloseCode::Policy, reason: err.to_string().into() };
+            // The client may already be gone, the token is rejected either way
+            let _ = socket.close(Some(frame)).await;
+            Err(WebSocketError::InvalidToken(err))
+        }
+    }
+}
+
+/// Reads the token from the first data message.
+async fn first_message<S: AsyncRead + AsyncWrite + Unpin>(socket: &mut WebSocketStream<S>) -> Result<String, WebSocketError> {
+    while let Some(message) = socket.next().await {
+        let text = match message? {
+            Message::Text(text) => text.to_string(),
+            Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Message::Close(_) => break,
+            _ => continue
+        };
+
+        return extract::bearer(&text).map(str::to_string).ok_or(WebSocketError::MissingToken)
+    }
+
+    Err(WebSocketError::MissingToken)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message;
+    use super::{accept, WebSocketError};
+    use crate::{Account, Encoding, Tokenize, TokenizeError};
+
+    struct TestAccount {
+        last_token_reset: u64
+    }
+
+    impl Account for TestAccount {
+        fn last_token_reset(&self) -> u64 {
+            self.last_token_reset
+        }
+    }
+
+    fn tokenize() -> Tokenize {
+        Tokenize::new("uwu").set_encoding(Encoding::UrlSafe)
+    }
+
+    async fn connect(subprotocol: Option<String>, first_message: Option<String>) -> (Result<u64, WebSocketError>, Option<Message>) {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = async {
+            accept(&tokenize(), server, |_id| async { Some(TestAccount { last_token_reset: 7 }) }).await
+                .map(|(_, account)| account.last_token_reset)
+        };
+        let client = async {
+            let mut request = "ws://localhost/".into_client_request().unwrap();
+            if let Some(subprotocol) = subprotocol {
+                request.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, subprotocol.parse().unwrap());
+            }
+
+            let (mut socket, _) = tokio_tungstenite::client_async(request, client).await.unwrap();
+            if let Some(message) = first_message {
+                socket.send(Message::text(message)).await.unwrap();
+            }
+
+            socket.next().await.and_then(Result::ok)
+        };
+
+        tokio::join!(server, client)
+    }
+
+    #[tokio::test]
+    async fn accept_connections() {
+        let token = tokenize().generate_token("326359466171826176").expect("Couldn't generate new token").to_string();
+
+        assert_eq!(connect(Some(format!("tokenize, {}", token)), None).await.0.unwrap(), 7);
+        assert_eq!(connect(None, Some(format!("Bearer {}", token))).await.0.unwrap(), 7);
+
+        let (result, message) = connect(None, Some("thisisinvalid".to_string())).await;
+        assert!(matches!(result, Err(WebSocketError::InvalidToken(TokenizeError::Malformed))));
+        assert!(matches!(message, Some(Message::Close(Some(frame))) if frame.code == CloseCode::Policy));
+    }
+}
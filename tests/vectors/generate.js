@@ -0,0 +1,43 @@
+// Generates tokens.json with the token generation of the reference JavaScript implementation
+// (https://github.com/cyyynthia/tokenize), with the clock replaced by fixed timestamps.
+//
+//   node tests/vectors/generate.js > tests/vectors/tokens.json
+
+const { createHmac } = require("crypto");
+
+const VERSION = 1;
+
+// Same steps as Tokenize#generate and Tokenize#_computeHmac of the reference implementation
+function generate(secret, prefix, accountId, generatedAt) {
+  const prefixPart = prefix ? `${prefix}.` : "";
+  const accountPart = Buffer.from(accountId).toString("base64").replace(/=/g, "");
+  const timePart = Buffer.from(String(generatedAt)).toString("base64").replace(/=/g, "");
+  const token = `${prefixPart}${accountPart}.${timePart}`;
+  const signature = createHmac("sha256", secret).update(`TTF.${VERSION}.${token}`).digest("base64").replace(/=/g, "");
+
+  return `${token}.${signature}`;
+}
+
+const cases = [
+  ["uwu", null, "326359466171826176", 95334807],
+  ["uwu", null, "1", 0],
+  ["uwu", null, "42", 1],
+  ["uwu", "app", "326359466171826176", 95334807],
+  ["uwu", "bot", "80351110224678912", 185207600],
+  ["owo", null, "ünïcödé", 123456789],
+  ["owo", "svc", "~~~", 2147483647],
+  ["owo", null, "???", 99999999999],
+  ["a much longer secret, with spaces and punctuation!", null, "user@example.com", 31536000],
+  ["a much longer secret, with spaces and punctuation!", "prod-eu", "c2e5a5e0-4c4e-4b8e-9a3c-0f1f6b4d2e11", 250000000],
+  ["🔑", "x", "0", 7]
+];
+
+const vectors = cases.map(([secret, prefix, accountId, generatedAt]) => ({
+  secret,
+  prefix,
+  account_id: accountId,
+  generated_at: generatedAt,
+  token: generate(secret, prefix, accountId, generatedAt)
+}));
+
+console.log(JSON.stringify(vectors, null, 2));